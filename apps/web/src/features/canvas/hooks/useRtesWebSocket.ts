@@ -2,7 +2,11 @@
 
 import { useCallback, useEffect, useRef, useState } from "react";
 import { toast } from "@/components/ui/toast";
-import type { RtesNodeUpdate } from "../types/execution";
+import {
+  isResyncMessage,
+  type RtesControlMessage,
+  type RtesNodeUpdate,
+} from "../types/execution";
 
 // WebSocket connection status
 export type WsConnectionStatus =
@@ -165,6 +169,8 @@ export function useRtesWebSocket(options: UseRtesWebSocketOptions): UseRtesWebSo
       const params = new URLSearchParams();
       if (executionId) params.set("execution_id", executionId);
       if (workflowId) params.set("workflow_id", String(workflowId));
+      // The canvas shows inputs, params and errors, so opt in to the full payload
+      params.set("include", "status,output,input,params,error");
       const wsUrl = `${DEFAULT_RTES_URL}${params.toString() ? `?${params.toString()}` : ""}`;
       const ws = new WebSocket(wsUrl);
       connectionTimeoutRef.current = setTimeout(() => {
@@ -189,7 +195,13 @@ export function useRtesWebSocket(options: UseRtesWebSocketOptions): UseRtesWebSo
       ws.onmessage = (event) => {
         if (!mountedRef.current) return;
         try {
-          const data = JSON.parse(event.data) as RtesNodeUpdate;
+          const data = JSON.parse(event.data) as RtesNodeUpdate | RtesControlMessage;
+          if (isResyncMessage(data)) {
+            // Updates were dropped; reconnecting replays the persisted state
+            cleanup();
+            connect();
+            return;
+          }
           onMessage?.(data);
         } catch {
           // Silent failure - invalid message format
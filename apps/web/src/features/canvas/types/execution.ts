@@ -98,6 +98,25 @@ export interface RtesNodeUpdate {
   processed_count?: number | null;
   aggregator_state?: string | null;
   used_inputs?: unknown;
+  executed_at?: string | null;
+  duration_ms?: number | null;
+  // Set on workflow completion frames that ended in failure/halt
+  failure_reason?: string | null;
+}
+
+/**
+ * Control frame from RTES. `resync` means live updates were dropped because
+ * the client fell behind; the execution state must be re-fetched.
+ */
+export interface RtesControlMessage {
+  type: "resync";
+  skipped: number;
+}
+
+export function isResyncMessage(
+  message: RtesNodeUpdate | RtesControlMessage,
+): message is RtesControlMessage {
+  return (message as RtesControlMessage).type === "resync";
 }
 
 // Stack frame for split node lineage tracking
@@ -174,6 +193,8 @@ export function rtesUpdateToNodeData(update: RtesNodeUpdate): NodeExecutionData
     error: update.error
       ? { message: update.error.message, code: update.error.code, details: update.error.details }
       : undefined,
+    executedAt: update.executed_at ?? undefined,
+    durationMs: update.duration_ms ?? undefined,
     lineageHash: update.lineage_hash ?? undefined,
     lineageStack: update.lineage_stack ?? undefined,
     splitNodeId: update.split_node_id ?? latestLineageFrame?.split_node_id,
@@ -30,6 +30,14 @@ export interface RtesExecutionDocument {
   updated_at?: string;
 }
 
+/**
+ * Page of workflow executions from RTES (matches Rust ExecutionPage)
+ */
+export interface RtesExecutionPage {
+  executions: RtesExecutionDocument[];
+  next_cursor: string | null;
+}
+
 export interface RtesHydratedNode {
   latest?: RtesNodeExecutionInstance;
   lineages?: Record<string, RtesNodeExecutionInstance>;
@@ -79,18 +87,30 @@ export async function fetchWorkflowExecutions(
       return [];
     }
 
-    // Step 2: Fetch from RTES (validates via Redis token)
-    const response = await fetch(`${RTES_BASE_URL}/workflows/${workflowId}/executions`);
+    // Step 2: Fetch every page from RTES (validates via Redis token)
+    const executions: RtesExecutionDocument[] = [];
+    let after: string | null = null;
+    do {
+      const params = new URLSearchParams({ limit: "200" });
+      if (after) params.set("after", after);
+      const response = await fetch(
+        `${RTES_BASE_URL}/workflows/${workflowId}/executions?${params.toString()}`,
+      );
 
-    if (!response.ok) {
-      if (response.status === 401 || response.status === 403) {
-        console.warn("[RTES API] Unauthorized access to executions");
-        return [];
+      if (!response.ok) {
+        if (response.status === 401 || response.status === 403) {
+          console.warn("[RTES API] Unauthorized access to executions");
+          return [];
+        }
+        throw new Error(`RTES API error: ${response.status}`);
       }
-      throw new Error(`RTES API error: ${response.status}`);
-    }
 
-    return await response.json();
+      const page: RtesExecutionPage = await response.json();
+      executions.push(...page.executions);
+      after = page.next_cursor;
+    } while (after);
+
+    return executions;
   } catch {
     return [];
   }
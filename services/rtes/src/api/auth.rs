@@ -0,0 +1,461 @@
+//! User JWT validation shared by the HTTP, SSE and WebSocket endpoints.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::http::HeaderMap;
+use jsonwebtoken::{DecodingKey, Validation, decode, errors::ErrorKind};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{Span, warn};
+use uuid::Uuid;
+
+use crate::{api::error::ApiError, infra::prometheus};
+
+/// Minimum gap between two warnings for the same failure reason and user.
+const FAILURE_LOG_INTERVAL: Duration = Duration::from_mins(1);
+
+/// Reason/user pairs the failure log remembers at most; while full, new
+/// pairs are counted but not logged.
+const FAILURE_LOG_MAX_KEYS: usize = 1024;
+
+/// Claims of a user JWT. The frontend's tokens carry the user in `sub`;
+/// tokens minted by other services may name it `user_id` instead.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Claims {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub:     Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Organization the user acts for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:  Option<String>,
+    /// Expiry timestamp
+    pub exp:     usize,
+    /// Accept any other fields without failing deserialization
+    #[serde(flatten)]
+    pub extra:   HashMap<String, Value>,
+}
+
+impl Claims {
+    /// The authenticated user: `sub` when present, otherwise `user_id`.
+    pub(crate) fn user_id(&self) -> Option<&str> {
+        self.sub
+            .as_deref()
+            .or(self.user_id.as_deref())
+            .filter(|id| !id.is_empty())
+    }
+}
+
+/// Why a caller was refused, the `reason` label of
+/// `rtes_auth_failures_total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum AuthFailure {
+    /// The JWT's `exp` has passed
+    Expired,
+    /// The JWT wasn't signed with the configured secret
+    InvalidSignature,
+    /// Any other unusable JWT: malformed, wrong audience or issuer, no user
+    Invalid,
+    /// The caller holds no grant for the execution or workflow
+    NoGrant,
+}
+
+impl AuthFailure {
+    pub(crate) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::InvalidSignature => "invalid-signature",
+            Self::Invalid => "invalid",
+            Self::NoGrant => "no-grant",
+        }
+    }
+
+    const fn from_jwt_error(kind: &ErrorKind) -> Self {
+        match kind {
+            ErrorKind::ExpiredSignature => Self::Expired,
+            ErrorKind::InvalidSignature => Self::InvalidSignature,
+            _ => Self::Invalid,
+        }
+    }
+}
+
+/// When each reason/user pair was last logged, and how many failures like
+/// it were suppressed since.
+#[derive(Debug, Default)]
+struct FailureLog {
+    entries: HashMap<(AuthFailure, Option<String>), (Instant, u64)>,
+}
+
+impl FailureLog {
+    /// Whether a failure seen at `now` should be logged; `Some` carries how
+    /// many like it were suppressed since the last one logged.
+    fn admit(&mut self, reason: AuthFailure, user: Option<&str>, now: Instant) -> Option<u64> {
+        let key = (reason, user.map(str::to_owned));
+        if let Some((logged_at, suppressed)) = self.entries.get_mut(&key) {
+            if now.duration_since(*logged_at) < FAILURE_LOG_INTERVAL {
+                *suppressed += 1;
+                return None;
+            }
+            let skipped = *suppressed;
+            *logged_at = now;
+            *suppressed = 0;
+            return Some(skipped);
+        }
+        if self.entries.len() >= FAILURE_LOG_MAX_KEYS {
+            self.entries
+                .retain(|_, (logged_at, _)| now.duration_since(*logged_at) < FAILURE_LOG_INTERVAL);
+            if self.entries.len() >= FAILURE_LOG_MAX_KEYS {
+                return None;
+            }
+        }
+        self.entries.insert(key, (now, 0));
+        Some(0)
+    }
+}
+
+/// Short, stable stand-in for a user id in logs, so failures can be
+/// correlated without writing the id itself.
+fn user_ref(user_id: &str) -> String {
+    let mut hashed = Uuid::new_v5(&Uuid::NAMESPACE_OID, user_id.as_bytes())
+        .simple()
+        .to_string();
+    hashed.truncate(12);
+    hashed
+}
+
+/// Count a refused caller in `rtes_auth_failures_total` and warn about it,
+/// at most once per [`FAILURE_LOG_INTERVAL`] for the same reason and user
+/// so repeated failures can't flood the logs.
+pub(crate) fn record_auth_failure(reason: AuthFailure, user_id: Option<&str>, detail: &str) {
+    static LOG: OnceLock<Mutex<FailureLog>> = OnceLock::new();
+    prometheus::auth_failure(reason.as_str());
+    let user = user_id.map(user_ref);
+    let admitted = LOG
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .admit(reason, user.as_deref(), Instant::now());
+    if let Some(suppressed) = admitted {
+        warn!(
+            reason = reason.as_str(),
+            user = user.as_deref().unwrap_or("-"),
+            suppressed,
+            "Auth failure: {}",
+            detail
+        );
+    }
+}
+
+/// The bearer token of the `Authorization` header, if any.
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("Authorization")
+        .map(|value| value.to_str().unwrap_or("").replace("Bearer ", ""))
+}
+
+/// Who a request acts as. Built once per request, so its JWT is decoded, and
+/// a bad one counted and logged, only once however many checks follow.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Caller {
+    /// The JWT's user; `None` without a JWT, leaving execution tokens
+    pub user_id: Option<String>,
+    /// Organization the caller acts for
+    pub org_id:  Option<String>,
+}
+
+impl Caller {
+    /// The caller presenting `token`, if any. A JWT decides the organization
+    /// with its `org_id` claim, and `requested` (the `X-Org-Id` header or
+    /// `?org_id=`) must agree: a mismatch is refused rather than looked up,
+    /// so naming another organization never reads as a missing execution.
+    /// Without a JWT, `requested` is all there is; grant checks still hold
+    /// it against the execution's organization.
+    pub(crate) fn from_token(
+        token: Option<&str>,
+        requested: Option<&str>,
+    ) -> Result<Self, ApiError> {
+        let requested = requested.map(str::trim).filter(|org| !org.is_empty());
+        let Some(token) = token else {
+            return Ok(Self { user_id: None, org_id: requested.map(str::to_owned) });
+        };
+        let cfg = crate::config::Config::get();
+        let validation = jwt_validation(cfg.jwt_audience.as_deref(), cfg.jwt_issuer.as_deref());
+        let (user_id, claims) = verify_claims_with(token, cfg.jwt_secret.as_bytes(), &validation)?;
+        let org_id = claims.org_id.filter(|org| !org.is_empty());
+        if requested.is_some_and(|requested| org_id.as_deref() != Some(requested)) {
+            return Err(ApiError::OrgMismatch);
+        }
+        Ok(Self { user_id: Some(user_id), org_id })
+    }
+}
+
+/// The user of a valid bearer JWT, checked without logging or recording it
+/// on the span; for callers that only need a key, like the rate limiter.
+pub(crate) fn peek_user_id(headers: &HeaderMap) -> Option<String> {
+    let token = bearer_token(headers)?;
+    let cfg = crate::config::Config::get();
+    let validation = jwt_validation(cfg.jwt_audience.as_deref(), cfg.jwt_issuer.as_deref());
+    decode_claims(&token, cfg.jwt_secret.as_bytes(), &validation)
+        .ok()?
+        .user_id()
+        .map(str::to_owned)
+}
+
+/// Expiry is always checked; `aud` and `iss` only when configured, so
+/// tokens minted by other services sharing the secret can be turned away.
+fn jwt_validation(audience: Option<&str>, issuer: Option<&str>) -> Validation {
+    let mut validation = Validation::default();
+    if let Some(audience) = audience {
+        validation.set_audience(&[audience]);
+        validation.required_spec_claims.insert("aud".to_string());
+    }
+    if let Some(issuer) = issuer {
+        validation.set_issuer(&[issuer]);
+        validation.required_spec_claims.insert("iss".to_string());
+    }
+    validation
+}
+
+/// Decode and validate a JWT. Every caller goes through here, so signature,
+/// expiry and audience checks cannot drift between endpoints.
+pub(crate) fn decode_claims(
+    token: &str,
+    secret: &[u8],
+    validation: &Validation,
+) -> Result<Claims, jsonwebtoken::errors::Error> {
+    decode::<Claims>(token, &DecodingKey::from_secret(secret), validation).map(|data| data.claims)
+}
+
+/// Validate a JWT naming a user and return the user with the claims,
+/// recording the user on the request span. A refused JWT is counted and
+/// logged here, once.
+fn verify_claims_with(
+    token: &str,
+    secret: &[u8],
+    validation: &Validation,
+) -> Result<(String, Claims), ApiError> {
+    match decode_claims(token, secret, validation) {
+        Ok(claims) => {
+            let Some(user_id) = claims.user_id().map(str::to_owned) else {
+                record_auth_failure(AuthFailure::Invalid, None, "JWT has no sub or user_id claim");
+                return Err(ApiError::InvalidToken);
+            };
+            Span::current().record("user_id", user_id.as_str());
+            Ok((user_id, claims))
+        },
+        Err(e) => {
+            record_jwt_failure(&e);
+            Err(ApiError::InvalidToken)
+        },
+    }
+}
+
+/// Count and log a JWT that didn't validate.
+fn record_jwt_failure(e: &jsonwebtoken::errors::Error) {
+    let detail = match e.kind() {
+        ErrorKind::InvalidAudience | ErrorKind::InvalidIssuer => {
+            format!("JWT audience/issuer mismatch: {e}")
+        },
+        ErrorKind::MissingRequiredClaim(claim) if matches!(claim.as_str(), "aud" | "iss") => {
+            format!("JWT missing required {claim} claim")
+        },
+        ErrorKind::ExpiredSignature => "JWT expired".to_string(),
+        _ => format!("Invalid JWT: {e}"),
+    };
+    record_auth_failure(AuthFailure::from_jwt_error(e.kind()), None, &detail);
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{collections::HashMap, time::Instant};
+
+    use axum::http::HeaderMap;
+    use jsonwebtoken::{EncodingKey, Header, encode, errors::ErrorKind};
+
+    use super::{
+        AuthFailure,
+        Caller,
+        Claims,
+        FAILURE_LOG_INTERVAL,
+        FAILURE_LOG_MAX_KEYS,
+        FailureLog,
+        bearer_token,
+        jwt_validation,
+        user_ref,
+        verify_claims_with,
+    };
+    use crate::{api::error::ApiError, config::Config};
+
+    fn ensure_config_initialized() {
+        let _ = Config::init();
+    }
+
+    fn user_claims(sub: &str) -> Claims {
+        Claims { sub: Some(sub.to_string()), exp: usize::MAX / 2, ..Claims::default() }
+    }
+
+    fn decode_user_id_with(
+        token: &str,
+        secret: &[u8],
+        validation: &jsonwebtoken::Validation,
+    ) -> Result<String, ApiError> {
+        verify_claims_with(token, secret, validation).map(|(user_id, _)| user_id)
+    }
+
+    #[test]
+    fn missing_token_leaves_the_caller_anonymous() {
+        ensure_config_initialized();
+        assert_eq!(Caller::from_token(None, None), Ok(Caller::default()));
+        assert_eq!(
+            Caller::from_token(None, Some("org-1")),
+            Ok(Caller { user_id: None, org_id: Some("org-1".to_string()) })
+        );
+    }
+
+    #[test]
+    fn invalid_jwt_returns_unauthorized_error() {
+        ensure_config_initialized();
+        assert_eq!(
+            Caller::from_token(Some("invalid.token.value"), None),
+            Err(ApiError::InvalidToken)
+        );
+    }
+
+    #[test]
+    fn valid_jwt_extracts_sub_claim() {
+        ensure_config_initialized();
+        let token = encode(
+            &Header::default(),
+            &user_claims("user-42"),
+            &EncodingKey::from_secret(Config::get().jwt_secret.as_bytes()),
+        )
+        .expect("token encoding should succeed");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {token}")
+                .parse()
+                .expect("authorization header should parse"),
+        );
+
+        let token = bearer_token(&headers).expect("auth header exists");
+        let caller = Caller::from_token(Some(&token), None).expect("jwt should be valid");
+        assert_eq!(caller.user_id.as_deref(), Some("user-42"));
+    }
+
+    #[test]
+    fn user_id_claim_is_accepted_when_sub_is_absent() {
+        let secret = b"test-secret";
+        let validation = jwt_validation(None, None);
+        let encode_claims = |claims: &Claims| {
+            encode(&Header::default(), claims, &EncodingKey::from_secret(secret))
+                .expect("token encoding should succeed")
+        };
+
+        let named = Claims {
+            user_id: Some("user-7".to_string()),
+            exp: usize::MAX / 2,
+            ..Claims::default()
+        };
+        assert_eq!(
+            decode_user_id_with(&encode_claims(&named), secret, &validation),
+            Ok("user-7".to_string())
+        );
+
+        let both = Claims { user_id: Some("user-7".to_string()), ..user_claims("user-42") };
+        assert_eq!(
+            decode_user_id_with(&encode_claims(&both), secret, &validation),
+            Ok("user-42".to_string())
+        );
+
+        let anonymous = Claims { exp: usize::MAX / 2, ..Claims::default() };
+        assert_eq!(
+            decode_user_id_with(&encode_claims(&anonymous), secret, &validation),
+            Err(ApiError::InvalidToken)
+        );
+    }
+
+    #[test]
+    fn audience_and_issuer_are_enforced_only_when_configured() {
+        let secret = b"test-secret";
+        let token = |extra: &[(&str, &str)]| {
+            let extra: HashMap<_, _> = extra
+                .iter()
+                .map(|(key, value)| ((*key).to_string(), (*value).into()))
+                .collect();
+            encode(
+                &Header::default(),
+                &Claims { extra, ..user_claims("user-42") },
+                &EncodingKey::from_secret(secret),
+            )
+            .expect("token encoding should succeed")
+        };
+        let ours = token(&[("aud", "rtes"), ("iss", "rune-api")]);
+        let foreign = token(&[("aud", "billing"), ("iss", "rune-api")]);
+        let bare = token(&[]);
+
+        let strict = jwt_validation(Some("rtes"), Some("rune-api"));
+        assert_eq!(decode_user_id_with(&ours, secret, &strict), Ok("user-42".to_string()));
+        for rejected in [&foreign, &bare] {
+            assert_eq!(decode_user_id_with(rejected, secret, &strict), Err(ApiError::InvalidToken));
+        }
+
+        let permissive = jwt_validation(None, None);
+        assert_eq!(decode_user_id_with(&bare, secret, &permissive), Ok("user-42".to_string()));
+    }
+
+    #[test]
+    fn jwt_errors_map_to_failure_reasons() {
+        assert_eq!(AuthFailure::from_jwt_error(&ErrorKind::ExpiredSignature), AuthFailure::Expired);
+        assert_eq!(
+            AuthFailure::from_jwt_error(&ErrorKind::InvalidSignature),
+            AuthFailure::InvalidSignature
+        );
+        assert_eq!(AuthFailure::from_jwt_error(&ErrorKind::InvalidToken), AuthFailure::Invalid);
+    }
+
+    #[test]
+    fn failure_log_suppresses_repeats_within_the_interval() {
+        let mut log = FailureLog::default();
+        let start = Instant::now();
+
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("u1"), start), Some(0));
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("u1"), start), None);
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("u1"), start), None);
+        // Other users and reasons are logged on their own
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("u2"), start), Some(0));
+        assert_eq!(log.admit(AuthFailure::Expired, Some("u1"), start), Some(0));
+
+        let later = start + FAILURE_LOG_INTERVAL;
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("u1"), later), Some(2));
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("u1"), later), None);
+    }
+
+    #[test]
+    fn full_failure_log_makes_room_once_entries_age_out() {
+        let mut log = FailureLog::default();
+        let start = Instant::now();
+        for n in 0..FAILURE_LOG_MAX_KEYS {
+            assert_eq!(log.admit(AuthFailure::NoGrant, Some(&n.to_string()), start), Some(0));
+        }
+
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("late"), start), None);
+        let later = start + FAILURE_LOG_INTERVAL;
+        assert_eq!(log.admit(AuthFailure::NoGrant, Some("late"), later), Some(0));
+    }
+
+    #[test]
+    fn user_ref_is_short_stable_and_hides_the_id() {
+        let hashed = user_ref("user-42");
+        assert_eq!(hashed.len(), 12);
+        assert_eq!(hashed, user_ref("user-42"));
+        assert_ne!(hashed, user_ref("user-43"));
+        assert!(!hashed.contains("user"));
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::api::state::StoreError;
+
+/// A failed API request, rendered as `{ "code": "...", "message": "..." }`
+/// so clients can branch on `code` instead of the message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ApiError {
+    /// The JWT is malformed, expired or addressed to another service
+    InvalidToken,
+    /// No JWT was sent and no execution token grants access
+    Unauthorized,
+    /// The JWT user holds no grant for the resource
+    Forbidden,
+    /// The requested organization isn't the one the JWT names
+    OrgMismatch,
+    ExecutionNotFound,
+    /// The execution exists but the node hasn't run in it
+    NodeNotFound,
+    /// `?path=` names nothing in the execution's context
+    ContextPathNotFound,
+    /// No execution token has the given `jti`
+    TokenNotFound,
+    /// The queue isn't one of the DLQs the admin routes may touch
+    UnknownDlq,
+    /// The execution kept changing while it was repaired
+    ExecutionChanged,
+    InvalidCursor,
+    /// `?include=` or `?since=` of a live stream doesn't parse
+    InvalidQuery,
+    /// `?fields=` named a path executions don't have
+    InvalidField,
+    /// A batch request named more ids than `EXECUTIONS_BATCH_MAX`
+    BatchTooLarge,
+    /// The execution already has `WS_MAX_CONN_PER_EXECUTION` sockets open
+    TooManyConnections,
+    /// The client exceeded `RATE_LIMIT_RPS`
+    RateLimited,
+    /// MongoDB failed to serve the request
+    Database,
+    /// The token store failed to check a grant
+    Internal,
+    /// A store was briefly unreachable; the request may succeed if retried
+    Unavailable,
+    /// RabbitMQ failed to serve a DLQ peek or replay
+    Broker,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code:    &'static str,
+    message: &'static str,
+}
+
+impl ApiError {
+    pub(crate) const fn status(self) -> StatusCode {
+        match self {
+            Self::InvalidToken | Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden | Self::OrgMismatch => StatusCode::FORBIDDEN,
+            Self::ExecutionNotFound
+            | Self::NodeNotFound
+            | Self::ContextPathNotFound
+            | Self::TokenNotFound
+            | Self::UnknownDlq => StatusCode::NOT_FOUND,
+            Self::ExecutionChanged => StatusCode::CONFLICT,
+            Self::InvalidCursor | Self::InvalidQuery | Self::InvalidField | Self::BatchTooLarge => {
+                StatusCode::BAD_REQUEST
+            },
+            Self::TooManyConnections | Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::Database | Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Broker => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Stable machine-readable code; never change one once published.
+    pub(crate) const fn code(self) -> &'static str {
+        match self {
+            Self::InvalidToken => "INVALID_TOKEN",
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::Forbidden => "FORBIDDEN",
+            Self::OrgMismatch => "ORG_MISMATCH",
+            Self::ExecutionNotFound => "EXECUTION_NOT_FOUND",
+            Self::NodeNotFound => "NODE_NOT_FOUND",
+            Self::ContextPathNotFound => "CONTEXT_PATH_NOT_FOUND",
+            Self::TokenNotFound => "TOKEN_NOT_FOUND",
+            Self::UnknownDlq => "UNKNOWN_DLQ",
+            Self::ExecutionChanged => "EXECUTION_CHANGED",
+            Self::InvalidCursor => "INVALID_CURSOR",
+            Self::InvalidQuery => "INVALID_QUERY",
+            Self::InvalidField => "INVALID_FIELD",
+            Self::BatchTooLarge => "BATCH_TOO_LARGE",
+            Self::TooManyConnections => "TOO_MANY_CONNECTIONS",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::Database => "DB_ERROR",
+            Self::Internal => "INTERNAL_ERROR",
+            Self::Unavailable => "SERVICE_UNAVAILABLE",
+            Self::Broker => "BROKER_ERROR",
+        }
+    }
+
+    const fn message(self) -> &'static str {
+        match self {
+            Self::InvalidToken => "Invalid Token",
+            Self::Unauthorized | Self::Forbidden => "Unauthorized",
+            Self::OrgMismatch => "Organization does not match the token",
+            Self::ExecutionNotFound => "Execution not found",
+            Self::NodeNotFound => "Node not found",
+            Self::ContextPathNotFound => "Context path not found",
+            Self::TokenNotFound => "Token not found",
+            Self::UnknownDlq => "Unknown DLQ",
+            Self::ExecutionChanged => "Execution changed during repair",
+            Self::InvalidCursor => "Invalid cursor",
+            Self::InvalidQuery => "Invalid query parameter",
+            Self::InvalidField => "Unknown field",
+            Self::BatchTooLarge => "Too many execution ids",
+            Self::TooManyConnections => "Too many connections for this execution",
+            Self::RateLimited => "Too many requests",
+            Self::Database => "Database Error",
+            Self::Internal => "Internal Error",
+            Self::Unavailable => "Service Unavailable",
+            Self::Broker => "Broker Error",
+        }
+    }
+
+    /// The error for a failed store call: [`Self::Unavailable`] while the
+    /// store is only briefly unreachable, `otherwise` for anything else.
+    pub(crate) const fn from_store(err: &StoreError, otherwise: Self) -> Self {
+        if err.is_transient() {
+            Self::Unavailable
+        } else {
+            otherwise
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody { code: self.code(), message: self.message() };
+        (self.status(), Json(body)).into_response()
+    }
+}
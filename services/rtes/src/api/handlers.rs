@@ -1,259 +1,1354 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::Infallible,
+    future::Future,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 use axum::{
     Json,
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
-use jsonwebtoken::{DecodingKey, Validation, decode};
+use futures::{StreamExt, future, stream};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
 use tracing::{error, info, warn};
 
-use crate::api::state::AppState;
-
-/// JWT claims - uses frontend's existing JWT with 'sub' field for user_id
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    /// User ID from JWT 'sub' claim
-    sub:   String,
-    /// Expiry timestamp
-    exp:   usize,
-    /// Accept any other fields without failing deserialization
-    #[serde(flatten)]
-    extra: HashMap<String, Value>,
-}
+use crate::{
+    api::{
+        auth::{AuthFailure, Caller, bearer_token, record_auth_failure},
+        error::ApiError,
+        state::{AppState, StoreError, StoreResult},
+        ws::{WsControl, WsFields, WsFilter, WsParams, parse_since},
+    },
+    domain::models::{
+        ExecutionAccessScope,
+        ExecutionCursor,
+        ExecutionDocument,
+        ExecutionFields,
+        ExecutionGraph,
+        ExecutionPage,
+        ExecutionProgress,
+        SplitIterations,
+        StatusCursor,
+        TokenScope,
+    },
+    infra::{dlq, messaging::PARSE_ERRORS_QUEUE},
+};
 
 pub(crate) async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
 
-/// Helper to extract and validate JWT, returning user_id on success
-/// Returns None if no Authorization header present (to allow fallback to
-/// token-based auth)
-fn try_extract_user_id(headers: &HeaderMap) -> Option<Result<String, (StatusCode, &'static str)>> {
-    let token = match headers.get("Authorization") {
-        Some(value) => value.to_str().unwrap_or("").replace("Bearer ", ""),
-        None => return None, // No header = try token-based auth
+/// Upper bound on each dependency ping so a hung backend fails the probe
+/// instead of hanging it.
+const READINESS_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ping a dependency, mapping errors and timeouts to a message.
+async fn check_dependency<F>(ping: F) -> Result<(), String>
+where
+    F: Future<Output = StoreResult<()>>,
+{
+    match tokio::time::timeout(READINESS_PING_TIMEOUT, ping).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {READINESS_PING_TIMEOUT:?}")),
+    }
+}
+
+/// Readiness: 200 only when Redis and MongoDB answer a ping and every
+/// RabbitMQ consumer (the only producers on the broadcast channel) is
+/// consuming; otherwise 503 naming the failed dependencies. `/health` stays a
+/// pure liveness probe.
+pub(crate) async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse {
+    let (redis, mongodb) = tokio::join!(
+        check_dependency(state.token_store.ping()),
+        check_dependency(state.execution_store.ping()),
+    );
+
+    let mut failed = Vec::new();
+    let mut dependencies = serde_json::Map::new();
+    for (name, result) in [("redis", redis), ("mongodb", mongodb)] {
+        let entry = match result {
+            Ok(()) => serde_json::json!({ "ok": true }),
+            Err(e) => {
+                warn!(dependency = name, error = %e, "Readiness check failed");
+                failed.push(name);
+                serde_json::json!({ "ok": false, "error": e })
+            },
+        };
+        dependencies.insert(name.to_string(), entry);
+    }
+    if !state.consumers.is_ready() {
+        failed.push("consumers");
+    }
+
+    let ready = failed.is_empty();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
     };
+    (
+        status,
+        Json(serde_json::json!({
+            "ready": ready,
+            "failed": failed,
+            "dependencies": dependencies,
+            "consumers": state.consumers.snapshot(),
+        })),
+    )
+}
+
+/// Header naming the organization a caller acts for.
+pub(crate) const ORG_ID_HEADER: &str = "x-org-id";
+
+/// The caller of a request, its JWT decoded once. It acts for the `org_id`
+/// claim of its JWT, which [`ORG_ID_HEADER`] may repeat but not contradict,
+/// or the header alone without a JWT. Executions are only read and deleted
+/// within that organization; without one only executions stored without an
+/// organization are reachable.
+fn request_caller(headers: &HeaderMap) -> Result<Caller, ApiError> {
+    let requested = headers
+        .get(ORG_ID_HEADER)
+        .and_then(|value| value.to_str().ok());
+    Caller::from_token(bearer_token(headers).as_deref(), requested)
+}
 
-    let cfg = crate::config::Config::get();
-    let validation = Validation::default();
+/// Fail fast with a recently remembered `404` or denial of `execution_id`
+/// for this caller, before anything is loaded. A remembered denial still
+/// counts as an auth failure.
+fn check_negative_cache(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    org_id: Option<&str>,
+) -> Result<(), ApiError> {
+    let user_id = caller.user_id.as_deref();
+    match state
+        .negative_cache
+        .get(execution_id, org_id, user_id, Instant::now())
+    {
+        None => Ok(()),
+        Some(error @ (ApiError::Forbidden | ApiError::Unauthorized)) => {
+            record_auth_failure(
+                AuthFailure::NoGrant,
+                user_id,
+                &format!("No grant for execution {execution_id} (cached)"),
+            );
+            Err(error)
+        },
+        Some(error) => Err(error),
+    }
+}
 
-    Some(
-        match decode::<Claims>(
-            &token,
-            &DecodingKey::from_secret(cfg.jwt_secret.as_bytes()),
-            &validation,
-        ) {
-            Ok(c) => Ok(c.claims.sub),
+/// Authorize read access to an execution; any viewer or owner grant will do.
+async fn authorize_execution(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    workflow_id: &str,
+    org_id: Option<&str>,
+) -> Result<(), ApiError> {
+    authorize_execution_as(state, caller, execution_id, workflow_id, org_id, TokenScope::Viewer)
+        .await
+}
+
+/// Authorize access to an execution with at least `required` scope: the JWT
+/// user's grants when the caller presented a JWT, otherwise the
+/// execution token index (execution_id + workflow_id).
+///
+/// Read denials are remembered in the negative cache; grants, and denials
+/// of owner-only actions, never are.
+async fn authorize_execution_as(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    workflow_id: &str,
+    org_id: Option<&str>,
+    required: TokenScope,
+) -> Result<(), ApiError> {
+    let authorized =
+        check_execution_access(state, caller, execution_id, workflow_id, org_id, required).await;
+    if let Err(error @ (ApiError::Forbidden | ApiError::Unauthorized)) = authorized
+        && required == TokenScope::Viewer
+    {
+        state.negative_cache.record_denied(
+            execution_id,
+            org_id,
+            caller.user_id.as_deref(),
+            error,
+            Instant::now(),
+        );
+    }
+    authorized
+}
+
+async fn check_execution_access(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    workflow_id: &str,
+    org_id: Option<&str>,
+    required: TokenScope,
+) -> Result<(), ApiError> {
+    tracing::Span::current().record("workflow_id", workflow_id);
+    // Try JWT-based auth first
+    if let Some(user_id) = caller.user_id.as_deref() {
+        // Validate user has access to this execution
+        return match state
+            .token_store
+            .validate_access_for_execution(user_id, execution_id, org_id, required)
+            .await
+        {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                record_auth_failure(
+                    AuthFailure::NoGrant,
+                    Some(user_id),
+                    &format!("No grant for execution {execution_id}"),
+                );
+                Err(ApiError::Forbidden)
+            },
             Err(e) => {
-                warn!("Invalid JWT token: {}", e);
-                Err((StatusCode::UNAUTHORIZED, "Invalid Token"))
+                error!("Token validation error: {}", e);
+                Err(ApiError::from_store(&e, ApiError::Internal))
             },
+        };
+    }
+
+    // Fallback: Token-based auth (execution_id + workflow_id validation)
+    info!("No JWT provided, trying token-based auth for execution {}", execution_id);
+    match state
+        .token_store
+        .validate_execution_access(execution_id, workflow_id, org_id, required)
+        .await
+    {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            record_auth_failure(
+                AuthFailure::NoGrant,
+                None,
+                &format!("No grant for execution {execution_id}"),
+            );
+            Err(ApiError::Unauthorized)
+        },
+        Err(e) => {
+            error!("Token validation error: {}", e);
+            Err(ApiError::from_store(&e, ApiError::Internal))
         },
+    }
+}
+
+/// Authorize access to an execution from its `workflow_id` alone, so
+/// callers without access never cause the document itself to be read. A
+/// missing execution is 404.
+async fn authorize_execution_by_id(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+) -> Result<(), ApiError> {
+    authorize_execution_by_id_as(state, caller, execution_id, TokenScope::Viewer).await
+}
+
+/// [`authorize_execution_by_id`] requiring the given grant scope.
+async fn authorize_execution_by_id_as(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    required: TokenScope,
+) -> Result<(), ApiError> {
+    let org_id = caller.org_id.as_deref();
+    check_negative_cache(state, caller, execution_id, org_id)?;
+    let owner = match state
+        .execution_store
+        .get_execution_owner(execution_id, org_id)
+        .await
+    {
+        Ok(Some(owner)) => owner,
+        Ok(None) => {
+            state
+                .negative_cache
+                .record_missing(execution_id, org_id, Instant::now());
+            return Err(ApiError::ExecutionNotFound);
+        },
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Err(ApiError::from_store(&e, ApiError::Database));
+        },
+    };
+    authorize_execution_as(
+        state,
+        caller,
+        execution_id,
+        &owner.workflow_id,
+        owner.org_id.as_deref(),
+        required,
     )
+    .await
+}
+
+/// Load an execution document in the given view, mapping a missing document
+/// to 404. Executions recently missing or denied to this caller aren't
+/// loaded again.
+async fn load_execution(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    view: ExecutionView,
+) -> Result<ExecutionDocument, ApiError> {
+    let org_id = caller.org_id.as_deref();
+    check_negative_cache(state, caller, execution_id, org_id)?;
+    let loaded = match view {
+        ExecutionView::Full => {
+            state
+                .execution_store
+                .get_execution_document(execution_id, org_id)
+                .await
+        },
+        ExecutionView::Compact => {
+            state
+                .execution_store
+                .get_execution_document_compact(execution_id, org_id)
+                .await
+        },
+    };
+    match loaded {
+        Ok(Some(doc)) => Ok(doc),
+        Ok(None) => {
+            state
+                .negative_cache
+                .record_missing(execution_id, org_id, Instant::now());
+            Err(ApiError::ExecutionNotFound)
+        },
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err(ApiError::from_store(&e, ApiError::Database))
+        },
+    }
+}
+
+/// How much of each node an execution response carries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExecutionView {
+    /// The whole stored document
+    #[default]
+    Full,
+    /// Node input, output, parameters and used inputs left out
+    Compact,
 }
 
-/// GET /executions/{execution_id} - Get a specific past execution
+/// Query params for GET /executions/{execution_id}
+#[derive(Debug, Deserialize)]
+pub(crate) struct ExecutionQuery {
+    /// Comma-separated node statuses to keep (case-insensitive)
+    status: Option<String>,
+    #[serde(default)]
+    view:   ExecutionView,
+    /// Comma-separated paths to return (see [`ExecutionFields`])
+    fields: Option<String>,
+}
+
+/// Load an execution projected to `fields`, with whatever the status filter
+/// and derived `progress` read selected too. Uses the negative cache like
+/// [`load_execution`].
+async fn load_execution_fields(
+    state: &AppState,
+    caller: &Caller,
+    execution_id: &str,
+    fields: &ExecutionFields,
+    filters_status: bool,
+) -> Result<ExecutionDocument, ApiError> {
+    let org_id = caller.org_id.as_deref();
+    check_negative_cache(state, caller, execution_id, org_id)?;
+    let mut load = fields.clone();
+    if filters_status || fields.top.contains("progress") {
+        load = load.with("nodes.latest.status");
+    }
+    match state
+        .execution_store
+        .get_execution_fields(execution_id, org_id, &load)
+        .await
+    {
+        Ok(Some(doc)) => Ok(doc),
+        Ok(None) => {
+            state
+                .negative_cache
+                .record_missing(execution_id, org_id, Instant::now());
+            Err(ApiError::ExecutionNotFound)
+        },
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err(ApiError::from_store(&e, ApiError::Database))
+        },
+    }
+}
+
+/// Keep only nodes whose latest status is one of the comma-separated
+/// `statuses`, compared case-insensitively.
+fn retain_nodes_with_status(doc: &mut ExecutionDocument, statuses: &str) {
+    let wanted: Vec<String> = statuses
+        .split(',')
+        .map(|status| status.trim().to_lowercase())
+        .filter(|status| !status.is_empty())
+        .collect();
+    doc.nodes.retain(|_, node| {
+        node.latest
+            .as_ref()
+            .and_then(|latest| latest.status.as_deref())
+            .is_some_and(|status| wanted.contains(&status.to_lowercase()))
+    });
+}
+
+/// Weak ETag for an execution document, from its last write time and
+/// workflow version. `None` for documents written before `updated_at` existed.
+fn execution_etag(doc: &ExecutionDocument) -> Option<HeaderValue> {
+    let updated_at = doc.updated_at?;
+    let etag = format!(
+        "W/\"{}-{}\"",
+        updated_at.timestamp_millis(),
+        doc.workflow_version_id.unwrap_or_default()
+    );
+    HeaderValue::from_str(&etag).ok()
+}
+
+/// Whether an `If-None-Match` header lists `etag` (or `*`), using the weak
+/// comparison conditional GETs call for.
+fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    let Ok(etag) = etag.to_str().map(opaque) else {
+        return false;
+    };
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// GET /executions/{execution_id} - Get a specific past execution. Responds
+/// 304 when `If-None-Match` carries the current ETag.
 pub(crate) async fn get_execution(
     State(state): State<AppState>,
     Path(execution_id): Path<String>,
+    Query(query): Query<ExecutionQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let fields = query
+        .fields
+        .as_deref()
+        .map(ExecutionFields::parse)
+        .transpose()
+        .map_err(|field| {
+            warn!(field = %field, "Unknown execution field requested");
+            ApiError::InvalidField
+        })?;
+    // Check access from the workflow_id alone before loading the document
+    let caller = request_caller(&headers)?;
+    authorize_execution_by_id(&state, &caller, &execution_id).await?;
+    let mut doc = match &fields {
+        Some(fields) => {
+            let mut doc = load_execution_fields(
+                &state,
+                &caller,
+                &execution_id,
+                fields,
+                query.status.is_some(),
+            )
+            .await?;
+            if query.view == ExecutionView::Compact {
+                doc.compact();
+            }
+            doc
+        },
+        None => load_execution(&state, &caller, &execution_id, query.view).await?,
+    };
+
+    let etag = execution_etag(&doc);
+    if let Some(etag) = etag.as_ref().filter(|etag| if_none_match(&headers, etag)) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag.clone())]).into_response());
+    }
+
+    if doc.progress.is_none() {
+        doc.progress = Some(ExecutionProgress::from_nodes(&doc.nodes));
+    }
+    if let Some(statuses) = query.status.as_deref() {
+        retain_nodes_with_status(&mut doc, statuses);
+    }
+    let mut response = match fields {
+        Some(fields) => {
+            let mut selected = serde_json::to_value(&doc).map_err(|e| {
+                error!("Failed to serialize execution: {}", e);
+                ApiError::Internal
+            })?;
+            fields.retain(&mut selected);
+            Json(selected).into_response()
+        },
+        None => Json(doc).into_response(),
+    };
+    if let Some(etag) = etag {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+    Ok(response)
+}
+
+/// Body of POST /executions:batch
+#[derive(Debug, Deserialize)]
+pub(crate) struct ExecutionBatchRequest {
+    execution_ids: Vec<String>,
+}
+
+/// One entry of a batch lookup: the execution, or `"not_found"` when it
+/// doesn't exist or the caller can't access it.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum ExecutionBatchEntry {
+    Found(Box<ExecutionDocument>),
+    Missing(ExecutionBatchMiss),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ExecutionBatchMiss {
+    NotFound,
+}
+
+/// POST /executions:batch - Several executions at once, keyed by id and
+/// fetched in one query. Executions the caller can't access are reported
+/// as `"not_found"`, like missing ones, instead of failing the whole batch,
+/// so the response doesn't reveal which ids exist.
+pub(crate) async fn get_executions_batch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ExecutionBatchRequest>,
+) -> Result<Json<BTreeMap<String, ExecutionBatchEntry>>, ApiError> {
+    let mut execution_ids = request.execution_ids;
+    execution_ids.sort_unstable();
+    execution_ids.dedup();
+    if execution_ids.len() > crate::config::Config::get().executions_batch_max {
+        return Err(ApiError::BatchTooLarge);
+    }
+    let caller = request_caller(&headers)?;
+
+    let docs = state
+        .execution_store
+        .get_execution_documents(&execution_ids, caller.org_id.as_deref())
+        .await
+        .map_err(|e| {
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database)
+        })?;
+    let checks = docs.into_iter().map(|doc| async {
+        let access = authorize_execution(
+            &state,
+            &caller,
+            &doc.execution_id,
+            &doc.workflow_id,
+            doc.org_id.as_deref(),
+        )
+        .await;
+        (doc, access)
+    });
+
+    let mut entries = BTreeMap::new();
+    for (mut doc, access) in future::join_all(checks).await {
+        match access {
+            Ok(()) => {
+                if doc.progress.is_none() {
+                    doc.progress = Some(ExecutionProgress::from_nodes(&doc.nodes));
+                }
+                entries.insert(doc.execution_id.clone(), ExecutionBatchEntry::Found(Box::new(doc)));
+            },
+            Err(ApiError::Forbidden | ApiError::Unauthorized) => {},
+            Err(e) => return Err(e),
+        }
+    }
+    for execution_id in execution_ids {
+        entries
+            .entry(execution_id)
+            .or_insert(ExecutionBatchEntry::Missing(ExecutionBatchMiss::NotFound));
+    }
+    Ok(Json(entries))
+}
+
+/// GET /executions/{execution_id}/nodes/{node_id} - One node's latest run and
+/// every lineage, without loading the rest of the execution
+pub(crate) async fn get_execution_node(
+    State(state): State<AppState>,
+    Path((execution_id, node_id)): Path<(String, String)>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    // First, fetch the execution to get its workflow_id for validation
-    let doc = match state
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = authorize_execution_by_id(&state, &caller, &execution_id).await {
+        return e.into_response();
+    }
+    let mut doc = match state
         .execution_store
-        .get_execution_document(&execution_id)
+        .get_node(&execution_id, caller.org_id.as_deref(), &node_id)
         .await
     {
         Ok(Some(doc)) => doc,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Execution not found").into_response(),
+        Ok(None) => return ApiError::ExecutionNotFound.into_response(),
         Err(e) => {
             error!("Database error: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response();
+            return ApiError::from_store(&e, ApiError::Database).into_response();
         },
     };
 
-    let workflow_id = &doc.workflow_id;
+    let Some(node) = doc.nodes.remove(&node_id) else {
+        return ApiError::NodeNotFound.into_response();
+    };
+    Json(node).into_response()
+}
+
+/// GET /executions/{execution_id}/nodes:stream - Every node with its runs as
+/// NDJSON, one `{node_id, node}` object per line, streamed as it's read so
+/// neither side holds a large execution at once
+pub(crate) async fn stream_execution_nodes(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let caller = request_caller(&headers)?;
+    authorize_execution_by_id(&state, &caller, &execution_id).await?;
+    let nodes = state
+        .execution_store
+        .stream_nodes(&execution_id, caller.org_id.as_deref())
+        .await
+        .map_err(|e| {
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database)
+        })?;
+    // A failure mid-stream can't change the status any more; it aborts the
+    // body so the client sees a truncated response rather than a clean end
+    let lines = nodes.map(move |entry| {
+        let entry = entry.inspect_err(|e| {
+            error!(execution_id = %execution_id, error = %e, "Node stream failed");
+        })?;
+        let mut line = serde_json::to_vec(&entry).map_err(|e| StoreError::Fatal(e.into()))?;
+        line.push(b'\n');
+        Ok::<_, StoreError>(line)
+    });
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(lines)).into_response())
+}
 
-    // Try JWT-based auth first
-    if let Some(jwt_result) = try_extract_user_id(&headers) {
-        match jwt_result {
-            Ok(user_id) => {
-                // Validate user has access to this execution
-                match state
-                    .token_store
-                    .validate_access_for_execution(&user_id, &execution_id)
-                    .await
-                {
-                    Ok(true) => return Json(doc).into_response(),
-                    Ok(false) => {
-                        warn!("Unauthorized access attempt for execution: {}", execution_id);
-                        return (StatusCode::FORBIDDEN, "Unauthorized").into_response();
-                    },
-                    Err(e) => {
-                        error!("Token validation error: {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Error")
-                            .into_response();
-                    },
-                }
+/// GET /executions/{execution_id}/splits/{split_node_id} - Every branch run
+/// of a loop/fan-out node, ordered by `item_index`, without payloads
+pub(crate) async fn get_execution_split(
+    State(state): State<AppState>,
+    Path((execution_id, split_node_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<SplitIterations>, ApiError> {
+    let caller = request_caller(&headers)?;
+    authorize_execution_by_id(&state, &caller, &execution_id).await?;
+    match state
+        .execution_store
+        .get_node_iterations(&execution_id, caller.org_id.as_deref(), &split_node_id)
+        .await
+    {
+        Ok(Some(split)) => Ok(Json(split)),
+        Ok(None) => Err(ApiError::ExecutionNotFound),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err(ApiError::from_store(&e, ApiError::Database))
+        },
+    }
+}
+
+/// GET /executions/{execution_id}/graph - The workflow's nodes and edges,
+/// without any run data, for drawing the DAG
+pub(crate) async fn get_execution_graph(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ExecutionGraph>, ApiError> {
+    let caller = request_caller(&headers)?;
+    authorize_execution_by_id(&state, &caller, &execution_id).await?;
+    let fields =
+        ExecutionFields { top: BTreeSet::from(["graph".to_owned()]), nodes: BTreeMap::new() };
+    let doc = load_execution_fields(&state, &caller, &execution_id, &fields, false).await?;
+    if let Some(graph) = doc.graph {
+        return Ok(Json(graph));
+    }
+
+    // Stored before the graph was: rebuild it from the node definitions
+    let doc = load_execution(&state, &caller, &execution_id, ExecutionView::Compact).await?;
+    Ok(Json(ExecutionGraph::from_document(&doc)))
+}
+
+/// Query params for GET /executions/{execution_id}/context
+#[derive(Debug, Deserialize)]
+pub(crate) struct ContextQuery {
+    /// Dotted path (`a.b.0`) or JSON pointer (`/a/b/0`) to a sub-tree
+    path: Option<String>,
+}
+
+/// JSON pointer for a context path: pointers pass through, dotted paths are
+/// split on `.` with `~` and `/` escaped per RFC 6901.
+fn context_pointer(path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        return path.to_string();
+    }
+    path.split('.').fold(String::new(), |mut pointer, segment| {
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        pointer
+    })
+}
+
+/// GET /executions/{execution_id}/context - The execution's
+/// `accumulated_context`, or the sub-tree at `?path=`
+pub(crate) async fn get_execution_context(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+    Query(query): Query<ContextQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = authorize_execution_by_id(&state, &caller, &execution_id).await {
+        return e.into_response();
+    }
+    // The compact view leaves node payloads in MongoDB; only the context is needed
+    let mut doc = match load_execution(&state, &caller, &execution_id, ExecutionView::Compact).await
+    {
+        Ok(doc) => doc,
+        Err(e) => return e.into_response(),
+    };
+
+    let pointer = context_pointer(query.path.as_deref().unwrap_or_default());
+    let Some(context) = doc.accumulated_context.pointer_mut(&pointer) else {
+        return ApiError::ContextPathNotFound.into_response();
+    };
+    Json(context.take()).into_response()
+}
+
+/// Query params for GET /executions/{execution_id}/events; same selection as
+/// the WebSocket
+#[derive(Debug, Deserialize)]
+pub(crate) struct ExecutionEventsQuery {
+    /// Comma-separated payload fields (`status,output,input,params,error`)
+    include:      Option<String>,
+    /// Only stream node events from this branch
+    branch_id:    Option<String>,
+    /// Only stream node events from this lineage
+    lineage_hash: Option<String>,
+    /// RFC 3339 timestamp; history replay skips runs executed at or before it
+    since:        Option<String>,
+}
+
+/// GET /executions/{execution_id}/events - Server-sent events alternative to
+/// the WebSocket: replays the stored execution, then streams live updates as
+/// `data:` frames in the WebSocket's JSON shape.
+pub(crate) async fn get_execution_events(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+    Query(query): Query<ExecutionEventsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let fields = match query.include.as_deref().map(WsFields::from_str) {
+        None => WsFields::default(),
+        Some(Ok(fields)) => fields,
+        Some(Err(e)) => {
+            warn!(execution_id = %execution_id, error = %e, "Rejecting event stream include");
+            return ApiError::InvalidQuery.into_response();
+        },
+    };
+    let since = match parse_since(query.since.as_deref()) {
+        Ok(since) => since,
+        Err(e) => {
+            warn!(execution_id = %execution_id, error = %e, "Rejecting event stream since");
+            return ApiError::InvalidQuery.into_response();
+        },
+    };
+
+    // Same as get_execution: check access from the workflow_id alone before
+    // loading the document
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = authorize_execution_by_id(&state, &caller, &execution_id).await {
+        return e.into_response();
+    }
+    // Subscribe before reading history so nothing written in between is lost
+    let rx = state.tx.subscribe();
+    let doc = match load_execution(&state, &caller, &execution_id, ExecutionView::Full).await {
+        Ok(doc) => doc,
+        Err(e) => return e.into_response(),
+    };
+
+    let params = WsParams::new(
+        execution_id,
+        doc.org_id.clone(),
+        fields,
+        WsFilter { branch_id: query.branch_id, lineage_hash: query.lineage_hash },
+        since,
+    );
+    let mut history = params.history_frames(doc);
+    history.extend(WsControl::SnapshotComplete { execution_id: None }.frame());
+    let live = BroadcastStream::new(rx).filter_map(move |next| {
+        let frame = match next {
+            Ok(msg) => params.live_frame(&msg),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                warn!(
+                    execution_id = %params.execution_id,
+                    skipped,
+                    "SSE receiver lagged; skipping stale messages"
+                );
+                WsControl::Resync { skipped }.frame()
             },
-            Err(e) => return e.into_response(),
-        }
+        };
+        future::ready(frame)
+    });
+    let events = stream::iter(history)
+        .chain(live)
+        .map(|json| Ok::<_, Infallible>(Event::default().data(json)));
+
+    let heartbeat = Duration::from_secs(crate::config::Config::get().ws_heartbeat_secs.max(1));
+    Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(heartbeat))
+        .into_response()
+}
+
+/// DELETE /executions/{execution_id} - Delete an execution and its status log
+pub(crate) async fn delete_execution(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    // Deleting needs an owner grant; viewers may only read
+    if let Err(e) =
+        authorize_execution_by_id_as(&state, &caller, &execution_id, TokenScope::Owner).await
+    {
+        return e.into_response();
     }
 
-    // Fallback: Token-based auth (execution_id + workflow_id validation)
-    info!("No JWT provided, trying token-based auth for execution {}", execution_id);
     match state
-        .token_store
-        .validate_execution_access(&execution_id, workflow_id)
+        .execution_store
+        .delete_execution(&execution_id, caller.org_id.as_deref())
         .await
     {
-        Ok(true) => Json(doc).into_response(),
-        Ok(false) => {
-            warn!("Unauthorized access attempt for execution: {}", execution_id);
-            (StatusCode::UNAUTHORIZED, "Unauthorized").into_response()
+        Ok(true) => {
+            info!("Deleted execution {}", execution_id);
+            StatusCode::NO_CONTENT.into_response()
         },
+        Ok(false) => ApiError::ExecutionNotFound.into_response(),
         Err(e) => {
-            error!("Token validation error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Internal Error").into_response()
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database).into_response()
         },
     }
 }
 
-/// GET /workflows/{workflow_id}/executions - Get all past executions for a
-/// workflow
-pub(crate) async fn get_workflow_executions(
+/// Query params for GET /executions/{execution_id}/statuses
+#[derive(Debug, Deserialize)]
+pub(crate) struct StatusHistoryQuery {
+    limit:  Option<usize>,
+    after:  Option<String>,
+    /// Entries to skip, counted from `after` when both are given
+    offset: Option<u64>,
+}
+
+const DEFAULT_STATUS_PAGE_SIZE: usize = 100;
+const MAX_STATUS_PAGE_SIZE: usize = 1000;
+
+/// Response header carrying the cursor for the next page, when there is one.
+pub(crate) const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+/// GET /executions/{execution_id}/statuses (alias `/status-history`) - Page
+/// through the node status log of an execution in `executed_at` order, by
+/// cursor or offset
+pub(crate) async fn get_execution_statuses(
     State(state): State<AppState>,
-    Path(workflow_id): Path<String>,
+    Path(execution_id): Path<String>,
+    Query(query): Query<StatusHistoryQuery>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
+    let after = match query.after.as_deref().map(StatusCursor::decode) {
+        Some(None) => return ApiError::InvalidCursor.into_response(),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_STATUS_PAGE_SIZE)
+        .clamp(1, MAX_STATUS_PAGE_SIZE);
+
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = authorize_execution_by_id(&state, &caller, &execution_id).await {
+        return e.into_response();
+    }
+
+    // Fetch one extra entry to learn whether another page exists
+    let fetch_limit = i64::try_from(limit + 1).unwrap_or(i64::MAX);
+    let mut records = match state
+        .execution_store
+        .get_status_history(
+            &execution_id,
+            caller.org_id.as_deref(),
+            after.as_ref(),
+            query.offset.unwrap_or(0),
+            fetch_limit,
+        )
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return ApiError::from_store(&e, ApiError::Database).into_response();
+        },
+    };
+
+    let next_cursor = if records.len() > limit {
+        records.truncate(limit);
+        records.last().map(StatusCursor::after)
+    } else {
+        None
+    };
+
+    let mut response = Json(records).into_response();
+    if let Some(cursor) = next_cursor
+        && let Ok(value) = HeaderValue::from_str(&cursor.encode())
+    {
+        response.headers_mut().insert(NEXT_CURSOR_HEADER, value);
+    }
+    response
+}
+
+/// Authorize access to a workflow's executions: the JWT user's grants when
+/// the caller presented a JWT, otherwise the wildcard workflow token index.
+/// Either way only grants of the caller's organization count.
+async fn authorize_workflow(
+    state: &AppState,
+    caller: &Caller,
+    workflow_id: &str,
+) -> Result<(), ApiError> {
+    let org_id = caller.org_id.as_deref();
     // Try JWT-based auth first
-    if let Some(jwt_result) = try_extract_user_id(&headers) {
-        match jwt_result {
-            Ok(user_id) => {
-                // Validate user has access to this workflow (wildcard or specific execution
-                // grant)
-                match state
-                    .token_store
-                    .validate_access(&user_id, None, &workflow_id)
-                    .await
-                {
-                    Ok(true) => {
-                        return match state
-                            .execution_store
-                            .get_executions_for_workflow(&workflow_id)
-                            .await
-                        {
-                            Ok(executions) => Json(executions).into_response(),
-                            Err(e) => {
-                                error!("Database error: {}", e);
-                                (StatusCode::INTERNAL_SERVER_ERROR, "Database Error")
-                                    .into_response()
-                            },
-                        };
-                    },
-                    Ok(false) => {
-                        warn!("Unauthorized access attempt for workflow: {}", workflow_id);
-                        return (StatusCode::FORBIDDEN, "Unauthorized").into_response();
-                    },
-                    Err(e) => {
-                        error!("Token validation error: {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal Error")
-                            .into_response();
-                    },
-                }
+    if let Some(user_id) = caller.user_id.as_deref() {
+        // Validate user has access to this workflow (wildcard or specific execution
+        // grant)
+        return match state
+            .token_store
+            .validate_access(user_id, None, workflow_id, org_id, TokenScope::Viewer)
+            .await
+        {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                record_auth_failure(
+                    AuthFailure::NoGrant,
+                    Some(user_id),
+                    &format!("No grant for workflow {workflow_id}"),
+                );
+                Err(ApiError::Forbidden)
             },
-            Err(e) => return e.into_response(),
-        }
+            Err(e) => {
+                error!("Token validation error: {}", e);
+                Err(ApiError::from_store(&e, ApiError::Internal))
+            },
+        };
     }
 
     // Fallback: Token-based auth (workflow_id validation via Redis index)
     info!("No JWT provided, trying token-based auth for workflow {}", workflow_id);
     match state
         .token_store
-        .validate_workflow_access(&workflow_id)
+        .validate_workflow_access(workflow_id, org_id)
         .await
     {
-        Ok(true) => match state
-            .execution_store
-            .get_executions_for_workflow(&workflow_id)
-            .await
-        {
-            Ok(executions) => Json(executions).into_response(),
-            Err(e) => {
-                error!("Database error: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response()
-            },
-        },
+        Ok(true) => Ok(()),
         Ok(false) => {
-            warn!("Unauthorized access attempt for workflow: {}", workflow_id);
-            (StatusCode::UNAUTHORIZED, "Unauthorized").into_response()
+            record_auth_failure(
+                AuthFailure::NoGrant,
+                None,
+                &format!("No grant for workflow {workflow_id}"),
+            );
+            Err(ApiError::Unauthorized)
         },
         Err(e) => {
             error!("Token validation error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Internal Error").into_response()
+            Err(ApiError::from_store(&e, ApiError::Internal))
         },
     }
 }
 
-#[cfg(test)]
-#[allow(clippy::expect_used)]
-mod tests {
-    use std::collections::HashMap;
+/// Query params for GET /workflows/{workflow_id}/executions
+#[derive(Debug, Deserialize)]
+pub(crate) struct WorkflowExecutionsQuery {
+    limit:     Option<usize>,
+    after:     Option<String>,
+    /// Only list executions where a node of this type ran
+    node_type: Option<String>,
+}
 
-    use axum::http::HeaderMap;
-    use jsonwebtoken::{EncodingKey, Header, encode};
+const DEFAULT_WORKFLOW_EXECUTIONS_PAGE_SIZE: usize = 50;
+const MAX_WORKFLOW_EXECUTIONS_PAGE_SIZE: usize = 200;
+
+/// GET /workflows/{workflow_id}/executions - Page through past executions of a
+/// workflow, newest first
+pub(crate) async fn get_workflow_executions(
+    State(state): State<AppState>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<WorkflowExecutionsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ExecutionPage>, ApiError> {
+    let after = match query.after.as_deref().map(ExecutionCursor::decode) {
+        Some(None) => return Err(ApiError::InvalidCursor),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_WORKFLOW_EXECUTIONS_PAGE_SIZE)
+        .clamp(1, MAX_WORKFLOW_EXECUTIONS_PAGE_SIZE);
+
+    let caller = request_caller(&headers)?;
+    authorize_workflow(&state, &caller, &workflow_id).await?;
+
+    state
+        .execution_store
+        .get_executions_for_workflow_paginated(
+            &workflow_id,
+            caller.org_id.as_deref(),
+            after.as_ref(),
+            query.node_type.as_deref(),
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database)
+        })
+}
 
-    use super::{Claims, try_extract_user_id};
-    use crate::config::Config;
+/// Query params for GET /workflows/{workflow_id}/stats
+#[derive(Debug, Deserialize)]
+pub(crate) struct WorkflowStatsQuery {
+    /// Only count executions where a node of this type ran
+    node_type: Option<String>,
+}
 
-    fn ensure_config_initialized() {
-        let _ = Config::init();
+/// GET /workflows/{workflow_id}/stats - Status counts, durations and last
+/// run across a workflow's executions
+pub(crate) async fn get_workflow_stats(
+    State(state): State<AppState>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<WorkflowStatsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = authorize_workflow(&state, &caller, &workflow_id).await {
+        return e.into_response();
     }
 
-    #[test]
-    fn missing_auth_header_returns_none() {
-        ensure_config_initialized();
-        let headers = HeaderMap::new();
-        assert!(try_extract_user_id(&headers).is_none());
+    match state
+        .execution_store
+        .workflow_stats(&workflow_id, caller.org_id.as_deref(), query.node_type.as_deref())
+        .await
+    {
+        Ok(workflow_stats) => Json(workflow_stats).into_response(),
+        Err(e) => {
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database).into_response()
+        },
     }
+}
 
-    #[test]
-    fn invalid_jwt_returns_unauthorized_error() {
-        ensure_config_initialized();
-        let mut headers = HeaderMap::new();
-        headers.insert("Authorization", "Bearer invalid.token.value".parse().expect("header"));
+/// Query params for GET /executions
+#[derive(Debug, Deserialize)]
+pub(crate) struct ListExecutionsQuery {
+    limit:  Option<usize>,
+    offset: Option<u64>,
+}
 
-        let result = try_extract_user_id(&headers);
-        assert!(result.is_some());
-        let err = result
-            .expect("result exists")
-            .expect_err("jwt should be invalid");
-        assert_eq!(err.0, axum::http::StatusCode::UNAUTHORIZED);
-    }
+const DEFAULT_EXECUTIONS_PAGE_SIZE: usize = 50;
+const MAX_EXECUTIONS_PAGE_SIZE: usize = 500;
 
-    #[test]
-    fn valid_jwt_extracts_sub_claim() {
-        ensure_config_initialized();
-        let token = encode(
-            &Header::default(),
-            &Claims { sub: "user-42".to_string(), exp: usize::MAX / 2, extra: HashMap::new() },
-            &EncodingKey::from_secret(Config::get().jwt_secret.as_bytes()),
+/// GET /executions - List summaries of every execution the authenticated user
+/// holds a grant for, newest first
+pub(crate) async fn list_user_executions(
+    State(state): State<AppState>,
+    Query(query): Query<ListExecutionsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    let Some(user_id) = caller.user_id.as_deref() else {
+        return ApiError::Unauthorized.into_response();
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_EXECUTIONS_PAGE_SIZE)
+        .clamp(1, MAX_EXECUTIONS_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0);
+
+    let scope = match state.token_store.list_user_tokens(user_id).await {
+        Ok(mut tokens) => {
+            tokens.retain(|token| token.in_org(caller.org_id.as_deref()));
+            ExecutionAccessScope::from_tokens(&tokens)
+        },
+        Err(e) => {
+            error!("Token lookup error: {}", e);
+            return ApiError::from_store(&e, ApiError::Internal).into_response();
+        },
+    };
+
+    match state
+        .execution_store
+        .list_executions_for_user(
+            &scope,
+            caller.org_id.as_deref(),
+            i64::try_from(limit).unwrap_or(i64::MAX),
+            offset,
         )
-        .expect("token encoding should succeed");
+        .await
+    {
+        Ok(executions) => Json(executions).into_response(),
+        Err(e) => {
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database).into_response()
+        },
+    }
+}
+
+/// GET /metrics - Prometheus scrape endpoint for consumer, status write and
+/// WebSocket activity. Internal route.
+pub(crate) async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], crate::infra::prometheus::render())
+}
+
+/// GET /connections - Number of open `/rt` WebSockets. A high count next to
+/// resync frames points at slow subscribers lagging the broadcast. Internal
+/// route.
+pub(crate) async fn connections(State(state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "active": state.connections.load(std::sync::atomic::Ordering::Relaxed),
+    }))
+}
+
+/// GET /queues - Ready, unacked and dead-lettered messages per consumed
+/// queue as of the last poll, to tell whether the consumers keep up.
+/// Internal route.
+pub(crate) async fn queues(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.queues.snapshot())
+}
+
+/// Query params for GET /dlq/{queue}
+#[derive(Debug, Deserialize)]
+pub(crate) struct DlqQuery {
+    limit: Option<usize>,
+}
+
+const DEFAULT_DLQ_BATCH: usize = 10;
+
+/// GET /dlq/{queue} - Peek at dead-lettered messages without removing them.
+/// Admin route; requires the `x-admin-token` shared secret, and only the
+/// `.dlq` queues of the RTES consumers and `parse-errors` are readable.
+pub(crate) async fn peek_dlq(
+    Path(queue): Path<String>,
+    Query(query): Query<DlqQuery>,
+) -> impl IntoResponse {
+    if queue != PARSE_ERRORS_QUEUE && dlq::source_queue(&queue).is_none() {
+        return ApiError::UnknownDlq.into_response();
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DLQ_BATCH)
+        .clamp(1, dlq::MAX_BATCH);
+
+    match dlq::peek(&crate::config::Config::get().amqp_url, &queue, limit).await {
+        Ok(messages) => {
+            Json(serde_json::json!({ "queue": queue, "messages": messages })).into_response()
+        },
+        Err(e) => {
+            error!(queue = %queue, error = %e, "DLQ peek failed");
+            ApiError::Broker.into_response()
+        },
+    }
+}
+
+/// Query params for POST /dlq/{queue}/replay
+#[derive(Debug, Deserialize)]
+pub(crate) struct DlqReplayQuery {
+    batch: Option<usize>,
+}
+
+/// POST /dlq/{queue}/replay - Republish up to `batch` dead-lettered messages
+/// to the queue they were rejected from. Admin route; requires the
+/// `x-admin-token` shared secret.
+pub(crate) async fn replay_dlq(
+    Path(queue): Path<String>,
+    Query(query): Query<DlqReplayQuery>,
+) -> impl IntoResponse {
+    let Some(source) = dlq::source_queue(&queue) else {
+        return ApiError::UnknownDlq.into_response();
+    };
+    let batch = query
+        .batch
+        .unwrap_or(DEFAULT_DLQ_BATCH)
+        .clamp(1, dlq::MAX_BATCH);
+
+    match dlq::replay(&crate::config::Config::get().amqp_url, &queue, source, batch).await {
+        Ok(summary) => {
+            if let Some(e) = &summary.error {
+                warn!(queue = %queue, error = %e, "DLQ replay stopped early");
+            }
+            info!(queue = %queue, replayed = summary.replayed, "DLQ replay finished");
+            Json(serde_json::json!({ "queue": queue, "target": source, "summary": summary }))
+                .into_response()
+        },
+        Err(e) => {
+            error!(queue = %queue, error = %e, "DLQ replay failed");
+            ApiError::Broker.into_response()
+        },
+    }
+}
+
+/// Header carrying the shared secret for the admin routes.
+pub(crate) const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+/// Check the admin secret header against the configured `RTES_ADMIN_TOKEN`:
+/// [`ApiError::Unauthorized`] when it is missing or wrong. Without a
+/// configured secret the routes stay disabled ([`ApiError::Forbidden`]).
+fn authorize_admin(headers: &HeaderMap, expected: Option<&str>) -> Result<(), ApiError> {
+    let Some(expected) = expected else {
+        return Err(ApiError::Forbidden);
+    };
+    let provided = headers
+        .get(ADMIN_TOKEN_HEADER)
+        .map(HeaderValue::as_bytes)
+        .ok_or(ApiError::Unauthorized)?;
+    if constant_time_eq(provided, expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized)
+    }
+}
+
+/// Middleware refusing requests without the admin secret, see
+/// [`authorize_admin`]. Every admin route sits behind it rather than
+/// checking the secret itself, so none can be added unguarded.
+pub(crate) async fn require_admin(request: Request, next: Next) -> Response {
+    match authorize_admin(request.headers(), crate::config::Config::get().admin_token.as_deref()) {
+        Ok(()) => next.run(request).await,
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+/// Compare secrets without short-circuiting on the first differing byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// POST /tokens/{jti}/revoke - Revoke an execution token so it stops granting
+/// access. Admin route; requires the `x-admin-token` shared secret.
+pub(crate) async fn revoke_token(
+    State(state): State<AppState>,
+    Path(jti): Path<String>,
+) -> impl IntoResponse {
+    match state.token_store.revoke_token(&jti).await {
+        Ok(true) => {
+            info!("Revoked execution token {}", jti);
+            StatusCode::NO_CONTENT.into_response()
+        },
+        Ok(false) => ApiError::TokenNotFound.into_response(),
+        Err(e) => {
+            error!("Token store error: {}", e);
+            ApiError::from_store(&e, ApiError::Internal).into_response()
+        },
+    }
+}
+
+/// POST /executions/{execution_id}/repair - Rewrite an execution stored in
+/// an older document shape into the current one, for documents no status
+/// write will touch again. Admin route; requires the `x-admin-token` shared
+/// secret, and only finds executions of the caller's organization.
+pub(crate) async fn repair_execution(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let caller = match request_caller(&headers) {
+        Ok(caller) => caller,
+        Err(e) => return e.into_response(),
+    };
+    match state
+        .execution_store
+        .repair_execution(&execution_id, caller.org_id.as_deref())
+        .await
+    {
+        Ok(true) => {
+            info!("Repaired execution {}", execution_id);
+            StatusCode::NO_CONTENT.into_response()
+        },
+        Ok(false) => ApiError::ExecutionNotFound.into_response(),
+        Err(StoreError::Conflict(e)) => {
+            warn!(execution_id = %execution_id, error = %e, "Execution repair gave up");
+            ApiError::ExecutionChanged.into_response()
+        },
+        Err(e) => {
+            error!("Database error: {}", e);
+            ApiError::from_store(&e, ApiError::Database).into_response()
+        },
+    }
+}
 
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use axum::http::HeaderMap;
+
+    use super::{ADMIN_TOKEN_HEADER, ApiError, authorize_admin, context_pointer};
+
+    #[test]
+    fn admin_routes_require_the_configured_secret() {
         let mut headers = HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            format!("Bearer {token}")
-                .parse()
-                .expect("authorization header should parse"),
-        );
+        assert_eq!(authorize_admin(&headers, None), Err(ApiError::Forbidden));
+        assert_eq!(authorize_admin(&headers, Some("s3cret")), Err(ApiError::Unauthorized));
+
+        headers.insert(ADMIN_TOKEN_HEADER, "wrong".parse().expect("header"));
+        assert_eq!(authorize_admin(&headers, Some("s3cret")), Err(ApiError::Unauthorized));
 
-        let result = try_extract_user_id(&headers).expect("auth header exists");
-        assert_eq!(result.expect("jwt should be valid"), "user-42");
+        headers.insert(ADMIN_TOKEN_HEADER, "s3cret".parse().expect("header"));
+        assert_eq!(authorize_admin(&headers, Some("s3cret")), Ok(()));
+        assert_eq!(authorize_admin(&headers, None), Err(ApiError::Forbidden));
+    }
+
+    #[test]
+    fn context_paths_map_to_json_pointers() {
+        assert_eq!(context_pointer(""), "");
+        assert_eq!(context_pointer("a.b.0"), "/a/b/0");
+        assert_eq!(context_pointer("/a/b"), "/a/b");
+        assert_eq!(context_pointer("a/b.c~d"), "/a~1b/c~0d");
     }
 }
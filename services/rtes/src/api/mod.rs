@@ -1,4 +1,8 @@
+pub mod auth;
+pub mod error;
 pub mod handlers;
+pub mod negative_cache;
+pub mod rate_limit;
 pub mod routes;
 pub mod state;
 pub mod ws;
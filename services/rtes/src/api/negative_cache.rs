@@ -0,0 +1,173 @@
+//! Short-lived memory of execution lookups that ended in `404` or an access
+//! denial, so repeated probes of the same id skip MongoDB.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::api::error::ApiError;
+
+/// Entries kept at most; once full, expired entries are dropped and new
+/// outcomes go uncached until there is room again.
+const MAX_ENTRIES: usize = 10_000;
+
+/// An execution as one organization sees it; another organization may
+/// store an execution under the same id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Execution {
+    id:  String,
+    org: Option<String>,
+}
+
+impl Execution {
+    fn new(execution_id: &str, org_id: Option<&str>) -> Self {
+        Self { id: execution_id.to_owned(), org: org_id.map(str::to_owned) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Probe {
+    /// No such execution, whoever asks
+    Missing(Execution),
+    /// This caller (JWT user, or `None` without a JWT) was refused it
+    Denied { execution: Execution, caller: Option<String> },
+}
+
+/// Negative outcomes of execution lookups, each forgotten after the TTL.
+///
+/// Access grants are never cached, so a cached entry can only delay a newly
+/// created execution or grant by up to the TTL. A disabled cache (the
+/// default) remembers nothing.
+#[derive(Clone, Debug, Default)]
+pub struct NegativeCache {
+    ttl:     Option<Duration>,
+    entries: Arc<Mutex<HashMap<Probe, (ApiError, Instant)>>>,
+}
+
+impl NegativeCache {
+    /// Remember outcomes for `ttl`; zero disables the cache.
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl: (!ttl.is_zero()).then_some(ttl), entries: Arc::default() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Probe, (ApiError, Instant)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// The remembered outcome of `caller` looking up `execution_id` in
+    /// `org_id`, if it hasn't expired.
+    pub(crate) fn get(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        caller: Option<&str>,
+        now: Instant,
+    ) -> Option<ApiError> {
+        self.ttl?;
+        let entries = self.lock();
+        let execution = Execution::new(execution_id, org_id);
+        let denied =
+            Probe::Denied { execution: execution.clone(), caller: caller.map(str::to_owned) };
+        [Probe::Missing(execution), denied]
+            .iter()
+            .filter_map(|probe| entries.get(probe))
+            .find(|(_, expires_at)| *expires_at > now)
+            .map(|(error, _)| *error)
+    }
+
+    /// Remember that `execution_id` doesn't exist in `org_id`.
+    pub(crate) fn record_missing(&self, execution_id: &str, org_id: Option<&str>, now: Instant) {
+        let probe = Probe::Missing(Execution::new(execution_id, org_id));
+        self.insert(probe, ApiError::ExecutionNotFound, now);
+    }
+
+    /// Remember that `caller` was refused `execution_id` of `org_id` with
+    /// `error`.
+    pub(crate) fn record_denied(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        caller: Option<&str>,
+        error: ApiError,
+        now: Instant,
+    ) {
+        let probe = Probe::Denied {
+            execution: Execution::new(execution_id, org_id),
+            caller:    caller.map(str::to_owned),
+        };
+        self.insert(probe, error, now);
+    }
+
+    fn insert(&self, probe: Probe, error: ApiError, now: Instant) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        let mut entries = self.lock();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+        if entries.len() < MAX_ENTRIES {
+            entries.insert(probe, (error, now + ttl));
+        }
+    }
+
+    /// Drop every outcome remembered for `execution_id`, in any
+    /// organization, e.g. once it is stored.
+    pub fn forget(&self, execution_id: &str) {
+        if self.ttl.is_none() {
+            return;
+        }
+        self.lock().retain(|probe, _| match probe {
+            Probe::Missing(execution) | Probe::Denied { execution, .. } => {
+                execution.id != execution_id
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::NegativeCache;
+    use crate::api::error::ApiError;
+
+    #[test]
+    fn outcomes_expire_are_per_caller_and_forgotten_on_creation() {
+        let cache = NegativeCache::new(Duration::from_secs(2));
+        let now = Instant::now();
+
+        cache.record_missing("exec-1", None, now);
+        assert_eq!(cache.get("exec-1", None, None, now), Some(ApiError::ExecutionNotFound));
+        assert_eq!(
+            cache.get("exec-1", None, Some("user-1"), now),
+            Some(ApiError::ExecutionNotFound)
+        );
+        assert_eq!(cache.get("exec-1", None, None, now + Duration::from_secs(2)), None);
+        // Missing in one organization says nothing about another's
+        assert_eq!(cache.get("exec-1", Some("org-1"), None, now), None);
+
+        // A denial only answers the caller that was refused
+        cache.record_denied("exec-2", Some("org-1"), Some("user-1"), ApiError::Forbidden, now);
+        assert_eq!(
+            cache.get("exec-2", Some("org-1"), Some("user-1"), now),
+            Some(ApiError::Forbidden)
+        );
+        assert_eq!(cache.get("exec-2", Some("org-1"), Some("user-2"), now), None);
+        assert_eq!(cache.get("exec-2", Some("org-1"), None, now), None);
+        assert_eq!(cache.get("exec-2", Some("org-2"), Some("user-1"), now), None);
+
+        cache.forget("exec-1");
+        cache.forget("exec-2");
+        assert_eq!(cache.get("exec-1", None, None, now), None);
+        assert_eq!(cache.get("exec-2", Some("org-1"), Some("user-1"), now), None);
+
+        let disabled = NegativeCache::new(Duration::ZERO);
+        disabled.record_missing("exec-1", None, now);
+        assert_eq!(disabled.get("exec-1", None, None, now), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Per-client token buckets throttling the public HTTP routes.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::api::{auth::peek_user_id, error::ApiError, state::AppState};
+
+/// Probe routes; never throttled.
+const UNLIMITED_ROUTES: [&str; 2] = ["/health", "/readyz"];
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    /// Time one request takes to refill
+    interval:  Duration,
+    /// How far past `now` a bucket may be booked: `burst - 1` intervals
+    tolerance: Duration,
+}
+
+/// Token buckets keyed by client. Each is kept as the instant it will be
+/// full again (GCRA), so refills need no per-request bookkeeping. A disabled
+/// limiter (the default) lets every request through.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimiter {
+    limit:           Option<Limit>,
+    /// When each client's bucket is full again
+    buckets:         Arc<Mutex<HashMap<String, Instant>>>,
+    /// Peers whose forwarded headers name the client
+    trusted_proxies: Arc<[IpAddr]>,
+}
+
+impl RateLimiter {
+    /// `rate` requests per second per client, in bursts of up to `burst`
+    /// (at least 1). A `rate` of 0 disables limiting.
+    pub fn new(rate: u32, burst: u32) -> Self {
+        let limit = (rate > 0).then(|| {
+            let interval = Duration::from_secs(1) / rate;
+            Limit { interval, tolerance: interval * burst.saturating_sub(1) }
+        });
+        Self { limit, buckets: Arc::default(), trusted_proxies: Arc::default() }
+    }
+
+    /// Believe `X-Forwarded-For`/`X-Real-IP` only from these peers.
+    #[must_use]
+    pub fn with_trusted_proxies(mut self, proxies: &[IpAddr]) -> Self {
+        self.trusted_proxies = proxies.into();
+        self
+    }
+
+    pub const fn is_enabled(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Instant>> {
+        self.buckets
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Spend one request from `key`'s bucket, or return how long until the
+    /// bucket holds one again.
+    pub(crate) fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        let mut buckets = self.lock();
+        let full_at = buckets.entry(key.to_owned()).or_insert(now);
+        let booked = full_at.saturating_duration_since(now);
+        let allowed = if booked > limit.tolerance {
+            Err(booked.saturating_sub(limit.tolerance))
+        } else {
+            *full_at = now.max(*full_at) + limit.interval;
+            Ok(())
+        };
+        drop(buckets);
+        allowed
+    }
+
+    /// Forget buckets that have refilled completely; a new bucket starts
+    /// full, so this changes no decision. Returns how many were dropped.
+    pub fn evict_idle(&self, now: Instant) -> usize {
+        let mut buckets = self.lock();
+        let before = buckets.len();
+        buckets.retain(|_, full_at| *full_at > now);
+        before - buckets.len()
+    }
+
+    /// Periodically evict idle buckets until `cancel_token` fires.
+    pub fn spawn_sweeper(&self, interval: Duration, cancel_token: CancellationToken) {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    () = cancel_token.cancelled() => break,
+                    _ = ticker.tick() => {
+                        let evicted = limiter.evict_idle(Instant::now());
+                        if evicted > 0 {
+                            debug!(evicted, "Evicted idle rate limit buckets");
+                        }
+                    },
+                }
+            }
+        });
+    }
+
+    /// Bucket key for a request: the user of a valid JWT, otherwise the
+    /// client IP. Forwarded headers are only believed from a trusted proxy;
+    /// any other peer is keyed by its own address, so a client can't pick
+    /// its bucket by setting them.
+    fn client_key(&self, request: &Request) -> String {
+        if let Some(user_id) = peek_user_id(request.headers()) {
+            return format!("user:{user_id}");
+        }
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let client = match peer {
+            Some(peer) if self.trusted_proxies.contains(&peer) => {
+                self.forwarded_ip(request.headers()).or(Some(peer))
+            },
+            _ => peer,
+        };
+        client.map_or_else(|| "ip:unknown".to_owned(), |ip| format!("ip:{ip}"))
+    }
+
+    /// The client a trusted proxy forwarded for: the last `X-Forwarded-For`
+    /// hop that isn't a trusted proxy itself (earlier hops are whatever the
+    /// client sent), else `X-Real-IP`.
+    fn forwarded_ip(&self, headers: &HeaderMap) -> Option<IpAddr> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let hops: Vec<IpAddr> = header("x-forwarded-for")
+            .into_iter()
+            .flat_map(|hops| hops.split(','))
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+        hops.iter()
+            .rev()
+            .find(|hop| !self.trusted_proxies.contains(hop))
+            .or_else(|| hops.first())
+            .copied()
+            .or_else(|| header("x-real-ip")?.trim().parse().ok())
+    }
+}
+
+/// Answer `429` with `Retry-After` once the client's bucket is empty.
+pub(crate) async fn rate_limit(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let exempt = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| UNLIMITED_ROUTES.contains(&path.as_str()));
+    if exempt || !state.rate_limiter.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let key = state.rate_limiter.client_key(&request);
+    match state.rate_limiter.check(&key, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            warn!(client = %key, "Rate limit exceeded");
+            let retry_after = (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).max(1);
+            ([(RETRY_AFTER, retry_after.to_string())], ApiError::RateLimited).into_response()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[test]
+    fn buckets_refill_at_the_configured_rate_and_idle_ones_are_evicted() {
+        let limiter = RateLimiter::new(2, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check("user:a", start), Ok(()));
+        }
+        assert_eq!(limiter.check("user:a", start), Err(Duration::from_millis(500)));
+        // Other clients have their own bucket
+        assert_eq!(limiter.check("user:b", start), Ok(()));
+
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.check("user:a", later), Ok(()));
+        assert!(limiter.check("user:a", later).is_err());
+
+        // user:b is full again and can be forgotten; user:a is still empty
+        assert_eq!(limiter.evict_idle(later), 1);
+        assert_eq!(limiter.evict_idle(later + Duration::from_secs(2)), 1);
+
+        let disabled = RateLimiter::new(0, 3);
+        assert!(!disabled.is_enabled());
+        assert!((0..100).all(|_| disabled.check("user:a", start).is_ok()));
+    }
+}
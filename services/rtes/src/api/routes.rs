@@ -1,22 +1,170 @@
 use axum::{
     Router,
-    http::{HeaderValue, Method},
-    routing::get,
+    extract::{DefaultBodyLimit, MatchedPath, RawPathParams, Request},
+    http::{HeaderName, HeaderValue, Method},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
 };
-use tower_http::cors::CorsLayer;
+use tower_http::{compression::CompressionLayer, cors::CorsLayer, limit::RequestBodyLimitLayer};
+use tracing::{Instrument, field};
 
 use crate::{
-    api::{handlers, state::AppState, ws},
-    config::Config,
+    api::{handlers, rate_limit, state::AppState, ws},
+    config::{Config, DEFAULT_CORS_ORIGIN},
 };
 
+/// Single-port router serving the public API together with the internal
+/// (metrics/admin) routes. Used when `ADMIN_PORT` is not set.
+///
+/// Reachable by API clients here, the monitoring routes require the admin
+/// token like the other admin routes.
 pub fn app(state: AppState) -> Router {
+    let monitoring = monitoring_routes().route_layer(middleware::from_fn(handlers::require_admin));
+    with_cors(api_routes(&state).merge(admin_routes()).merge(monitoring)).with_state(state)
+}
+
+/// Public API/WebSocket router without the internal routes, served on `PORT`
+/// when `ADMIN_PORT` is set.
+pub fn public_app(state: AppState) -> Router {
+    with_cors(api_routes(&state)).with_state(state)
+}
+
+/// Internal-only router served on `ADMIN_PORT` so operators can firewall it.
+pub fn admin_app(state: AppState) -> Router {
+    admin_routes()
+        .merge(monitoring_routes())
+        .route("/health", get(handlers::health_check))
+        .route("/readyz", get(handlers::readiness_check))
+        .with_state(state)
+}
+
+fn api_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/health", get(handlers::health_check))
+        .route("/readyz", get(handlers::readiness_check))
+        // WebSocket: Real-time updates for specific execution
+        // Uses query params: ?execution_id=...&workflow_id=...
+        .route("/rt", get(ws::ws_handler))
+        // HTTP: List executions the authenticated user has a grant for
+        .route("/executions", get(handlers::list_user_executions))
+        // HTTP: Several executions by id in one request
+        .route("/executions:batch", post(handlers::get_executions_batch))
+        // HTTP: Get or delete a specific past execution
+        .route(
+            "/executions/{execution_id}",
+            get(handlers::get_execution).delete(handlers::delete_execution),
+        )
+        // SSE: History replay then live updates, for clients that can't use /rt
+        .route("/executions/{execution_id}/events", get(handlers::get_execution_events))
+        // HTTP: One node's latest run and lineages
+        .route(
+            "/executions/{execution_id}/nodes/{node_id}",
+            get(handlers::get_execution_node),
+        )
+        // HTTP: Every node of an execution as NDJSON, for very large executions
+        .route(
+            "/executions/{execution_id}/nodes:stream",
+            get(handlers::stream_execution_nodes),
+        )
+        // HTTP: A loop/fan-out node's branch runs, for progress bars
+        .route(
+            "/executions/{execution_id}/splits/{split_node_id}",
+            get(handlers::get_execution_split),
+        )
+        // HTTP: The workflow graph an execution runs, without run data
+        .route("/executions/{execution_id}/graph", get(handlers::get_execution_graph))
+        // HTTP: Fetch an execution's accumulated context (or a sub-tree of it)
+        .route("/executions/{execution_id}/context", get(handlers::get_execution_context))
+        // HTTP: Page through the node status log of an execution
+        .route("/executions/{execution_id}/statuses", get(handlers::get_execution_statuses))
+        .route(
+            "/executions/{execution_id}/status-history",
+            get(handlers::get_execution_statuses),
+        )
+        // HTTP: Get all past executions for a workflow
+        .route("/workflows/{workflow_id}/executions", get(handlers::get_workflow_executions))
+        // HTTP: Aggregate status counts and durations for a workflow
+        .route("/workflows/{workflow_id}/stats", get(handlers::get_workflow_stats))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::rate_limit))
+        .route_layer(middleware::from_fn(request_span))
+        // Execution documents can run to megabytes of JSON. The default
+        // predicate leaves small bodies, event streams and the /rt upgrade
+        // alone.
+        .layer(CompressionLayer::new())
+        .layer(body_limit())
+        .layer(DefaultBodyLimit::disable())
+}
+
+/// Admin routes, each requiring the `x-admin-token` header on either port.
+/// Never add public API routes here.
+fn admin_routes() -> Router<AppState> {
+    Router::new()
+        // Peek at dead-lettered consumer messages: ?limit=N
+        .route("/dlq/{queue}", get(handlers::peek_dlq))
+        // Republish dead-lettered messages to their source queue: ?batch=N
+        .route("/dlq/{queue}/replay", post(handlers::replay_dlq))
+        // Rewrite a legacy execution document into the current shape
+        .route("/executions/{execution_id}/repair", post(handlers::repair_execution))
+        // Revoke an execution token by jti
+        .route("/tokens/{jti}/revoke", post(handlers::revoke_token))
+        .route_layer(middleware::from_fn(handlers::require_admin))
+        .route_layer(middleware::from_fn(request_span))
+        .layer(body_limit())
+        .layer(DefaultBodyLimit::disable())
+}
+
+/// Operational state of this instance, open to anyone who can reach the
+/// admin port.
+fn monitoring_routes() -> Router<AppState> {
+    Router::new()
+        // Prometheus scrape endpoint
+        .route("/metrics", get(handlers::metrics))
+        // Open WebSocket connections
+        .route("/connections", get(handlers::connections))
+        // Consumed queue backlogs: { queue: { ready, unacked, dlq } }
+        .route("/queues", get(handlers::queues))
+        .route_layer(middleware::from_fn(request_span))
+}
+
+/// Answer bodies over `MAX_BODY_BYTES` with 413. It replaces axum's
+/// per-extractor default, so the configured size holds for every route.
+fn body_limit() -> RequestBodyLimitLayer {
+    RequestBodyLimitLayer::new(Config::get().max_body_bytes)
+}
+
+/// Run each request inside a span carrying its route and `execution_id` /
+/// `workflow_id` path parameters, so handler and store logs can be
+/// correlated. Authorization fills in `user_id` (and the `workflow_id` of an
+/// execution) once known.
+async fn request_span(params: RawPathParams, request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path().to_owned(), |path| path.as_str().to_owned());
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        route = %route,
+        execution_id = field::Empty,
+        workflow_id = field::Empty,
+        user_id = field::Empty,
+    );
+    for (name, value) in &params {
+        if matches!(name, "execution_id" | "workflow_id") {
+            span.record(name, value);
+        }
+    }
+    next.run(request).instrument(span).await
+}
+
+fn with_cors(router: Router<AppState>) -> Router<AppState> {
     let cfg = Config::get();
     let cors = CorsLayer::new()
         .allow_origin(
             cfg.cors_origin
                 .parse::<HeaderValue>()
-                .unwrap_or_else(|_| HeaderValue::from_static("http://localhost:3000")),
+                .unwrap_or_else(|_| HeaderValue::from_static(DEFAULT_CORS_ORIGIN)),
         )
         .allow_methods([
             Method::GET,
@@ -26,20 +174,17 @@ pub fn app(state: AppState) -> Router {
             Method::DELETE,
             Method::PATCH,
         ])
-        .allow_headers([axum::http::header::AUTHORIZATION, axum::http::header::CONTENT_TYPE])
+        .allow_headers([
+            axum::http::header::AUTHORIZATION,
+            axum::http::header::CONTENT_TYPE,
+            axum::http::header::IF_NONE_MATCH,
+            HeaderName::from_static(handlers::ORG_ID_HEADER),
+        ])
+        .expose_headers([
+            HeaderName::from_static(handlers::NEXT_CURSOR_HEADER),
+            axum::http::header::ETAG,
+        ])
         .allow_credentials(true);
 
-    Router::new()
-        .route("/health", get(handlers::health_check))
-        // WebSocket: Real-time updates for specific execution
-        // Uses query params: ?execution_id=...&workflow_id=...
-        .route("/rt", get(ws::ws_handler))
-        // HTTP: Get specific past execution
-        .route("/executions/{execution_id}", get(handlers::get_execution))
-        // HTTP: Get all past executions for a workflow
-        .route("/workflows/{workflow_id}/executions", get(handlers::get_workflow_executions))
-        // TODO: Add GET /executions endpoint to list all executions for the authenticated user
-        // This is needed for the frontend /create/executions page
-        .layer(cors)
-        .with_state(state)
+    router.layer(cors)
 }
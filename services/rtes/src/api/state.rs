@@ -1,86 +1,415 @@
-use std::sync::Arc;
+use std::sync::{Arc, atomic::AtomicUsize};
 
 use async_trait::async_trait;
+use futures::{
+    StreamExt,
+    stream::{self, BoxStream},
+};
 use tokio::sync::broadcast;
 
-use crate::domain::models::{
-    CompletionMessage,
-    ExecutionDocument,
-    ExecutionToken,
-    NodeExecutionMessage,
-    NodeStatusMessage,
-    WorkerMessage,
+use crate::{
+    api::{negative_cache::NegativeCache, rate_limit::RateLimiter, ws::ExecutionConnections},
+    domain::models::{
+        CompletionMessage,
+        ExecutionAccessScope,
+        ExecutionCursor,
+        ExecutionDocument,
+        ExecutionFields,
+        ExecutionOwner,
+        ExecutionPage,
+        ExecutionSummary,
+        ExecutionToken,
+        NodeEntry,
+        NodeExecutionMessage,
+        NodeStatusMessage,
+        NodeStatusRecord,
+        SplitIterations,
+        StatusCursor,
+        TokenScope,
+        WorkerMessage,
+        WorkflowStats,
+    },
+    infra::{consumer_health::ConsumerHealth, queue_depth::QueueDepths},
 };
 
-pub type StoreError = Box<dyn std::error::Error + Send + Sync>;
+/// An execution's nodes, one at a time, as read from the store.
+pub type NodeStream = BoxStream<'static, StoreResult<NodeEntry>>;
+
+/// The backend error behind a [`StoreError`].
+pub type StoreSource = Box<dyn std::error::Error + Send + Sync>;
+
+/// A failed store call, classified so callers can tell an outage worth
+/// retrying from a request that will keep failing. A missing record isn't
+/// an error: reads answer `None` and writes `false`.
+#[derive(Debug)]
+pub enum StoreError {
+    /// The write collides with stored data, e.g. a duplicate key
+    Conflict(StoreSource),
+    /// The backend is unreachable, overloaded or failing over; a retry may
+    /// succeed
+    Transient(StoreSource),
+    /// Anything else; retrying won't help
+    Fatal(StoreSource),
+}
+
+impl StoreError {
+    /// Whether retrying the call may succeed.
+    #[must_use]
+    pub const fn is_transient(&self) -> bool {
+        matches!(self, Self::Transient(_))
+    }
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Conflict(e) | Self::Transient(e) | Self::Fatal(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Conflict(e) | Self::Transient(e) | Self::Fatal(e) => e.source(),
+        }
+    }
+}
+
 pub type StoreResult<T> = Result<T, StoreError>;
 
 #[async_trait]
 pub trait TokenStorePort: Send + Sync {
     async fn add_token(&self, token: &ExecutionToken) -> StoreResult<()>;
 
+    /// Whether the user holds a grant of at least `required_scope` for the
+    /// workflow (and execution, when given), confined to `target_org_id`.
     async fn validate_access(
         &self,
         user_id: &str,
         target_execution_id: Option<&str>,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool>;
 
+    /// Whether the user holds a grant of at least `required_scope` for the
+    /// execution, confined to `target_org_id` (the execution's organization).
     async fn validate_access_for_execution(
         &self,
         user_id: &str,
         target_execution_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool>;
 
+    /// Whether the execution has a grant of at least `required_scope` for
+    /// the workflow, confined to `target_org_id`.
     async fn validate_execution_access(
         &self,
         target_execution_id: &str,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
+    ) -> StoreResult<bool>;
+
+    /// Whether the workflow has a wildcard grant, confined to
+    /// `target_org_id`.
+    async fn validate_workflow_access(
+        &self,
+        target_workflow_id: &str,
+        target_org_id: Option<&str>,
     ) -> StoreResult<bool>;
 
-    async fn validate_workflow_access(&self, target_workflow_id: &str) -> StoreResult<bool>;
+    /// Unexpired tokens granted to a user.
+    async fn list_user_tokens(&self, user_id: &str) -> StoreResult<Vec<ExecutionToken>>;
+
+    /// Remove the token with this `jti` from every index so it no longer
+    /// grants access; `false` when no such token is stored.
+    async fn revoke_token(&self, jti: &str) -> StoreResult<bool>;
+
+    /// Round-trip to the backing store, for readiness checks.
+    async fn ping(&self) -> StoreResult<()>;
 }
 
 #[async_trait]
 pub trait ExecutionStorePort: Send + Sync {
     async fn upsert_execution_definition(&self, msg: &NodeExecutionMessage) -> StoreResult<()>;
 
+    /// The execution `execution_id` of `org_id`; `None` reaches only an
+    /// execution stored without an organization. Every read and delete below
+    /// is scoped the same way, so one organization never sees another's
+    /// execution that shares the id.
     async fn get_execution_document(
         &self,
         execution_id: &str,
+        org_id: Option<&str>,
     ) -> StoreResult<Option<ExecutionDocument>>;
 
+    /// The stored executions of `org_id` among `execution_ids`, in no
+    /// particular order; missing ids are left out. Backends should fetch them
+    /// in one query; by default each is loaded on its own.
+    async fn get_execution_documents(
+        &self,
+        execution_ids: &[String],
+        org_id: Option<&str>,
+    ) -> StoreResult<Vec<ExecutionDocument>> {
+        let mut docs = Vec::with_capacity(execution_ids.len());
+        for execution_id in execution_ids {
+            docs.extend(self.get_execution_document(execution_id, org_id).await?);
+        }
+        Ok(docs)
+    }
+
+    /// An execution document without node payloads (see
+    /// [`ExecutionDocument::compact`]). Backends should strip them before
+    /// loading; by default the full document is loaded and stripped.
+    async fn get_execution_document_compact(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        let mut doc = self.get_execution_document(execution_id, org_id).await?;
+        if let Some(doc) = doc.as_mut() {
+            doc.compact();
+        }
+        Ok(doc)
+    }
+
+    /// Just the `workflow_id` and `org_id` of an execution, for authorizing
+    /// a request before loading the document. Backends should project them
+    /// alone; by default the compact document is loaded.
+    async fn get_execution_owner(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Option<ExecutionOwner>> {
+        let doc = self
+            .get_execution_document_compact(execution_id, org_id)
+            .await?;
+        Ok(doc.map(|doc| ExecutionOwner { workflow_id: doc.workflow_id, org_id: doc.org_id }))
+    }
+
+    /// The execution narrowed to `fields`, plus its identity and ETag inputs.
+    /// Callers prune the response to `fields` themselves; backends should
+    /// project in the store, and by default the full document is loaded.
+    async fn get_execution_fields(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        _fields: &ExecutionFields,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        self.get_execution_document(execution_id, org_id).await
+    }
+
+    /// The execution with `nodes` narrowed to `node_id` (empty when the node
+    /// hasn't run). Backends should avoid loading other nodes; by default
+    /// the full document is loaded and narrowed.
+    async fn get_node(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        node_id: &str,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        let mut doc = self.get_execution_document(execution_id, org_id).await?;
+        if let Some(doc) = doc.as_mut() {
+            doc.nodes.retain(|id, _| id == node_id);
+        }
+        Ok(doc)
+    }
+
+    /// Every branch run of `split_node_id` with payloads left out, ordered
+    /// by `item_index`; `None` when the execution doesn't exist.
+    async fn get_node_iterations(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        split_node_id: &str,
+    ) -> StoreResult<Option<SplitIterations>> {
+        let doc = self
+            .get_execution_document_compact(execution_id, org_id)
+            .await?;
+        Ok(doc.map(|doc| doc.split_iterations(split_node_id)))
+    }
+
+    /// Every node of an execution with its runs, in no particular order;
+    /// empty when the execution doesn't exist. Backends should read them
+    /// off a cursor instead of loading the whole document, which is what
+    /// the default does.
+    async fn stream_nodes(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<NodeStream> {
+        let nodes = self
+            .get_execution_document(execution_id, org_id)
+            .await?
+            .map(|doc| doc.nodes)
+            .unwrap_or_default();
+        Ok(stream::iter(
+            nodes
+                .into_iter()
+                .map(|(node_id, node)| Ok(NodeEntry { node_id, node })),
+        )
+        .boxed())
+    }
+
+    /// Every execution of `org_id` for `workflow_id`.
     async fn get_executions_for_workflow(
         &self,
         workflow_id: &str,
+        org_id: Option<&str>,
     ) -> StoreResult<Vec<ExecutionDocument>>;
 
-    async fn update_node_status(&self, msg: &NodeStatusMessage) -> StoreResult<()>;
+    /// A page of a workflow's executions of `org_id`, newest first, starting
+    /// strictly after `after` when given. `node_type` keeps only executions
+    /// where a node of that type ran.
+    async fn get_executions_for_workflow_paginated(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        after: Option<&ExecutionCursor>,
+        node_type: Option<&str>,
+        limit: i64,
+    ) -> StoreResult<ExecutionPage>;
+
+    /// Status counts, durations and last run of a workflow's executions of
+    /// `org_id`, optionally only those where a node of `node_type` ran.
+    /// Backends should aggregate in place; by default every execution is
+    /// loaded.
+    async fn workflow_stats(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        node_type: Option<&str>,
+    ) -> StoreResult<WorkflowStats> {
+        let mut executions = self
+            .get_executions_for_workflow(workflow_id, org_id)
+            .await?;
+        if let Some(node_type) = node_type {
+            executions.retain(|doc| doc.ran_node_type(node_type));
+        }
+        Ok(WorkflowStats::from_executions(workflow_id, &executions))
+    }
+
+    /// Summaries of executions of `org_id` within `scope`, newest first.
+    async fn list_executions_for_user(
+        &self,
+        scope: &ExecutionAccessScope,
+        org_id: Option<&str>,
+        limit: i64,
+        offset: u64,
+    ) -> StoreResult<Vec<ExecutionSummary>>;
+
+    /// Apply a node status; `false` when it was dropped instead (its
+    /// execution is missing or belongs to another organization, or it would
+    /// show a node of a completed execution running again), so it must not
+    /// reach live clients either.
+    async fn update_node_status(&self, msg: &NodeStatusMessage) -> StoreResult<bool>;
 
-    async fn complete_execution(&self, msg: &CompletionMessage) -> StoreResult<()>;
+    /// Apply status messages in order, returning for each whether it was
+    /// applied, as [`update_node_status`](Self::update_node_status) does.
+    /// Backends may group the writes per execution; by default each message
+    /// is applied on its own.
+    async fn update_node_statuses(&self, msgs: &[NodeStatusMessage]) -> StoreResult<Vec<bool>> {
+        let mut applied = Vec::with_capacity(msgs.len());
+        for msg in msgs {
+            applied.push(self.update_node_status(msg).await?);
+        }
+        Ok(applied)
+    }
+
+    /// Status log entries for an execution of `org_id` in `executed_at`
+    /// order, starting strictly after `after` when given and skipping the
+    /// first `offset`.
+    async fn get_status_history(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        after: Option<&StatusCursor>,
+        offset: u64,
+        limit: i64,
+    ) -> StoreResult<Vec<NodeStatusRecord>>;
+
+    /// Record a workflow completion; `false` when the execution had already
+    /// recorded this one (a redelivery), leaving it untouched.
+    async fn complete_execution(&self, msg: &CompletionMessage) -> StoreResult<bool>;
+
+    /// Delete an execution of `org_id` and its status log; `false` when it
+    /// didn't exist.
+    async fn delete_execution(&self, execution_id: &str, org_id: Option<&str>)
+    -> StoreResult<bool>;
+
+    /// Rewrite an execution of `org_id` stored in an older shape
+    /// (array-shaped `nodes`, an unnormalized definition) into the current
+    /// one, keeping its runs; `false` when it doesn't exist.
+    async fn repair_execution(&self, execution_id: &str, org_id: Option<&str>)
+    -> StoreResult<bool>;
+
+    /// Round-trip to the backing store, for readiness checks.
+    async fn ping(&self) -> StoreResult<()>;
 }
 
 #[derive(Clone)]
 pub struct AppState {
-    pub token_store:     Arc<dyn TokenStorePort>,
-    pub execution_store: Arc<dyn ExecutionStorePort>,
-    pub tx:              broadcast::Sender<WorkerMessage>,
+    pub token_store:           Arc<dyn TokenStorePort>,
+    pub execution_store:       Arc<dyn ExecutionStorePort>,
+    pub tx:                    broadcast::Sender<WorkerMessage>,
+    /// Startup/run state of the RabbitMQ consumers, reported by `/readyz`
+    pub consumers:             ConsumerHealth,
+    /// Backlog of the consumed queues as of the last poll, for `/queues`
+    pub queues:                QueueDepths,
+    /// Open `/rt` sockets, each holding a broadcast receiver
+    pub connections:           Arc<AtomicUsize>,
+    /// Open `/rt` sockets per execution, for `WS_MAX_CONN_PER_EXECUTION`
+    pub execution_connections: ExecutionConnections,
+    /// Per-client throttling of the public routes; disabled unless set with
+    /// [`AppState::with_rate_limiter`]
+    pub rate_limiter:          RateLimiter,
+    /// Recent `404`s and denials of execution lookups; disabled unless set
+    /// with [`AppState::with_negative_cache`]
+    pub negative_cache:        NegativeCache,
 }
 
 impl AppState {
-    pub fn new<TS, ES>(token_store: TS, execution_store: ES) -> Self
+    pub fn new<TS, ES>(token_store: TS, execution_store: ES, broadcast_capacity: usize) -> Self
     where
         TS: TokenStorePort + 'static,
         ES: ExecutionStorePort + 'static,
     {
-        Self::from_shared(Arc::new(token_store), Arc::new(execution_store))
+        Self::from_shared(Arc::new(token_store), Arc::new(execution_store), broadcast_capacity)
     }
 
+    /// `broadcast_capacity` is how many updates a slow WebSocket subscriber may
+    /// fall behind before it lags; clamped to at least 1.
     pub fn from_shared(
         token_store: Arc<dyn TokenStorePort>,
         execution_store: Arc<dyn ExecutionStorePort>,
+        broadcast_capacity: usize,
     ) -> Self {
-        let (tx, _) = broadcast::channel(100);
-        Self { token_store, execution_store, tx }
+        let (tx, _) = broadcast::channel(broadcast_capacity.max(1));
+        Self {
+            token_store,
+            execution_store,
+            tx,
+            consumers: ConsumerHealth::default(),
+            queues: QueueDepths::default(),
+            connections: Arc::new(AtomicUsize::new(0)),
+            execution_connections: ExecutionConnections::default(),
+            rate_limiter: RateLimiter::default(),
+            negative_cache: NegativeCache::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    #[must_use]
+    pub fn with_negative_cache(mut self, negative_cache: NegativeCache) -> Self {
+        self.negative_cache = negative_cache;
+        self
     }
 }
@@ -1,21 +1,59 @@
+use std::{
+    collections::{BTreeMap, HashMap, btree_map::Entry, hash_map::Entry as HashMapEntry},
+    str::FromStr,
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
 use axum::{
+    body::Bytes,
     extract::{
         Query,
         State,
         WebSocketUpgrade,
-        ws::{Message, WebSocket},
+        ws::{CloseFrame, Message, WebSocket, close_code},
     },
+    http::HeaderMap,
     response::IntoResponse,
 };
-use futures::{sink::SinkExt, stream::StreamExt};
+use chrono::{DateTime, FixedOffset};
+use futures::{
+    sink::SinkExt,
+    stream::{SplitSink, SplitStream, StreamExt},
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::sync::broadcast::error::RecvError;
-use tracing::{error, info, warn};
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc,
+    },
+    time::Instant,
+};
+use tracing::{Instrument, debug, error, info, warn};
 
 use crate::{
-    api::state::AppState,
-    domain::models::{NodeError, NodeExecutionInstance, StackFrame, WorkerMessage},
+    api::{
+        auth::{AuthFailure, Caller, bearer_token, record_auth_failure},
+        error::ApiError,
+        state::{AppState, StoreResult},
+    },
+    config::Config,
+    domain::models::{
+        ExecutionDocument,
+        HydratedNode,
+        NodeError,
+        NodeExecutionInstance,
+        StackFrame,
+        TokenScope,
+        WorkerMessage,
+    },
+    infra::prometheus,
 };
 
 #[derive(Debug, Serialize, Clone, PartialEq)]
@@ -24,6 +62,8 @@ pub(crate) struct WsNodeUpdateDto {
     pub(crate) input:            Option<Value>,
     pub(crate) params:           Option<Value>,
     pub(crate) output:           Option<Value>,
+    /// Omitted unless the node failed, so older clients see no new key
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) error:            Option<NodeError>,
     pub(crate) status:           Option<String>,
     pub(crate) lineage_hash:     Option<String>,
@@ -35,6 +75,19 @@ pub(crate) struct WsNodeUpdateDto {
     pub(crate) processed_count:  Option<i32>,
     pub(crate) aggregator_state: Option<String>,
     pub(crate) used_inputs:      Option<Value>,
+    /// When the node run finished and how long it took; omitted on
+    /// completion and status-only frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) executed_at:      Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) duration_ms:      Option<i64>,
+    /// Why the workflow failed or halted; only set on completion frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) failure_reason:   Option<String>,
+    /// Execution the frame belongs to; only set for executions added with a
+    /// `subscribe` message, so frames of the one connected to keep their shape
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) execution_id:     Option<String>,
 }
 
 impl From<&WorkerMessage> for WsNodeUpdateDto {
@@ -56,14 +109,18 @@ impl From<&WorkerMessage> for WsNodeUpdateDto {
                 processed_count:  s.processed_count,
                 aggregator_state: s.aggregator_state.clone(),
                 used_inputs:      s.used_inputs.clone(),
+                executed_at:      Some(s.executed_at.clone()),
+                duration_ms:      Some(s.duration_ms),
+                failure_reason:   None,
+                execution_id:     None,
             },
-            WorkerMessage::WorkflowCompletion(_c) => Self {
+            WorkerMessage::WorkflowCompletion(c) => Self {
                 node_id:          None,
                 input:            None,
                 params:           None,
                 output:           None,
                 error:            None,
-                status:           Some("completed".to_string()),
+                status:           Some(c.status.clone()),
                 lineage_hash:     None,
                 lineage_stack:    None,
                 split_node_id:    None,
@@ -73,6 +130,10 @@ impl From<&WorkerMessage> for WsNodeUpdateDto {
                 processed_count:  None,
                 aggregator_state: None,
                 used_inputs:      None,
+                executed_at:      None,
+                duration_ms:      None,
+                failure_reason:   c.failure_reason.clone(),
+                execution_id:     None,
             },
             WorkerMessage::NodeExecution(_) => Self {
                 node_id:          None,
@@ -90,6 +151,10 @@ impl From<&WorkerMessage> for WsNodeUpdateDto {
                 processed_count:  None,
                 aggregator_state: None,
                 used_inputs:      None,
+                executed_at:      None,
+                duration_ms:      None,
+                failure_reason:   None,
+                execution_id:     None,
             },
         }
     }
@@ -112,6 +177,10 @@ fn dto_from_execution_instance(node_id: String, exec: NodeExecutionInstance) ->
         processed_count:  exec.processed_count,
         aggregator_state: exec.aggregator_state,
         used_inputs:      exec.used_inputs,
+        executed_at:      exec.executed_at,
+        duration_ms:      exec.duration_ms,
+        failure_reason:   None,
+        execution_id:     None,
     }
 }
 
@@ -132,6 +201,117 @@ const fn dto_with_status(status: String) -> WsNodeUpdateDto {
         processed_count:  None,
         aggregator_state: None,
         used_inputs:      None,
+        executed_at:      None,
+        duration_ms:      None,
+        failure_reason:   None,
+        execution_id:     None,
+    }
+}
+
+/// Payload fields a client can select with `?include=`; the remaining
+/// (identity/lineage) fields are always sent.
+const PROJECTABLE_FIELDS: [&str; 5] = ["status", "output", "input", "params", "error"];
+
+/// Fields sent when the client doesn't pass `?include=`.
+const DEFAULT_FIELDS: [&str; 2] = ["status", "output"];
+
+/// Projection applied to every frame of a connection, parsed from a
+/// comma-separated `?include=` list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WsFields(Vec<&'static str>);
+
+impl Default for WsFields {
+    fn default() -> Self {
+        Self(DEFAULT_FIELDS.to_vec())
+    }
+}
+
+impl FromStr for WsFields {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let field = PROJECTABLE_FIELDS
+                .into_iter()
+                .find(|field| *field == name)
+                .ok_or_else(|| format!("Unknown include field '{name}'"))?;
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        Ok(Self(fields))
+    }
+}
+
+impl WsFields {
+    /// Serialize `dto` as a text frame, dropping payload fields the client
+    /// didn't ask for.
+    fn frame(&self, dto: &WsNodeUpdateDto) -> Option<String> {
+        let mut value = serde_json::to_value(dto).ok()?;
+        if let Value::Object(map) = &mut value {
+            for field in PROJECTABLE_FIELDS {
+                if !self.0.contains(&field) {
+                    map.remove(field);
+                }
+            }
+        }
+        serde_json::to_string(&value).ok()
+    }
+}
+
+/// Control frames sent alongside node updates, tagged by `type`. They carry
+/// no `node_id`/`status`, so node-update handlers ignore them.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum WsControl {
+    /// The subscriber lagged and `skipped` live updates were dropped; the
+    /// client should re-fetch the execution document.
+    Resync { skipped: u64 },
+    /// A `subscribe` message was accepted; frames of `execution_id` follow,
+    /// starting with its history
+    Subscribed { execution_id: String },
+    /// A `subscribe` message was refused; `code` is the one the HTTP API
+    /// would answer with
+    SubscribeFailed { execution_id: String, code: &'static str },
+    /// An `unsubscribe` message was handled; no more frames of
+    /// `execution_id` follow
+    Unsubscribed { execution_id: String },
+    /// Answer to a `list` message: every execution the socket follows
+    Subscriptions { execution_ids: Vec<String> },
+    /// The stored history of the execution the connection was opened for
+    /// couldn't be read; `code` is the one the HTTP API would answer with.
+    /// The connection is closed after it.
+    ReplayFailed { code: &'static str },
+    /// The history replay is over; `execution_id` is only set after the
+    /// replay of an execution added with `subscribe`
+    SnapshotComplete {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        execution_id: Option<String>,
+    },
+}
+
+/// Messages a client may send on an open socket, tagged by `action`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum WsCommand {
+    /// Follow another execution on this connection. `workflow_id` defaults
+    /// to the one the connection was opened with.
+    Subscribe {
+        execution_id: String,
+        #[serde(default)]
+        workflow_id:  Option<String>,
+    },
+    /// Stop following an execution, including the one the connection was
+    /// opened for
+    Unsubscribe { execution_id: String },
+    /// Ask which executions the connection follows
+    List,
+}
+
+impl WsControl {
+    pub(crate) fn frame(&self) -> Option<String> {
+        serde_json::to_string(self).ok()
     }
 }
 
@@ -140,150 +320,911 @@ const fn dto_with_status(status: String) -> WsNodeUpdateDto {
 pub(crate) struct WsQueryParams {
     pub(crate) execution_id: String,
     pub(crate) workflow_id:  String,
+    /// Comma-separated payload fields (`status,output,input,params,error`)
+    pub(crate) include:      Option<String>,
+    /// Only stream node frames from this branch
+    pub(crate) branch_id:    Option<String>,
+    /// Only stream node frames from this lineage
+    pub(crate) lineage_hash: Option<String>,
+    /// RFC 3339 timestamp; history replay skips runs executed at or before it
+    pub(crate) since:        Option<String>,
+    /// JWT for clients that cannot set an `Authorization` header
+    pub(crate) token:        Option<String>,
+    /// Organization the socket acts for; only its executions are read. With
+    /// a JWT it must match the token's `org_id` claim
+    pub(crate) org_id:       Option<String>,
+}
+
+/// Parse a `?since=` replay cursor, as an error message for a 400.
+pub(crate) fn parse_since(raw: Option<&str>) -> Result<Option<DateTime<FixedOffset>>, String> {
+    raw.map(|raw| {
+        DateTime::parse_from_rfc3339(raw)
+            .map_err(|e| format!("Invalid since timestamp '{raw}': {e}; expected RFC 3339"))
+    })
+    .transpose()
+}
+
+/// Branch/lineage restriction for node frames; frames without a `node_id`
+/// (execution status, completion) always pass.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WsFilter {
+    pub(crate) branch_id:    Option<String>,
+    pub(crate) lineage_hash: Option<String>,
+}
+
+impl WsFilter {
+    fn matches(&self, dto: &WsNodeUpdateDto) -> bool {
+        if dto.node_id.is_none() {
+            return true;
+        }
+        let branch_ok = self
+            .branch_id
+            .as_ref()
+            .is_none_or(|wanted| dto.branch_id.as_ref() == Some(wanted));
+        let lineage_ok = self
+            .lineage_hash
+            .as_ref()
+            .is_none_or(|wanted| dto.lineage_hash.as_ref() == Some(wanted));
+        branch_ok && lineage_ok
+    }
+}
+
+/// Whether the live frames of a followed execution go out as they arrive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Follow {
+    /// Its history is still being queued; live frames are held until then
+    Replaying,
+    Live,
+}
+
+/// Execution ids a connection follows, shared by its send and receive
+/// tasks.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Subscriptions(Arc<Mutex<HashMap<String, Follow>>>);
+
+impl Subscriptions {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Follow>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn contains(&self, execution_id: &str) -> bool {
+        self.lock().contains_key(execution_id)
+    }
+
+    /// Follow `execution_id`; false if it already was.
+    fn insert(&self, execution_id: &str, follow: Follow) -> bool {
+        match self.lock().entry(execution_id.to_owned()) {
+            HashMapEntry::Occupied(_) => false,
+            HashMapEntry::Vacant(slot) => {
+                slot.insert(follow);
+                true
+            },
+        }
+    }
+
+    /// Whether live frames of `execution_id` must wait for its history.
+    fn replaying(&self, execution_id: &str) -> bool {
+        self.lock().get(execution_id) == Some(&Follow::Replaying)
+    }
+
+    /// Send live frames of `execution_id` as they arrive from now on.
+    fn go_live(&self, execution_id: &str) {
+        if let Some(follow) = self.lock().get_mut(execution_id) {
+            *follow = Follow::Live;
+        }
+    }
+
+    /// Stop following `execution_id`; false if it wasn't.
+    fn remove(&self, execution_id: &str) -> bool {
+        self.lock().remove(execution_id).is_some()
+    }
+
+    /// The followed execution ids, sorted.
+    fn list(&self) -> Vec<String> {
+        let mut execution_ids: Vec<String> = self.lock().keys().cloned().collect();
+        execution_ids.sort_unstable();
+        execution_ids
+    }
 }
 
 /// Internal params for WebSocket connection
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct WsParams {
-    pub(crate) execution_id: String,
+    /// Execution the connection was opened for
+    pub(crate) execution_id:  String,
+    /// Organization every followed execution is read from: the JWT's
+    /// `org_id`, or `?org_id=` without a JWT
+    pub(crate) org_id:        Option<String>,
+    /// Every execution the connection follows, `execution_id` included
+    pub(crate) subscriptions: Subscriptions,
+    pub(crate) fields:        WsFields,
+    pub(crate) filter:        WsFilter,
+    /// Replay only runs executed after this, for reconnecting clients
+    pub(crate) since:         Option<DateTime<FixedOffset>>,
+}
+
+/// How much of a run is filled in, to pick one among runs that share a
+/// lineage; ties go to the later run.
+fn run_richness(run: &NodeExecutionInstance) -> (usize, Option<&str>) {
+    let filled = [
+        run.input.is_some(),
+        run.parameters.is_some(),
+        run.output.is_some(),
+        run.status.is_some(),
+        run.error.is_some(),
+        run.duration_ms.is_some(),
+        run.lineage_stack.is_some(),
+        run.used_inputs.is_some(),
+    ]
+    .into_iter()
+    .filter(|filled| *filled)
+    .count();
+    (filled, run.executed_at.as_deref())
+}
+
+/// The runs a history replay sends for a node: one per lineage hash (the
+/// richest when stored lineages overlap), plus `latest` unless a lineage
+/// already covers its hash.
+fn replay_runs(node: HydratedNode) -> Vec<NodeExecutionInstance> {
+    let mut runs: BTreeMap<Option<String>, NodeExecutionInstance> = BTreeMap::new();
+    for run in node.lineages.into_values().chain(node.latest) {
+        match runs.entry(run.lineage_hash.clone()) {
+            Entry::Vacant(slot) => {
+                slot.insert(run);
+            },
+            Entry::Occupied(mut slot) => {
+                if run_richness(&run) > run_richness(slot.get()) {
+                    slot.insert(run);
+                }
+            },
+        }
+    }
+    runs.into_values().collect()
+}
+
+impl WsParams {
+    pub(crate) fn new(
+        execution_id: String,
+        org_id: Option<String>,
+        fields: WsFields,
+        filter: WsFilter,
+        since: Option<DateTime<FixedOffset>>,
+    ) -> Self {
+        let subscriptions = Subscriptions::default();
+        subscriptions.insert(&execution_id, Follow::Live);
+        Self { execution_id, org_id, subscriptions, fields, filter, since }
+    }
+
+    /// The `execution_id` to tag frames of `execution_id` with: none for
+    /// the execution the connection was opened for.
+    fn tag(&self, execution_id: &str) -> Option<String> {
+        (execution_id != self.execution_id).then(|| execution_id.to_owned())
+    }
+
+    /// Whether a stored run is newer than `since`. Runs with a missing or
+    /// unparsable `executed_at` are sent rather than risk dropping an update.
+    fn missed(&self, run: &NodeExecutionInstance) -> bool {
+        let Some(since) = self.since else {
+            return true;
+        };
+        run.executed_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .is_none_or(|at| at > since)
+    }
+
+    /// Frames replaying a stored execution: each distinct run of every node
+    /// (only those after `since`, when set and this is the execution the
+    /// connection was opened for), then the execution status.
+    pub(crate) fn history_frames(&self, doc: ExecutionDocument) -> Vec<String> {
+        let tag = self.tag(&doc.execution_id);
+        let mut frames = Vec::new();
+        for (node_id, node) in doc.nodes {
+            frames.extend(
+                replay_runs(node)
+                    .into_iter()
+                    .filter(|exec| tag.is_some() || self.missed(exec))
+                    .map(|exec| WsNodeUpdateDto {
+                        execution_id: tag.clone(),
+                        ..dto_from_execution_instance(node_id.clone(), exec)
+                    })
+                    .filter(|dto| self.filter.matches(dto))
+                    .filter_map(|dto| self.fields.frame(&dto)),
+            );
+        }
+        if let Some(status) = doc.status {
+            let dto = WsNodeUpdateDto {
+                failure_reason: doc.failure_reason,
+                execution_id: tag,
+                ..dto_with_status(status)
+            };
+            frames.extend(self.fields.frame(&dto));
+        }
+        frames
+    }
+
+    /// Frame for a live broadcast message, if it belongs to a followed
+    /// execution of the caller's organization and passes the branch/lineage
+    /// filter.
+    pub(crate) fn live_frame(&self, msg: &WorkerMessage) -> Option<String> {
+        let execution_id = live_execution(msg)?;
+        // Execution ids are only unique within an organization
+        if !self.subscriptions.contains(execution_id) || live_org(msg) != self.org_id.as_deref() {
+            return None;
+        }
+        let outbound =
+            WsNodeUpdateDto { execution_id: self.tag(execution_id), ..WsNodeUpdateDto::from(msg) };
+        if self.filter.matches(&outbound) {
+            self.fields.frame(&outbound)
+        } else {
+            None
+        }
+    }
+}
+
+/// The execution a live broadcast message updates, if it is one clients see.
+fn live_execution(msg: &WorkerMessage) -> Option<&str> {
+    match msg {
+        WorkerMessage::NodeStatus(s) => Some(&s.execution_id),
+        WorkerMessage::WorkflowCompletion(c) => Some(&c.execution_id),
+        WorkerMessage::NodeExecution(_) => None,
+    }
+}
+
+/// The organization a live broadcast message was sent for.
+fn live_org(msg: &WorkerMessage) -> Option<&str> {
+    match msg {
+        WorkerMessage::NodeStatus(s) => s.org_id.as_deref(),
+        WorkerMessage::WorkflowCompletion(c) => c.org_id.as_deref(),
+        WorkerMessage::NodeExecution(e) => e.org_id.as_deref(),
+    }
 }
 
 pub(crate) async fn ws_handler(
     ws: WebSocketUpgrade,
+    headers: HeaderMap,
     Query(query): Query<WsQueryParams>,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
     let execution_id = query.execution_id;
     let workflow_id = query.workflow_id;
+    // /rt takes its ids as query params, so the request span can't fill them in
+    let span = tracing::Span::current();
+    span.record("execution_id", execution_id.as_str());
+    span.record("workflow_id", workflow_id.as_str());
+    let fields = match query.include.as_deref().map(WsFields::from_str) {
+        None => WsFields::default(),
+        Some(Ok(fields)) => fields,
+        Some(Err(e)) => {
+            warn!(execution_id = %execution_id, error = %e, "Rejecting WebSocket include");
+            return ApiError::InvalidQuery.into_response();
+        },
+    };
+    let since = match parse_since(query.since.as_deref()) {
+        Ok(since) => since,
+        Err(e) => {
+            warn!(execution_id = %execution_id, error = %e, "Rejecting WebSocket since");
+            return ApiError::InvalidQuery.into_response();
+        },
+    };
 
     info!("WebSocket connection attempt for execution: {} workflow: {}", execution_id, workflow_id);
 
-    // Validate access: execution must have a valid grant in Redis
-    // (grants are published via API -> RabbitMQ -> RTES token consumer when /run is
-    // called)
-    match state
-        .token_store
-        .validate_execution_access(&execution_id, &workflow_id)
-        .await
-    {
-        Ok(true) => {
-            let params = WsParams { execution_id: execution_id.clone() };
-            ws.on_upgrade(move |socket| handle_socket(socket, state, params))
-        },
+    // Validate access: the JWT user's grants when a token is supplied (header
+    // first, then `?token=`), otherwise the execution must have a valid grant in
+    // Redis (grants are published via API -> RabbitMQ -> RTES token consumer when
+    // /run is called)
+    let token = bearer_token(&headers).or(query.token);
+    let Caller { user_id, org_id } =
+        match Caller::from_token(token.as_deref(), query.org_id.as_deref()) {
+            Ok(caller) => caller,
+            Err(e) => return e.into_response(),
+        };
+    let access =
+        check_access(&state, user_id.as_deref(), &execution_id, &workflow_id, org_id.as_deref())
+            .await;
+    match access {
+        Ok(true) => {},
         Ok(false) => {
-            warn!(
-                "Unauthorized WS access attempt for execution: {} workflow: {}",
-                execution_id, workflow_id
-            );
-            (axum::http::StatusCode::FORBIDDEN, "Unauthorized").into_response()
+            return denied(user_id.as_deref(), &execution_id, &workflow_id).into_response();
         },
         Err(e) => {
             error!("Token validation error: {}", e);
-            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Internal Error").into_response()
+            return ApiError::from_store(&e, ApiError::Internal).into_response();
         },
     }
+    // Only after auth, so the answer to an unauthorized caller doesn't tell
+    // whether the execution exists
+    if let Err(e) = check_owner(&state, &execution_id, &workflow_id, org_id.as_deref()).await {
+        return e.into_response();
+    }
+
+    // Claimed before upgrading; dropping the closure of a failed upgrade
+    // releases it
+    let max = Config::get().ws_max_conn_per_execution;
+    let Some(slot) = state.execution_connections.try_open(&execution_id, max) else {
+        warn!(execution_id = %execution_id, max, "Refusing WebSocket: connection cap reached");
+        return ApiError::TooManyConnections.into_response();
+    };
+    let filter = WsFilter { branch_id: query.branch_id, lineage_hash: query.lineage_hash };
+    let params = WsParams::new(execution_id.clone(), org_id, fields, filter, since);
+    let caller = WsCaller { user_id, workflow_id };
+    // The socket outlives the upgrade request; keep its logs in the same span
+    ws.on_upgrade(move |socket| handle_socket(socket, state, params, caller, slot).instrument(span))
 }
 
-#[allow(clippy::too_many_lines)]
-async fn handle_socket(socket: WebSocket, state: AppState, params: WsParams) {
-    let (mut sender, mut receiver) = socket.split();
-    let mut rx = state.tx.subscribe();
+/// Whether the caller may follow `execution_id`: the JWT user's grants when
+/// a token is supplied (header first, then `?token=`), otherwise the
+/// execution must have a valid grant in Redis (grants are published via API
+/// -> RabbitMQ -> RTES token consumer when /run is called).
+async fn check_access(
+    state: &AppState,
+    user_id: Option<&str>,
+    execution_id: &str,
+    workflow_id: &str,
+    org_id: Option<&str>,
+) -> StoreResult<bool> {
+    match user_id {
+        Some(user_id) => {
+            state
+                .token_store
+                .validate_access_for_execution(user_id, execution_id, org_id, TokenScope::Viewer)
+                .await
+        },
+        None => {
+            state
+                .token_store
+                .validate_execution_access(execution_id, workflow_id, org_id, TokenScope::Viewer)
+                .await
+        },
+    }
+}
 
-    let execution_id = params.execution_id.clone();
+/// The error for a caller without a grant for `execution_id`. Same contract
+/// as the HTTP routes: 403 only for a JWT user lacking a grant.
+fn denied(user_id: Option<&str>, execution_id: &str, workflow_id: &str) -> ApiError {
+    record_auth_failure(
+        AuthFailure::NoGrant,
+        user_id,
+        &format!("No WS grant for execution {execution_id} workflow {workflow_id}"),
+    );
+    if user_id.is_some() {
+        ApiError::Forbidden
+    } else {
+        ApiError::Unauthorized
+    }
+}
 
-    // Send history
-    if let Ok(Some(doc)) = state
+/// Reject a `workflow_id` that doesn't own the execution of `org_id`, so a
+/// grant for one execution can't be streamed under another workflow's id
+/// (and its logs and filters). An execution not stored yet is let through:
+/// clients often connect right after starting a run.
+async fn check_owner(
+    state: &AppState,
+    execution_id: &str,
+    workflow_id: &str,
+    org_id: Option<&str>,
+) -> Result<(), ApiError> {
+    match state
         .execution_store
-        .get_execution_document(&execution_id)
+        .get_execution_owner(execution_id, org_id)
         .await
     {
-        for (node_id, node) in doc.nodes {
-            if !node.lineages.is_empty() {
-                for (_, exec) in node.lineages {
-                    let dto = dto_from_execution_instance(node_id.clone(), exec);
-                    if let Ok(json) = serde_json::to_string(&dto)
-                        && sender.send(Message::Text(json.into())).await.is_err()
-                    {
-                        return;
-                    }
-                }
-            } else if let Some(exec) = node.latest {
-                let dto = dto_from_execution_instance(node_id.clone(), exec);
-                if let Ok(json) = serde_json::to_string(&dto)
-                    && sender.send(Message::Text(json.into())).await.is_err()
-                {
-                    return;
-                }
-            }
+        Ok(Some(owner)) if owner.workflow_id != workflow_id => {
+            warn!(
+                execution_id = %execution_id,
+                claimed = %workflow_id,
+                actual = %owner.workflow_id,
+                "Refusing WebSocket: execution belongs to another workflow"
+            );
+            Err(ApiError::Forbidden)
+        },
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err(ApiError::from_store(&e, ApiError::Database))
+        },
+    }
+}
+
+/// Counts an open socket in [`AppState::connections`] and the Prometheus
+/// gauge until dropped.
+struct ConnectionGuard {
+    active:  Arc<AtomicUsize>,
+    _metric: prometheus::WebSocketConnection,
+}
+
+impl ConnectionGuard {
+    fn open(active: &Arc<AtomicUsize>) -> Self {
+        active.fetch_add(1, Ordering::Relaxed);
+        Self { active: active.clone(), _metric: prometheus::WebSocketConnection::open() }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Open sockets per execution, so one execution can't be made to replay its
+/// history to an unbounded number of clients.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionConnections {
+    open: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl ExecutionConnections {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, usize>> {
+        self.open
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Claim a socket for `execution_id`, or `None` when `max` are already
+    /// open (0 lifts the cap). The claim is released when the slot drops.
+    pub(crate) fn try_open(&self, execution_id: &str, max: usize) -> Option<ExecutionSlot> {
+        let mut open = self.lock();
+        let count = open.entry(execution_id.to_owned()).or_default();
+        if max != 0 && *count >= max {
+            return None;
         }
-        if let Some(status) = doc.status {
-            let dto = dto_with_status(status);
-            if let Ok(json) = serde_json::to_string(&dto)
-                && sender.send(Message::Text(json.into())).await.is_err()
-            {
-                return;
+        *count += 1;
+        drop(open);
+        Some(ExecutionSlot { connections: self.clone(), execution_id: execution_id.to_owned() })
+    }
+
+    /// Sockets currently open for `execution_id`.
+    pub fn open_for(&self, execution_id: &str) -> usize {
+        self.lock().get(execution_id).copied().unwrap_or_default()
+    }
+}
+
+/// One claimed socket of an execution; see [`ExecutionConnections`].
+#[derive(Debug)]
+pub(crate) struct ExecutionSlot {
+    connections:  ExecutionConnections,
+    execution_id: String,
+}
+
+impl Drop for ExecutionSlot {
+    fn drop(&mut self) {
+        let mut open = self.connections.lock();
+        if let Some(count) = open.get_mut(&self.execution_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                open.remove(&self.execution_id);
             }
         }
     }
+}
 
-    let mut send_task = tokio::spawn(async move {
-        let execution_id = params.execution_id.clone();
-        loop {
-            let msg = match rx.recv().await {
-                Ok(msg) => msg,
-                Err(RecvError::Lagged(skipped)) => {
-                    warn!(
-                        execution_id = %execution_id,
-                        skipped,
-                        "WebSocket receiver lagged; skipping stale messages"
-                    );
-                    continue;
-                },
-                Err(RecvError::Closed) => break,
-            };
+/// Who opened a socket, for checking the executions it subscribes to.
+#[derive(Debug)]
+struct WsCaller {
+    user_id:     Option<String>,
+    /// Workflow a `subscribe` message without its own `workflow_id` names
+    workflow_id: String,
+}
 
-            let should_send = match &msg {
-                WorkerMessage::NodeStatus(s) => s.execution_id == execution_id,
-                WorkerMessage::WorkflowCompletion(c) => c.execution_id == execution_id,
-                WorkerMessage::NodeExecution(_) => false,
-            };
+/// What the receive task queues for the send task.
+#[derive(Debug)]
+enum Outbound {
+    Frame(String),
+    /// Everything a `subscribe` answers with is queued; the live frames held
+    /// back for this execution meanwhile may follow
+    Replayed(String),
+}
 
-            let outbound = WsNodeUpdateDto::from(&msg);
+/// Messages queued for the send task by the receive task.
+const OUTBOX_CAPACITY: usize = 64;
 
-            if should_send
-                && let Ok(json) = serde_json::to_string(&outbound)
-                && sender.send(Message::Text(json.into())).await.is_err()
-            {
-                break;
+/// Most live frames held back during one history replay; updates beyond it
+/// are only counted, and reported in a `resync` frame.
+const REPLAY_BUFFER_MAX: usize = 10_000;
+
+/// Live frames of an execution that arrive while its history is replayed,
+/// held back so they follow the replay instead of racing it.
+#[derive(Debug, Default)]
+struct ReplayBuffer {
+    frames:  Vec<String>,
+    /// Live updates lost, by the broadcast channel or for want of room here
+    skipped: u64,
+}
+
+impl ReplayBuffer {
+    /// Hold back the frame for `next`, if the connection wants it. False
+    /// once the broadcast channel is closed.
+    fn push(&mut self, params: &WsParams, next: Result<WorkerMessage, RecvError>) -> bool {
+        match next {
+            Ok(msg) => {
+                if let Some(json) = params.live_frame(&msg) {
+                    self.hold(json);
+                }
+                true
+            },
+            Err(RecvError::Lagged(skipped)) => {
+                self.skipped += skipped;
+                true
+            },
+            Err(RecvError::Closed) => false,
+        }
+    }
+
+    fn hold(&mut self, json: String) {
+        if self.frames.len() < REPLAY_BUFFER_MAX {
+            self.frames.push(json);
+        } else {
+            self.skipped += 1;
+        }
+    }
+
+    /// The held frames, then a `resync` frame if any update was lost.
+    fn release(self, execution_id: &str) -> Vec<String> {
+        if self.skipped > 0 {
+            warn!(
+                execution_id = %execution_id,
+                skipped = self.skipped,
+                "Live updates lost during WebSocket history replay"
+            );
+        }
+        let resync = (self.skipped > 0)
+            .then(|| WsControl::Resync { skipped: self.skipped }.frame())
+            .flatten();
+        self.frames.into_iter().chain(resync).collect()
+    }
+}
+
+/// Send the stored history of the connection's execution and the
+/// `snapshot_complete` marker, then the live frames that arrived meanwhile.
+/// `rx` is drained while the history is read and sent, so a slow replay
+/// can't make the broadcast channel drop them. False if the client is gone,
+/// or the history couldn't be read and the connection was closed with
+/// `replay_failed` rather than claiming a complete snapshot.
+async fn replay_history(
+    state: &AppState,
+    params: &WsParams,
+    sender: &mut SplitSink<WebSocket, Message>,
+    rx: &mut broadcast::Receiver<WorkerMessage>,
+) -> bool {
+    let mut buffer = ReplayBuffer::default();
+    let sent = {
+        let history = async {
+            let loaded = state
+                .execution_store
+                .get_execution_document(&params.execution_id, params.org_id.as_deref())
+                .await;
+            match loaded {
+                Ok(Some(doc)) => {
+                    for json in params.history_frames(doc) {
+                        sender.send(Message::Text(json.into())).await?;
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    error!(execution_id = %params.execution_id, error = %e, "Failed to replay execution history");
+                    let code = ApiError::from_store(&e, ApiError::Database).code();
+                    if let Some(json) = (WsControl::ReplayFailed { code }).frame() {
+                        sender.send(Message::Text(json.into())).await?;
+                    }
+                    let close = CloseFrame { code: close_code::ERROR, reason: code.into() };
+                    sender.send(Message::Close(Some(close))).await?;
+                    return Ok(false);
+                },
+            }
+            if let Some(json) = (WsControl::SnapshotComplete { execution_id: None }).frame() {
+                sender.send(Message::Text(json.into())).await?;
+            }
+            Ok::<_, axum::Error>(true)
+        };
+        tokio::pin!(history);
+        let mut open = true;
+        loop {
+            tokio::select! {
+                sent = &mut history => break sent,
+                next = rx.recv(), if open => open = buffer.push(params, next),
             }
         }
-    });
+    };
+    if !matches!(sent, Ok(true)) {
+        return false;
+    }
+    send_all(sender, buffer.release(&params.execution_id)).await
+}
+
+/// Send `frames` in order. False if the client is gone.
+async fn send_all(sender: &mut SplitSink<WebSocket, Message>, frames: Vec<String>) -> bool {
+    for json in frames {
+        if sender.send(Message::Text(json.into())).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    state: AppState,
+    params: WsParams,
+    caller: WsCaller,
+    slot: ExecutionSlot,
+) {
+    let _connection = ConnectionGuard::open(&state.connections);
+    let (mut sender, receiver) = socket.split();
+    let mut rx = state.tx.subscribe();
+    let (outbox, outbox_rx) = mpsc::channel::<Outbound>(OUTBOX_CAPACITY);
+
+    let execution_id = params.execution_id.clone();
+
+    if !replay_history(&state, &params, &mut sender, &mut rx).await {
+        return;
+    }
 
-    let exec_id = execution_id.clone();
-    let mut recv_task = tokio::spawn(async move {
-        let execution_id = execution_id.clone();
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let Message::Close(_) = msg {
+    let recv_params = params.clone();
+    let mut send_task = tokio::spawn(send(sender, params, rx, outbox_rx));
+    let mut recv_task = tokio::spawn(receive(receiver, state, recv_params, caller, slot, outbox));
+    // Wait for the aborted task too, so the connection only stops counting
+    // once its broadcast receiver is gone
+    tokio::select! {
+        _ = (&mut send_task) => {
+            recv_task.abort();
+            let _ = recv_task.await;
+        },
+        _ = (&mut recv_task) => {
+            send_task.abort();
+            let _ = send_task.await;
+        },
+    };
+
+    info!("WebSocket disconnected for execution: {}", execution_id);
+}
+
+/// Forward live updates of the followed executions, and what the receive
+/// task queues on `outbox`, until the client is gone. Live frames of an
+/// execution still being replayed for a `subscribe` are held until its
+/// [`Outbound::Replayed`] comes through.
+async fn send(
+    mut sender: SplitSink<WebSocket, Message>,
+    params: WsParams,
+    mut rx: broadcast::Receiver<WorkerMessage>,
+    mut outbox: mpsc::Receiver<Outbound>,
+) {
+    let execution_id = params.execution_id.clone();
+    let heartbeat = Duration::from_secs(Config::get().ws_heartbeat_secs.max(1));
+    // Idle connections get a ping every `heartbeat` so proxies keep them open
+    let mut ticker = tokio::time::interval_at(Instant::now() + heartbeat, heartbeat);
+    let mut held: HashMap<String, ReplayBuffer> = HashMap::new();
+    loop {
+        let next = tokio::select! {
+            next = rx.recv() => next,
+            Some(outbound) = outbox.recv() => {
+                let frames = match outbound {
+                    Outbound::Frame(json) => vec![json],
+                    Outbound::Replayed(subscribed) => {
+                        params.subscriptions.go_live(&subscribed);
+                        // A failed subscribe no longer follows it; drop what was held
+                        held.remove(&subscribed)
+                            .filter(|_| params.subscriptions.contains(&subscribed))
+                            .map(|buffer| buffer.release(&subscribed))
+                            .unwrap_or_default()
+                    },
+                };
+                if !send_all(&mut sender, frames).await {
+                    break;
+                }
+                continue;
+            },
+            _ = ticker.tick() => {
+                if sender.send(Message::Ping(Bytes::new())).await.is_err() {
+                    info!(execution_id = %execution_id, "WebSocket ping failed; disconnecting");
+                    break;
+                }
+                continue;
+            },
+        };
+        let msg = match next {
+            Ok(msg) => msg,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    execution_id = %execution_id,
+                    skipped,
+                    "WebSocket receiver lagged; skipping stale messages"
+                );
+                // Tell the client it missed updates so it can refetch state
+                if let Some(json) = (WsControl::Resync { skipped }).frame()
+                    && sender.send(Message::Text(json.into())).await.is_err()
+                {
+                    break;
+                }
+                continue;
+            },
+            Err(RecvError::Closed) => break,
+        };
+
+        let Some(json) = params.live_frame(&msg) else {
+            continue;
+        };
+        if let Some(replaying) =
+            live_execution(&msg).filter(|id| params.subscriptions.replaying(id))
+        {
+            held.entry(replaying.to_owned()).or_default().hold(json);
+        } else if sender.send(Message::Text(json.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Read client messages until the socket closes, following the executions
+/// `subscribe` messages ask for and queueing their frames on `outbox`.
+async fn receive(
+    mut receiver: SplitStream<WebSocket>,
+    state: AppState,
+    params: WsParams,
+    caller: WsCaller,
+    slot: ExecutionSlot,
+    outbox: mpsc::Sender<Outbound>,
+) {
+    let execution_id = &params.execution_id;
+    // One claimed slot per followed execution, released once this returns
+    let mut slots = vec![slot];
+    while let Some(Ok(msg)) = receiver.next().await {
+        match msg {
+            Message::Text(text) => match serde_json::from_str::<WsCommand>(&text) {
+                Ok(command) => {
+                    let queued = run_command(&state, &params, &caller, &mut slots, command).await;
+                    for outbound in queued {
+                        if outbox.send(outbound).await.is_err() {
+                            return;
+                        }
+                    }
+                },
+                Err(e) => {
+                    debug!(execution_id = %execution_id, error = %e, "Ignoring unknown WebSocket message");
+                },
+            },
+            Message::Close(_) => {
                 info!("WebSocket close message received for execution: {}", execution_id);
                 break;
-            }
+            },
+            // Heartbeat replies only prove the peer is alive
+            Message::Pong(_) => debug!(execution_id = %execution_id, "WebSocket pong received"),
+            _ => {},
         }
-    });
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
+    }
+}
+
+/// Carry out a client message and return what to queue for the send task.
+async fn run_command(
+    state: &AppState,
+    params: &WsParams,
+    caller: &WsCaller,
+    slots: &mut Vec<ExecutionSlot>,
+    command: WsCommand,
+) -> Vec<Outbound> {
+    let frames: Vec<String> = match command {
+        WsCommand::Subscribe { execution_id, workflow_id } => {
+            let workflow_id = workflow_id.unwrap_or_else(|| caller.workflow_id.clone());
+            let frames = subscribe(state, params, caller, slots, &execution_id, &workflow_id).await;
+            return frames
+                .into_iter()
+                .map(Outbound::Frame)
+                .chain([Outbound::Replayed(execution_id)])
+                .collect();
+        },
+        WsCommand::Unsubscribe { execution_id } => {
+            if params.subscriptions.remove(&execution_id) {
+                slots.retain(|slot| slot.execution_id != execution_id);
+                info!(unsubscribed = %execution_id, "WebSocket stopped following an execution");
+            }
+            WsControl::Unsubscribed { execution_id }
+                .frame()
+                .into_iter()
+                .collect()
+        },
+        WsCommand::List => WsControl::Subscriptions { execution_ids: params.subscriptions.list() }
+            .frame()
+            .into_iter()
+            .collect(),
+    };
+    frames.into_iter().map(Outbound::Frame).collect()
+}
+
+/// Add `execution_id` to the executions a socket follows, checked the way
+/// the upgrade request was, and return the frames to answer with: a
+/// `subscribed` frame, the execution's history and `snapshot_complete`, or
+/// `subscribe_failed`, also when the history can't be read. The execution is
+/// followed before its history is read, so nothing written in between is
+/// lost, but as [`Follow::Replaying`]: the send task holds its live frames
+/// until these frames are out.
+async fn subscribe(
+    state: &AppState,
+    params: &WsParams,
+    caller: &WsCaller,
+    slots: &mut Vec<ExecutionSlot>,
+    execution_id: &str,
+    workflow_id: &str,
+) -> Vec<String> {
+    let failed = |e: ApiError| {
+        WsControl::SubscribeFailed { execution_id: execution_id.to_owned(), code: e.code() }
+            .frame()
+            .into_iter()
+            .collect()
+    };
+    let subscribed = WsControl::Subscribed { execution_id: execution_id.to_owned() }.frame();
+    if params.subscriptions.contains(execution_id) {
+        return subscribed.into_iter().collect();
+    }
+
+    let org_id = params.org_id.as_deref();
+    let user_id = caller.user_id.as_deref();
+    match check_access(state, user_id, execution_id, workflow_id, org_id).await {
+        Ok(true) => {},
+        Ok(false) => return failed(denied(user_id, execution_id, workflow_id)),
+        Err(e) => {
+            error!("Token validation error: {}", e);
+            return failed(ApiError::from_store(&e, ApiError::Internal));
+        },
+    }
+    if let Err(e) = check_owner(state, execution_id, workflow_id, org_id).await {
+        return failed(e);
+    }
+    let max = Config::get().ws_max_conn_per_execution;
+    let Some(slot) = state.execution_connections.try_open(execution_id, max) else {
+        warn!(execution_id = %execution_id, max, "Refusing subscription: connection cap reached");
+        return failed(ApiError::TooManyConnections);
     };
+    slots.push(slot);
+    params.subscriptions.insert(execution_id, Follow::Replaying);
+    info!(subscribed = %execution_id, "WebSocket subscribed to another execution");
 
-    info!("WebSocket disconnected for execution: {}", exec_id);
+    let mut frames: Vec<String> = subscribed.into_iter().collect();
+    match state
+        .execution_store
+        .get_execution_document(execution_id, org_id)
+        .await
+    {
+        Ok(Some(doc)) => frames.extend(params.history_frames(doc)),
+        Ok(None) => {},
+        Err(e) => {
+            error!(subscribed = %execution_id, error = %e, "Failed to replay subscribed execution");
+            params.subscriptions.remove(execution_id);
+            slots.retain(|slot| slot.execution_id != execution_id);
+            return failed(ApiError::from_store(&e, ApiError::Database));
+        },
+    }
+    frames.extend(
+        WsControl::SnapshotComplete { execution_id: Some(execution_id.to_owned()) }.frame(),
+    );
+    frames
 }
 
 #[cfg(test)]
+#[allow(clippy::expect_used)]
 mod tests {
+    use std::collections::HashMap;
+
     use serde_json::json;
 
-    use super::{WsNodeUpdateDto, dto_from_execution_instance, dto_with_status};
+    use super::{
+        ExecutionConnections,
+        Follow,
+        WsCommand,
+        WsControl,
+        WsFields,
+        WsFilter,
+        WsNodeUpdateDto,
+        WsParams,
+        dto_from_execution_instance,
+        dto_with_status,
+        parse_since,
+    };
     use crate::domain::models::{
         CompletionMessage,
+        ExecutionDocument,
+        HydratedNode,
+        NodeError,
         NodeExecutionInstance,
         NodeStatusMessage,
         WorkerMessage,
@@ -312,12 +1253,16 @@ mod tests {
             lineage_stack:    None,
             lineage_hash:     None,
             used_inputs:      None,
+            seq:              None,
+            org_id:           None,
         }));
 
         let dto = WsNodeUpdateDto::from(&message);
         assert_eq!(dto.node_id.as_deref(), Some("node-1"));
         assert_eq!(dto.status.as_deref(), Some("running"));
         assert_eq!(dto.input, Some(json!({"a": 1})));
+        assert_eq!(dto.executed_at.as_deref(), Some("2026-01-01T00:00:00Z"));
+        assert_eq!(dto.duration_ms, Some(5));
     }
 
     #[test]
@@ -330,6 +1275,7 @@ mod tests {
             completed_at:      "2026-01-01T00:00:00Z".to_string(),
             total_duration_ms: 10,
             failure_reason:    None,
+            org_id:            None,
         }));
 
         let dto = WsNodeUpdateDto::from(&message);
@@ -342,15 +1288,346 @@ mod tests {
         let exec = NodeExecutionInstance {
             input: Some(json!({"input": true})),
             status: Some("success".to_string()),
+            executed_at: Some("2026-01-01T00:00:00Z".to_string()),
+            duration_ms: Some(12),
             ..NodeExecutionInstance::default()
         };
 
         let node_dto = dto_from_execution_instance("node-123".to_string(), exec);
         assert_eq!(node_dto.node_id.as_deref(), Some("node-123"));
         assert_eq!(node_dto.status.as_deref(), Some("success"));
+        assert_eq!(node_dto.executed_at.as_deref(), Some("2026-01-01T00:00:00Z"));
+        assert_eq!(node_dto.duration_ms, Some(12));
+
+        let frame = serde_json::to_value(dto_with_status("completed".to_string())).expect("json");
+        assert!(frame.get("executed_at").is_none());
+        assert!(frame.get("duration_ms").is_none());
 
         let status_dto = dto_with_status("completed".to_string());
         assert_eq!(status_dto.node_id, None);
         assert_eq!(status_dto.status.as_deref(), Some("completed"));
     }
+
+    #[test]
+    fn include_projection_omits_excluded_fields() {
+        let exec = NodeExecutionInstance {
+            input: Some(json!({"big": "payload"})),
+            parameters: Some(json!({"p": 1})),
+            output: Some(json!({"out": true})),
+            status: Some("success".to_string()),
+            ..NodeExecutionInstance::default()
+        };
+        let dto = dto_from_execution_instance("node-1".to_string(), exec);
+
+        let frame = WsFields::default().frame(&dto).expect("frame");
+        let compact: serde_json::Value = serde_json::from_str(&frame).expect("frame is JSON");
+        assert_eq!(compact.get("node_id"), Some(&json!("node-1")));
+        assert_eq!(compact.get("status"), Some(&json!("success")));
+        assert_eq!(compact.get("output"), Some(&json!({"out": true})));
+        assert!(compact.get("input").is_none());
+        assert!(compact.get("params").is_none());
+        assert!(compact.get("error").is_none());
+
+        let fields: WsFields = "input, error".parse().expect("valid include list");
+        let frame = fields.frame(&dto).expect("frame");
+        let selected: serde_json::Value = serde_json::from_str(&frame).expect("frame is JSON");
+        assert_eq!(selected.get("input"), Some(&json!({"big": "payload"})));
+        // `error` is requested but the node didn't fail, so it stays omitted
+        assert!(selected.get("error").is_none());
+        assert!(selected.get("status").is_none());
+        assert!(selected.get("output").is_none());
+
+        assert!("status,bogus".parse::<WsFields>().is_err());
+    }
+
+    #[test]
+    fn resync_control_frame_reports_skipped_count_without_node_fields() {
+        let frame = WsControl::Resync { skipped: 42 }.frame().expect("frame");
+        let frame: serde_json::Value = serde_json::from_str(&frame).expect("frame is JSON");
+        assert_eq!(frame, json!({"type": "resync", "skipped": 42}));
+    }
+
+    #[test]
+    fn node_errors_reach_live_and_history_frames() {
+        let error = NodeError {
+            message: "boom".to_string(),
+            code:    "HTTP_500".to_string(),
+            details: None,
+        };
+        let with_error: WsFields = "status,error".parse().expect("valid include list");
+
+        let history = dto_from_execution_instance(
+            "node-1".to_string(),
+            NodeExecutionInstance {
+                status: Some("failed".to_string()),
+                error: Some(error.clone()),
+                ..NodeExecutionInstance::default()
+            },
+        );
+        let frame: serde_json::Value =
+            serde_json::from_str(&with_error.frame(&history).expect("frame")).expect("JSON");
+        assert_eq!(frame.pointer("/error/code"), Some(&json!("HTTP_500")));
+
+        let live = WsNodeUpdateDto::from(&WorkerMessage::NodeStatus(Box::new(NodeStatusMessage {
+            workflow_id:      "wf-1".to_string(),
+            execution_id:     "exec-1".to_string(),
+            node_id:          "node-1".to_string(),
+            node_name:        "Node".to_string(),
+            status:           "failed".to_string(),
+            input:            None,
+            parameters:       None,
+            output:           None,
+            error:            Some(error),
+            executed_at:      "2026-01-01T00:00:00Z".to_string(),
+            duration_ms:      5,
+            branch_id:        None,
+            split_node_id:    None,
+            item_index:       None,
+            total_items:      None,
+            processed_count:  None,
+            aggregator_state: None,
+            lineage_stack:    None,
+            lineage_hash:     None,
+            used_inputs:      None,
+            seq:              None,
+            org_id:           None,
+        })));
+        let frame: serde_json::Value =
+            serde_json::from_str(&with_error.frame(&live).expect("frame")).expect("JSON");
+        assert_eq!(frame.pointer("/error/message"), Some(&json!("boom")));
+    }
+
+    #[test]
+    fn dto_from_halted_completion_carries_status_and_reason() {
+        let message = WorkerMessage::WorkflowCompletion(Box::new(CompletionMessage {
+            workflow_id:       "wf-1".to_string(),
+            execution_id:      "exec-1".to_string(),
+            status:            "halted".to_string(),
+            final_context:     json!({}),
+            completed_at:      "2026-01-01T00:00:00Z".to_string(),
+            total_duration_ms: 10,
+            failure_reason:    Some("stopped by user".to_string()),
+            org_id:            None,
+        }));
+
+        let dto = WsNodeUpdateDto::from(&message);
+        assert_eq!(dto.status.as_deref(), Some("halted"));
+        assert_eq!(dto.failure_reason.as_deref(), Some("stopped by user"));
+
+        let frame: serde_json::Value =
+            serde_json::from_str(&WsFields::default().frame(&dto).expect("frame")).expect("JSON");
+        assert_eq!(frame.get("failure_reason"), Some(&json!("stopped by user")));
+    }
+
+    #[test]
+    fn live_frames_only_reach_subscribers_of_the_same_organization() {
+        let completion = |org_id: Option<&str>| {
+            WorkerMessage::WorkflowCompletion(Box::new(CompletionMessage {
+                workflow_id:       "wf-1".to_string(),
+                execution_id:      "exec-1".to_string(),
+                status:            "completed".to_string(),
+                final_context:     json!({}),
+                completed_at:      "2026-01-01T00:00:00Z".to_string(),
+                total_duration_ms: 10,
+                failure_reason:    None,
+                org_id:            org_id.map(str::to_owned),
+            }))
+        };
+        let params = |org_id: Option<&str>| {
+            WsParams::new(
+                "exec-1".to_string(),
+                org_id.map(str::to_owned),
+                WsFields::default(),
+                WsFilter::default(),
+                None,
+            )
+        };
+
+        assert!(
+            params(Some("org-a"))
+                .live_frame(&completion(Some("org-a")))
+                .is_some()
+        );
+        assert!(
+            params(Some("org-a"))
+                .live_frame(&completion(Some("org-b")))
+                .is_none()
+        );
+        assert!(
+            params(Some("org-a"))
+                .live_frame(&completion(None))
+                .is_none()
+        );
+        assert!(
+            params(None)
+                .live_frame(&completion(Some("org-b")))
+                .is_none()
+        );
+        assert!(params(None).live_frame(&completion(None)).is_some());
+    }
+
+    #[test]
+    fn history_replay_sends_each_lineage_once() {
+        let run = |hash: Option<&str>, output: Option<serde_json::Value>| NodeExecutionInstance {
+            status: Some("success".to_string()),
+            executed_at: Some("2026-01-01T00:00:00Z".to_string()),
+            lineage_hash: hash.map(str::to_string),
+            output,
+            ..NodeExecutionInstance::default()
+        };
+        let node = HydratedNode {
+            // `latest` duplicates lineage-b, and two stored lineages share lineage-a
+            latest: Some(run(Some("lineage-b"), None)),
+            lineages: HashMap::from([
+                ("lineage-a".to_string(), run(Some("lineage-a"), None)),
+                ("lineage-a-legacy".to_string(), run(Some("lineage-a"), Some(json!({ "n": 1 })))),
+                ("lineage-b".to_string(), run(Some("lineage-b"), Some(json!({ "n": 2 })))),
+            ]),
+            lineage_count: 2,
+            ..HydratedNode::default()
+        };
+        let doc = ExecutionDocument {
+            execution_id: "exec-1".to_string(),
+            nodes: HashMap::from([("node-1".to_string(), node)]),
+            ..ExecutionDocument::default()
+        };
+        let params = WsParams::new(
+            "exec-1".to_string(),
+            None,
+            WsFields::default(),
+            WsFilter::default(),
+            None,
+        );
+
+        let frames: Vec<serde_json::Value> = params
+            .history_frames(doc)
+            .iter()
+            .map(|frame| serde_json::from_str(frame).expect("frame should be json"))
+            .collect();
+        assert_eq!(frames.len(), 2);
+        for (frame, (hash, output)) in frames
+            .iter()
+            .zip([("lineage-a", json!({ "n": 1 })), ("lineage-b", json!({ "n": 2 }))])
+        {
+            assert_eq!(frame.get("lineage_hash"), Some(&json!(hash)));
+            assert_eq!(frame.get("output"), Some(&output), "richest run should win");
+        }
+    }
+
+    #[test]
+    fn since_replays_only_runs_after_the_timestamp() {
+        let node = |executed_at: Option<&str>| HydratedNode {
+            latest: Some(NodeExecutionInstance {
+                status: Some("success".to_string()),
+                executed_at: executed_at.map(str::to_string),
+                ..NodeExecutionInstance::default()
+            }),
+            ..HydratedNode::default()
+        };
+        let doc = ExecutionDocument {
+            execution_id: "exec-1".to_string(),
+            nodes: HashMap::from([
+                ("before".to_string(), node(Some("2026-01-01T00:00:00Z"))),
+                ("at".to_string(), node(Some("2026-01-01T01:00:00+01:00"))),
+                ("after".to_string(), node(Some("2026-01-01T00:00:01Z"))),
+                ("malformed".to_string(), node(Some("yesterday"))),
+                ("unknown".to_string(), node(None)),
+            ]),
+            status: Some("running".to_string()),
+            ..ExecutionDocument::default()
+        };
+        let params = WsParams::new(
+            "exec-1".to_string(),
+            None,
+            WsFields::default(),
+            WsFilter::default(),
+            parse_since(Some("2026-01-01T00:00:00Z")).expect("valid since"),
+        );
+
+        let mut sent: Vec<String> = params
+            .history_frames(doc)
+            .iter()
+            .map(|frame| serde_json::from_str::<serde_json::Value>(frame).expect("json"))
+            .filter_map(|frame| frame.get("node_id")?.as_str().map(str::to_string))
+            .collect();
+        sent.sort();
+        assert_eq!(sent, ["after", "malformed", "unknown"]);
+
+        assert!(parse_since(Some("not-a-time")).is_err());
+        assert_eq!(parse_since(None), Ok(None));
+    }
+
+    #[test]
+    fn frames_of_subscribed_executions_name_their_execution() {
+        let params = WsParams::new(
+            "exec-1".to_string(),
+            None,
+            WsFields::default(),
+            WsFilter::default(),
+            None,
+        );
+        let doc = |execution_id: &str| ExecutionDocument {
+            execution_id: execution_id.to_string(),
+            status: Some("running".to_string()),
+            ..ExecutionDocument::default()
+        };
+        // The execution each replayed frame names, if any
+        let tags = |execution_id: &str| -> Vec<Option<serde_json::Value>> {
+            params
+                .history_frames(doc(execution_id))
+                .iter()
+                .map(|frame| {
+                    serde_json::from_str::<serde_json::Value>(frame)
+                        .expect("frame is JSON")
+                        .get("execution_id")
+                        .cloned()
+                })
+                .collect()
+        };
+
+        assert_eq!(tags("exec-1"), vec![None]);
+        assert!(params.subscriptions.insert("exec-2", Follow::Live));
+        assert!(!params.subscriptions.insert("exec-2", Follow::Live));
+        assert_eq!(tags("exec-2"), vec![Some(json!("exec-2"))]);
+
+        let command: WsCommand =
+            serde_json::from_str(r#"{"action": "subscribe", "execution_id": "exec-2"}"#)
+                .expect("subscribe parses");
+        assert_eq!(
+            command,
+            WsCommand::Subscribe { execution_id: "exec-2".to_string(), workflow_id: None }
+        );
+        let refused = WsControl::SubscribeFailed {
+            execution_id: "exec-3".to_string(),
+            code:         "FORBIDDEN",
+        };
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&refused.frame().expect("frame"))
+                .expect("frame is JSON"),
+            json!({"type": "subscribe_failed", "execution_id": "exec-3", "code": "FORBIDDEN"})
+        );
+    }
+
+    #[test]
+    fn execution_connections_are_capped_and_released_on_drop() {
+        let connections = ExecutionConnections::default();
+        let first = connections.try_open("exec-1", 2).expect("below the cap");
+        let second = connections.try_open("exec-1", 2).expect("at the cap");
+        assert!(connections.try_open("exec-1", 2).is_none());
+        // Other executions have their own budget
+        let other = connections
+            .try_open("exec-2", 2)
+            .expect("separate execution");
+
+        drop(first);
+        assert_eq!(connections.open_for("exec-1"), 1);
+        let third = connections
+            .try_open("exec-1", 2)
+            .expect("a slot was released");
+
+        drop((second, third, other));
+        assert_eq!(connections.open_for("exec-1"), 0);
+        assert!(connections.lock().is_empty(), "idle executions should not linger");
+        assert!((0..5).all(|_| connections.try_open("exec-1", 0).is_some()));
+    }
 }
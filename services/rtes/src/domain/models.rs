@@ -1,6 +1,6 @@
 #![allow(unreachable_pub)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use mongodb::bson::DateTime;
 use serde::{Deserialize, Serialize, de::Deserializer};
@@ -58,6 +58,26 @@ pub struct StackFrame {
     pub total_items:   i32,
 }
 
+/// What a grant lets its holder do. Scopes are ordered, so an owner can do
+/// everything a viewer can.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    /// Read the execution and follow it live
+    Viewer,
+    /// Also change it, e.g. delete it. Grants issued before scopes existed
+    /// are owners.
+    #[default]
+    Owner,
+}
+
+impl TokenScope {
+    /// Whether a grant of this scope satisfies `required`.
+    pub fn allows(self, required: Self) -> bool {
+        self >= required
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ExecutionToken {
     pub execution_id: Option<String>,
@@ -65,6 +85,63 @@ pub struct ExecutionToken {
     pub iat:          i64,
     pub exp:          i64,
     pub user_id:      String,
+    /// Unique token id, the handle used to revoke this grant
+    #[serde(default = "ExecutionToken::new_jti")]
+    pub jti:          String,
+    #[serde(default)]
+    pub scope:        TokenScope,
+    /// Organization the grant is confined to; a grant without one only
+    /// reaches executions stored without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:       Option<String>,
+}
+
+impl ExecutionToken {
+    /// Fresh random token id for stored grants that predate `jti`.
+    pub fn new_jti() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// Token id derived from the grant itself, so the same grant delivered
+    /// twice is stored once.
+    pub fn grant_jti(&self) -> String {
+        let key = format!(
+            "{}\n{}\n{}\n{}\n{}",
+            self.user_id,
+            self.workflow_id,
+            self.execution_id.as_deref().unwrap_or("*"),
+            self.iat,
+            self.exp
+        );
+        // Grants from before scopes and organizations keep the id they were
+        // stored under
+        let key = match self.scope {
+            TokenScope::Viewer => format!("{key}\nviewer"),
+            TokenScope::Owner => key,
+        };
+        let key = match &self.org_id {
+            Some(org_id) => format!("{key}\n{org_id}"),
+            None => key,
+        };
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, key.as_bytes()).to_string()
+    }
+
+    /// Whether this grant may reach an execution of `org_id`.
+    pub fn in_org(&self, org_id: Option<&str>) -> bool {
+        self.org_id.as_deref() == org_id
+    }
+
+    /// Why this token can never grant access at `now` (unix seconds), if so:
+    /// already expired, or issued more than `skew_secs` in the future.
+    pub const fn validity_error(&self, now: i64, skew_secs: i64) -> Option<&'static str> {
+        if self.exp <= now {
+            Some("token already expired")
+        } else if self.iat > now.saturating_add(skew_secs) {
+            Some("token issued in the future")
+        } else {
+            None
+        }
+    }
 }
 
 /// Token payload consumed from RabbitMQ.
@@ -84,6 +161,16 @@ pub struct ExecutionTokenPayload {
     pub iat:           i64,
     pub exp:           i64,
     pub user_id:       String,
+    /// Token id from the issuer; kept as-is when the payload grants a single
+    /// token
+    #[serde(default)]
+    pub jti:           Option<String>,
+    /// Scope of every grant in the payload; owner when absent
+    #[serde(default)]
+    pub scope:         TokenScope,
+    /// Organization every grant in the payload is confined to
+    #[serde(default, alias = "orgId")]
+    pub org_id:        Option<String>,
 }
 
 impl ExecutionTokenPayload {
@@ -117,30 +204,37 @@ impl ExecutionTokenPayload {
 
         let execution_ids = Self::normalize_ids(self.execution_id, self.execution_ids);
 
+        let grant = |workflow_id: String, execution_id: Option<String>| {
+            let mut token = ExecutionToken {
+                execution_id,
+                workflow_id,
+                iat: self.iat,
+                exp: self.exp,
+                user_id: self.user_id.clone(),
+                jti: String::new(),
+                scope: self.scope,
+                org_id: self.org_id.clone(),
+            };
+            token.jti = token.grant_jti();
+            token
+        };
+
         let mut tokens = Vec::new();
         if execution_ids.is_empty() {
             for workflow_id in workflow_ids {
-                tokens.push(ExecutionToken {
-                    execution_id: None,
-                    workflow_id,
-                    iat: self.iat,
-                    exp: self.exp,
-                    user_id: self.user_id.clone(),
-                });
+                tokens.push(grant(workflow_id, None));
+            }
+        } else {
+            for workflow_id in workflow_ids {
+                for execution_id in &execution_ids {
+                    tokens.push(grant(workflow_id.clone(), Some(execution_id.clone())));
+                }
             }
-            return Ok(tokens);
         }
 
-        for workflow_id in workflow_ids {
-            for execution_id in &execution_ids {
-                tokens.push(ExecutionToken {
-                    execution_id: Some(execution_id.clone()),
-                    workflow_id:  workflow_id.clone(),
-                    iat:          self.iat,
-                    exp:          self.exp,
-                    user_id:      self.user_id.clone(),
-                });
-            }
+        // A single grant keeps the issuer's id so it can be revoked by it
+        if let (Some(jti), [token]) = (self.jti, tokens.as_mut_slice()) {
+            token.jti = jti;
         }
 
         Ok(tokens)
@@ -177,6 +271,47 @@ pub struct NodeStatusMessage {
     pub lineage_stack:    Option<Vec<StackFrame>>,
     pub lineage_hash:     Option<String>,
     pub used_inputs:      Option<Value>,
+    /// Increases with each status the worker sends for the node; a status
+    /// whose `seq` is not above the stored one is stale and not applied.
+    /// Unset falls back to last write wins.
+    #[serde(default)]
+    pub seq:              Option<u64>,
+    /// Organization of the execution; the status only applies to an
+    /// execution of the same organization, or without one to an execution
+    /// stored without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:           Option<String>,
+}
+
+/// A node status transition persisted in the per-execution status log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NodeStatusRecord {
+    /// Log entry id; breaks ties between entries sharing `executed_at`.
+    pub id:      String,
+    #[serde(flatten)]
+    pub message: NodeStatusMessage,
+}
+
+/// Position in the status log, ordered by `executed_at` then entry id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusCursor {
+    pub executed_at: String,
+    pub id:          String,
+}
+
+impl StatusCursor {
+    pub fn after(record: &NodeStatusRecord) -> Self {
+        Self { executed_at: record.message.executed_at.clone(), id: record.id.clone() }
+    }
+
+    pub fn encode(&self) -> String {
+        crate::util::cursor::encode(&[&self.executed_at, &self.id])
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let mut parts = crate::util::cursor::decode(cursor, 2)?.into_iter();
+        Some(Self { executed_at: parts.next()?, id: parts.next()? })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -189,6 +324,9 @@ pub struct CompletionMessage {
     pub completed_at:      String,
     pub total_duration_ms: i64,
     pub failure_reason:    Option<String>,
+    /// See [`NodeStatusMessage::org_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:            Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -204,6 +342,10 @@ pub struct NodeExecutionMessage {
     pub lineage_stack:       Option<Vec<StackFrame>>,
     pub from_node:           Option<String>,
     pub is_worker_initiated: Option<bool>,
+    /// Organization the execution is stored under; see
+    /// [`ExecutionDocument::org_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:              Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -241,16 +383,136 @@ pub struct NodeExecutionInstance {
     pub processed_count:  Option<i32>,
     #[serde(default)]
     pub aggregator_state: Option<String>,
+    /// `seq` of the status message this run was written from
+    #[serde(default)]
+    pub seq:              Option<u64>,
+}
+
+impl NodeExecutionInstance {
+    /// Every serialized field of a run, for validating `?fields=` paths.
+    pub const FIELDS: [&str; 19] = [
+        "input",
+        "parameters",
+        "output",
+        "status",
+        "error",
+        "executed_at",
+        "duration_ms",
+        "lineage_hash",
+        "lineage_stack",
+        "used_inputs",
+        "node_type",
+        "name",
+        "branch_id",
+        "split_node_id",
+        "item_index",
+        "total_items",
+        "processed_count",
+        "aggregator_state",
+        "seq",
+    ];
+
+    /// Fields holding node payloads, dropped by the compact execution view.
+    pub const PAYLOAD_FIELDS: [&str; 4] = ["input", "output", "parameters", "used_inputs"];
+
+    /// Drop the payload fields, keeping status, timing and error.
+    pub fn strip_payloads(&mut self) {
+        self.input = None;
+        self.output = None;
+        self.parameters = None;
+        self.used_inputs = None;
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct HydratedNode {
     #[serde(default)]
-    pub latest:   Option<NodeExecutionInstance>,
+    pub latest:        Option<NodeExecutionInstance>,
+    #[serde(default)]
+    pub lineages:      HashMap<String, NodeExecutionInstance>,
+    /// Number of distinct lineages (loop iterations / branches) this node ran
+    /// across.
     #[serde(default)]
-    pub lineages: HashMap<String, NodeExecutionInstance>,
+    pub lineage_count: u64,
     #[serde(flatten, default)]
-    pub extra:    HashMap<String, Value>,
+    pub extra:         HashMap<String, Value>,
+}
+
+impl HydratedNode {
+    /// A node as stored under `nodes`, in any shape it was ever written in:
+    /// a `latest`/`lineages` entry, or a bare run from before lineages.
+    pub fn from_stored(value: Value) -> Self {
+        match value {
+            Value::Object(obj) => {
+                if obj.contains_key("lineages") || obj.contains_key("latest") {
+                    let latest: Option<NodeExecutionInstance> = obj
+                        .get("latest")
+                        .cloned()
+                        .and_then(|v| serde_json::from_value(v).ok());
+
+                    let lineages: HashMap<String, NodeExecutionInstance> = obj
+                        .get("lineages")
+                        .cloned()
+                        .and_then(|v| serde_json::from_value(v).ok())
+                        .unwrap_or_default();
+
+                    // Documents written before the counter existed fall back to
+                    // the number of stored lineages.
+                    let lineage_count = obj
+                        .get("lineage_count")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(lineages.len() as u64);
+
+                    let mut extra = obj.into_iter().collect::<HashMap<_, _>>();
+                    extra.remove("latest");
+                    extra.remove("lineages");
+                    extra.remove("lineage_count");
+
+                    Self { latest, lineages, lineage_count, extra }
+                } else {
+                    let obj_clone = obj.clone();
+                    serde_json::from_value::<NodeExecutionInstance>(Value::Object(obj_clone))
+                        .ok()
+                        .filter(|inst| inst.status.is_some())
+                        .map_or_else(
+                            || Self {
+                                latest:        None,
+                                lineages:      HashMap::new(),
+                                lineage_count: 0,
+                                extra:         obj.into_iter().collect(),
+                            },
+                            |instance| Self {
+                                latest:        Some(instance),
+                                lineages:      HashMap::new(),
+                                lineage_count: 0,
+                                extra:         HashMap::new(),
+                            },
+                        )
+                }
+            },
+            other => serde_json::from_value::<NodeExecutionInstance>(other).map_or_else(
+                |_| Self {
+                    latest:        None,
+                    lineages:      HashMap::new(),
+                    lineage_count: 0,
+                    extra:         HashMap::new(),
+                },
+                |instance| Self {
+                    latest:        Some(instance),
+                    lineages:      HashMap::new(),
+                    lineage_count: 0,
+                    extra:         HashMap::new(),
+                },
+            ),
+        }
+    }
+}
+
+/// One line of `GET /executions/{execution_id}/nodes:stream`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NodeEntry {
+    pub node_id: String,
+    pub node:    HydratedNode,
 }
 
 /// Stored hydrated execution document.
@@ -258,12 +520,20 @@ pub struct HydratedNode {
 pub struct ExecutionDocument {
     pub execution_id:        String,
     pub workflow_id:         String,
+    /// Organization the execution belongs to; unset on executions stored
+    /// before organizations, which only grants without one reach
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:              Option<String>,
     #[serde(default)]
     pub workflow_version:    Option<i32>,
     #[serde(default)]
     pub workflow_version_id: Option<i64>,
     #[serde(default)]
     pub workflow_definition: Value,
+    /// Node and edge topology of the workflow, stored apart from node runs;
+    /// unset on executions stored before it existed
+    #[serde(default)]
+    pub graph:               Option<ExecutionGraph>,
     #[serde(default)]
     pub accumulated_context: Value,
     #[serde(default, deserialize_with = "deserialize_nodes")]
@@ -277,86 +547,577 @@ pub struct ExecutionDocument {
     pub created_at:          Option<DateTime>,
     #[serde(default, with = "datetime_iso")]
     pub updated_at:          Option<DateTime>,
+    /// When the TTL index removes this execution; unset keeps it forever
+    #[serde(default, with = "datetime_iso")]
+    pub expires_at:          Option<DateTime>,
+    /// Wall time the worker reported on completion
+    #[serde(default)]
+    pub total_duration_ms:   Option<i64>,
+    /// `completed_at` of the completion applied to this execution; tells a
+    /// redelivered completion from a new one
+    #[serde(default)]
+    pub completed_at:        Option<String>,
+    /// Why the workflow failed or halted, from its completion
+    #[serde(default)]
+    pub failure_reason:      Option<String>,
+    /// Context the workflow finished with, from its completion; null until
+    /// it completes
+    #[serde(default)]
+    pub final_context:       Value,
+    /// Node counters kept current by status writes; unset on executions
+    /// stored before they existed
+    #[serde(default)]
+    pub progress:            Option<ExecutionProgress>,
 }
 
-/// Deterministically hash a lineage stack for use as a stable key.
-pub fn compute_lineage_hash(stack: &[StackFrame]) -> Option<String> {
-    serde_json::to_vec(stack)
-        .ok()
-        .map(|bytes| Uuid::new_v5(&Uuid::NAMESPACE_OID, &bytes).to_string())
+/// The workflow graph an execution runs, without parameters, credentials
+/// or any run data, for drawing the DAG.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Normalized edges: `id`, `src`, `dst`, `condition` (`null` when
+    /// unconditional), `sourceHandle`, `targetHandle` (empty when unset) and
+    /// whatever else the workflow definition put on them
+    pub edges: Vec<Value>,
 }
 
-fn deserialize_nodes<'de, D>(deserializer: D) -> Result<HashMap<String, HydratedNode>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let raw: HashMap<String, Value> = HashMap::deserialize(deserializer)?;
-    let mut result: HashMap<String, HydratedNode> = HashMap::new();
+/// A workflow node as drawn in the graph.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct GraphNode {
+    pub id:        String,
+    #[serde(default)]
+    pub name:      String,
+    #[serde(rename = "type", default)]
+    pub node_type: String,
+    #[serde(default)]
+    pub trigger:   bool,
+}
 
-    for (node_id, value) in raw {
-        let hydrated = match value {
-            Value::Object(obj) => {
-                if obj.contains_key("lineages") || obj.contains_key("latest") {
-                    let latest: Option<NodeExecutionInstance> = obj
-                        .get("latest")
-                        .cloned()
-                        .and_then(|v| serde_json::from_value(v).ok());
+impl GraphNode {
+    /// The graph view of a node definition; `None` for anything that isn't
+    /// a JSON object.
+    fn from_definition(definition: &Value) -> Option<Self> {
+        definition.as_object()?;
+        serde_json::from_value(definition.clone()).ok()
+    }
+}
 
-                    let lineages: HashMap<String, NodeExecutionInstance> = obj
-                        .get("lineages")
-                        .cloned()
-                        .and_then(|v| serde_json::from_value(v).ok())
-                        .unwrap_or_default();
+impl ExecutionGraph {
+    /// The graph of normalized node definitions and edges, in definition
+    /// order.
+    pub fn from_definition(nodes: &[Value], edges: Vec<Value>) -> Self {
+        Self {
+            nodes: nodes
+                .iter()
+                .filter_map(GraphNode::from_definition)
+                .collect(),
+            edges,
+        }
+    }
 
-                    let mut extra = obj.clone().into_iter().collect::<HashMap<_, _>>();
-                    extra.remove("latest");
-                    extra.remove("lineages");
+    /// The graph of an execution stored before [`ExecutionDocument::graph`]
+    /// existed, rebuilt from the definition fields kept on each node, in id
+    /// order.
+    pub fn from_document(doc: &ExecutionDocument) -> Self {
+        let mut nodes: Vec<GraphNode> = doc
+            .nodes
+            .iter()
+            .filter_map(|(node_id, node)| {
+                let mut definition: serde_json::Map<String, Value> =
+                    node.extra.clone().into_iter().collect();
+                definition
+                    .entry("id")
+                    .or_insert_with(|| Value::String(node_id.clone()));
+                GraphNode::from_definition(&Value::Object(definition))
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        Self { nodes, edges: doc.edges.clone() }
+    }
+}
+
+/// How many of an execution's nodes have succeeded, failed or are running,
+/// by each node's latest status.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionProgress {
+    #[serde(default)]
+    pub total_nodes: i64,
+    #[serde(default)]
+    pub succeeded:   i64,
+    #[serde(default)]
+    pub failed:      i64,
+    #[serde(default)]
+    pub running:     i64,
+}
+
+impl ExecutionProgress {
+    /// Counter (field name) a node status is tallied under; other statuses
+    /// such as `pending` or `waiting` aren't counted.
+    pub fn counter(status: &str) -> Option<&'static str> {
+        match status.to_ascii_lowercase().as_str() {
+            "success" | "succeeded" | "completed" => Some("succeeded"),
+            "failed" | "error" => Some("failed"),
+            "running" => Some("running"),
+            _ => None,
+        }
+    }
+
+    /// Count from the nodes' latest statuses, for executions stored without
+    /// counters.
+    pub fn from_nodes(nodes: &HashMap<String, HydratedNode>) -> Self {
+        let mut progress =
+            Self { total_nodes: i64::try_from(nodes.len()).unwrap_or(i64::MAX), ..Self::default() };
+        let statuses = nodes
+            .values()
+            .filter_map(|node| node.latest.as_ref()?.status.as_deref());
+        for counter in statuses.filter_map(Self::counter) {
+            match counter {
+                "succeeded" => progress.succeeded += 1,
+                "failed" => progress.failed += 1,
+                "running" => progress.running += 1,
+                _ => {},
+            }
+        }
+        progress
+    }
+}
+
+/// A run of a node inside one of a split's branches.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NodeIteration {
+    pub node_id: String,
+    #[serde(flatten)]
+    pub run:     NodeExecutionInstance,
+}
+
+/// Every branch run of a split (loop/fan-out) node, for progress views.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SplitIterations {
+    pub execution_id:  String,
+    pub workflow_id:   String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id:        Option<String>,
+    pub split_node_id: String,
+    /// Ordered by `item_index`, then node and `executed_at`
+    pub iterations:    Vec<NodeIteration>,
+}
+
+impl ExecutionDocument {
+    /// Whether the execution belongs to `org_id`; one stored without an
+    /// organization only belongs to `None`.
+    pub fn in_org(&self, org_id: Option<&str>) -> bool {
+        self.org_id.as_deref() == org_id
+    }
 
-                    HydratedNode { latest, lineages, extra }
+    /// The runs recorded under `split_node_id`'s branches: each node's
+    /// lineages, or its latest run when it has none.
+    pub fn split_iterations(&self, split_node_id: &str) -> SplitIterations {
+        let mut iterations: Vec<NodeIteration> = self
+            .nodes
+            .iter()
+            .flat_map(|(node_id, node)| {
+                let runs: Vec<&NodeExecutionInstance> = if node.lineages.is_empty() {
+                    node.latest.iter().collect()
                 } else {
-                    let obj_clone = obj.clone();
-                    serde_json::from_value::<NodeExecutionInstance>(Value::Object(obj_clone))
-                        .ok()
-                        .filter(|inst| inst.status.is_some())
-                        .map_or_else(
-                            || HydratedNode {
-                                latest:   None,
-                                lineages: HashMap::new(),
-                                extra:    obj.into_iter().collect(),
-                            },
-                            |instance| HydratedNode {
-                                latest:   Some(instance),
-                                lineages: HashMap::new(),
-                                extra:    HashMap::new(),
-                            },
-                        )
-                }
-            },
-            other => serde_json::from_value::<NodeExecutionInstance>(other.clone()).map_or_else(
-                |_| HydratedNode {
-                    latest:   None,
-                    lineages: HashMap::new(),
-                    extra:    HashMap::new(),
+                    node.lineages.values().collect()
+                };
+                runs.into_iter()
+                    .filter(|run| run.split_node_id.as_deref() == Some(split_node_id))
+                    .map(|run| NodeIteration { node_id: node_id.clone(), run: run.clone() })
+            })
+            .collect();
+        iterations.sort_by(|a, b| {
+            // Runs without an index go last
+            (a.run.item_index.is_none(), a.run.item_index, &a.node_id, &a.run.executed_at).cmp(&(
+                b.run.item_index.is_none(),
+                b.run.item_index,
+                &b.node_id,
+                &b.run.executed_at,
+            ))
+        });
+        SplitIterations {
+            execution_id: self.execution_id.clone(),
+            workflow_id: self.workflow_id.clone(),
+            org_id: self.org_id.clone(),
+            split_node_id: split_node_id.to_owned(),
+            iterations,
+        }
+    }
+
+    /// Whether some node's latest run was of `node_type`.
+    pub fn ran_node_type(&self, node_type: &str) -> bool {
+        self.nodes.values().any(|node| {
+            node.latest
+                .as_ref()
+                .and_then(|latest| latest.node_type.as_deref())
+                == Some(node_type)
+        })
+    }
+
+    /// Strip payloads from every node's latest run and lineages, leaving what
+    /// a graph view needs (the compact view).
+    pub fn compact(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.latest
+                .iter_mut()
+                .chain(node.lineages.values_mut())
+                .for_each(NodeExecutionInstance::strip_payloads);
+        }
+    }
+}
+
+/// Paths of an execution selected with `?fields=`, e.g.
+/// `status,nodes.latest.status`. Only known paths parse, so a selection can
+/// be turned into a MongoDB projection without injecting arbitrary ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionFields {
+    /// Top-level fields kept whole
+    pub top:   BTreeSet<String>,
+    /// Parts of every node kept (`latest`, `lineages`, `lineage_count`):
+    /// `None` keeps the part whole, otherwise only those run fields
+    pub nodes: BTreeMap<String, Option<BTreeSet<String>>>,
+}
+
+impl ExecutionFields {
+    /// Top-level fields of [`ExecutionDocument`].
+    pub const TOP_LEVEL: [&str; 21] = [
+        "execution_id",
+        "workflow_id",
+        "org_id",
+        "workflow_version",
+        "workflow_version_id",
+        "workflow_definition",
+        "graph",
+        "accumulated_context",
+        "nodes",
+        "edges",
+        "status",
+        "name",
+        "node_type",
+        "created_at",
+        "updated_at",
+        "expires_at",
+        "total_duration_ms",
+        "completed_at",
+        "failure_reason",
+        "final_context",
+        "progress",
+    ];
+
+    /// Parse a comma-separated field list, naming the first unknown path.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut fields = Self::default();
+        for path in list
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+        {
+            let segments: Vec<&str> = path.split('.').collect();
+            match segments.as_slice() {
+                [field] if Self::TOP_LEVEL.contains(field) => {
+                    fields.top.insert((*field).to_owned());
                 },
-                |instance| HydratedNode {
-                    latest:   Some(instance),
-                    lineages: HashMap::new(),
-                    extra:    HashMap::new(),
+                ["nodes", "lineage_count"] => {
+                    fields.nodes.insert("lineage_count".to_owned(), None);
                 },
-            ),
+                ["nodes", part @ ("latest" | "lineages")] => {
+                    fields.nodes.insert((*part).to_owned(), None);
+                },
+                ["nodes", part @ ("latest" | "lineages"), field]
+                    if NodeExecutionInstance::FIELDS.contains(field) =>
+                {
+                    if let Some(run_fields) = fields
+                        .nodes
+                        .entry((*part).to_owned())
+                        .or_insert_with(|| Some(BTreeSet::new()))
+                    {
+                        run_fields.insert((*field).to_owned());
+                    }
+                },
+                _ => return Err(path.to_owned()),
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Whether `nodes` is selected only in part.
+    pub fn narrows_nodes(&self) -> bool {
+        !self.nodes.is_empty() && !self.top.contains("nodes")
+    }
+
+    /// Also select `path`, which must be a known path.
+    #[must_use]
+    pub fn with(mut self, path: &str) -> Self {
+        if let Ok(extra) = Self::parse(path) {
+            self.top.extend(extra.top);
+            for (part, run_fields) in extra.nodes {
+                match (self.nodes.get_mut(&part), run_fields) {
+                    (Some(Some(kept)), Some(run_fields)) => kept.extend(run_fields),
+                    (Some(kept), None) => *kept = None,
+                    (Some(None), Some(_)) => {},
+                    (None, run_fields) => {
+                        self.nodes.insert(part, run_fields);
+                    },
+                }
+            }
+        }
+        self
+    }
+
+    /// Drop everything not selected from a serialized execution.
+    pub fn retain(&self, execution: &mut Value) {
+        let Some(execution) = execution.as_object_mut() else {
+            return;
+        };
+        let nodes = execution.remove("nodes");
+        execution.retain(|field, _| self.top.contains(field));
+        let Some(mut nodes) = nodes else {
+            return;
         };
-        result.insert(node_id, hydrated);
+        if self.top.contains("nodes") {
+            execution.insert("nodes".to_owned(), nodes);
+            return;
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        for node in nodes
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|nodes| nodes.values_mut())
+        {
+            let Some(node) = node.as_object_mut() else {
+                continue;
+            };
+            node.retain(|part, _| self.nodes.contains_key(part));
+            for (part, run_fields) in &self.nodes {
+                let Some(run_fields) = run_fields else {
+                    continue;
+                };
+                let keep = |run: &mut Value| {
+                    if let Some(run) = run.as_object_mut() {
+                        run.retain(|field, _| run_fields.contains(field));
+                    }
+                };
+                match (part.as_str(), node.get_mut(part)) {
+                    ("latest", Some(latest)) => keep(latest),
+                    ("lineages", Some(Value::Object(lineages))) => {
+                        lineages.values_mut().for_each(keep);
+                    },
+                    _ => {},
+                }
+            }
+        }
+        execution.insert("nodes".to_owned(), nodes);
+    }
+}
+
+/// Position in a workflow's execution list, ordered by `created_at` then
+/// `execution_id`, both descending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionCursor {
+    pub created_at:   Option<DateTime>,
+    pub execution_id: String,
+}
+
+impl ExecutionCursor {
+    pub fn after(doc: &ExecutionDocument) -> Self {
+        Self { created_at: doc.created_at, execution_id: doc.execution_id.clone() }
+    }
+
+    pub fn encode(&self) -> String {
+        let created_at = self
+            .created_at
+            .map(|dt| dt.timestamp_millis().to_string())
+            .unwrap_or_default();
+        crate::util::cursor::encode(&[&created_at, &self.execution_id])
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let mut parts = crate::util::cursor::decode(cursor, 2)?.into_iter();
+        let created_at = parts.next()?;
+        let created_at = if created_at.is_empty() {
+            None
+        } else {
+            Some(DateTime::from_millis(created_at.parse().ok()?))
+        };
+        Some(Self { created_at, execution_id: parts.next()? })
+    }
+}
+
+/// One page of a workflow's executions.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ExecutionPage {
+    pub executions:  Vec<ExecutionDocument>,
+    /// Pass back as `?after=` for the next page; `null` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Aggregate health of a workflow's executions.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct WorkflowStats {
+    pub workflow_id:     String,
+    pub total:           u64,
+    /// Executions per status; those without a final status count as
+    /// `running`
+    pub by_status:       BTreeMap<String, u64>,
+    /// Over executions that reported a `total_duration_ms`, rounded to
+    /// whole milliseconds
+    pub avg_duration_ms: Option<i64>,
+    pub p95_duration_ms: Option<i64>,
+    /// Creation time of the newest execution
+    #[serde(default, with = "datetime_iso")]
+    pub last_run_at:     Option<DateTime>,
+}
+
+impl WorkflowStats {
+    /// Status recorded for executions that haven't completed.
+    pub const RUNNING: &str = "running";
+
+    /// Stats over already-loaded executions. The p95 is the nearest-rank
+    /// percentile of the reported durations.
+    pub fn from_executions(workflow_id: &str, executions: &[ExecutionDocument]) -> Self {
+        let mut by_status = BTreeMap::new();
+        for doc in executions {
+            let status = doc.status.as_deref().unwrap_or(Self::RUNNING);
+            *by_status.entry(status.to_string()).or_default() += 1;
+        }
+
+        let mut durations: Vec<i64> = executions
+            .iter()
+            .filter_map(|doc| doc.total_duration_ms)
+            .collect();
+        durations.sort_unstable();
+        let count = i64::try_from(durations.len()).unwrap_or(i64::MAX);
+        let sum = durations
+            .iter()
+            .fold(0_i64, |sum, ms| sum.saturating_add(*ms));
+        let avg_duration_ms = (count > 0).then(|| sum.saturating_add(count / 2) / count);
+        let p95_rank = (durations.len() * 95).div_ceil(100);
+        let p95_duration_ms = durations.get(p95_rank.saturating_sub(1)).copied();
+
+        Self {
+            workflow_id: workflow_id.to_string(),
+            total: executions.len() as u64,
+            by_status,
+            avg_duration_ms,
+            p95_duration_ms,
+            last_run_at: executions.iter().filter_map(|doc| doc.created_at).max(),
+        }
+    }
+}
+
+/// What an execution is authorized by, readable before the document itself.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionOwner {
+    pub workflow_id: String,
+    #[serde(default)]
+    pub org_id:      Option<String>,
+}
+
+/// Lightweight execution listing entry (no nodes, definition or context).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionSummary {
+    pub execution_id: String,
+    pub workflow_id:  String,
+    #[serde(default)]
+    pub status:       Option<String>,
+    #[serde(default, with = "datetime_iso")]
+    pub created_at:   Option<DateTime>,
+    #[serde(default, with = "datetime_iso")]
+    pub updated_at:   Option<DateTime>,
+    #[serde(default)]
+    pub progress:     Option<ExecutionProgress>,
+}
+
+/// Executions a user can see, derived from their valid execution tokens:
+/// wildcard grants cover whole workflows, specific grants single executions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionAccessScope {
+    pub workflow_ids:  Vec<String>,
+    pub execution_ids: Vec<String>,
+}
+
+impl ExecutionAccessScope {
+    pub fn from_tokens(tokens: &[ExecutionToken]) -> Self {
+        let mut scope = Self::default();
+        for token in tokens {
+            let (ids, id) = match &token.execution_id {
+                Some(execution_id) => (&mut scope.execution_ids, execution_id),
+                None => (&mut scope.workflow_ids, &token.workflow_id),
+            };
+            if !ids.contains(id) {
+                ids.push(id.clone());
+            }
+        }
+        scope
     }
 
-    Ok(result)
+    pub const fn is_empty(&self) -> bool {
+        self.workflow_ids.is_empty() && self.execution_ids.is_empty()
+    }
+}
+
+/// Deterministically hash a lineage stack for use as a stable key.
+pub fn compute_lineage_hash(stack: &[StackFrame]) -> Option<String> {
+    serde_json::to_vec(stack)
+        .ok()
+        .map(|bytes| Uuid::new_v5(&Uuid::NAMESPACE_OID, &bytes).to_string())
+}
+
+fn deserialize_nodes<'de, D>(deserializer: D) -> Result<HashMap<String, HydratedNode>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw: HashMap<String, Value> = HashMap::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(node_id, value)| (node_id, HydratedNode::from_stored(value)))
+        .collect())
 }
 
 #[cfg(test)]
 #[allow(clippy::expect_used, clippy::indexing_slicing)]
 mod tests {
+    use std::collections::HashMap;
+
+    use mongodb::bson::DateTime;
     use serde_json::json;
 
-    use super::{ExecutionTokenPayload, StackFrame, compute_lineage_hash};
+    use super::{
+        ExecutionDocument,
+        ExecutionFields,
+        ExecutionGraph,
+        ExecutionToken,
+        ExecutionTokenPayload,
+        GraphNode,
+        HydratedNode,
+        NodeExecutionInstance,
+        StackFrame,
+        TokenScope,
+        WorkflowStats,
+        compute_lineage_hash,
+    };
+
+    #[test]
+    fn token_validity_checks_expiry_and_issue_time_with_skew() {
+        let token = |iat, exp| ExecutionToken {
+            execution_id: None,
+            workflow_id: "wf-1".to_string(),
+            iat,
+            exp,
+            user_id: "user-1".to_string(),
+            jti: "jti-1".to_string(),
+            scope: TokenScope::Owner,
+            org_id: None,
+        };
+        let now = 1_000;
+
+        assert_eq!(token(900, 2_000).validity_error(now, 60), None);
+        assert_eq!(token(1_060, 2_000).validity_error(now, 60), None);
+        assert_eq!(token(900, 1_000).validity_error(now, 60), Some("token already expired"));
+        assert_eq!(token(1_061, 2_000).validity_error(now, 60), Some("token issued in the future"));
+    }
 
     #[test]
     fn expands_legacy_single_token_payload() {
@@ -368,12 +1129,16 @@ mod tests {
             iat:           100,
             exp:           200,
             user_id:       "user-1".to_string(),
+            jti:           Some("jti-1".to_string()),
+            scope:         TokenScope::Owner,
+            org_id:        None,
         };
 
         let expanded = payload.expand().expect("payload should be valid");
         assert_eq!(expanded.len(), 1);
         assert_eq!(expanded[0].execution_id.as_deref(), Some("exec-1"));
         assert_eq!(expanded[0].workflow_id, "wf-1");
+        assert_eq!(expanded[0].jti, "jti-1");
     }
 
     #[test]
@@ -386,9 +1151,12 @@ mod tests {
             iat:           100,
             exp:           200,
             user_id:       "user-1".to_string(),
+            jti:           None,
+            scope:         TokenScope::Owner,
+            org_id:        None,
         };
 
-        let expanded = payload.expand().expect("payload should be valid");
+        let expanded = payload.clone().expand().expect("payload should be valid");
         assert_eq!(expanded.len(), 4);
         assert!(
             expanded.iter().any(|token| token.workflow_id == "wf-1"
@@ -398,6 +1166,13 @@ mod tests {
             expanded.iter().any(|token| token.workflow_id == "wf-2"
                 && token.execution_id.as_deref() == Some("exec-2"))
         );
+        let jtis: std::collections::HashSet<_> = expanded.iter().map(|token| &token.jti).collect();
+        assert_eq!(jtis.len(), 4, "each expanded grant needs its own jti");
+        assert_eq!(
+            payload.expand().expect("payload should expand again"),
+            expanded,
+            "redelivered grants must keep their jti"
+        );
     }
 
     #[test]
@@ -410,11 +1185,19 @@ mod tests {
             iat:           100,
             exp:           200,
             user_id:       "user-1".to_string(),
+            jti:           None,
+            scope:         TokenScope::Viewer,
+            org_id:        None,
         };
 
         let expanded = payload.expand().expect("payload should be valid");
         assert_eq!(expanded.len(), 2);
         assert!(expanded.iter().all(|token| token.execution_id.is_none()));
+        assert!(
+            expanded
+                .iter()
+                .all(|token| token.scope == TokenScope::Viewer)
+        );
     }
 
     #[test]
@@ -427,6 +1210,9 @@ mod tests {
             iat:           100,
             exp:           200,
             user_id:       "user-1".to_string(),
+            jti:           None,
+            scope:         TokenScope::Owner,
+            org_id:        None,
         };
 
         assert!(payload.expand().is_err());
@@ -471,4 +1257,166 @@ mod tests {
         assert_eq!(first, second);
         assert!(first.is_some());
     }
+
+    #[test]
+    fn compact_view_strips_payloads_from_latest_and_lineages() {
+        let run = NodeExecutionInstance {
+            input: Some(json!({ "big": "payload" })),
+            parameters: Some(json!({ "url": "https://example.com" })),
+            output: Some(json!([1, 2, 3])),
+            used_inputs: Some(json!({})),
+            status: Some("failed".to_string()),
+            duration_ms: Some(12),
+            ..NodeExecutionInstance::default()
+        };
+        let mut doc = ExecutionDocument::default();
+        doc.nodes.insert(
+            "node-1".to_string(),
+            HydratedNode {
+                latest: Some(run.clone()),
+                lineages: [("lineage-a".to_string(), run)].into(),
+                ..HydratedNode::default()
+            },
+        );
+
+        doc.compact();
+
+        let node = &doc.nodes["node-1"];
+        let compact = NodeExecutionInstance {
+            status: Some("failed".to_string()),
+            duration_ms: Some(12),
+            ..NodeExecutionInstance::default()
+        };
+        assert_eq!(node.latest.as_ref(), Some(&compact));
+        assert_eq!(node.lineages.get("lineage-a"), Some(&compact));
+    }
+
+    #[test]
+    fn workflow_stats_count_unfinished_runs_as_running() {
+        let execution =
+            |status: Option<&str>, duration: Option<i64>, created_ms: i64| ExecutionDocument {
+                status: status.map(ToOwned::to_owned),
+                total_duration_ms: duration,
+                created_at: Some(DateTime::from_millis(created_ms)),
+                ..ExecutionDocument::default()
+            };
+        let mut executions: Vec<_> = (1..=19)
+            .map(|i| execution(Some("completed"), Some(i * 10), i))
+            .collect();
+        executions.push(execution(Some("failed"), Some(1_000), 20));
+        executions.push(execution(None, None, 21));
+
+        let stats = WorkflowStats::from_executions("wf-1", &executions);
+        assert_eq!(stats.total, 21);
+        assert_eq!(
+            stats.by_status,
+            [("completed".to_string(), 19), ("failed".to_string(), 1), ("running".to_string(), 1)]
+                .into()
+        );
+        assert_eq!(stats.avg_duration_ms, Some(145));
+        assert_eq!(stats.p95_duration_ms, Some(190));
+        assert_eq!(stats.last_run_at, Some(DateTime::from_millis(21)));
+
+        let empty = WorkflowStats::from_executions("wf-1", &[]);
+        assert_eq!(empty.total, 0);
+        assert_eq!(empty.avg_duration_ms, None);
+        assert_eq!(empty.p95_duration_ms, None);
+    }
+
+    #[test]
+    fn execution_fields_accept_only_known_paths_and_prune_the_rest() {
+        for unknown in
+            ["secret", "nodes.latest.bogus", "nodes.lineage_count.x", "$where", "nodes.x"]
+        {
+            assert_eq!(ExecutionFields::parse(unknown), Err(unknown.to_string()));
+        }
+
+        let fields = ExecutionFields::parse("status, nodes.latest.status,nodes.lineage_count")
+            .expect("known paths should parse");
+        let mut execution = json!({
+            "execution_id": "exec-1",
+            "workflow_id": "wf-1",
+            "status": "completed",
+            "accumulated_context": { "secret": true },
+            "nodes": {
+                "node-1": {
+                    "latest": { "status": "success", "output": { "big": true } },
+                    "lineages": { "l-1": { "status": "success" } },
+                    "lineage_count": 1,
+                },
+            },
+        });
+        fields.retain(&mut execution);
+        assert_eq!(
+            execution,
+            json!({
+                "status": "completed",
+                "nodes": { "node-1": { "latest": { "status": "success" }, "lineage_count": 1 } },
+            })
+        );
+
+        // Asking for a whole part wins over single fields of it
+        let widened = ExecutionFields::parse("nodes.latest.status")
+            .expect("known paths should parse")
+            .with("nodes.latest");
+        assert_eq!(widened.nodes.get("latest"), Some(&None));
+    }
+
+    #[test]
+    fn graph_keeps_topology_and_drops_parameters_and_credentials() {
+        let nodes = [
+            json!({
+                "id": "n2",
+                "name": "Send",
+                "type": "http",
+                "parameters": { "url": "https://example.com" },
+                "credentials": { "token": "secret" },
+            }),
+            json!({ "id": "n1", "name": "Start", "type": "trigger", "trigger": true }),
+            json!("not a node"),
+        ];
+        let edges = vec![json!({ "id": "e1", "src": "n1", "dst": "n2" })];
+
+        let graph = ExecutionGraph::from_definition(&nodes, edges.clone());
+        let node = |id: &str, name: &str, node_type: &str, trigger| GraphNode {
+            id: id.to_string(),
+            name: name.to_string(),
+            node_type: node_type.to_string(),
+            trigger,
+        };
+        assert_eq!(
+            graph,
+            ExecutionGraph {
+                nodes: vec![
+                    node("n2", "Send", "http", false),
+                    node("n1", "Start", "trigger", true)
+                ],
+                edges: edges.clone(),
+            }
+        );
+        assert!(
+            !serde_json::to_string(&graph)
+                .expect("graph should serialize")
+                .contains("secret")
+        );
+
+        // Executions stored without a graph get it from their node definitions
+        let mut doc = ExecutionDocument { edges, ..ExecutionDocument::default() };
+        for definition in &nodes[..2] {
+            let mut extra: HashMap<String, serde_json::Value> =
+                serde_json::from_value(definition.clone()).expect("definition is an object");
+            let id = extra
+                .remove("id")
+                .and_then(|id| id.as_str().map(str::to_owned))
+                .expect("definition has an id");
+            doc.nodes
+                .insert(id, HydratedNode { extra, ..HydratedNode::default() });
+        }
+        let rebuilt = ExecutionGraph::from_document(&doc);
+        assert_eq!(
+            rebuilt.nodes,
+            [node("n1", "Start", "trigger", true), node("n2", "Send", "http", false)]
+        );
+        assert_eq!(rebuilt.edges, graph.edges);
+    }
 }
@@ -0,0 +1,141 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use opentelemetry::{KeyValue, global, metrics::Counter};
+use serde::Serialize;
+use tracing::error;
+
+/// Lifecycle of a RabbitMQ consumer as seen by `/readyz`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", content = "error", rename_all = "snake_case")]
+pub enum ConsumerState {
+    /// Connecting or declaring its exchange/queue
+    Starting,
+    /// Queue declared and `basic_consume` registered
+    Running,
+    /// Last attempt failed before or while consuming
+    Failed(String),
+}
+
+/// Shared registry of consumer states, so a consumer whose queue declaration
+/// fails is visible instead of retrying silently in the background.
+#[derive(Clone, Debug)]
+pub struct ConsumerHealth {
+    states:   Arc<Mutex<BTreeMap<&'static str, ConsumerState>>>,
+    failures: Counter<u64>,
+}
+
+impl Default for ConsumerHealth {
+    fn default() -> Self {
+        let failures = global::meter("rtes")
+            .u64_counter("rtes.consumer.failures")
+            .with_description("RabbitMQ consumer start or run failures")
+            .build();
+        Self { states: Arc::default(), failures }
+    }
+}
+
+impl ConsumerHealth {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<&'static str, ConsumerState>> {
+        self.states
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Register a consumer as starting and return its reporter.
+    pub fn register(&self, name: &'static str) -> ConsumerReporter {
+        self.lock().insert(name, ConsumerState::Starting);
+        ConsumerReporter { name, health: self.clone() }
+    }
+
+    /// Current state of every registered consumer, ordered by name.
+    pub fn snapshot(&self) -> BTreeMap<&'static str, ConsumerState> {
+        self.lock().clone()
+    }
+
+    /// Names and errors of consumers whose last attempt failed.
+    pub fn failures(&self) -> Vec<(&'static str, String)> {
+        self.lock()
+            .iter()
+            .filter_map(|(name, state)| match state {
+                ConsumerState::Failed(e) => Some((*name, e.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// True once every registered consumer is running.
+    pub fn is_ready(&self) -> bool {
+        self.lock()
+            .values()
+            .all(|state| *state == ConsumerState::Running)
+    }
+
+    fn set(&self, name: &'static str, state: ConsumerState) {
+        self.lock().insert(name, state);
+    }
+}
+
+/// Handle a single consumer uses to report its own state.
+#[derive(Clone, Debug)]
+pub struct ConsumerReporter {
+    name:   &'static str,
+    health: ConsumerHealth,
+}
+
+impl ConsumerReporter {
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// True while the current attempt has not reached `running`.
+    pub fn is_starting(&self) -> bool {
+        self.health.lock().get(self.name) == Some(&ConsumerState::Starting)
+    }
+
+    pub fn starting(&self) {
+        self.health.set(self.name, ConsumerState::Starting);
+    }
+
+    pub fn running(&self) {
+        self.health.set(self.name, ConsumerState::Running);
+    }
+
+    pub fn failed(&self, err: &str) {
+        error!(consumer = self.name, error = %err, "Consumer failed");
+        self.health
+            .failures
+            .add(1, &[KeyValue::new("consumer", self.name)]);
+        self.health
+            .set(self.name, ConsumerState::Failed(err.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConsumerHealth, ConsumerState};
+
+    #[test]
+    fn declaration_failure_is_observable() {
+        let health = ConsumerHealth::default();
+        let token = health.register("token");
+        let status = health.register("status");
+        assert!(!health.is_ready());
+
+        token.running();
+        status.failed("PRECONDITION_FAILED - inequivalent arg 'durable'");
+
+        assert!(!health.is_ready());
+        assert_eq!(
+            health.failures(),
+            vec![("status", "PRECONDITION_FAILED - inequivalent arg 'durable'".to_string())]
+        );
+        assert_eq!(health.snapshot().get("token"), Some(&ConsumerState::Running));
+
+        status.running();
+        assert!(health.is_ready());
+        assert!(health.failures().is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+
+use lapin::{
+    BasicProperties,
+    Connection,
+    ConnectionProperties,
+    message::Delivery,
+    options::{BasicAckOptions, BasicGetOptions, BasicNackOptions, ConfirmSelectOptions},
+    types::{AMQPValue, FieldTable},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    config::Config,
+    infra::messaging::{
+        PARSE_ERROR_HEADER,
+        RETRY_HEADER,
+        SOURCE_QUEUE_HEADER,
+        counter_header,
+        dlq_name,
+        publish_confirmed,
+    },
+};
+
+/// Upper bound on messages handled by a single peek or replay.
+pub const MAX_BATCH: usize = 100;
+
+/// Headers RabbitMQ adds when dead-lettering; dropped on replay, together
+/// with the retry counter and parse error tags, so the message looks fresh
+/// to the consumer.
+const DEAD_LETTER_HEADERS: [&str; 7] = [
+    "x-death",
+    "x-first-death-exchange",
+    "x-first-death-queue",
+    "x-first-death-reason",
+    "x-last-death-exchange",
+    "x-last-death-queue",
+    "x-last-death-reason",
+];
+
+/// Header counting how often a message was replayed off its DLQ. Unlike the
+/// retry counter it survives replay, so a message that keeps failing stops
+/// cycling once it reaches `RABBITMQ_MAX_REPLAYS`.
+pub const REPLAY_HEADER: &str = "x-replay-count";
+
+/// Header set on a message that reached `RABBITMQ_MAX_REPLAYS`; replay leaves
+/// it in the DLQ for an operator to handle.
+pub const REPLAY_EXHAUSTED_HEADER: &str = "x-rune-replay-exhausted";
+
+/// A dead-lettered message with its delivery metadata.
+#[derive(Debug, Serialize)]
+pub struct DlqMessage {
+    pub exchange:     String,
+    pub routing_key:  String,
+    pub redelivered:  bool,
+    /// How often the message was already replayed (`x-replay-count`)
+    pub replays:      u32,
+    pub content_type: Option<String>,
+    /// AMQP headers, including RabbitMQ's `x-death` history
+    pub headers:      Value,
+    /// Raw payload, decoded as UTF-8 with invalid bytes replaced
+    pub payload:      String,
+}
+
+impl From<&Delivery> for DlqMessage {
+    fn from(delivery: &Delivery) -> Self {
+        Self {
+            exchange:     delivery.exchange.to_string(),
+            routing_key:  delivery.routing_key.to_string(),
+            redelivered:  delivery.redelivered,
+            replays:      counter_header(&delivery.properties, REPLAY_HEADER),
+            content_type: delivery
+                .properties
+                .content_type()
+                .as_ref()
+                .map(ToString::to_string),
+            headers:      delivery
+                .properties
+                .headers()
+                .as_ref()
+                .map_or(Value::Null, |headers| {
+                    amqp_to_json(&AMQPValue::FieldTable(headers.clone()))
+                }),
+            payload:      String::from_utf8_lossy(&delivery.data).into_owned(),
+        }
+    }
+}
+
+/// Outcome of a replay batch.
+#[derive(Debug, Default, Serialize)]
+pub struct ReplaySummary {
+    /// Republished to the source queue and acked off the DLQ
+    pub replayed:      usize,
+    /// At `RABBITMQ_MAX_REPLAYS` already, so moved to the back of the DLQ
+    /// with `x-rune-replay-exhausted` set instead of replayed
+    pub exhausted:     usize,
+    /// Taken from the DLQ but returned to it because republishing failed
+    pub left_in_place: usize,
+    /// Why the batch stopped early, if it did
+    pub error:         Option<String>,
+}
+
+/// The consumer queue whose DLQ is `queue`, if any. Only these DLQs may be
+/// inspected or replayed, so the endpoints cannot touch arbitrary queues.
+pub fn source_queue(queue: &str) -> Option<&'static str> {
+    Config::get()
+        .consumer_queues()
+        .into_iter()
+        .find(|source| dlq_name(source) == queue)
+}
+
+/// Read up to `limit` messages from `queue` without acknowledging them.
+/// Closing the connection returns them to the queue (flagged redelivered), so
+/// peeking never removes anything.
+pub async fn peek(
+    amqp_url: &str,
+    queue: &str,
+    limit: usize,
+) -> Result<Vec<DlqMessage>, lapin::Error> {
+    let conn = Connection::connect(amqp_url, ConnectionProperties::default()).await?;
+    let channel = conn.create_channel().await?;
+
+    let mut messages = Vec::new();
+    while messages.len() < limit {
+        let Some(message) = channel
+            .basic_get(queue, BasicGetOptions { no_ack: false })
+            .await?
+        else {
+            break;
+        };
+        messages.push(DlqMessage::from(&message.delivery));
+    }
+
+    let _ = conn.close(200, "DLQ peek finished").await;
+    Ok(messages)
+}
+
+/// Move up to `limit` messages from `queue` back onto `source`.
+///
+/// Each message is acked off the DLQ only after the broker confirms the
+/// republish; the first failure is nacked back onto the DLQ and ends the batch.
+/// Messages replayed `RABBITMQ_MAX_REPLAYS` times already are republished to
+/// the back of `queue` marked exhausted instead, and only messages queued
+/// when the batch started are taken, so none is seen twice.
+pub async fn replay(
+    amqp_url: &str,
+    queue: &str,
+    source: &str,
+    limit: usize,
+) -> Result<ReplaySummary, lapin::Error> {
+    let conn = Connection::connect(amqp_url, ConnectionProperties::default()).await?;
+    let channel = conn.create_channel().await?;
+    channel
+        .confirm_select(ConfirmSelectOptions::default())
+        .await?;
+
+    let max_replays = Config::get().rabbitmq_max_replays;
+    let mut summary = ReplaySummary::default();
+    let mut backlog = limit;
+    let mut taken = 0;
+    while taken < backlog {
+        let Some(message) = channel
+            .basic_get(queue, BasicGetOptions { no_ack: false })
+            .await?
+        else {
+            break;
+        };
+        if taken == 0 {
+            // Exhausted messages go to the back of the queue; stop before
+            // they come round again
+            let queued = usize::try_from(message.message_count).unwrap_or(usize::MAX);
+            backlog = backlog.min(queued.saturating_add(1));
+        }
+        taken += 1;
+        let delivery = message.delivery;
+
+        let (target, properties, exhausted) = match replay_target(&delivery.properties, max_replays)
+        {
+            ReplayTarget::Source(properties) => (source, properties, false),
+            ReplayTarget::Exhausted(properties) => (queue, properties, true),
+        };
+        if let Err(e) = publish_confirmed(&channel, target, &delivery.data, properties).await {
+            summary.left_in_place += 1;
+            summary.error = Some(e);
+            delivery
+                .nack(BasicNackOptions { requeue: true, ..BasicNackOptions::default() })
+                .await?;
+            break;
+        }
+        delivery.ack(BasicAckOptions::default()).await?;
+        if exhausted {
+            summary.exhausted += 1;
+        } else {
+            summary.replayed += 1;
+        }
+    }
+
+    let _ = conn.close(200, "DLQ replay finished").await;
+    Ok(summary)
+}
+
+/// Where replay sends a dead-lettered message, with the properties to
+/// publish it with.
+#[derive(Debug)]
+enum ReplayTarget {
+    /// Back to its source queue, dead-letter headers dropped and
+    /// `x-replay-count` incremented
+    Source(BasicProperties),
+    /// Back onto the DLQ with `x-rune-replay-exhausted` set
+    Exhausted(BasicProperties),
+}
+
+fn replay_target(properties: &BasicProperties, max_replays: u32) -> ReplayTarget {
+    let replays = counter_header(properties, REPLAY_HEADER);
+    if replays >= max_replays {
+        let mut headers = properties.headers().clone().unwrap_or_default();
+        headers.insert(REPLAY_EXHAUSTED_HEADER.into(), AMQPValue::Boolean(true));
+        return ReplayTarget::Exhausted(properties.clone().with_headers(headers));
+    }
+    let stripped = strip_dead_letter_headers(properties);
+    let mut headers = stripped.headers().clone().unwrap_or_default();
+    headers.insert(REPLAY_HEADER.into(), AMQPValue::LongLongInt(i64::from(replays + 1)));
+    ReplayTarget::Source(stripped.with_headers(headers))
+}
+
+/// Drop the dead-letter, retry and parse error headers. The replay counter is
+/// kept.
+fn strip_dead_letter_headers(properties: &BasicProperties) -> BasicProperties {
+    let Some(headers) = properties.headers() else {
+        return properties.clone();
+    };
+    let kept: BTreeMap<_, _> = headers
+        .inner()
+        .iter()
+        .filter(|(key, _)| {
+            !DEAD_LETTER_HEADERS.contains(&key.as_str())
+                && ![RETRY_HEADER, PARSE_ERROR_HEADER, SOURCE_QUEUE_HEADER, REPLAY_EXHAUSTED_HEADER]
+                    .contains(&key.as_str())
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    properties.clone().with_headers(FieldTable::from(kept))
+}
+
+/// Render an AMQP header value as plain JSON.
+fn amqp_to_json(value: &AMQPValue) -> Value {
+    match value {
+        AMQPValue::Boolean(b) => Value::from(*b),
+        AMQPValue::ShortShortInt(n) => Value::from(*n),
+        AMQPValue::ShortShortUInt(n) => Value::from(*n),
+        AMQPValue::ShortInt(n) => Value::from(*n),
+        AMQPValue::ShortUInt(n) => Value::from(*n),
+        AMQPValue::LongInt(n) => Value::from(*n),
+        AMQPValue::LongUInt(n) => Value::from(*n),
+        AMQPValue::LongLongInt(n) => Value::from(*n),
+        AMQPValue::Timestamp(n) => Value::from(*n),
+        AMQPValue::Float(n) => Value::from(*n),
+        AMQPValue::Double(n) => Value::from(*n),
+        AMQPValue::ShortString(s) => Value::from(s.as_str()),
+        AMQPValue::LongString(s) => Value::from(String::from_utf8_lossy(s.as_bytes())),
+        AMQPValue::ByteArray(b) => Value::from(String::from_utf8_lossy(b.as_slice())),
+        AMQPValue::FieldArray(values) => values.as_slice().iter().map(amqp_to_json).collect(),
+        AMQPValue::FieldTable(table) => Value::Object(
+            table
+                .inner()
+                .iter()
+                .map(|(k, v)| (k.to_string(), amqp_to_json(v)))
+                .collect(),
+        ),
+        AMQPValue::DecimalValue(d) => serde_json::json!({ "scale": d.scale, "value": d.value }),
+        AMQPValue::Void => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lapin::{
+        BasicProperties,
+        types::{AMQPValue, FieldArray, FieldTable},
+    };
+    use serde_json::json;
+
+    use super::{
+        REPLAY_HEADER,
+        ReplayTarget,
+        amqp_to_json,
+        replay_target,
+        source_queue,
+        strip_dead_letter_headers,
+    };
+    use crate::config::Config;
+
+    #[test]
+    fn only_consumer_dlqs_are_known() {
+        let _ = Config::init();
+        let cfg = Config::get();
+        assert_eq!(
+            source_queue(&format!("{}.dlq", cfg.rabbitmq_status_queue)),
+            Some(cfg.rabbitmq_status_queue.as_str())
+        );
+        assert_eq!(source_queue(&cfg.rabbitmq_status_queue), None);
+        assert_eq!(source_queue("billing.dlq"), None);
+    }
+
+    #[test]
+    fn replay_drops_only_dead_letter_headers() {
+        let mut headers = FieldTable::default();
+        headers.insert("x-death".into(), AMQPValue::FieldArray(FieldArray::default()));
+        headers.insert("x-first-death-reason".into(), AMQPValue::LongString("rejected".into()));
+        headers.insert("x-rune-retries".into(), AMQPValue::LongLongInt(3));
+        headers.insert("x-rune-parse-error".into(), AMQPValue::LongString("EOF".into()));
+        headers.insert("x-rune-source-queue".into(), AMQPValue::LongString("q".into()));
+        headers.insert("x-replay-count".into(), AMQPValue::LongLongInt(1));
+        headers.insert("traceparent".into(), AMQPValue::LongString("00-abc-def-01".into()));
+        let properties = BasicProperties::default()
+            .with_content_type("application/json".into())
+            .with_headers(headers);
+
+        let stripped = strip_dead_letter_headers(&properties);
+        let kept = stripped
+            .headers()
+            .clone()
+            .map(|headers| amqp_to_json(&AMQPValue::FieldTable(headers)));
+        assert_eq!(kept, Some(json!({ "traceparent": "00-abc-def-01", "x-replay-count": 1 })));
+        assert_eq!(stripped.content_type(), properties.content_type());
+    }
+
+    #[test]
+    fn replays_stop_at_the_limit_and_mark_the_message() {
+        let mut headers = FieldTable::default();
+        headers.insert("x-death".into(), AMQPValue::FieldArray(FieldArray::default()));
+        let mut properties = BasicProperties::default().with_headers(headers);
+
+        for replay in 1..=2 {
+            let ReplayTarget::Source(replayed) = replay_target(&properties, 2) else {
+                unreachable!("replay {replay} should go back to the source queue");
+            };
+            let count = replayed
+                .headers()
+                .as_ref()
+                .and_then(|headers| headers.inner().get(REPLAY_HEADER).cloned());
+            assert_eq!(count, Some(AMQPValue::LongLongInt(replay)));
+            // Dead-lettered again by the consumer before the next replay
+            let mut headers = replayed.headers().clone().unwrap_or_default();
+            headers.insert("x-death".into(), AMQPValue::FieldArray(FieldArray::default()));
+            properties = replayed.with_headers(headers);
+        }
+
+        let ReplayTarget::Exhausted(exhausted) = replay_target(&properties, 2) else {
+            unreachable!("a message past the limit should stay in the DLQ");
+        };
+        let headers = exhausted
+            .headers()
+            .clone()
+            .map(|headers| amqp_to_json(&AMQPValue::FieldTable(headers)));
+        assert_eq!(
+            headers,
+            Some(json!({ "x-death": [], "x-replay-count": 2, "x-rune-replay-exhausted": true }))
+        );
+    }
+
+    #[test]
+    fn x_death_headers_render_as_plain_json() {
+        let mut death = FieldTable::default();
+        death.insert("queue".into(), AMQPValue::LongString("workflow.node.status".into()));
+        death.insert("reason".into(), AMQPValue::LongString("rejected".into()));
+        death.insert("count".into(), AMQPValue::LongLongInt(2));
+        let mut headers = FieldTable::default();
+        headers.insert(
+            "x-death".into(),
+            AMQPValue::FieldArray(FieldArray::from(vec![AMQPValue::FieldTable(death)])),
+        );
+
+        assert_eq!(
+            amqp_to_json(&AMQPValue::FieldTable(headers)),
+            json!({ "x-death": [{ "count": 2, "queue": "workflow.node.status", "reason": "rejected" }] })
+        );
+    }
+}
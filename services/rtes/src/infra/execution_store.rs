@@ -1,44 +1,221 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
 use async_trait::async_trait;
 use chrono::Utc;
+use futures::StreamExt;
 use mongodb::{
     Client as MongoClient,
     Collection,
-    bson::{self, doc},
-    options::ClientOptions,
+    IndexModel,
+    bson::{self, Bson, doc},
+    error::{
+        ErrorKind,
+        RETRYABLE_ERROR,
+        RETRYABLE_WRITE_ERROR,
+        SYSTEM_OVERLOADED_ERROR,
+        TRANSIENT_TRANSACTION_ERROR,
+        WriteFailure,
+    },
+    options::{
+        ClientOptions,
+        CollectionOptions,
+        IndexOptions,
+        ReadPreference,
+        SelectionCriteria,
+        UpdateOneModel,
+        WriteConcern,
+    },
 };
+use serde::Deserialize;
 use serde_json::{Map, Value};
 use tracing::{info, warn};
+use uuid::Uuid;
 
 use crate::{
-    api::state::{ExecutionStorePort, StoreResult},
+    api::state::{ExecutionStorePort, NodeStream, StoreError, StoreResult},
+    config::{MongoReadPreference, MongoWriteConcern},
     domain::models::{
         CompletionMessage,
+        ExecutionAccessScope,
+        ExecutionCursor,
         ExecutionDocument,
+        ExecutionFields,
+        ExecutionGraph,
+        ExecutionOwner,
+        ExecutionPage,
+        ExecutionProgress,
+        ExecutionSummary,
+        HydratedNode,
+        NodeEntry,
         NodeExecutionInstance,
         NodeExecutionMessage,
         NodeStatusMessage,
+        NodeStatusRecord,
+        StatusCursor,
+        WorkflowStats,
         compute_lineage_hash,
     },
+    infra::metrics::DocumentSizeSampler,
     retry_backoff,
+    util::retry::{RetryPolicy, with_backoff_if},
 };
 
+/// One document per execution, keyed by `execution_id`.
+const EXECUTIONS: &str = "executions";
+/// Append-only log of the node status messages applied to executions.
+const EXECUTION_STATUS: &str = "execution_status";
+
 #[derive(Clone)]
 pub struct ExecutionStore {
-    client:  MongoClient,
-    db_name: String,
+    client:        MongoClient,
+    db_name:       String,
+    size_sampler:  Arc<DocumentSizeSampler>,
+    /// Lifetime of new executions; `None` keeps them forever
+    execution_ttl: Option<Duration>,
+    /// Where execution reads and listings go; `None` is the primary
+    read_criteria: Option<SelectionCriteria>,
+    /// Backoff for MongoDB operations retried in place
+    retry:         RetryPolicy,
 }
 
 impl ExecutionStore {
-    pub async fn new(uri: &str, db_name: &str) -> Result<Self, mongodb::error::Error> {
+    /// Connect and ensure indexes. With `execution_ttl` set, new executions
+    /// get an `expires_at` and MongoDB TTL indexes remove them together with
+    /// their status log once it passes.
+    ///
+    /// `write_concern` applies to every write. `read_preference` only routes
+    /// the execution reads and workflow listings served by the API: the
+    /// status update reads the document it is about to write, and must read
+    /// it from the primary so it never applies a status to a stale copy.
+    pub async fn new(
+        uri: &str,
+        db_name: &str,
+        execution_ttl: Option<Duration>,
+        read_preference: Option<MongoReadPreference>,
+        write_concern: Option<MongoWriteConcern>,
+    ) -> Result<Self, mongodb::error::Error> {
         info!(mongodb_uri = %uri, mongodb_db = %db_name, "Connecting to MongoDB");
-        let client_options = ClientOptions::parse(uri).await?;
+        let mut client_options = ClientOptions::parse(uri).await?;
+        if let Some(write_concern) = write_concern {
+            client_options.write_concern = Some(match write_concern {
+                MongoWriteConcern::Majority => WriteConcern::majority(),
+                MongoWriteConcern::Nodes(nodes) => WriteConcern::nodes(nodes),
+            });
+        }
         let client = MongoClient::with_options(client_options)?;
         info!(mongodb_db = %db_name, "MongoDB client initialized");
-        Ok(Self { client, db_name: db_name.to_string() })
+        let store = Self {
+            client,
+            db_name: db_name.to_string(),
+            size_sampler: Arc::new(DocumentSizeSampler::new(0)),
+            execution_ttl,
+            read_criteria: read_preference.map(selection_criteria),
+            retry: RetryPolicy::default(),
+        };
+        // Best-effort: a MongoDB that is down at startup is reported by /readyz
+        if let Err(e) = store.ensure_indexes().await {
+            warn!(mongodb_db = %db_name, error = %e, "Failed to ensure MongoDB indexes");
+        }
+        Ok(store)
+    }
+
+    /// Create the indexes the store's queries rely on. Indexes that already
+    /// exist (by name) are left alone, so this is safe to run on every start.
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let db = self.client.database(&self.db_name);
+        for (collection_name, index) in required_indexes(self.execution_ttl.is_some()) {
+            let index_name = index
+                .options
+                .as_ref()
+                .and_then(|options| options.name.clone())
+                .unwrap_or_default();
+            let collection = db.collection::<bson::Document>(collection_name);
+            // Listing fails for a collection that doesn't exist yet
+            let existing = collection.list_index_names().await.unwrap_or_default();
+            if existing.contains(&index_name) {
+                info!(collection = collection_name, index = %index_name, "MongoDB index already present");
+                continue;
+            }
+            collection.create_index(index).await?;
+            info!(collection = collection_name, index = %index_name, "Created MongoDB index");
+        }
+        Ok(())
+    }
+
+    /// Record the BSON size of the execution document every `every` writes
+    /// (0 disables sampling).
+    #[must_use]
+    pub fn with_document_size_sampling(mut self, every: u64) -> Self {
+        self.size_sampler = Arc::new(DocumentSizeSampler::new(every));
+        self
+    }
+
+    /// Retry failed reads and writes per `retry` instead of the default
+    /// policy.
+    #[must_use]
+    pub const fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sample the stored size of an execution document when the sampler
+    /// triggers. Sizing is best-effort and never fails the write path.
+    async fn sample_document_size(&self, execution_id: &str) {
+        use futures::TryStreamExt;
+
+        if !self.size_sampler.should_sample() {
+            return;
+        }
+
+        let pipeline = vec![
+            doc! { "$match": { "execution_id": execution_id } },
+            doc! { "$project": { "_id": 0, "size": { "$bsonSize": "$$ROOT" } } },
+        ];
+        let size = match self.execution_collection().aggregate(pipeline).await {
+            Ok(mut cursor) => cursor.try_next().await,
+            Err(e) => Err(e),
+        };
+        match size {
+            Ok(Some(result)) => {
+                let size = result
+                    .get("size")
+                    .and_then(|v| v.as_i64().or_else(|| v.as_i32().map(i64::from)))
+                    .and_then(|v| u64::try_from(v).ok());
+                if let Some(size) = size {
+                    self.size_sampler.record(execution_id, size);
+                }
+            },
+            Ok(None) => {},
+            Err(e) => {
+                warn!(execution_id = %execution_id, error = %e, "Failed to sample execution document size");
+            },
+        }
     }
 
     fn execution_collection(&self) -> Collection<ExecutionDocument> {
-        self.client.database(&self.db_name).collection("executions")
+        self.client.database(&self.db_name).collection(EXECUTIONS)
+    }
+
+    /// `executions` routed by the configured read preference, for reads that
+    /// may lag the primary.
+    fn execution_read_collection(&self) -> Collection<ExecutionDocument> {
+        self.client.database(&self.db_name).collection_with_options(
+            EXECUTIONS,
+            CollectionOptions::builder()
+                .selection_criteria(self.read_criteria.clone())
+                .build(),
+        )
+    }
+
+    /// Append-only log of every node status message applied to an execution.
+    fn status_collection(&self) -> Collection<NodeStatusRecord> {
+        self.client
+            .database(&self.db_name)
+            .collection(EXECUTION_STATUS)
     }
 
     pub(crate) async fn upsert_execution_definition(
@@ -59,30 +236,36 @@ impl ExecutionStore {
             .cloned()
             .unwrap_or_else(|| Value::Array(Vec::new()));
 
-        let mut nodes_doc = bson::Document::new();
-        if let Some(Value::Array(nodes)) = normalized_workflow.get("nodes") {
-            for node in nodes {
-                if let Some(node_id) = node.get("id").and_then(Value::as_str)
-                    && let Ok(node_bson) = bson::to_document(node)
-                {
-                    nodes_doc.insert(node_id.to_string(), bson::Bson::Document(node_bson.clone()));
-                }
-            }
-        }
+        let nodes_doc = definition_nodes(&normalized_workflow);
 
-        let filter = doc! {
-            "execution_id": &msg.execution_id,
-        };
+        let graph = ExecutionGraph::from_definition(
+            normalized_workflow
+                .get("nodes")
+                .and_then(Value::as_array)
+                .map_or(&[], Vec::as_slice),
+            edges_bson.as_array().cloned().unwrap_or_default(),
+        );
 
-        let update = doc! {
+        // Another organization's execution under the same id fails the upsert
+        // on the unique `execution_id` index instead of being overwritten
+        let filter = execution_filter(&msg.execution_id, msg.org_id.as_deref());
+
+        // Redefining the execution resets its nodes, so the counters restart
+        let progress = ExecutionProgress {
+            total_nodes: i64::try_from(nodes_doc.len()).unwrap_or(i64::MAX),
+            ..ExecutionProgress::default()
+        };
+        let mut update = doc! {
             "$set": {
                 "nodes": nodes_doc,
                 "edges": bson::to_bson(&edges_bson)?,
+                "graph": bson::to_bson(&graph)?,
                 "accumulated_context": bson::to_bson(&msg.accumulated_context)?,
                 "workflow_id": &msg.workflow_id,
                 "workflow_version": msg.workflow_version,
                 "workflow_version_id": msg.workflow_version_id,
                 "execution_id": &msg.execution_id,
+                "progress": bson::to_bson(&progress)?,
                 "updated_at": now,
             },
             "$setOnInsert": {
@@ -90,85 +273,459 @@ impl ExecutionStore {
             },
             "$unset": { "workflow_definition": "" },
         };
+        if let Some(ttl) = self.execution_ttl
+            && let Ok(set_on_insert) = update.get_document_mut("$setOnInsert")
+        {
+            let ttl_ms = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+            set_on_insert.insert(
+                "expires_at",
+                bson::DateTime::from_millis(now.timestamp_millis().saturating_add(ttl_ms)),
+            );
+        }
 
         self.execution_collection()
             .update_one(filter, update)
             .upsert(true)
             .await?;
         info!(execution_id = %msg.execution_id, "Upserted execution definition");
+        self.sample_document_size(&msg.execution_id).await;
         Ok(())
     }
 
     pub(crate) async fn get_execution_document(
         &self,
         execution_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<Option<ExecutionDocument>, mongodb::error::Error> {
+        self.find_execution_document(
+            &self.execution_read_collection(),
+            execution_id,
+            execution_filter(execution_id, org_id),
+        )
+        .await
+    }
+
+    /// Fetch the execution `filter` matches; `execution_id` is for the logs.
+    async fn find_execution_document(
+        &self,
+        collection: &Collection<ExecutionDocument>,
+        execution_id: &str,
+        filter: bson::Document,
     ) -> Result<Option<ExecutionDocument>, mongodb::error::Error> {
         info!(execution_id = %execution_id, mongodb_db = %self.db_name, "Fetching execution document");
-        let filter = doc! { "execution_id": execution_id };
-        let doc = self.execution_collection().find_one(filter).await?;
+        let doc = collection.find_one(filter).await?;
         info!(execution_id = %execution_id, found = doc.is_some(), "Fetched execution document");
         Ok(doc)
     }
 
-    /// Get all executions for a given workflow
+    /// Fetch an execution with node payloads projected out on the server, so
+    /// large inputs and outputs never leave MongoDB.
+    pub(crate) async fn get_execution_document_compact(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<Option<ExecutionDocument>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let pipeline = vec![
+            doc! { "$match": execution_filter(execution_id, org_id) },
+            doc! { "$limit": 1 },
+            doc! { "$set": { "nodes": compact_nodes_expression() } },
+        ];
+        let doc = self
+            .execution_read_collection()
+            .aggregate(pipeline)
+            .with_type::<ExecutionDocument>()
+            .await?
+            .try_next()
+            .await?;
+        info!(execution_id = %execution_id, found = doc.is_some(), "Fetched compact execution document");
+        Ok(doc)
+    }
+
+    /// Fetch only an execution's `workflow_id` and `org_id`, so access can
+    /// be checked before the document itself is read.
+    pub(crate) async fn get_execution_owner(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<Option<ExecutionOwner>, mongodb::error::Error> {
+        self.client
+            .database(&self.db_name)
+            .collection::<ExecutionOwner>(EXECUTIONS)
+            .find_one(execution_filter(execution_id, org_id))
+            .projection(doc! { "_id": 0, "workflow_id": 1, "org_id": 1 })
+            .await
+    }
+
+    /// Fetch an execution projected to `fields` on the server, so unselected
+    /// parts never leave MongoDB.
+    pub(crate) async fn get_execution_fields(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        fields: &ExecutionFields,
+    ) -> Result<Option<ExecutionDocument>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let pipeline = vec![
+            doc! { "$match": execution_filter(execution_id, org_id) },
+            doc! { "$limit": 1 },
+            doc! { "$project": fields_projection(fields) },
+        ];
+        let doc = self
+            .execution_read_collection()
+            .aggregate(pipeline)
+            .with_type::<ExecutionDocument>()
+            .await?
+            .try_next()
+            .await?;
+        info!(execution_id = %execution_id, found = doc.is_some(), "Fetched projected execution document");
+        Ok(doc)
+    }
+
+    /// Fetch an execution's identity and status with `nodes` narrowed to
+    /// `node_id` on the server, leaving the definition, context and other
+    /// nodes in MongoDB.
+    pub(crate) async fn get_node(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        node_id: &str,
+    ) -> Result<Option<ExecutionDocument>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let pipeline = vec![
+            doc! { "$match": execution_filter(execution_id, org_id) },
+            doc! { "$limit": 1 },
+            doc! {
+                "$project": {
+                    "execution_id": 1,
+                    "workflow_id": 1,
+                    "org_id": 1,
+                    "workflow_version": 1,
+                    "workflow_version_id": 1,
+                    "status": 1,
+                    "created_at": 1,
+                    "updated_at": 1,
+                    "nodes": single_node_expression(node_id),
+                }
+            },
+        ];
+        let doc = self
+            .execution_read_collection()
+            .aggregate(pipeline)
+            .with_type::<ExecutionDocument>()
+            .await?
+            .try_next()
+            .await?;
+        info!(execution_id = %execution_id, node_id = %node_id, found = doc.is_some(), "Fetched execution node");
+        Ok(doc)
+    }
+
+    /// Stream an execution's nodes off an aggregation cursor, one
+    /// `nodes` entry per result, so no more than a batch of them is held at
+    /// once.
+    pub(crate) async fn stream_nodes(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<NodeStream, mongodb::error::Error> {
+        /// A `nodes` entry as `$objectToArray` yields it.
+        #[derive(Deserialize)]
+        struct StoredNode {
+            k: String,
+            v: Value,
+        }
+
+        let pipeline = vec![
+            doc! { "$match": execution_filter(execution_id, org_id) },
+            doc! { "$limit": 1 },
+            doc! {
+                "$project": {
+                    "_id": 0,
+                    // Array-shaped legacy `nodes` can't be read as a map
+                    "node": {
+                        "$cond": [
+                            { "$eq": [{ "$type": "$nodes" }, "object"] },
+                            { "$objectToArray": "$nodes" },
+                            [],
+                        ]
+                    },
+                }
+            },
+            doc! { "$unwind": "$node" },
+            doc! { "$replaceRoot": { "newRoot": "$node" } },
+        ];
+        let cursor = self
+            .execution_read_collection()
+            .aggregate(pipeline)
+            .with_type::<StoredNode>()
+            .await?;
+        info!(execution_id = %execution_id, "Streaming execution nodes");
+        Ok(cursor
+            .map(|stored| {
+                stored
+                    .map(|StoredNode { k, v }| NodeEntry {
+                        node_id: k,
+                        node:    HydratedNode::from_stored(v),
+                    })
+                    .map_err(StoreError::from)
+            })
+            .boxed())
+    }
+
+    /// Get all executions of `org_id` for a given workflow
     pub(crate) async fn get_executions_for_workflow(
         &self,
         workflow_id: &str,
+        org_id: Option<&str>,
     ) -> Result<Vec<ExecutionDocument>, mongodb::error::Error> {
         use futures::TryStreamExt;
 
         info!(workflow_id = %workflow_id, mongodb_db = %self.db_name, "Fetching executions for workflow");
-        let filter = doc! { "workflow_id": workflow_id };
-        let cursor = self.execution_collection().find(filter).await?;
+        let filter = workflow_filter(workflow_id, org_id);
+        let cursor = self.execution_read_collection().find(filter).await?;
         let executions: Vec<ExecutionDocument> = cursor.try_collect().await?;
         info!(workflow_id = %workflow_id, count = executions.len(), "Fetched executions for workflow");
         Ok(executions)
     }
 
-    #[allow(clippy::too_many_lines)]
-    pub(crate) async fn update_node_status(
+    /// Fetch several executions of `org_id` in one `$in` query. Ids that
+    /// don't exist there are simply absent from the result.
+    pub(crate) async fn get_execution_documents(
         &self,
-        msg: &NodeStatusMessage,
-    ) -> Result<(), mongodb::error::Error> {
-        let repair_pipeline = vec![doc! {
-            "$set": {
-                "nodes": {
-                    "$cond": [
-                        { "$isArray": "$nodes" },
-                        bson::Document::new(),
-                        "$nodes"
-                    ]
+        execution_ids: &[String],
+        org_id: Option<&str>,
+    ) -> Result<Vec<ExecutionDocument>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let filter = executions_filter(execution_ids, org_id);
+        let cursor = self.execution_read_collection().find(filter).await?;
+        let executions: Vec<ExecutionDocument> = cursor.try_collect().await?;
+        info!(
+            requested = execution_ids.len(),
+            found = executions.len(),
+            "Fetched execution documents"
+        );
+        Ok(executions)
+    }
+
+    /// Aggregate a workflow's executions of `org_id` in one `$facet` pass.
+    /// The p95 uses `$percentile` (MongoDB 7.0+), which is approximate.
+    pub(crate) async fn workflow_stats(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        node_type: Option<&str>,
+    ) -> Result<WorkflowStats, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        #[derive(Deserialize)]
+        struct StatusCount {
+            #[serde(rename = "_id")]
+            status: String,
+            count:  u64,
+        }
+        #[derive(Deserialize)]
+        struct Summary {
+            total:           u64,
+            avg_duration_ms: Option<i64>,
+            p95_duration_ms: Option<i64>,
+            last_run_at:     Option<bson::DateTime>,
+        }
+        #[derive(Deserialize)]
+        struct Facets {
+            by_status: Vec<StatusCount>,
+            summary:   Vec<Summary>,
+        }
+
+        let mut filter = workflow_filter(workflow_id, org_id);
+        if let Some(node_type) = node_type {
+            filter.insert("$expr", ran_node_type_expression(node_type));
+        }
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! {
+                "$facet": {
+                    "by_status": [
+                        { "$group": {
+                            "_id": { "$ifNull": ["$status", WorkflowStats::RUNNING] },
+                            "count": { "$sum": 1 },
+                        } },
+                    ],
+                    "summary": [
+                        { "$group": {
+                            "_id": null,
+                            "total": { "$sum": 1 },
+                            "avg_duration_ms": { "$avg": "$total_duration_ms" },
+                            "p95_duration_ms": { "$percentile": {
+                                "input": "$total_duration_ms",
+                                "p": [0.95],
+                                "method": "approximate",
+                            } },
+                            "last_run_at": { "$max": "$created_at" },
+                        } },
+                        { "$set": {
+                            "avg_duration_ms": { "$toLong": { "$round": ["$avg_duration_ms", 0] } },
+                            "p95_duration_ms": { "$toLong": { "$arrayElemAt": ["$p95_duration_ms", 0] } },
+                        } },
+                    ],
                 }
-            }
-        }];
+            },
+        ];
+        let facets = self
+            .execution_collection()
+            .aggregate(pipeline)
+            .with_type::<Facets>()
+            .await?
+            .try_next()
+            .await?;
+
+        let mut stats =
+            WorkflowStats { workflow_id: workflow_id.to_string(), ..WorkflowStats::default() };
+        let Some(facets) = facets else {
+            return Ok(stats);
+        };
+        stats.by_status = facets
+            .by_status
+            .into_iter()
+            .map(|entry| (entry.status, entry.count))
+            .collect();
+        if let Some(summary) = facets.summary.into_iter().next() {
+            stats.total = summary.total;
+            stats.avg_duration_ms = summary.avg_duration_ms;
+            stats.p95_duration_ms = summary.p95_duration_ms;
+            stats.last_run_at = summary.last_run_at;
+        }
+        info!(workflow_id = %workflow_id, total = stats.total, "Aggregated workflow stats");
+        Ok(stats)
+    }
+
+    /// A page of a workflow's executions of `org_id` ordered by `created_at`
+    /// then `execution_id` (both descending), using the cursor as a range
+    /// filter.
+    pub(crate) async fn get_executions_for_workflow_paginated(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        after: Option<&ExecutionCursor>,
+        node_type: Option<&str>,
+        limit: i64,
+    ) -> Result<ExecutionPage, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        let mut filter = workflow_filter(workflow_id, org_id);
+        if let Some(node_type) = node_type {
+            filter.insert("$expr", ran_node_type_expression(node_type));
+        }
+        if let Some(cursor) = after {
+            // Missing `created_at` sorts lowest, so those documents come last
+            let range = cursor.created_at.map_or_else(
+                || vec![doc! { "created_at": null, "execution_id": { "$lt": &cursor.execution_id } }],
+                |created_at| {
+                    vec![
+                        doc! { "created_at": { "$lt": created_at } },
+                        doc! { "created_at": created_at, "execution_id": { "$lt": &cursor.execution_id } },
+                        doc! { "created_at": null },
+                    ]
+                },
+            );
+            filter.insert("$or", range);
+        }
+
+        // Fetch one extra document to learn whether another page exists
+        let cursor = self
+            .execution_read_collection()
+            .find(filter)
+            .sort(doc! { "created_at": -1, "execution_id": -1 })
+            .limit(limit.saturating_add(1))
+            .await?;
+        let mut executions: Vec<ExecutionDocument> = cursor.try_collect().await?;
+
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        let next_cursor = if executions.len() > limit {
+            executions.truncate(limit);
+            executions
+                .last()
+                .map(|doc| ExecutionCursor::after(doc).encode())
+        } else {
+            None
+        };
+        info!(workflow_id = %workflow_id, count = executions.len(), "Fetched execution page for workflow");
+        Ok(ExecutionPage { executions, next_cursor })
+    }
+
+    /// Execution summaries of `org_id` visible through `scope`, newest first.
+    pub(crate) async fn list_executions_for_user(
+        &self,
+        scope: &ExecutionAccessScope,
+        org_id: Option<&str>,
+        limit: i64,
+        offset: u64,
+    ) -> Result<Vec<ExecutionSummary>, mongodb::error::Error> {
+        use futures::TryStreamExt;
 
-        let computed_lineage_hash = msg
-            .lineage_stack
-            .as_ref()
-            .filter(|stack| !stack.is_empty())
-            .and_then(|stack| compute_lineage_hash(stack));
+        if scope.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let lineage_hash = computed_lineage_hash
-            .or_else(|| msg.lineage_hash.clone())
-            .unwrap_or_else(|| "default".to_string());
+        let filter = doc! {
+            "org_id": org_id,
+            "$or": [
+                { "workflow_id": { "$in": &scope.workflow_ids } },
+                { "execution_id": { "$in": &scope.execution_ids } },
+            ]
+        };
+        let cursor = self
+            .client
+            .database(&self.db_name)
+            .collection::<ExecutionSummary>(EXECUTIONS)
+            .find(filter)
+            .projection(doc! {
+                "_id": 0,
+                "execution_id": 1,
+                "workflow_id": 1,
+                "status": 1,
+                "created_at": 1,
+                "updated_at": 1,
+                "progress": 1,
+            })
+            .sort(doc! { "created_at": -1, "execution_id": -1 })
+            .skip(offset)
+            .limit(limit)
+            .await?;
+        let executions: Vec<ExecutionSummary> = cursor.try_collect().await?;
+        info!(count = executions.len(), "Listed executions for user");
+        Ok(executions)
+    }
 
+    /// Apply one status message; `false` when it was dropped, see
+    /// [`ExecutionStorePort::update_node_status`].
+    pub(crate) async fn update_node_status(
+        &self,
+        msg: &NodeStatusMessage,
+    ) -> Result<bool, mongodb::error::Error> {
         info!(
             execution_id = %msg.execution_id,
             workflow_id = %msg.workflow_id,
             node_id = %msg.node_id,
             status = %msg.status,
-            lineage_hash = %lineage_hash,
+            lineage_hash = %status_lineage_hash(msg),
             mongodb_db = %self.db_name,
             "Updating node status"
         );
-        let filter = doc! {
-            "execution_id": &msg.execution_id,
-        };
-
-        let base_path = format!("nodes.{}", msg.node_id);
-
-        let doc = retry_backoff!("get_execution_document", {
-            self.get_execution_document(&msg.execution_id).await
+        // Always from the primary: the update is computed from this read. Any
+        // organization's, so a status for another one's execution is logged
+        // as foreign below rather than as missing
+        let primary = &self.execution_collection();
+        let doc = retry_backoff!(&self.retry, "get_execution_document", {
+            self.find_execution_document(
+                primary,
+                &msg.execution_id,
+                doc! { "execution_id": &msg.execution_id },
+            )
+            .await
         })
         .await?;
 
@@ -178,113 +735,37 @@ impl ExecutionStore {
                 node_id = %msg.node_id,
                 "Execution document not found; cannot update node status"
             );
-            return Ok(());
-        };
-
-        let (node_name, node_type) = doc.nodes.get(&msg.node_id).map_or((None, None), |n| {
-            let name = n.latest.as_ref().and_then(|l| l.name.clone()).or_else(|| {
-                n.extra
-                    .get("name")
-                    .and_then(Value::as_str)
-                    .map(String::from)
-            });
-            let node_type = n
-                .latest
-                .as_ref()
-                .and_then(|l| l.node_type.clone())
-                .or_else(|| {
-                    n.extra
-                        .get("type")
-                        .and_then(Value::as_str)
-                        .map(String::from)
-                });
-            (name, node_type)
-        });
-        let node_execution = NodeExecutionInstance {
-            input: msg.input.clone(),
-            parameters: msg.parameters.clone(),
-            output: msg.output.clone(),
-            status: Some(msg.status.clone()),
-            error: msg.error.clone(),
-            executed_at: Some(msg.executed_at.clone()),
-            duration_ms: Some(msg.duration_ms),
-            node_type,
-            name: node_name,
-            lineage_hash: if lineage_hash == "default" {
-                None
-            } else {
-                Some(lineage_hash.clone())
-            },
-            lineage_stack: msg.lineage_stack.clone(),
-            used_inputs: msg.used_inputs.clone(),
-            branch_id: msg.branch_id.clone(),
-            split_node_id: msg.split_node_id.clone(),
-            item_index: msg.item_index,
-            total_items: msg.total_items,
-            processed_count: msg.processed_count,
-            aggregator_state: msg.aggregator_state.clone(),
-        };
-
-        let mut set_fields = doc! {
-            format!("{base_path}.latest"): bson::to_bson(&node_execution)?,
-            "updated_at": bson::DateTime::from_millis(Utc::now().timestamp_millis()),
+            return Ok(false);
         };
-
-        if lineage_hash != "default" {
-            set_fields.insert(
-                format!("{base_path}.lineages.{lineage_hash}"),
-                bson::to_bson(&node_execution)?,
-            );
+        if !doc.in_org(msg.org_id.as_deref()) {
+            log_foreign_status(&doc, msg);
+            return Ok(false);
         }
+        if reopens_finished_execution(&doc, msg) {
+            log_late_status(&doc, msg);
+            return Ok(false);
+        }
+        if drop_stale(&doc, &[msg]).is_empty() {
+            return Ok(false);
+        }
+        let filter = execution_filter(&msg.execution_id, doc.org_id.as_deref());
 
-        let update = doc! { "$set": set_fields };
-
-        let max_retries: u32 = 5;
-        let mut backoff = std::time::Duration::from_millis(250);
-
-        for attempt in 0..=max_retries {
-            if let Err(e) = self
-                .execution_collection()
-                .update_one(doc! { "execution_id": &msg.execution_id }, repair_pipeline.clone())
-                .await
-            {
-                if attempt == max_retries {
-                    return Err(e);
-                }
-                warn!(
-                    execution_id = %msg.execution_id,
-                    attempt = attempt + 1,
-                    backoff_ms = backoff.as_millis(),
-                    "Node status repair failed; will retry with backoff"
-                );
-                tokio::time::sleep(backoff).await;
-                backoff = backoff.saturating_mul(2);
-                continue;
-            }
+        let update = node_status_pipeline(&doc, &[msg])?;
 
-            match self
-                .execution_collection()
+        let update = &update;
+        let filter = &filter;
+        retry_backoff!(&self.retry, "node_status_update", {
+            self.execution_collection()
+                .update_one(filter.clone(), repair_nodes_pipeline())
+                .await?;
+            self.execution_collection()
                 .update_one(filter.clone(), update.clone())
                 .upsert(false)
                 .await
-            {
-                Ok(_) => break,
-                Err(e) => {
-                    if attempt == max_retries {
-                        return Err(e);
-                    }
-                    warn!(
-                        execution_id = %msg.execution_id,
-                        node_id = %msg.node_id,
-                        attempt = attempt + 1,
-                        backoff_ms = backoff.as_millis(),
-                        "Node status update failed; will retry with backoff"
-                    );
-                    tokio::time::sleep(backoff).await;
-                    backoff = backoff.saturating_mul(2);
-                },
-            }
-        }
+        })
+        .await?;
+
+        self.append_status_log(&[(msg, doc.expires_at)]).await;
 
         info!(
             execution_id = %msg.execution_id,
@@ -292,107 +773,1340 @@ impl ExecutionStore {
             status = %msg.status,
             "Updated node status"
         );
-        Ok(())
+        self.sample_document_size(&msg.execution_id).await;
+        Ok(true)
     }
 
-    pub(crate) async fn complete_execution(
+    /// Apply a batch of status messages with one read and one `bulk_write`.
+    ///
+    /// Messages are grouped by execution and each group becomes a single
+    /// update applied in message order, so a later status for a node wins as
+    /// it would sequentially. Returns for each message whether it was
+    /// applied. Requires MongoDB 8.0+.
+    pub(crate) async fn update_node_statuses(
         &self,
-        msg: &CompletionMessage,
-    ) -> Result<(), mongodb::error::Error> {
-        info!(
-            execution_id = %msg.execution_id,
-            workflow_id = %msg.workflow_id,
-            status = %msg.status,
-            mongodb_db = %self.db_name,
-            "Completing execution"
-        );
-        let filter = doc! {
-            "execution_id": &msg.execution_id,
-        };
+        msgs: &[NodeStatusMessage],
+    ) -> Result<Vec<bool>, mongodb::error::Error> {
+        use futures::TryStreamExt;
 
-        let update = doc! {
-            "$set": {
-                "status": &msg.status,
-                "updated_at": bson::DateTime::from_millis(Utc::now().timestamp_millis()),
-            }
+        let groups = group_by_execution(msgs);
+        let execution_ids: Vec<&str> = groups.iter().map(|(id, _)| *id).collect();
+        let execution_ids = &execution_ids;
+        let docs: HashMap<String, ExecutionDocument> =
+            retry_backoff!(&self.retry, "get_execution_documents", {
+                self.execution_collection()
+                    .find(doc! { "execution_id": { "$in": execution_ids } })
+                    .await?
+                    .try_collect::<Vec<_>>()
+                    .await
+            })
+            .await?
+            .into_iter()
+            .map(|doc| (doc.execution_id.clone(), doc))
+            .collect();
+
+        let namespace = self.execution_collection().namespace();
+        let mut models = Vec::new();
+        let mut applied = Vec::new();
+        for (execution_id, group) in &groups {
+            let Some(doc) = docs.get(*execution_id) else {
+                warn!(
+                    execution_id = %execution_id,
+                    dropped = group.len(),
+                    "Execution document not found; cannot update node status"
+                );
+                continue;
+            };
+            let group: Vec<&NodeStatusMessage> = group
+                .iter()
+                .copied()
+                .filter(|msg| {
+                    if !doc.in_org(msg.org_id.as_deref()) {
+                        log_foreign_status(doc, msg);
+                        return false;
+                    }
+                    let late = reopens_finished_execution(doc, msg);
+                    if late {
+                        log_late_status(doc, msg);
+                    }
+                    !late
+                })
+                .collect();
+            let group = drop_stale(doc, &group);
+            if group.is_empty() {
+                continue;
+            }
+            let filter = execution_filter(execution_id, doc.org_id.as_deref());
+            models.push(
+                UpdateOneModel::builder()
+                    .namespace(namespace.clone())
+                    .filter(filter.clone())
+                    .update(repair_nodes_pipeline())
+                    .build(),
+            );
+            models.push(
+                UpdateOneModel::builder()
+                    .namespace(namespace.clone())
+                    .filter(filter)
+                    .update(node_status_pipeline(doc, &group)?)
+                    .build(),
+            );
+            applied.extend(group.iter().map(|msg| (*msg, doc.expires_at)));
+        }
+
+        let updated_executions = models.len() / 2;
+        if !models.is_empty() {
+            let models = &models;
+            retry_backoff!(&self.retry, "node_status_bulk_write", {
+                self.client.bulk_write(models.clone()).await
+            })
+            .await?;
+        }
+        self.append_status_log(&applied).await;
+
+        info!(
+            messages = msgs.len(),
+            applied = applied.len(),
+            executions = updated_executions,
+            "Applied node status batch"
+        );
+        for (execution_id, _) in &groups {
+            self.sample_document_size(execution_id).await;
+        }
+        Ok(msgs
+            .iter()
+            .map(|msg| {
+                applied
+                    .iter()
+                    .any(|(applied, _)| std::ptr::eq(*applied, msg))
+            })
+            .collect())
+    }
+
+    /// Append applied status messages to the status log, each carrying its
+    /// execution's `expires_at` so both expire together. Failures are only
+    /// logged: the execution document is already updated.
+    async fn append_status_log(&self, msgs: &[(&NodeStatusMessage, Option<bson::DateTime>)]) {
+        let Some((first, _)) = msgs.first() else {
+            return;
+        };
+        let mut records = Vec::with_capacity(msgs.len());
+        for (msg, expires_at) in msgs {
+            // UUIDv7 ids sort by creation time, keeping the log order stable
+            // for entries that share an `executed_at`.
+            let record =
+                NodeStatusRecord { id: Uuid::now_v7().to_string(), message: (*msg).clone() };
+            match bson::to_document(&record) {
+                Ok(mut record) => {
+                    if let Some(expires_at) = expires_at {
+                        record.insert("expires_at", *expires_at);
+                    }
+                    records.push(record);
+                },
+                Err(e) => {
+                    warn!(execution_id = %msg.execution_id, error = %e, "Failed to encode status log entry");
+                },
+            }
+        }
+        let log = self.status_collection().clone_with_type::<bson::Document>();
+        if let Err(e) = log.insert_many(records).await {
+            warn!(
+                execution_id = %first.execution_id,
+                entries = msgs.len(),
+                error = %e,
+                "Failed to append node status to status log"
+            );
+        }
+    }
+
+    /// Page through the status log of an execution of `org_id` using a range
+    /// filter on `(executed_at, id)` rather than skip/limit, so pages stay
+    /// stable while new statuses are appended.
+    pub(crate) async fn get_status_history(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        after: Option<&StatusCursor>,
+        offset: u64,
+        limit: i64,
+    ) -> Result<Vec<NodeStatusRecord>, mongodb::error::Error> {
+        use futures::TryStreamExt;
+
+        info!(execution_id = %execution_id, mongodb_db = %self.db_name, "Fetching status history");
+        let mut filter = execution_filter(execution_id, org_id);
+        if let Some(cursor) = after {
+            filter.insert(
+                "$or",
+                vec![
+                    doc! { "executed_at": { "$gt": &cursor.executed_at } },
+                    doc! { "executed_at": &cursor.executed_at, "id": { "$gt": &cursor.id } },
+                ],
+            );
+        }
+
+        let cursor = self
+            .status_collection()
+            .find(filter)
+            .sort(doc! { "executed_at": 1, "id": 1 })
+            .skip(offset)
+            .limit(limit)
+            .await?;
+        let records: Vec<NodeStatusRecord> = cursor.try_collect().await?;
+        info!(execution_id = %execution_id, count = records.len(), "Fetched status history");
+        Ok(records)
+    }
+
+    /// Apply a completion unless the execution already recorded it. Returns
+    /// `false` for such a duplicate; a completion for an execution that never
+    /// appears is dropped after a few retries and counts as applied.
+    pub(crate) async fn complete_execution(
+        &self,
+        msg: &CompletionMessage,
+    ) -> Result<bool, mongodb::error::Error> {
+        info!(
+            execution_id = %msg.execution_id,
+            workflow_id = %msg.workflow_id,
+            status = %msg.status,
+            mongodb_db = %self.db_name,
+            "Completing execution"
+        );
+        let filter = completion_filter(msg);
+        let update = completion_update(msg)?;
+
+        // A completion can overtake its execution's definition, so a missing
+        // document is retried, waiting longer than for a failed write. Only
+        // that is retried: a duplicate completion is a result, and a store
+        // error goes back to the consumer's own retry.
+        let policy = self
+            .retry
+            .with_base_delay(self.retry.base_delay.saturating_mul(2));
+        let (filter, update) = (&filter, &update);
+        let completed = with_backoff_if(
+            &policy,
+            "complete_execution",
+            || self.try_complete_execution(msg, filter, update),
+            CompletionError::is_missing,
+        )
+        .await;
+        match completed {
+            Ok(true) => {},
+            Ok(false) => return Ok(false),
+            Err(CompletionError::Missing) => {
+                warn!(
+                    execution_id = %msg.execution_id,
+                    workflow_id = %msg.workflow_id,
+                    "Completion received for missing execution document; retries exhausted; execution document still missing"
+                );
+                return Ok(true);
+            },
+            Err(CompletionError::Store(e)) => return Err(e),
+        }
+        info!(execution_id = %msg.execution_id, status = %msg.status, "Completed execution");
+        Ok(true)
+    }
+
+    /// One completion attempt: `Ok(true)` when it was recorded, `Ok(false)`
+    /// for a duplicate of one already recorded.
+    async fn try_complete_execution(
+        &self,
+        msg: &CompletionMessage,
+        filter: &bson::Document,
+        update: &[bson::Document],
+    ) -> Result<bool, CompletionError> {
+        let result = self
+            .execution_collection()
+            .update_one(filter.clone(), update.to_vec())
+            .upsert(false)
+            .await?;
+        if result.matched_count > 0 {
+            return Ok(true);
+        }
+        let exists = self
+            .execution_collection()
+            .count_documents(execution_filter(&msg.execution_id, msg.org_id.as_deref()))
+            .limit(1)
+            .await?
+            > 0;
+        if exists {
+            info!(
+                execution_id = %msg.execution_id,
+                status = %msg.status,
+                "Ignoring duplicate completion"
+            );
+            return Ok(false);
+        }
+        warn!(
+            execution_id = %msg.execution_id,
+            workflow_id = %msg.workflow_id,
+            "Completion received for missing execution document"
+        );
+        Err(CompletionError::Missing)
+    }
+
+    pub(crate) async fn ping(&self) -> Result<(), mongodb::error::Error> {
+        self.client
+            .database(&self.db_name)
+            .run_command(doc! { "ping": 1 })
+            .await?;
+        Ok(())
+    }
+
+    /// Delete an execution document of `org_id` and its status log,
+    /// returning whether the document existed. The log is removed first so a
+    /// failure never leaves status rows pointing at a deleted document.
+    pub(crate) async fn delete_execution(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<bool, mongodb::error::Error> {
+        let filter = execution_filter(execution_id, org_id);
+
+        let statuses = self.status_collection().delete_many(filter.clone()).await?;
+
+        let result = match self.execution_collection().delete_one(filter).await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!(
+                    execution_id = %execution_id,
+                    deleted_statuses = statuses.deleted_count,
+                    error = %e,
+                    "Deleted status log but failed to delete execution document"
+                );
+                return Err(e);
+            },
+        };
+
+        info!(
+            execution_id = %execution_id,
+            deleted_statuses = statuses.deleted_count,
+            deleted = result.deleted_count,
+            "Deleted execution"
+        );
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Rewrite an execution of `org_id` into the current document shape (see
+    /// [`repair_update`]); `false` when it doesn't exist.
+    ///
+    /// The rewrite only lands if no status was written since the document
+    /// was read, and is recomputed otherwise; an execution that keeps
+    /// changing is a [`StoreError::Conflict`].
+    pub(crate) async fn repair_execution(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        let collection = self
+            .execution_collection()
+            .clone_with_type::<bson::Document>();
+        for _ in 0..REPAIR_ATTEMPTS {
+            let Some(raw) = collection
+                .find_one(execution_filter(execution_id, org_id))
+                .await
+                .map_err(StoreError::from)?
+            else {
+                return Ok(false);
+            };
+            let now = bson::DateTime::from_millis(Utc::now().timestamp_millis());
+            let update = repair_update(&raw, now)
+                .map_err(|e| StoreError::from(mongodb::error::Error::from(e)))?;
+            let mut filter = execution_filter(execution_id, org_id);
+            filter.insert("updated_at", raw.get("updated_at").cloned().unwrap_or(Bson::Null));
+            let result = collection
+                .update_one(filter, update)
+                .await
+                .map_err(StoreError::from)?;
+            if result.matched_count > 0 {
+                info!(execution_id = %execution_id, "Repaired execution document");
+                return Ok(true);
+            }
+        }
+        Err(StoreError::Conflict("execution kept changing during repair".into()))
+    }
+}
+
+/// Reads of a changing execution before a repair gives up.
+const REPAIR_ATTEMPTS: usize = 3;
+
+#[async_trait]
+impl ExecutionStorePort for ExecutionStore {
+    async fn upsert_execution_definition(&self, msg: &NodeExecutionMessage) -> StoreResult<()> {
+        Self::upsert_execution_definition(self, msg)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_execution_document(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        Self::get_execution_document(self, execution_id, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_execution_document_compact(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        Self::get_execution_document_compact(self, execution_id, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_execution_owner(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Option<ExecutionOwner>> {
+        Self::get_execution_owner(self, execution_id, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_execution_fields(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        fields: &ExecutionFields,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        Self::get_execution_fields(self, execution_id, org_id, fields)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_node(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        node_id: &str,
+    ) -> StoreResult<Option<ExecutionDocument>> {
+        Self::get_node(self, execution_id, org_id, node_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn stream_nodes(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<NodeStream> {
+        Self::stream_nodes(self, execution_id, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_execution_documents(
+        &self,
+        execution_ids: &[String],
+        org_id: Option<&str>,
+    ) -> StoreResult<Vec<ExecutionDocument>> {
+        Self::get_execution_documents(self, execution_ids, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_executions_for_workflow(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Vec<ExecutionDocument>> {
+        Self::get_executions_for_workflow(self, workflow_id, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_executions_for_workflow_paginated(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        after: Option<&ExecutionCursor>,
+        node_type: Option<&str>,
+        limit: i64,
+    ) -> StoreResult<ExecutionPage> {
+        Self::get_executions_for_workflow_paginated(
+            self,
+            workflow_id,
+            org_id,
+            after,
+            node_type,
+            limit,
+        )
+        .await
+        .map_err(StoreError::from)
+    }
+
+    async fn workflow_stats(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        node_type: Option<&str>,
+    ) -> StoreResult<WorkflowStats> {
+        Self::workflow_stats(self, workflow_id, org_id, node_type)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn list_executions_for_user(
+        &self,
+        scope: &ExecutionAccessScope,
+        org_id: Option<&str>,
+        limit: i64,
+        offset: u64,
+    ) -> StoreResult<Vec<ExecutionSummary>> {
+        Self::list_executions_for_user(self, scope, org_id, limit, offset)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn update_node_status(&self, msg: &NodeStatusMessage) -> StoreResult<bool> {
+        Self::update_node_status(self, msg)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn update_node_statuses(&self, msgs: &[NodeStatusMessage]) -> StoreResult<Vec<bool>> {
+        Self::update_node_statuses(self, msgs)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn get_status_history(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        after: Option<&StatusCursor>,
+        offset: u64,
+        limit: i64,
+    ) -> StoreResult<Vec<NodeStatusRecord>> {
+        Self::get_status_history(self, execution_id, org_id, after, offset, limit)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn complete_execution(&self, msg: &CompletionMessage) -> StoreResult<bool> {
+        Self::complete_execution(self, msg)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn delete_execution(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        Self::delete_execution(self, execution_id, org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn repair_execution(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        Self::repair_execution(self, execution_id, org_id).await
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        Self::ping(self).await.map_err(StoreError::from)
+    }
+}
+
+/// Why a completion attempt failed.
+#[derive(Debug)]
+enum CompletionError {
+    /// No execution document to complete yet; worth retrying
+    Missing,
+    Store(mongodb::error::Error),
+}
+
+impl CompletionError {
+    const fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+}
+
+impl From<mongodb::error::Error> for CompletionError {
+    fn from(e: mongodb::error::Error) -> Self {
+        Self::Store(e)
+    }
+}
+
+/// MongoDB's `DuplicateKey` error code.
+const DUPLICATE_KEY: i32 = 11000;
+
+/// Server codes for a node that is stepping down, shutting down, unreachable
+/// or out of time; the same codes the driver retries reads on.
+const TRANSIENT_CODES: [i32; 13] =
+    [6, 7, 89, 91, 134, 189, 262, 9001, 10107, 11600, 11602, 13435, 13436];
+
+/// Labels the driver or server puts on errors worth retrying.
+const TRANSIENT_LABELS: [&str; 4] =
+    [RETRYABLE_WRITE_ERROR, TRANSIENT_TRANSACTION_ERROR, SYSTEM_OVERLOADED_ERROR, RETRYABLE_ERROR];
+
+/// The code the server rejected an operation with, if it did.
+const fn server_code(e: &mongodb::error::Error) -> Option<i32> {
+    match &*e.kind {
+        ErrorKind::Command(err) => Some(err.code),
+        ErrorKind::Write(WriteFailure::WriteError(err)) => Some(err.code),
+        ErrorKind::Write(WriteFailure::WriteConcernError(err)) => Some(err.code),
+        _ => None,
+    }
+}
+
+/// Duplicate keys are conflicts; network, server selection and failover
+/// errors are transient; anything else is fatal.
+impl From<mongodb::error::Error> for StoreError {
+    fn from(e: mongodb::error::Error) -> Self {
+        let code = server_code(&e);
+        if code == Some(DUPLICATE_KEY) {
+            return Self::Conflict(Box::new(e));
+        }
+        let transient = matches!(
+            &*e.kind,
+            ErrorKind::Io(_)
+                | ErrorKind::ConnectionPoolCleared { .. }
+                | ErrorKind::ServerSelection { .. }
+                | ErrorKind::DnsResolve { .. }
+        ) || code.is_some_and(|code| TRANSIENT_CODES.contains(&code))
+            || TRANSIENT_LABELS.iter().any(|label| e.contains_label(label));
+        if transient {
+            Self::Transient(Box::new(e))
+        } else {
+            Self::Fatal(Box::new(e))
+        }
+    }
+}
+
+/// The driver's read preference for a configured one.
+const fn selection_criteria(read_preference: MongoReadPreference) -> SelectionCriteria {
+    SelectionCriteria::ReadPreference(match read_preference {
+        MongoReadPreference::Primary => ReadPreference::Primary,
+        MongoReadPreference::PrimaryPreferred => ReadPreference::PrimaryPreferred { options: None },
+        MongoReadPreference::Secondary => ReadPreference::Secondary { options: None },
+        MongoReadPreference::SecondaryPreferred => {
+            ReadPreference::SecondaryPreferred { options: None }
+        },
+        MongoReadPreference::Nearest => ReadPreference::Nearest { options: None },
+    })
+}
+
+/// Indexes created by [`ExecutionStore::ensure_indexes`], by collection:
+/// execution lookups, a workflow's executions newest first, and an
+/// execution's status log in `executed_at` order. With `expiring` set, TTL
+/// indexes on `expires_at` remove executions and their status log entries.
+fn required_indexes(expiring: bool) -> Vec<(&'static str, IndexModel)> {
+    let named = |name: &str| IndexOptions::builder().name(name.to_string());
+    let ttl = || {
+        IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("expires_at_ttl".to_string())
+                    .expire_after(Duration::ZERO)
+                    .build(),
+            )
+            .build()
+    };
+    let mut indexes = vec![
+        (
+            EXECUTIONS,
+            IndexModel::builder()
+                .keys(doc! { "execution_id": 1 })
+                .options(named("execution_id_unique").unique(true).build())
+                .build(),
+        ),
+        (
+            EXECUTIONS,
+            IndexModel::builder()
+                .keys(doc! { "workflow_id": 1, "created_at": -1, "execution_id": -1 })
+                .options(named("workflow_id_created_at").build())
+                .build(),
+        ),
+        (
+            EXECUTION_STATUS,
+            IndexModel::builder()
+                .keys(doc! { "execution_id": 1, "executed_at": 1, "id": 1 })
+                .options(named("execution_id_executed_at").build())
+                .build(),
+        ),
+    ];
+    if expiring {
+        indexes.push((EXECUTIONS, ttl()));
+        indexes.push((EXECUTION_STATUS, ttl()));
+    }
+    indexes
+}
+
+/// Aggregation expression rebuilding `nodes` with
+/// [`NodeExecutionInstance::PAYLOAD_FIELDS`] removed from each node's
+/// `latest` and `lineages` entries. Legacy array-shaped `nodes` pass through.
+fn compact_nodes_expression() -> bson::Document {
+    let strip = |instance: &str| {
+        let payloads = NodeExecutionInstance::PAYLOAD_FIELDS.map(str::to_owned);
+        filter_run_fields(instance, &payloads, false)
+    };
+    let node = doc! {
+        "k": "$$node.k",
+        "v": {
+            "$mergeObjects": [
+                "$$node.v",
+                {
+                    "latest": strip("$$node.v.latest"),
+                    "lineages": {
+                        "$arrayToObject": {
+                            "$map": {
+                                "input": { "$objectToArray": { "$ifNull": ["$$node.v.lineages", {}] } },
+                                "as": "lineage",
+                                "in": { "k": "$$lineage.k", "v": strip("$$lineage.v") }
+                            }
+                        }
+                    }
+                }
+            ]
+        }
+    };
+    doc! {
+        "$cond": [
+            { "$eq": [{ "$type": "$nodes" }, "object"] },
+            {
+                "$arrayToObject": {
+                    "$map": { "input": { "$objectToArray": "$nodes" }, "as": "node", "in": node }
+                }
+            },
+            "$nodes",
+        ]
+    }
+}
+
+/// Aggregation expression keeping (`keep`) or dropping the `fields` of the
+/// run at `instance`; anything but an object passes through.
+fn filter_run_fields<'a>(
+    instance: &str,
+    fields: impl IntoIterator<Item = &'a String>,
+    keep: bool,
+) -> bson::Document {
+    let fields: Vec<&String> = fields.into_iter().collect();
+    let listed = doc! { "$in": ["$$this.k", fields] };
+    doc! {
+        "$cond": [
+            { "$eq": [{ "$type": instance }, "object"] },
+            {
+                "$arrayToObject": {
+                    "$filter": {
+                        "input": { "$objectToArray": instance },
+                        "cond": if keep { listed } else { doc! { "$not": [listed] } }
+                    }
+                }
+            },
+            instance,
+        ]
+    }
+}
+
+/// `$project` stage for [`ExecutionFields`], always keeping what
+/// authorization and the ETag read. Selected node parts are rebuilt from
+/// `nodes`; legacy array-shaped `nodes` pass through.
+fn fields_projection(fields: &ExecutionFields) -> bson::Document {
+    let mut projection = doc! {
+        "_id": 0,
+        "execution_id": 1,
+        "workflow_id": 1,
+        "org_id": 1,
+        "updated_at": 1,
+        "workflow_version_id": 1,
+    };
+    for field in &fields.top {
+        projection.insert(field.as_str(), 1);
+    }
+    if !fields.narrows_nodes() {
+        return projection;
+    }
+    let mut node = bson::Document::new();
+    for (part, run_fields) in &fields.nodes {
+        let path = format!("$$node.v.{part}");
+        let value = match (part.as_str(), run_fields) {
+            (_, None) => Bson::String(path),
+            ("lineages", Some(run_fields)) => Bson::Document(doc! {
+                "$arrayToObject": {
+                    "$map": {
+                        "input": { "$objectToArray": { "$ifNull": [path, {}] } },
+                        "as": "lineage",
+                        "in": { "k": "$$lineage.k", "v": filter_run_fields("$$lineage.v", run_fields, true) }
+                    }
+                }
+            }),
+            (_, Some(run_fields)) => Bson::Document(filter_run_fields(&path, run_fields, true)),
+        };
+        node.insert(part.as_str(), value);
+    }
+    if !node.contains_key("latest") && !node.contains_key("lineages") {
+        // Keeps a `lineage_count`-only node from reading as a legacy run
+        node.insert("lineages", doc! { "$literal": {} });
+    }
+    projection.insert(
+        "nodes",
+        doc! {
+            "$cond": [
+                { "$eq": [{ "$type": "$nodes" }, "object"] },
+                {
+                    "$arrayToObject": {
+                        "$map": {
+                            "input": { "$objectToArray": "$nodes" },
+                            "as": "node",
+                            "in": { "k": "$$node.k", "v": node }
+                        }
+                    }
+                },
+                "$nodes",
+            ]
+        },
+    );
+    projection
+}
+
+/// `$expr` matching executions where some node's latest run has
+/// `node_type`. Node ids are object keys, so this walks `nodes` as an array
+/// rather than querying a path; legacy array-shaped `nodes` never match.
+fn ran_node_type_expression(node_type: &str) -> bson::Document {
+    doc! {
+        "$anyElementTrue": [{
+            "$map": {
+                "input": {
+                    "$objectToArray": {
+                        "$cond": [{ "$eq": [{ "$type": "$nodes" }, "object"] }, "$nodes", {}]
+                    }
+                },
+                "in": { "$eq": ["$$this.v.latest.node_type", { "$literal": node_type }] }
+            }
+        }]
+    }
+}
+
+/// Aggregation expression keeping only `node_id` of the `nodes` object.
+/// Comparing keys instead of building a `nodes.<id>` path keeps ids with `.`
+/// working, and `$literal` stops an id starting with `$` being read as a
+/// field path. Legacy array-shaped `nodes` pass through unchanged.
+fn single_node_expression(node_id: &str) -> bson::Document {
+    doc! {
+        "$cond": [
+            { "$eq": [{ "$type": "$nodes" }, "object"] },
+            {
+                "$arrayToObject": {
+                    "$filter": {
+                        "input": { "$objectToArray": "$nodes" },
+                        "cond": { "$eq": ["$$this.k", { "$literal": node_id }] }
+                    }
+                }
+            },
+            "$nodes",
+        ]
+    }
+}
+
+/// Coerce a legacy array-shaped `nodes` field into an object so node paths
+/// can be `$set`, and backfill `lineage_count` on nodes stored before the
+/// counter existed with the number of their stored lineages (what reads fall
+/// back to), so counting a new lineage doesn't start it from zero.
+fn repair_nodes_pipeline() -> Vec<bson::Document> {
+    let uncounted = doc! {
+        "$and": [
+            { "$eq": [{ "$type": "$$node.v.lineages" }, "object"] },
+            { "$eq": [{ "$type": "$$node.v.lineage_count" }, "missing"] },
+        ]
+    };
+    let backfilled = doc! {
+        "$arrayToObject": {
+            "$map": {
+                "input": { "$objectToArray": "$nodes" },
+                "as": "node",
+                "in": {
+                    "k": "$$node.k",
+                    "v": {
+                        "$cond": [
+                            uncounted,
+                            {
+                                "$mergeObjects": [
+                                    "$$node.v",
+                                    { "lineage_count": { "$size": { "$objectToArray": "$$node.v.lineages" } } },
+                                ]
+                            },
+                            "$$node.v",
+                        ]
+                    }
+                }
+            }
+        }
+    };
+    vec![doc! {
+        "$set": {
+            "nodes": {
+                "$switch": {
+                    "branches": [
+                        { "case": { "$isArray": "$nodes" }, "then": bson::Document::new() },
+                        { "case": { "$eq": [{ "$type": "$nodes" }, "object"] }, "then": backfilled },
+                    ],
+                    "default": "$nodes",
+                }
+            }
+        }
+    }]
+}
+
+/// Node parts written by status updates rather than taken from the workflow
+/// definition.
+const NODE_RUN_PARTS: [&str; 3] = ["latest", "lineages", "lineage_count"];
+
+/// Stored nodes by id, whether kept as an object or in the legacy array
+/// shape.
+fn keyed_nodes(nodes: Option<&Bson>) -> BTreeMap<String, bson::Document> {
+    match nodes {
+        Some(Bson::Document(nodes)) => nodes
+            .iter()
+            .filter_map(|(id, node)| Some((id.clone(), node.as_document()?.clone())))
+            .collect(),
+        Some(Bson::Array(nodes)) => nodes
+            .iter()
+            .filter_map(Bson::as_document)
+            .filter_map(|node| Some((node.get_str("id").ok()?.to_string(), node.clone())))
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+/// The update rewriting a stored execution into the current shape.
+///
+/// The legacy `workflow_definition`, or else the definitions kept on the
+/// nodes, is normalized as [`ExecutionStore::upsert_execution_definition`]
+/// does, array-shaped `nodes` become an object, and `graph` and `progress`
+/// are rebuilt to match. Every node keeps its runs.
+fn repair_update(
+    raw: &bson::Document,
+    now: bson::DateTime,
+) -> Result<bson::Document, bson::ser::Error> {
+    let mut nodes = keyed_nodes(raw.get("nodes"));
+    let definition = if let Some(Bson::Document(definition)) = raw.get("workflow_definition") {
+        Bson::Document(definition.clone()).into_relaxed_extjson()
+    } else {
+        let definitions: Map<String, Value> = nodes
+            .iter()
+            .map(|(id, node)| {
+                let mut definition = node.clone();
+                for part in NODE_RUN_PARTS {
+                    definition.remove(part);
+                }
+                (id.clone(), Bson::Document(definition).into_relaxed_extjson())
+            })
+            .collect();
+        let edges = raw
+            .get("edges")
+            .cloned()
+            .map_or(Value::Null, Bson::into_relaxed_extjson);
+        serde_json::json!({ "nodes": definitions, "edges": edges })
+    };
+
+    let normalized = normalize_workflow_definition(&definition);
+    let definitions = normalized
+        .get("nodes")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+    let edges = normalized
+        .get("edges")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    for definition in definitions {
+        let Some(node_id) = definition
+            .get("id")
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+        else {
+            continue;
+        };
+        let node = nodes.entry(node_id.to_string()).or_default();
+        let runs: Vec<(String, Bson)> = NODE_RUN_PARTS
+            .iter()
+            .filter_map(|part| Some(((*part).to_string(), node.get(*part)?.clone())))
+            .collect();
+        *node = bson::to_document(definition)?;
+        node.extend(runs);
+    }
+
+    let hydrated: HashMap<String, HydratedNode> = nodes
+        .iter()
+        .filter_map(|(id, node)| Some((id.clone(), bson::from_document(node.clone()).ok()?)))
+        .collect();
+    let progress = ExecutionProgress::from_nodes(&hydrated);
+    let graph = ExecutionGraph::from_definition(definitions, edges.clone());
+    let nodes: bson::Document = nodes
+        .into_iter()
+        .map(|(id, node)| (id, Bson::Document(node)))
+        .collect();
+
+    Ok(doc! {
+        "$set": {
+            "nodes": nodes,
+            "edges": bson::to_bson(&edges)?,
+            "graph": bson::to_bson(&graph)?,
+            "progress": bson::to_bson(&progress)?,
+            "updated_at": now,
+        },
+        "$unset": { "workflow_definition": "" },
+    })
+}
+
+/// Lineage key a status is stored under: the hash of its lineage stack, the
+/// hash sent by the worker, or `"default"` outside of splits.
+fn status_lineage_hash(msg: &NodeStatusMessage) -> String {
+    msg.lineage_stack
+        .as_ref()
+        .filter(|stack| !stack.is_empty())
+        .and_then(|stack| compute_lineage_hash(stack))
+        .or_else(|| msg.lineage_hash.clone())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Group status messages by execution, keeping first-seen execution order and
+/// message order within each execution.
+fn group_by_execution(msgs: &[NodeStatusMessage]) -> Vec<(&str, Vec<&NodeStatusMessage>)> {
+    let mut groups: Vec<(&str, Vec<&NodeStatusMessage>)> = Vec::new();
+    for msg in msgs {
+        match groups
+            .iter_mut()
+            .find(|(execution_id, _)| *execution_id == msg.execution_id)
+        {
+            Some((_, group)) => group.push(msg),
+            None => groups.push((&msg.execution_id, vec![msg])),
+        }
+    }
+    groups
+}
+
+/// Matches the execution `execution_id` of `org_id`. `None` matches an
+/// execution stored without an organization, so writes never cross into
+/// another organization's execution that shares the id.
+fn execution_filter(execution_id: &str, org_id: Option<&str>) -> bson::Document {
+    doc! { "execution_id": execution_id, "org_id": org_id }
+}
+
+/// Matches the executions of `org_id` of the workflow `workflow_id`, like
+/// [`execution_filter`].
+fn workflow_filter(workflow_id: &str, org_id: Option<&str>) -> bson::Document {
+    doc! { "workflow_id": workflow_id, "org_id": org_id }
+}
+
+/// Matches the executions of `org_id` among `execution_ids`, like
+/// [`execution_filter`] does for one.
+fn executions_filter(execution_ids: &[String], org_id: Option<&str>) -> bson::Document {
+    doc! { "execution_id": { "$in": execution_ids }, "org_id": org_id }
+}
+
+/// Statuses a completion leaves an execution in.
+const TERMINAL_STATUSES: [&str; 3] = ["completed", "failed", "halted"];
+
+/// Matches the execution `msg` completes unless it already holds a terminal
+/// status from this same completion, so a redelivered message is a no-op.
+fn completion_filter(msg: &CompletionMessage) -> bson::Document {
+    doc! {
+        "execution_id": &msg.execution_id,
+        "org_id": msg.org_id.as_deref(),
+        "$or": [
+            { "status": { "$nin": TERMINAL_STATUSES.to_vec() } },
+            { "completed_at": { "$ne": &msg.completed_at } },
+        ],
+    }
+}
+
+/// Pipeline update recording `msg` on its execution.
+fn completion_update(msg: &CompletionMessage) -> Result<Vec<bson::Document>, bson::ser::Error> {
+    // No node is still running once the workflow has finished. The
+    // worker's context and reason are `$literal` so `$`-prefixed strings
+    // and keys aren't read as pipeline expressions.
+    Ok(vec![doc! {
+        "$set": {
+            "status": &msg.status,
+            "total_duration_ms": msg.total_duration_ms,
+            "completed_at": &msg.completed_at,
+            "failure_reason": { "$literal": &msg.failure_reason },
+            "final_context": { "$literal": bson::to_bson(&msg.final_context)? },
+            "updated_at": bson::DateTime::from_millis(Utc::now().timestamp_millis()),
+            "progress": {
+                "$cond": [
+                    { "$eq": [{ "$type": "$progress" }, "object"] },
+                    { "$mergeObjects": ["$progress", { "running": 0_i64 }] },
+                    "$$REMOVE",
+                ]
+            },
+        }
+    }])
+}
+
+fn is_finished(doc: &ExecutionDocument) -> bool {
+    doc.status
+        .as_deref()
+        .is_some_and(|status| TERMINAL_STATUSES.contains(&status))
+}
+
+/// Whether `msg` would show a node of an already completed execution as
+/// running again. The status and completion queues are consumed
+/// independently, so such a status can land after the completion; it is
+/// dropped, while late terminal node statuses are still recorded.
+fn reopens_finished_execution(doc: &ExecutionDocument, msg: &NodeStatusMessage) -> bool {
+    is_finished(doc) && ExecutionProgress::counter(&msg.status) == Some("running")
+}
+
+fn log_late_status(doc: &ExecutionDocument, msg: &NodeStatusMessage) {
+    warn!(
+        execution_id = %msg.execution_id,
+        node_id = %msg.node_id,
+        status = %msg.status,
+        execution_status = doc.status.as_deref().unwrap_or_default(),
+        "Dropping node status that arrived after the execution completed"
+    );
+}
+
+fn log_foreign_status(doc: &ExecutionDocument, msg: &NodeStatusMessage) {
+    warn!(
+        execution_id = %msg.execution_id,
+        node_id = %msg.node_id,
+        org_id = msg.org_id.as_deref().unwrap_or_default(),
+        execution_org_id = doc.org_id.as_deref().unwrap_or_default(),
+        "Dropping node status for an execution of another organization"
+    );
+}
+
+/// The update applying `msgs`, in order, to one execution document as it was
+/// read before any of them. Later messages overwrite earlier ones for the
+/// same node. Stale statuses must already be left out with [`drop_stale`];
+/// `lineage_count` is left to [`node_status_pipeline`].
+fn node_status_update(
+    doc: &ExecutionDocument,
+    msgs: &[&NodeStatusMessage],
+) -> Result<bson::Document, mongodb::error::Error> {
+    // Completion already zeroed the running counter
+    let finished = is_finished(doc);
+    let mut set_fields = bson::Document::new();
+    let mut increments = bson::Document::new();
+    // Each node's latest status as of the previous message of the batch
+    let mut latest_status: HashMap<&str, &str> = HashMap::new();
+    let mut progress_deltas: BTreeMap<&str, i64> = BTreeMap::new();
+
+    for msg in msgs {
+        let node = doc.nodes.get(&msg.node_id);
+        let lineage_hash = status_lineage_hash(msg);
+        let base_path = format!("nodes.{}", msg.node_id);
+
+        // Executions stored without counters keep deriving them on read
+        if doc.progress.is_some() {
+            let first_in_batch = !latest_status.contains_key(msg.node_id.as_str());
+            if first_in_batch && node.is_none() {
+                // Not in the definition; the write below adds it
+                *progress_deltas.entry("total_nodes").or_default() += 1;
+            }
+            let previous = latest_status
+                .insert(&msg.node_id, &msg.status)
+                .or_else(|| node.and_then(|n| n.latest.as_ref()?.status.as_deref()));
+            let before = previous
+                .and_then(ExecutionProgress::counter)
+                .filter(|counter| !(finished && *counter == "running"));
+            let after = ExecutionProgress::counter(&msg.status);
+            if before != after {
+                if let Some(counter) = before {
+                    *progress_deltas.entry(counter).or_default() -= 1;
+                }
+                if let Some(counter) = after {
+                    *progress_deltas.entry(counter).or_default() += 1;
+                }
+            }
+        }
+
+        let (node_name, node_type) = node.map_or((None, None), |n| {
+            let name = n.latest.as_ref().and_then(|l| l.name.clone()).or_else(|| {
+                n.extra
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map(String::from)
+            });
+            let node_type = n
+                .latest
+                .as_ref()
+                .and_then(|l| l.node_type.clone())
+                .or_else(|| {
+                    n.extra
+                        .get("type")
+                        .and_then(Value::as_str)
+                        .map(String::from)
+                });
+            (name, node_type)
+        });
+        let node_execution = NodeExecutionInstance {
+            input: msg.input.clone(),
+            parameters: msg.parameters.clone(),
+            output: msg.output.clone(),
+            status: Some(msg.status.clone()),
+            error: msg.error.clone(),
+            executed_at: Some(msg.executed_at.clone()),
+            duration_ms: Some(msg.duration_ms),
+            node_type,
+            name: node_name,
+            lineage_hash: if lineage_hash == "default" {
+                None
+            } else {
+                Some(lineage_hash.clone())
+            },
+            lineage_stack: msg.lineage_stack.clone(),
+            used_inputs: msg.used_inputs.clone(),
+            branch_id: msg.branch_id.clone(),
+            split_node_id: msg.split_node_id.clone(),
+            item_index: msg.item_index,
+            total_items: msg.total_items,
+            processed_count: msg.processed_count,
+            aggregator_state: msg.aggregator_state.clone(),
+            seq: msg.seq,
         };
 
-        let max_retries: u32 = 3;
-        let mut backoff = std::time::Duration::from_millis(500);
-
-        for attempt in 0..=max_retries {
-            let result = self
-                .execution_collection()
-                .update_one(filter.clone(), update.clone())
-                .upsert(false)
-                .await?;
-
-            if result.matched_count > 0 {
-                break;
-            }
-
-            if attempt == max_retries {
-                warn!(
-                    execution_id = %msg.execution_id,
-                    workflow_id = %msg.workflow_id,
-                    "Completion received for missing execution document; retries exhausted; execution document still missing"
-                );
-                return Ok(());
-            }
-
-            warn!(
-            execution_id = %msg.execution_id,
-            workflow_id = %msg.workflow_id,
-            attempt = attempt + 1,
-            backoff_ms = backoff.as_millis(),
-            "Completion received for missing execution document; will retry with exponential backoff"
+        set_fields.insert(format!("{base_path}.latest"), bson::to_bson(&node_execution)?);
+        if lineage_hash != "default" {
+            set_fields.insert(
+                format!("{base_path}.lineages.{lineage_hash}"),
+                bson::to_bson(&node_execution)?,
             );
-
-            tokio::time::sleep(backoff).await;
-            backoff = backoff.saturating_mul(2);
         }
-        info!(execution_id = %msg.execution_id, status = %msg.status, "Completed execution");
-        Ok(())
     }
-}
-
-#[async_trait]
-impl ExecutionStorePort for ExecutionStore {
-    async fn upsert_execution_definition(&self, msg: &NodeExecutionMessage) -> StoreResult<()> {
-        Self::upsert_execution_definition(self, msg)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+    set_fields.insert("updated_at", bson::DateTime::from_millis(Utc::now().timestamp_millis()));
+    for (counter, delta) in progress_deltas {
+        if delta != 0 {
+            increments.insert(format!("progress.{counter}"), delta);
+        }
     }
 
-    async fn get_execution_document(
-        &self,
-        execution_id: &str,
-    ) -> StoreResult<Option<ExecutionDocument>> {
-        Self::get_execution_document(self, execution_id)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+    let mut update = doc! { "$set": set_fields };
+    if !increments.is_empty() {
+        update.insert("$inc", increments);
     }
+    Ok(update)
+}
 
-    async fn get_executions_for_workflow(
-        &self,
-        workflow_id: &str,
-    ) -> StoreResult<Vec<ExecutionDocument>> {
-        Self::get_executions_for_workflow(self, workflow_id)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+/// `msgs` without the stale ones (see [`is_stale`]), in order. Those are
+/// neither written nor logged, nor broadcast as applied.
+fn drop_stale<'a>(
+    doc: &ExecutionDocument,
+    msgs: &[&'a NodeStatusMessage],
+) -> Vec<&'a NodeStatusMessage> {
+    // Each node's highest `seq` as of the previous message of the batch
+    let mut latest_seq: HashMap<&str, u64> = HashMap::new();
+    msgs.iter()
+        .copied()
+        .filter(|msg| !is_stale(msg, doc.nodes.get(&msg.node_id), &mut latest_seq))
+        .collect()
+}
+
+/// Whether `msg` is not newer, by `seq`, than the node's latest status:
+/// the stored one or an earlier message of the batch (`latest_seq`, which
+/// is advanced when it is newer). Unsequenced messages are never stale.
+fn is_stale<'a>(
+    msg: &'a NodeStatusMessage,
+    node: Option<&HydratedNode>,
+    latest_seq: &mut HashMap<&'a str, u64>,
+) -> bool {
+    let Some(seq) = msg.seq else {
+        return false;
+    };
+    let applied = latest_seq
+        .get(msg.node_id.as_str())
+        .copied()
+        .or_else(|| node.and_then(|n| n.latest.as_ref()?.seq));
+    if applied.is_some_and(|applied| seq <= applied) {
+        warn!(
+            execution_id = %msg.execution_id,
+            node_id = %msg.node_id,
+            seq,
+            applied,
+            "Skipping stale node status"
+        );
+        return true;
     }
+    latest_seq.insert(&msg.node_id, seq);
+    false
+}
 
-    async fn update_node_status(&self, msg: &NodeStatusMessage) -> StoreResult<()> {
-        Self::update_node_status(self, msg)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+/// The [`node_status_update`] applying `msgs` to one execution document as
+/// an aggregation pipeline, so it can depend on the document as stored when
+/// it is applied rather than as read:
+///
+/// - a node's `lineage_count` grows by one for each lineage entry it writes
+///   that is still missing, so a lineage written concurrently, or by a
+///   redelivery, is counted once;
+/// - everything a sequenced node's statuses write (its `latest`, their lineage
+///   entries, and their `lineage_count` and progress increments) only applies
+///   while the stored `seq` is still lower, so a status written between reading
+///   the document and this update is neither overwritten nor counted twice.
+///   Unsequenced messages keep last write wins.
+fn node_status_pipeline(
+    doc: &ExecutionDocument,
+    msgs: &[&NodeStatusMessage],
+) -> Result<Vec<bson::Document>, mongodb::error::Error> {
+    // Each node's messages in order; their updates don't depend on each other
+    let mut by_node: Vec<(&str, Vec<&NodeStatusMessage>)> = Vec::new();
+    for msg in msgs {
+        match by_node
+            .iter_mut()
+            .find(|(node_id, _)| *node_id == msg.node_id)
+        {
+            Some((_, node_msgs)) => node_msgs.push(msg),
+            None => by_node.push((&msg.node_id, vec![msg])),
+        }
     }
 
-    async fn complete_execution(&self, msg: &CompletionMessage) -> StoreResult<()> {
-        Self::complete_execution(self, msg)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+    let mut fields = bson::Document::new();
+    let mut increments: BTreeMap<String, Vec<Bson>> = BTreeMap::new();
+    for (node_id, node_msgs) in &by_node {
+        let guard = node_msgs.iter().filter_map(|msg| msg.seq).max().map(|seq| {
+            let seq = i64::try_from(seq).unwrap_or(i64::MAX);
+            doc! { "$lt": [{ "$ifNull": [format!("$nodes.{node_id}.latest.seq"), -1_i64] }, seq] }
+        });
+        let update = node_status_update(doc, node_msgs)?;
+        let lineages = format!("nodes.{node_id}.lineages.");
+        for (path, value) in update.get_document("$set").into_iter().flatten() {
+            if path.starts_with(&lineages) {
+                let missing = doc! { "$eq": [{ "$type": format!("${path}") }, "missing"] };
+                let new = guard.as_ref().map_or_else(
+                    || missing.clone(),
+                    |guard| doc! { "$and": [guard.clone(), missing.clone()] },
+                );
+                increments
+                    .entry(format!("nodes.{node_id}.lineage_count"))
+                    .or_default()
+                    .push(Bson::Document(doc! { "$cond": [new, 1_i64, 0_i64] }));
+            }
+            // Values are data, never expressions
+            let literal = Bson::Document(doc! { "$literal": value.clone() });
+            let value = match &guard {
+                Some(guard) if path != "updated_at" => Bson::Document(doc! {
+                    "$cond": [guard.clone(), literal, format!("${path}")]
+                }),
+                _ => literal,
+            };
+            fields.insert(path, value);
+        }
+        for (path, delta) in update.get_document("$inc").into_iter().flatten() {
+            let delta = guard.as_ref().map_or_else(
+                || delta.clone(),
+                |guard| Bson::Document(doc! { "$cond": [guard.clone(), delta.clone(), 0_i64] }),
+            );
+            increments.entry(path.clone()).or_default().push(delta);
+        }
     }
+    for (path, deltas) in increments {
+        let mut sum = vec![Bson::Document(doc! { "$ifNull": [format!("${path}"), 0_i64] })];
+        sum.extend(deltas);
+        fields.insert(path, doc! { "$add": sum });
+    }
+    Ok(vec![doc! { "$set": fields }])
 }
 
 fn normalize_workflow_definition(raw: &Value) -> Value {
@@ -411,6 +2125,28 @@ fn normalize_workflow_definition(raw: &Value) -> Value {
     Value::Object(workflow)
 }
 
+/// Node definitions of a normalized workflow keyed by id, the shape `nodes`
+/// is stored in. `normalize_workflow_definition` turns map-shaped input into
+/// an array, so both shapes end up here; nodes without an id are dropped.
+fn definition_nodes(normalized_workflow: &Value) -> bson::Document {
+    let mut nodes_doc = bson::Document::new();
+    let nodes = normalized_workflow
+        .get("nodes")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+    for node in nodes {
+        if let Some(node_id) = node
+            .get("id")
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+            && let Ok(node_bson) = bson::to_document(node)
+        {
+            nodes_doc.insert(node_id.to_string(), Bson::Document(node_bson));
+        }
+    }
+    nodes_doc
+}
+
 fn normalize_edges(raw_edges: Option<&Value>) -> Vec<Value> {
     match raw_edges {
         Some(Value::Array(edges)) => edges.iter().map(normalize_edge).collect(),
@@ -432,6 +2168,7 @@ fn normalize_edge_with_id(edge: &Value, fallback_id: Option<&str>) -> Value {
 
     let id = obj
         .and_then(|o| o.get("id").and_then(Value::as_str))
+        .filter(|id| !id.is_empty())
         .map(str::to_string)
         .or_else(|| fallback_id.map(String::from))
         .unwrap_or_default();
@@ -446,9 +2183,25 @@ fn normalize_edge_with_id(edge: &Value, fallback_id: Option<&str>) -> Value {
         .unwrap_or_default()
         .to_string();
 
+    // Conditional edges leave a node through a named handle; plain edges get
+    // no condition and empty handles so consumers never null-check
+    let condition = obj
+        .and_then(|o| o.get("condition"))
+        .cloned()
+        .unwrap_or(Value::Null);
+
+    let handle = |key: &str| {
+        obj.and_then(|o| o.get(key).and_then(Value::as_str))
+            .unwrap_or_default()
+            .to_string()
+    };
+
     normalized.insert("id".to_string(), Value::String(id));
     normalized.insert("src".to_string(), Value::String(src));
     normalized.insert("dst".to_string(), Value::String(dst));
+    normalized.insert("condition".to_string(), condition);
+    normalized.insert("sourceHandle".to_string(), Value::String(handle("sourceHandle")));
+    normalized.insert("targetHandle".to_string(), Value::String(handle("targetHandle")));
 
     if let Some(o) = obj {
         for (k, v) in o {
@@ -467,10 +2220,17 @@ fn normalize_nodes(raw_nodes: Value) -> Vec<Value> {
         Value::Object(map) => map
             .into_iter()
             .map(|(id, node_val)| {
-                let mut node_map = Map::new();
-                node_map.insert("id".to_string(), Value::String(id));
-                if let Value::Object(obj) = node_val {
-                    node_map.extend(obj);
+                let mut node_map = match node_val {
+                    Value::Object(obj) => obj,
+                    _ => Map::new(),
+                };
+                // The map key stands in for a missing or empty id
+                if node_map
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .is_none_or(str::is_empty)
+                {
+                    node_map.insert("id".to_string(), Value::String(id));
                 }
                 normalize_node(Value::Object(node_map))
             })
@@ -489,6 +2249,7 @@ fn normalize_node(node_val: Value) -> Value {
     normalized.insert("parameters".to_string(), Value::Object(Map::new()));
     normalized.insert("output".to_string(), Value::Object(Map::new()));
     normalized.insert("credentials".to_string(), Value::Null);
+    normalized.insert("credentials_ref".to_string(), Value::Null);
     normalized.insert("error".to_string(), Value::Null);
 
     if let Value::Object(obj) = node_val {
@@ -538,9 +2299,20 @@ fn normalize_node(node_val: Value) -> Value {
         .unwrap_or_default();
     normalized.insert("output".to_string(), Value::Object(output));
 
-    if normalized.contains_key("credentials") {
-        normalized.insert("credentials".to_string(), Value::Null);
-    }
+    // Secrets never reach MongoDB; the reference stays so the execution
+    // still shows which credential a node used
+    let credentials = normalized
+        .insert("credentials".to_string(), Value::Null)
+        .unwrap_or_default();
+    let credentials_ref = credentials_ref(&credentials)
+        .or_else(|| {
+            normalized
+                .get("credentials_ref")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .map_or(Value::Null, Value::String);
+    normalized.insert("credentials_ref".to_string(), credentials_ref);
 
     if !normalized.contains_key("error") {
         normalized.insert("error".to_string(), Value::Null);
@@ -549,12 +2321,86 @@ fn normalize_node(node_val: Value) -> Value {
     Value::Object(normalized)
 }
 
+/// The id (or, lacking one, the name) a node's credentials are known by:
+/// the credentials themselves when given as a bare reference.
+fn credentials_ref(credentials: &Value) -> Option<String> {
+    let reference = match credentials {
+        Value::String(reference) => Some(reference.as_str()),
+        Value::Object(obj) => ["id", "name"].into_iter().find_map(|key| {
+            obj.get(key)
+                .and_then(Value::as_str)
+                .filter(|r| !r.is_empty())
+        }),
+        _ => None,
+    };
+    reference
+        .filter(|reference| !reference.is_empty())
+        .map(str::to_string)
+}
+
 #[cfg(test)]
-#[allow(clippy::indexing_slicing)]
+#[allow(clippy::indexing_slicing, clippy::expect_used)]
 mod tests {
+    use mongodb::{
+        bson::{Bson, Document, doc},
+        options::{ReadPreference, SelectionCriteria},
+    };
     use serde_json::json;
 
-    use super::{normalize_edges, normalize_node, normalize_nodes, normalize_workflow_definition};
+    use super::{
+        completion_filter,
+        completion_update,
+        definition_nodes,
+        drop_stale,
+        execution_filter,
+        executions_filter,
+        fields_projection,
+        group_by_execution,
+        node_status_pipeline,
+        node_status_update,
+        normalize_edges,
+        normalize_node,
+        normalize_nodes,
+        normalize_workflow_definition,
+        reopens_finished_execution,
+        repair_nodes_pipeline,
+        repair_update,
+        required_indexes,
+        selection_criteria,
+        status_lineage_hash,
+    };
+    use crate::{
+        api::state::StoreError,
+        config::MongoReadPreference,
+        domain::models::{
+            CompletionMessage,
+            ExecutionDocument,
+            ExecutionFields,
+            ExecutionProgress,
+            HydratedNode,
+            NodeExecutionInstance,
+            NodeStatusMessage,
+        },
+    };
+
+    fn status(
+        execution_id: &str,
+        node_id: &str,
+        status: &str,
+        lineage: Option<&str>,
+    ) -> NodeStatusMessage {
+        serde_json::from_value(json!({
+            "workflow_id": "wf-1",
+            "execution_id": execution_id,
+            "node_id": node_id,
+            "node_name": node_id,
+            "status": status,
+            "executed_at": "2024-01-01T00:00:00Z",
+            "duration_ms": 1,
+            "lineage_hash": lineage,
+        }))
+        .expect("status message should deserialize")
+    }
 
     #[test]
     fn normalize_edges_supports_object_format() {
@@ -573,6 +2419,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn normalize_edges_supports_array_format_and_applies_defaults() {
+        let raw = json!([
+            {"id": "e1", "src": "a", "dst": "b"},
+            {"id": "e2", "src": "b", "dst": "c", "condition": "$x > 1", "sourceHandle": "true"},
+            {"src": "c", "dst": "d", "sourceHandle": 3, "label": "kept"},
+        ]);
+
+        let normalized = normalize_edges(Some(&raw));
+        assert_eq!(
+            normalized[0],
+            json!({
+                "id": "e1",
+                "src": "a",
+                "dst": "b",
+                "condition": null,
+                "sourceHandle": "",
+                "targetHandle": "",
+            })
+        );
+        assert_eq!(normalized[1]["condition"], "$x > 1");
+        assert_eq!(normalized[1]["sourceHandle"], "true");
+        assert_eq!(normalized[1]["targetHandle"], "");
+        // Without a map key there is no id to fall back to
+        assert_eq!(normalized[2]["id"], "");
+        assert_eq!(normalized[2]["sourceHandle"], "");
+        assert_eq!(normalized[2]["label"], "kept");
+    }
+
+    #[test]
+    fn normalize_edges_falls_back_to_the_map_key_for_missing_ids() {
+        let raw = json!({
+            "edge-1": {"src": "a", "dst": "b", "condition": {"op": "eq"}},
+            "edge-2": {"id": "", "src": "b", "dst": "c"},
+        });
+
+        let normalized = normalize_edges(Some(&raw));
+        let edge = |src: &str| {
+            normalized
+                .iter()
+                .find(|edge| edge["src"] == src)
+                .expect("edge should be kept")
+        };
+        assert_eq!(edge("a")["id"], "edge-1");
+        assert_eq!(edge("a")["condition"], json!({"op": "eq"}));
+        assert_eq!(edge("a")["sourceHandle"], "");
+        assert_eq!(edge("b")["id"], "edge-2");
+        assert_eq!(edge("b")["condition"], json!(null));
+    }
+
     #[test]
     fn normalize_nodes_supports_object_format() {
         let raw = json!({
@@ -587,6 +2483,32 @@ mod tests {
         assert!(normalized.iter().all(|node| node["output"].is_object()));
     }
 
+    #[test]
+    fn map_shaped_nodes_keep_their_definitions() {
+        let normalized = normalize_workflow_definition(&json!({
+            "nodes": {
+                "start": { "name": "Start", "trigger": true },
+                "send": { "id": "", "type": "http", "parameters": { "url": "https://x" } },
+                "renamed": { "id": "custom", "type": "log" },
+            },
+        }));
+
+        let nodes = definition_nodes(&normalized);
+        let mut ids: Vec<&str> = nodes.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["custom", "send", "start"]);
+        let start = nodes.get_document("start").expect("start is stored");
+        assert_eq!(start.get_str("name"), Ok("Start"));
+        assert_eq!(start.get_bool("trigger"), Ok(true));
+        let send = nodes.get_document("send").expect("send is stored");
+        assert_eq!(send.get_str("id"), Ok("send"));
+        assert_eq!(
+            send.get_document("parameters")
+                .and_then(|parameters| parameters.get_str("url")),
+            Ok("https://x")
+        );
+    }
+
     #[test]
     fn normalize_node_applies_defaults() {
         let normalized = normalize_node(json!({"id": "n1"}));
@@ -597,6 +2519,193 @@ mod tests {
         assert!(normalized["output"].is_object());
     }
 
+    #[test]
+    fn normalize_node_keeps_the_credentials_reference_but_not_the_secret() {
+        let normalized = normalize_node(json!({
+            "id": "n1",
+            "credentials": {"id": "cred-1", "name": "Prod API", "secret": "s3cr3t"},
+        }));
+        assert_eq!(normalized["credentials_ref"], "cred-1");
+        assert_eq!(normalized["credentials"], json!(null));
+        assert!(!normalized.to_string().contains("s3cr3t"));
+
+        let by_name = normalize_node(json!({"credentials": {"name": "Prod API", "token": "t"}}));
+        assert_eq!(by_name["credentials_ref"], "Prod API");
+        let bare = normalize_node(json!({"credentials": "cred-2"}));
+        assert_eq!(bare["credentials_ref"], "cred-2");
+        assert_eq!(bare["credentials"], json!(null));
+        let without = normalize_node(json!({"id": "n2"}));
+        assert_eq!(without["credentials_ref"], json!(null));
+    }
+
+    #[test]
+    fn repair_rewrites_legacy_documents_and_keeps_node_runs() {
+        let now = mongodb::bson::DateTime::from_millis(1_700_000_000_000);
+        let legacy = doc! {
+            "execution_id": "exec-1",
+            "workflow_definition": {
+                "nodes": [
+                    { "id": "start", "type": "trigger", "trigger": true },
+                    { "id": "send", "type": "http", "credentials": { "id": "cred-1", "key": "s3cr3t" } },
+                ],
+                "edges": { "e1": { "src": "start", "dst": "send" } },
+            },
+            "nodes": [
+                { "id": "start", "latest": { "status": "success" }, "lineage_count": 1 },
+                { "id": "orphan", "latest": { "status": "running" } },
+            ],
+        };
+
+        let update = repair_update(&legacy, now).expect("update should build");
+        assert_eq!(update.get_document("$unset"), Ok(&doc! { "workflow_definition": "" }));
+        let set = update.get_document("$set").expect("update should $set");
+        let nodes = set.get_document("nodes").expect("nodes become an object");
+        let mut ids: Vec<&str> = nodes.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["orphan", "send", "start"]);
+        let start = nodes.get_document("start").expect("start is kept");
+        assert_eq!(start.get_bool("trigger"), Ok(true));
+        assert_eq!(start.get_document("latest"), Ok(&doc! { "status": "success" }));
+        let send = nodes.get_document("send").expect("send is defined");
+        assert_eq!(send.get_str("credentials_ref"), Ok("cred-1"));
+        assert!(!format!("{set}").contains("s3cr3t"));
+        assert_eq!(set.get_array("edges").map(Vec::len), Ok(1));
+        let graph = set.get_document("graph").expect("graph is rebuilt");
+        assert_eq!(graph.get_array("nodes").map(Vec::len), Ok(2));
+        let progress = set.get_document("progress").expect("progress is rebuilt");
+        assert_eq!(progress.get_i64("total_nodes"), Ok(3));
+        assert_eq!(progress.get_i64("succeeded"), Ok(1));
+        assert_eq!(progress.get_i64("running"), Ok(1));
+
+        // Without a definition, the one kept on each node is renormalized
+        let current = doc! {
+            "execution_id": "exec-2",
+            "nodes": { "a": { "id": "a", "name": "A", "lineage_count": 2_i64 } },
+            "edges": [{ "id": "e1", "src": "a", "dst": "b" }],
+        };
+        let update = repair_update(&current, now).expect("update should build");
+        let set = update.get_document("$set").expect("update should $set");
+        let a = set
+            .get_document("nodes")
+            .and_then(|nodes| nodes.get_document("a"))
+            .expect("a is kept");
+        assert_eq!(a.get_str("name"), Ok("A"));
+        assert_eq!(a.get_i64("lineage_count"), Ok(2));
+        assert!(a.get_document("parameters").is_ok());
+        let edges = set.get_array("edges").expect("edges are kept");
+        assert_eq!(edges[0].as_document().and_then(|e| e.get("condition")), Some(&Bson::Null));
+    }
+
+    /// The `$set` stage of the pipeline applying `msgs` to `doc`.
+    fn pipeline_set(doc: &ExecutionDocument, msgs: &[&NodeStatusMessage]) -> Document {
+        let stages = node_status_pipeline(doc, msgs).expect("update");
+        stages[0].get_document("$set").expect("$set").clone()
+    }
+
+    #[test]
+    fn lineage_count_only_increments_for_lineages_missing_when_written() {
+        let mut doc = ExecutionDocument::default();
+        let msg = status("exec-a", "node-1", "success", Some("lineage-a"));
+        let counted = doc! {
+            "$add": [
+                { "$ifNull": ["$nodes.node-1.lineage_count", 0_i64] },
+                {
+                    "$cond": [
+                        { "$eq": [{ "$type": "$nodes.node-1.lineages.lineage-a" }, "missing"] },
+                        1_i64,
+                        0_i64,
+                    ]
+                },
+            ]
+        };
+        assert_eq!(
+            pipeline_set(&doc, &[&msg]).get_document("nodes.node-1.lineage_count"),
+            Ok(&counted)
+        );
+
+        // Whether the read document already has the lineage doesn't matter:
+        // a redelivery or concurrent write of it is only counted if the
+        // stored document still lacks it
+        let node = doc.nodes.entry("node-1".to_string()).or_default();
+        node.lineage_count = 1;
+        node.lineages
+            .insert("lineage-a".to_string(), NodeExecutionInstance::default());
+        assert_eq!(
+            pipeline_set(&doc, &[&msg]).get_document("nodes.node-1.lineage_count"),
+            Ok(&counted)
+        );
+
+        // Statuses without a lineage write no entry to count
+        let unlineaged = status("exec-a", "node-1", "success", None);
+        assert!(
+            pipeline_set(&doc, &[&unlineaged])
+                .get("nodes.node-1.lineage_count")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn legacy_nodes_get_their_lineage_count_backfilled_before_it_is_incremented() {
+        // Stored before the counter existed: two lineages, no lineage_count
+        let legacy = json!({
+            "lineages": { "lineage-a": { "status": "success" }, "lineage-b": { "status": "success" } },
+        });
+        let mut doc = ExecutionDocument::default();
+        doc.nodes
+            .insert("node-1".to_string(), HydratedNode::from_stored(legacy));
+        assert_eq!(doc.nodes["node-1"].lineage_count, 2);
+
+        let msg = status("exec-a", "node-1", "success", Some("lineage-c"));
+        let count = pipeline_set(&doc, &[&msg])
+            .get_document("nodes.node-1.lineage_count")
+            .and_then(|count| count.get_array("$add"))
+            .cloned()
+            .expect("count is added to");
+        assert_eq!(
+            count[0],
+            Bson::Document(doc! { "$ifNull": ["$nodes.node-1.lineage_count", 0_i64] })
+        );
+        assert_eq!(count.len(), 2);
+
+        // The repair step run before every status write seeds the missing
+        // counter from the stored lineages, so the new lineage yields 3
+        let repair = repair_nodes_pipeline();
+        let branches = repair[0]
+            .get_document("$set")
+            .and_then(|set| set.get_document("nodes"))
+            .and_then(|nodes| nodes.get_document("$switch"))
+            .and_then(|switch| switch.get_array("branches"))
+            .expect("nodes are repaired by case");
+        let node = branches[1]
+            .as_document()
+            .and_then(|branch| branch.get_document("then").ok())
+            .and_then(|then| then.get_document("$arrayToObject").ok())
+            .and_then(|to_object| to_object.get_document("$map").ok())
+            .and_then(|map| map.get_document("in").ok())
+            .and_then(|entry| entry.get_document("v").ok())
+            .and_then(|v| v.get_array("$cond").ok())
+            .expect("each node is backfilled conditionally");
+        assert_eq!(
+            node[0],
+            Bson::Document(doc! {
+                "$and": [
+                    { "$eq": [{ "$type": "$$node.v.lineages" }, "object"] },
+                    { "$eq": [{ "$type": "$$node.v.lineage_count" }, "missing"] },
+                ]
+            })
+        );
+        assert_eq!(
+            node[1],
+            Bson::Document(doc! {
+                "$mergeObjects": [
+                    "$$node.v",
+                    { "lineage_count": { "$size": { "$objectToArray": "$$node.v.lineages" } } },
+                ]
+            })
+        );
+        assert_eq!(node[2], Bson::String("$$node.v".to_string()));
+    }
+
     #[test]
     fn normalize_workflow_definition_handles_missing_fields() {
         let normalized = normalize_workflow_definition(&json!({"name": "wf"}));
@@ -604,4 +2713,570 @@ mod tests {
         assert_eq!(normalized["nodes"], json!([]));
         assert_eq!(normalized["edges"], json!([]));
     }
+
+    #[test]
+    fn batching_writes_once_per_execution_instead_of_once_per_message() {
+        let msgs: Vec<_> = (0..10)
+            .map(|i| {
+                let execution_id = if i % 2 == 0 { "exec-a" } else { "exec-b" };
+                status(execution_id, &format!("node-{}", i % 3), "running", None)
+            })
+            .collect();
+
+        let groups = group_by_execution(&msgs);
+        let per_message_writes = msgs.len();
+        let batched_writes = groups.len();
+        assert_eq!((per_message_writes, batched_writes), (10, 2));
+        assert_eq!(groups[0].0, "exec-a");
+        assert_eq!(groups.iter().map(|(_, group)| group.len()).sum::<usize>(), 10);
+    }
+
+    #[test]
+    fn batched_update_keeps_message_order_and_counts_each_new_lineage_once() {
+        let msgs = [
+            status("exec-a", "node-1", "running", Some("lineage-a")),
+            status("exec-a", "node-1", "success", Some("lineage-a")),
+            status("exec-a", "node-2", "failed", None),
+        ];
+        let group: Vec<_> = msgs.iter().collect();
+
+        let update = node_status_update(&ExecutionDocument::default(), &group).expect("update");
+        let set = update.get_document("$set").expect("$set");
+        assert_eq!(
+            set.get_document("nodes.node-1.latest")
+                .expect("node-1 latest")
+                .get_str("status"),
+            Ok("success")
+        );
+        assert_eq!(
+            set.get_document("nodes.node-2.latest")
+                .expect("node-2 latest")
+                .get_str("status"),
+            Ok("failed")
+        );
+        assert!(update.get_document("$inc").is_err());
+
+        // The lineage entry is written once, so it is counted at most once
+        let set = pipeline_set(&ExecutionDocument::default(), &group);
+        let count = set
+            .get_document("nodes.node-1.lineage_count")
+            .and_then(|count| count.get_array("$add"))
+            .expect("count is added to");
+        assert_eq!(count.len(), 2);
+        assert!(set.get("nodes.node-2.lineage_count").is_none());
+    }
+
+    #[test]
+    fn status_writes_move_nodes_between_progress_counters() {
+        let mut doc = ExecutionDocument {
+            progress: Some(ExecutionProgress {
+                total_nodes: 2,
+                running: 1,
+                ..ExecutionProgress::default()
+            }),
+            ..ExecutionDocument::default()
+        };
+        doc.nodes.insert(
+            "node-1".to_string(),
+            HydratedNode {
+                latest: Some(NodeExecutionInstance {
+                    status: Some("running".to_string()),
+                    ..NodeExecutionInstance::default()
+                }),
+                ..HydratedNode::default()
+            },
+        );
+        doc.nodes
+            .insert("node-2".to_string(), HydratedNode::default());
+        let msgs = [
+            status("exec-a", "node-1", "success", None),
+            status("exec-a", "node-2", "running", None),
+            status("exec-a", "node-2", "failed", None),
+            // Not in the definition, so it also joins the total
+            status("exec-a", "node-3", "pending", None),
+        ];
+        let group: Vec<_> = msgs.iter().collect();
+
+        let update = node_status_update(&doc, &group).expect("update");
+        let inc = update.get_document("$inc").expect("$inc");
+        assert_eq!(inc.get_i64("progress.running"), Ok(-1));
+        assert_eq!(inc.get_i64("progress.succeeded"), Ok(1));
+        assert_eq!(inc.get_i64("progress.failed"), Ok(1));
+        assert_eq!(inc.get_i64("progress.total_nodes"), Ok(1));
+
+        // Executions stored before the counters existed get no increments
+        doc.progress = None;
+        let update = node_status_update(&doc, &group).expect("update");
+        assert!(update.get_document("$inc").is_err());
+    }
+
+    #[test]
+    fn late_statuses_do_not_reopen_a_completed_execution() {
+        let mut doc = ExecutionDocument {
+            status: Some("completed".to_string()),
+            progress: Some(ExecutionProgress { total_nodes: 1, ..ExecutionProgress::default() }),
+            ..ExecutionDocument::default()
+        };
+        doc.nodes.insert(
+            "node-1".to_string(),
+            HydratedNode {
+                latest: Some(NodeExecutionInstance {
+                    status: Some("running".to_string()),
+                    ..NodeExecutionInstance::default()
+                }),
+                ..HydratedNode::default()
+            },
+        );
+        let running = status("exec-a", "node-1", "running", None);
+        let success = status("exec-a", "node-1", "success", None);
+
+        assert!(reopens_finished_execution(&doc, &running));
+        assert!(!reopens_finished_execution(&doc, &success));
+
+        // The terminal status is recorded without taking back the running
+        // count the completion already cleared
+        let update = node_status_update(&doc, &[&success]).expect("update");
+        let inc = update.get_document("$inc").expect("$inc");
+        assert_eq!(inc.get_i64("progress.succeeded"), Ok(1));
+        assert!(inc.get_i64("progress.running").is_err());
+
+        doc.status = None;
+        assert!(!reopens_finished_execution(&doc, &running));
+    }
+
+    #[test]
+    fn stored_runs_round_trip_branch_and_loop_metadata() {
+        let mut msg: NodeStatusMessage = serde_json::from_value(json!({
+            "workflow_id": "wf-1",
+            "execution_id": "exec-a",
+            "node_id": "loop-body",
+            "node_name": "Loop body",
+            "status": "success",
+            "executed_at": "2024-01-01T00:00:00Z",
+            "duration_ms": 3,
+            "lineage_stack": [{ "split_node_id": "split-1", "branch_id": "branch-2", "item_index": 2, "total_items": 5 }],
+            "branch_id": "branch-2",
+            "split_node_id": "split-1",
+            "item_index": 2,
+            "total_items": 5,
+            "processed_count": 3,
+            "aggregator_state": "collecting",
+        }))
+        .expect("status message should deserialize");
+        msg.seq = Some(4);
+        let mut doc = ExecutionDocument::default();
+        doc.nodes.insert(
+            "loop-body".to_string(),
+            serde_json::from_value(json!({ "name": "Loop body", "type": "http" }))
+                .expect("definition node"),
+        );
+
+        let update = node_status_update(&doc, &[&msg]).expect("update");
+        let set = update.get_document("$set").expect("$set");
+        let lineage_path = format!("nodes.loop-body.lineages.{}", status_lineage_hash(&msg));
+        for path in ["nodes.loop-body.latest", lineage_path.as_str()] {
+            let stored = set.get_document(path).expect("stored run").clone();
+            let run: NodeExecutionInstance =
+                mongodb::bson::from_document(stored).expect("run should read back");
+            assert_eq!(run.branch_id.as_deref(), Some("branch-2"), "{path}");
+            assert_eq!(run.split_node_id.as_deref(), Some("split-1"), "{path}");
+            assert_eq!(run.item_index, Some(2), "{path}");
+            assert_eq!(run.total_items, Some(5), "{path}");
+            assert_eq!(run.processed_count, Some(3), "{path}");
+            assert_eq!(run.aggregator_state.as_deref(), Some("collecting"), "{path}");
+            assert_eq!(run.lineage_stack, msg.lineage_stack, "{path}");
+            assert_eq!(run.name.as_deref(), Some("Loop body"), "{path}");
+            assert_eq!(run.node_type.as_deref(), Some("http"), "{path}");
+            assert_eq!(run.seq, Some(4), "{path}");
+        }
+    }
+
+    #[test]
+    fn stale_sequenced_statuses_do_not_overwrite_newer_ones() {
+        let mut doc = ExecutionDocument::default();
+        doc.nodes.insert(
+            "node-1".to_string(),
+            HydratedNode {
+                latest: Some(NodeExecutionInstance {
+                    status: Some("success".to_string()),
+                    seq: Some(5),
+                    ..NodeExecutionInstance::default()
+                }),
+                ..HydratedNode::default()
+            },
+        );
+        let sequenced = |status_name: &str, seq: Option<u64>| NodeStatusMessage {
+            seq,
+            ..status("exec-a", "node-1", status_name, None)
+        };
+        let latest_status = |msgs: &[NodeStatusMessage]| {
+            let group: Vec<_> = msgs.iter().collect();
+            let group = drop_stale(&doc, &group);
+            let update = node_status_update(&doc, &group).expect("update");
+            update
+                .get_document("$set")
+                .expect("$set")
+                .get_document("nodes.node-1.latest")
+                .ok()
+                .and_then(|latest| latest.get_str("status").ok().map(str::to_owned))
+        };
+
+        // A retried "running" older than the stored "success" is skipped,
+        // so it is not applied at all
+        assert_eq!(latest_status(&[sequenced("running", Some(4))]), None);
+        let stale = sequenced("running", Some(5));
+        assert!(drop_stale(&doc, &[&stale]).is_empty());
+        assert_eq!(latest_status(&[sequenced("failed", Some(6))]).as_deref(), Some("failed"));
+        // Within a batch too, whatever order the messages arrive in
+        let batch = [sequenced("failed", Some(7)), sequenced("running", Some(6))];
+        assert_eq!(latest_status(&batch).as_deref(), Some("failed"));
+        // Unsequenced statuses keep last write wins
+        assert_eq!(latest_status(&[sequenced("running", None)]).as_deref(), Some("running"));
+    }
+
+    #[test]
+    fn sequenced_updates_guard_latest_in_a_pipeline() {
+        let unsequenced = status("exec-a", "node-1", "success", Some("lin-1"));
+        let doc = ExecutionDocument::default();
+        // Unsequenced statuses keep last write wins
+        assert!(
+            pipeline_set(&doc, &[&unsequenced])
+                .get_document("nodes.node-1.latest")
+                .expect("latest")
+                .contains_key("$literal")
+        );
+
+        let sequenced = NodeStatusMessage { seq: Some(3), ..unsequenced };
+        let set = pipeline_set(&doc, &[&sequenced]);
+        let newer = Bson::Document(doc! {
+            "$lt": [{ "$ifNull": ["$nodes.node-1.latest.seq", -1_i64] }, 3_i64]
+        });
+        let guard = set
+            .get_document("nodes.node-1.latest")
+            .expect("latest")
+            .get_array("$cond")
+            .expect("latest is conditional");
+        assert_eq!(guard[0], newer);
+        assert_eq!(guard[2], Bson::String("$nodes.node-1.latest".to_string()));
+        // The lineage entry and its count are kept back with `latest`
+        let lineage = set
+            .get_document("nodes.node-1.lineages.lin-1")
+            .expect("lineage")
+            .get_array("$cond")
+            .expect("lineage is conditional");
+        assert_eq!(lineage[0], newer);
+        assert_eq!(lineage[2], Bson::String("$nodes.node-1.lineages.lin-1".to_string()));
+        let count = set
+            .get_document("nodes.node-1.lineage_count")
+            .expect("count")
+            .get_array("$add")
+            .expect("count is added to");
+        let missing = doc! { "$eq": [{ "$type": "$nodes.node-1.lineages.lin-1" }, "missing"] };
+        assert_eq!(
+            count[1],
+            Bson::Document(doc! { "$cond": [{ "$and": [newer, missing] }, 1_i64, 0_i64] })
+        );
+    }
+
+    #[test]
+    fn sequenced_updates_guard_progress_deltas_per_node() {
+        let mut doc = ExecutionDocument {
+            progress: Some(ExecutionProgress {
+                total_nodes: 2,
+                running: 2,
+                ..ExecutionProgress::default()
+            }),
+            ..ExecutionDocument::default()
+        };
+        for node_id in ["node-1", "node-2"] {
+            doc.nodes.insert(
+                node_id.to_string(),
+                HydratedNode {
+                    latest: Some(NodeExecutionInstance {
+                        status: Some("running".to_string()),
+                        ..NodeExecutionInstance::default()
+                    }),
+                    ..HydratedNode::default()
+                },
+            );
+        }
+        let sequenced =
+            NodeStatusMessage { seq: Some(2), ..status("exec-a", "node-1", "success", None) };
+        let unsequenced = status("exec-a", "node-2", "failed", None);
+
+        let set = pipeline_set(&doc, &[&sequenced, &unsequenced]);
+        let newer = Bson::Document(doc! {
+            "$lt": [{ "$ifNull": ["$nodes.node-1.latest.seq", -1_i64] }, 2_i64]
+        });
+        let sum = |counter: &str| {
+            set.get_document(format!("progress.{counter}"))
+                .expect("counter is updated")
+                .get_array("$add")
+                .expect("counter is added to")
+                .clone()
+        };
+        // node-1's move from running to succeeded only counts while its
+        // status is the newer one; node-2's is unconditional
+        assert_eq!(
+            sum("running"),
+            vec![
+                Bson::Document(doc! { "$ifNull": ["$progress.running", 0_i64] }),
+                Bson::Document(doc! { "$cond": [newer.clone(), -1_i64, 0_i64] }),
+                Bson::Int64(-1),
+            ]
+        );
+        assert_eq!(
+            sum("succeeded"),
+            vec![
+                Bson::Document(doc! { "$ifNull": ["$progress.succeeded", 0_i64] }),
+                Bson::Document(doc! { "$cond": [newer, 1_i64, 0_i64] }),
+            ]
+        );
+        assert_eq!(sum("failed")[1], Bson::Int64(1));
+    }
+
+    #[test]
+    fn required_indexes_have_unique_names_and_cover_lookups() {
+        let indexes = required_indexes(false);
+        let names: std::collections::HashSet<_> = indexes
+            .iter()
+            .filter_map(|(_, index)| index.options.as_ref()?.name.clone())
+            .collect();
+        assert_eq!(names.len(), indexes.len());
+
+        let (collection, execution_id) = &indexes[0];
+        assert_eq!(*collection, "executions");
+        assert_eq!(execution_id.keys.keys().collect::<Vec<_>>(), ["execution_id"]);
+        assert_eq!(
+            execution_id
+                .options
+                .as_ref()
+                .and_then(|options| options.unique),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn ttl_indexes_are_only_created_when_executions_expire() {
+        let ttl_collections = |expiring| {
+            required_indexes(expiring)
+                .into_iter()
+                .filter(|(_, index)| {
+                    index
+                        .options
+                        .as_ref()
+                        .and_then(|options| options.expire_after)
+                        == Some(std::time::Duration::ZERO)
+                })
+                .map(|(collection, _)| collection)
+                .collect::<Vec<_>>()
+        };
+        assert!(ttl_collections(false).is_empty());
+        assert_eq!(ttl_collections(true), ["executions", "execution_status"]);
+    }
+
+    #[test]
+    fn read_preferences_map_to_driver_modes() {
+        assert!(matches!(
+            selection_criteria(MongoReadPreference::Primary),
+            SelectionCriteria::ReadPreference(ReadPreference::Primary)
+        ));
+        assert!(matches!(
+            selection_criteria(MongoReadPreference::SecondaryPreferred),
+            SelectionCriteria::ReadPreference(ReadPreference::SecondaryPreferred { options: None })
+        ));
+        assert!(matches!(
+            selection_criteria(MongoReadPreference::Nearest),
+            SelectionCriteria::ReadPreference(ReadPreference::Nearest { options: None })
+        ));
+    }
+
+    #[test]
+    fn store_errors_are_classified_by_kind_and_code() {
+        use mongodb::error::{CommandError, Error, ErrorKind, WriteError, WriteFailure};
+
+        let command = |code: i32| {
+            let err: CommandError =
+                mongodb::bson::from_document(doc! { "code": code, "errmsg": "rejected" })
+                    .expect("command error should deserialize");
+            Error::from(ErrorKind::Command(err))
+        };
+        let duplicate: WriteError =
+            mongodb::bson::from_document(doc! { "code": 11000, "errmsg": "E11000 duplicate key" })
+                .expect("write error should deserialize");
+
+        assert!(matches!(
+            StoreError::from(Error::from(std::io::ErrorKind::ConnectionRefused)),
+            StoreError::Transient(_)
+        ));
+        // PrimarySteppedDown: a failover in progress
+        assert!(matches!(StoreError::from(command(189)), StoreError::Transient(_)));
+        assert!(matches!(
+            StoreError::from(Error::from(ErrorKind::Write(WriteFailure::WriteError(duplicate)))),
+            StoreError::Conflict(_)
+        ));
+        // Unauthorized
+        assert!(matches!(StoreError::from(command(13)), StoreError::Fatal(_)));
+        assert!(matches!(StoreError::from(Error::custom("bug")), StoreError::Fatal(_)));
+    }
+
+    #[test]
+    fn completion_update_records_the_outcome_literally() {
+        let completion = CompletionMessage {
+            workflow_id:       "wf-1".to_string(),
+            execution_id:      "exec-1".to_string(),
+            status:            "failed".to_string(),
+            final_context:     json!({ "$price": "$5" }),
+            completed_at:      "2026-01-01T00:00:00Z".to_string(),
+            total_duration_ms: 10,
+            failure_reason:    Some("$node failed".to_string()),
+            org_id:            None,
+        };
+
+        let update = completion_update(&completion).expect("update should build");
+        let set = update[0]
+            .get_document("$set")
+            .expect("update should $set the outcome");
+        assert_eq!(set.get_i64("total_duration_ms"), Ok(10));
+        assert_eq!(set.get_str("completed_at"), Ok("2026-01-01T00:00:00Z"));
+        assert_eq!(set.get_document("failure_reason"), Ok(&doc! { "$literal": "$node failed" }));
+        assert_eq!(set.get_document("final_context"), Ok(&doc! { "$literal": { "$price": "$5" } }));
+    }
+
+    #[test]
+    fn execution_reads_match_only_the_callers_organization() {
+        assert_eq!(
+            execution_filter("exec-1", Some("org-a")),
+            doc! { "execution_id": "exec-1", "org_id": "org-a" }
+        );
+        // No organization matches only executions stored without one
+        assert_eq!(
+            execution_filter("exec-1", None),
+            doc! { "execution_id": "exec-1", "org_id": Bson::Null }
+        );
+        assert_eq!(
+            executions_filter(&["exec-1".to_owned(), "exec-2".to_owned()], Some("org-a")),
+            doc! { "execution_id": { "$in": ["exec-1", "exec-2"] }, "org_id": "org-a" }
+        );
+    }
+
+    /// Whether `doc` matches `filter`, for the operators completion filters
+    /// use.
+    fn filter_matches(doc: &Document, filter: &Document) -> bool {
+        filter.iter().all(|(field, condition)| {
+            let value = doc.get(field).unwrap_or(&Bson::Null);
+            match (field.as_str(), condition) {
+                ("$or", Bson::Array(branches)) => branches
+                    .iter()
+                    .filter_map(Bson::as_document)
+                    .any(|branch| filter_matches(doc, branch)),
+                (_, Bson::Document(operators)) => {
+                    operators
+                        .iter()
+                        .all(|(operator, operand)| match operator.as_str() {
+                            "$ne" => value != operand,
+                            "$nin" => operand
+                                .as_array()
+                                .is_some_and(|values| !values.contains(value)),
+                            _ => unreachable!("unsupported operator {operator}"),
+                        })
+                },
+                (_, expected) => value == expected,
+            }
+        })
+    }
+
+    #[test]
+    fn completion_filter_skips_an_already_recorded_completion() {
+        let completion = |completed_at: &str| CompletionMessage {
+            workflow_id:       "wf-1".to_string(),
+            execution_id:      "exec-1".to_string(),
+            status:            "completed".to_string(),
+            final_context:     json!({}),
+            completed_at:      completed_at.to_string(),
+            total_duration_ms: 10,
+            failure_reason:    None,
+            org_id:            None,
+        };
+
+        let first = completion("2026-01-01T00:00:00Z");
+        let mut stored =
+            doc! { "execution_id": "exec-1", "org_id": Bson::Null, "status": "running" };
+        assert!(filter_matches(&stored, &completion_filter(&first)));
+
+        // Record the first delivery as its update would
+        let update = completion_update(&first).expect("update");
+        for (field, value) in update[0].get_document("$set").expect("$set") {
+            match value {
+                Bson::Document(expr) if expr.contains_key("$cond") => {},
+                Bson::Document(expr) if expr.contains_key("$literal") => {
+                    stored.insert(field, expr.get("$literal").cloned().unwrap_or(Bson::Null));
+                },
+                value => {
+                    stored.insert(field, value.clone());
+                },
+            }
+        }
+
+        // A redelivery no longer matches; another completion still does
+        assert!(!filter_matches(&stored, &completion_filter(&first)));
+        assert!(filter_matches(&stored, &completion_filter(&completion("2026-01-02T00:00:00Z"))));
+        assert_eq!(
+            completion_filter(&first),
+            doc! {
+                "execution_id": "exec-1",
+                "org_id": Bson::Null,
+                "$or": [
+                    { "status": { "$nin": ["completed", "failed", "halted"] } },
+                    { "completed_at": { "$ne": "2026-01-01T00:00:00Z" } },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn fields_projection_keeps_only_selected_paths() {
+        let fields =
+            ExecutionFields::parse("status,nodes.latest.status").expect("fields should parse");
+        let projection = fields_projection(&fields);
+        let mut projected: Vec<&str> = projection.keys().map(String::as_str).collect();
+        projected.sort_unstable();
+        assert_eq!(
+            projected,
+            [
+                "_id",
+                "execution_id",
+                "nodes",
+                "org_id",
+                "status",
+                "updated_at",
+                "workflow_id",
+                "workflow_version_id"
+            ]
+        );
+        let node = &projection.get_document("nodes").expect("nodes is rebuilt")["$cond"];
+        let Bson::Array(branches) = node else {
+            unreachable!("nodes should be a $cond");
+        };
+        let rebuilt = branches[1]
+            .as_document()
+            .and_then(|node| node.get_document("$arrayToObject").ok())
+            .and_then(|node| node.get_document("$map").ok())
+            .and_then(|node| node.get_document("in").ok())
+            .and_then(|node| node.get_document("v").ok())
+            .expect("each node is rebuilt");
+        assert_eq!(rebuilt.keys().collect::<Vec<_>>(), ["latest"]);
+        assert!(
+            rebuilt
+                .get_document("latest")
+                .expect("latest is filtered")
+                .to_string()
+                .contains(r#"["$$this.k", ["status"]]"#)
+        );
+
+        // Selecting whole nodes projects the stored map as-is
+        let whole =
+            fields_projection(&ExecutionFields::parse("nodes").expect("fields should parse"));
+        assert_eq!(whole.get("nodes"), Some(&Bson::Int32(1)));
+    }
 }
@@ -0,0 +1,526 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    api::state::{StoreResult, TokenStorePort},
+    domain::models::{ExecutionToken, TokenScope},
+    infra::token_store::{check_token_permissions, same_scope},
+};
+
+/// Token indexes mirroring the Redis sorted-set layout: grants keyed by
+/// user, by execution (specific grants) and by workflow (wildcard grants).
+#[derive(Debug, Default)]
+struct Indexes {
+    users:      HashMap<String, Vec<ExecutionToken>>,
+    executions: HashMap<String, Vec<ExecutionToken>>,
+    workflows:  HashMap<String, Vec<ExecutionToken>>,
+}
+
+/// In-process token store for single-node and local deployments that don't
+/// run Redis.
+///
+/// Expiry follows the Redis store: a token stops granting access once `exp`
+/// is reached. Expired tokens are dropped lazily on lookup and by the
+/// background sweep started with [`MemoryTokenStore::spawn_sweeper`].
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTokenStore {
+    indexes: Arc<Mutex<Indexes>>,
+}
+
+fn now_secs() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    i64::try_from(now).unwrap_or(i64::MAX)
+}
+
+/// Insert `token` into an index, replacing a member with the same jti like
+/// `ZADD` does for Redis.
+fn insert_member(
+    index: &mut HashMap<String, Vec<ExecutionToken>>,
+    key: &str,
+    token: &ExecutionToken,
+) {
+    let members = index.entry(key.to_string()).or_default();
+    match members.iter_mut().find(|member| member.jti == token.jti) {
+        Some(member) => member.clone_from(token),
+        None => members.push(token.clone()),
+    }
+}
+
+/// Drop expired members (and emptied keys) from an index, returning how many
+/// members were removed.
+fn prune_index(index: &mut HashMap<String, Vec<ExecutionToken>>, now: i64) -> usize {
+    let mut pruned = 0;
+    index.retain(|_, members| {
+        let before = members.len();
+        members.retain(|token| token.exp > now);
+        pruned += before - members.len();
+        !members.is_empty()
+    });
+    pruned
+}
+
+/// Drop members with this jti (and emptied keys) from an index, returning
+/// how many members were removed.
+fn remove_jti(index: &mut HashMap<String, Vec<ExecutionToken>>, jti: &str) -> usize {
+    let mut removed = 0;
+    index.retain(|_, members| {
+        let before = members.len();
+        members.retain(|token| token.jti != jti);
+        removed += before - members.len();
+        !members.is_empty()
+    });
+    removed
+}
+
+/// Unexpired members stored under `key`.
+fn valid_members(
+    index: &HashMap<String, Vec<ExecutionToken>>,
+    key: &str,
+    now: i64,
+) -> Vec<ExecutionToken> {
+    index
+        .get(key)
+        .map(|members| {
+            members
+                .iter()
+                .filter(|token| token.exp > now)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Indexes> {
+        self.indexes
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Remove every expired token from all indexes, returning the number of
+    /// members removed.
+    pub fn sweep_expired(&self) -> usize {
+        let now = now_secs();
+        let mut indexes = self.lock();
+        prune_index(&mut indexes.users, now)
+            + prune_index(&mut indexes.executions, now)
+            + prune_index(&mut indexes.workflows, now)
+    }
+
+    /// Periodically sweep expired tokens until `cancel_token` fires.
+    pub fn spawn_sweeper(&self, interval: Duration, cancel_token: CancellationToken) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    () = cancel_token.cancelled() => break,
+                    _ = ticker.tick() => {
+                        let pruned = store.sweep_expired();
+                        if pruned > 0 {
+                            info!(pruned, "Swept expired tokens from in-memory token store");
+                        }
+                    },
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl TokenStorePort for MemoryTokenStore {
+    async fn add_token(&self, token: &ExecutionToken) -> StoreResult<()> {
+        let mut indexes = self.lock();
+
+        // A re-issued grant for the same scope replaces the earlier one
+        let replaced: Vec<String> = indexes
+            .users
+            .get(&token.user_id)
+            .into_iter()
+            .flatten()
+            .filter(|member| member.jti != token.jti && same_scope(member, token))
+            .map(|member| member.jti.clone())
+            .collect();
+        for jti in replaced {
+            remove_jti(&mut indexes.users, &jti);
+            remove_jti(&mut indexes.executions, &jti);
+            remove_jti(&mut indexes.workflows, &jti);
+        }
+
+        insert_member(&mut indexes.users, &token.user_id, token);
+
+        // Index by execution_id if present (for WebSocket auth without JWT)
+        if let Some(execution_id) = &token.execution_id {
+            insert_member(&mut indexes.executions, execution_id, token);
+        }
+
+        // Index by workflow_id for wildcard tokens (for HTTP history without JWT)
+        if token.execution_id.is_none() {
+            insert_member(&mut indexes.workflows, &token.workflow_id, token);
+        }
+        drop(indexes);
+
+        Ok(())
+    }
+
+    async fn validate_access(
+        &self,
+        user_id: &str,
+        target_execution_id: Option<&str>,
+        target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
+    ) -> StoreResult<bool> {
+        let tokens = valid_members(&self.lock().users, user_id, now_secs());
+        Ok(tokens.iter().any(|token| {
+            check_token_permissions(
+                token,
+                target_execution_id,
+                target_workflow_id,
+                target_org_id,
+                required_scope,
+            )
+        }))
+    }
+
+    async fn validate_access_for_execution(
+        &self,
+        user_id: &str,
+        target_execution_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
+    ) -> StoreResult<bool> {
+        let tokens = valid_members(&self.lock().users, user_id, now_secs());
+        Ok(tokens.iter().any(|token| {
+            token.scope.allows(required_scope)
+                && token.in_org(target_org_id)
+                && token
+                    .execution_id
+                    .as_deref()
+                    .is_none_or(|tok_eid| tok_eid == target_execution_id)
+        }))
+    }
+
+    async fn validate_execution_access(
+        &self,
+        target_execution_id: &str,
+        target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
+    ) -> StoreResult<bool> {
+        let tokens = valid_members(&self.lock().executions, target_execution_id, now_secs());
+        Ok(tokens.iter().any(|token| {
+            token.workflow_id == target_workflow_id
+                && token.in_org(target_org_id)
+                && token.scope.allows(required_scope)
+        }))
+    }
+
+    async fn validate_workflow_access(
+        &self,
+        target_workflow_id: &str,
+        target_org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        let tokens = valid_members(&self.lock().workflows, target_workflow_id, now_secs());
+        Ok(tokens.iter().any(|token| token.in_org(target_org_id)))
+    }
+
+    async fn list_user_tokens(&self, user_id: &str) -> StoreResult<Vec<ExecutionToken>> {
+        Ok(valid_members(&self.lock().users, user_id, now_secs()))
+    }
+
+    async fn revoke_token(&self, jti: &str) -> StoreResult<bool> {
+        let mut indexes = self.lock();
+        let removed = remove_jti(&mut indexes.users, jti)
+            + remove_jti(&mut indexes.executions, jti)
+            + remove_jti(&mut indexes.workflows, jti);
+        drop(indexes);
+        Ok(removed > 0)
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{MemoryTokenStore, now_secs};
+    use crate::{
+        api::state::TokenStorePort,
+        domain::models::{ExecutionToken, TokenScope},
+    };
+
+    fn token(workflow_id: &str, execution_id: Option<&str>, exp: i64) -> ExecutionToken {
+        ExecutionToken {
+            execution_id: execution_id.map(ToOwned::to_owned),
+            workflow_id: workflow_id.to_string(),
+            iat: now_secs(),
+            exp,
+            user_id: "user-1".to_string(),
+            jti: ExecutionToken::new_jti(),
+            scope: TokenScope::Owner,
+            org_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_user_execution_and_workflow_indexes() {
+        let store = MemoryTokenStore::new();
+        let exp = now_secs() + 3600;
+        store
+            .add_token(&token("wf-1", Some("exec-1"), exp))
+            .await
+            .expect("add specific token");
+        store
+            .add_token(&token("wf-2", None, exp))
+            .await
+            .expect("add wildcard token");
+
+        assert!(
+            store
+                .validate_access("user-1", Some("exec-1"), "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("user index")
+        );
+        assert!(
+            store
+                .validate_access_for_execution("user-1", "exec-1", None, TokenScope::Viewer)
+                .await
+                .expect("user index")
+        );
+        assert!(
+            store
+                .validate_execution_access("exec-1", "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+        assert!(
+            !store
+                .validate_execution_access("exec-1", "wf-2", None, TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+        assert!(
+            store
+                .validate_workflow_access("wf-2", None)
+                .await
+                .expect("workflow index")
+        );
+        assert!(
+            !store
+                .validate_workflow_access("wf-1", None)
+                .await
+                .expect("workflow index")
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_grant_nothing_and_are_swept() {
+        let store = MemoryTokenStore::new();
+        let expired = now_secs() - 1;
+        store
+            .add_token(&token("wf-1", Some("exec-1"), expired))
+            .await
+            .expect("add specific token");
+        store
+            .add_token(&token("wf-1", None, expired))
+            .await
+            .expect("add wildcard token");
+
+        assert!(
+            !store
+                .validate_access("user-1", None, "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("user index")
+        );
+        assert!(
+            !store
+                .validate_execution_access("exec-1", "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+        assert!(
+            !store
+                .validate_workflow_access("wf-1", None)
+                .await
+                .expect("workflow index")
+        );
+
+        // user index holds both tokens, execution and workflow one each
+        assert_eq!(store.sweep_expired(), 4);
+        assert_eq!(store.sweep_expired(), 0);
+    }
+
+    #[tokio::test]
+    async fn revoked_tokens_no_longer_grant_access() {
+        let store = MemoryTokenStore::new();
+        let exp = now_secs() + 3600;
+        let revoked = token("wf-1", Some("exec-1"), exp);
+        let kept = token("wf-2", None, exp);
+        store.add_token(&revoked).await.expect("add specific token");
+        store.add_token(&kept).await.expect("add wildcard token");
+
+        assert!(store.revoke_token(&revoked.jti).await.expect("revoke"));
+        assert!(
+            !store
+                .revoke_token(&revoked.jti)
+                .await
+                .expect("revoke again")
+        );
+
+        assert!(
+            !store
+                .validate_access("user-1", Some("exec-1"), "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("user index")
+        );
+        assert!(
+            !store
+                .validate_execution_access("exec-1", "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+        assert!(
+            store
+                .validate_access("user-1", None, "wf-2", None, TokenScope::Viewer)
+                .await
+                .expect("user index")
+        );
+    }
+
+    #[tokio::test]
+    async fn re_adding_a_jti_updates_the_grant_instead_of_duplicating_it() {
+        let store = MemoryTokenStore::new();
+        let mut grant = token("wf-1", Some("exec-1"), now_secs() + 60);
+        store.add_token(&grant).await.expect("add token");
+        grant.exp += 3600;
+        store.add_token(&grant).await.expect("re-add token");
+
+        assert_eq!(store.list_user_tokens("user-1").await.expect("user index"), vec![grant]);
+    }
+
+    #[tokio::test]
+    async fn reissued_grants_for_a_scope_replace_instead_of_accumulating() {
+        let store = MemoryTokenStore::new();
+        let mut latest = token("wf-1", Some("exec-1"), now_secs() + 60);
+        for _ in 0..5 {
+            latest = token("wf-1", Some("exec-1"), latest.exp + 60);
+            store.add_token(&latest).await.expect("add token");
+        }
+        let wildcard = token("wf-1", None, now_secs() + 60);
+        store
+            .add_token(&wildcard)
+            .await
+            .expect("add wildcard token");
+
+        assert_eq!(
+            store.list_user_tokens("user-1").await.expect("user index"),
+            vec![latest.clone(), wildcard]
+        );
+        assert_eq!(store.lock().executions.get("exec-1").map(Vec::len), Some(1));
+        assert!(
+            store
+                .validate_execution_access("exec-1", "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+        // The latest grant is the one revocation finds
+        assert!(store.revoke_token(&latest.jti).await.expect("revoke"));
+        let remaining = store.list_user_tokens("user-1").await.expect("user index");
+        assert_eq!(remaining.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn an_owner_grant_survives_a_later_viewer_grant() {
+        let store = MemoryTokenStore::new();
+        let owner = token("wf-1", Some("exec-1"), now_secs() + 60);
+        store.add_token(&owner).await.expect("add owner grant");
+        let viewer = ExecutionToken {
+            scope: TokenScope::Viewer,
+            jti: ExecutionToken::new_jti(),
+            ..token("wf-1", Some("exec-1"), now_secs() + 120)
+        };
+        store.add_token(&viewer).await.expect("add viewer grant");
+
+        assert_eq!(
+            store.list_user_tokens("user-1").await.expect("user index"),
+            vec![owner, viewer]
+        );
+        assert!(
+            store
+                .validate_access_for_execution("user-1", "exec-1", None, TokenScope::Owner)
+                .await
+                .expect("user index")
+        );
+    }
+
+    #[tokio::test]
+    async fn grants_only_reach_executions_of_their_organization() {
+        let store = MemoryTokenStore::new();
+        let grant = ExecutionToken {
+            org_id: Some("org-a".to_string()),
+            ..token("wf-1", None, now_secs() + 60)
+        };
+        store.add_token(&grant).await.expect("add org grant");
+
+        for (org_id, granted) in [(Some("org-a"), true), (Some("org-b"), false), (None, false)] {
+            assert_eq!(
+                store
+                    .validate_access_for_execution("user-1", "exec-1", org_id, TokenScope::Viewer)
+                    .await
+                    .expect("user index"),
+                granted,
+                "{org_id:?}"
+            );
+            assert_eq!(
+                store
+                    .validate_access("user-1", None, "wf-1", org_id, TokenScope::Viewer)
+                    .await
+                    .expect("user index"),
+                granted,
+                "{org_id:?}"
+            );
+            assert_eq!(
+                store
+                    .validate_workflow_access("wf-1", org_id)
+                    .await
+                    .expect("workflow index"),
+                granted,
+                "{org_id:?}"
+            );
+        }
+        // Grants from before organizations only reach executions without one
+        store
+            .add_token(&token("wf-1", Some("exec-2"), now_secs() + 60))
+            .await
+            .expect("add legacy grant");
+        assert!(
+            store
+                .validate_execution_access("exec-2", "wf-1", None, TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+        assert!(
+            !store
+                .validate_execution_access("exec-2", "wf-1", Some("org-a"), TokenScope::Viewer)
+                .await
+                .expect("execution index")
+        );
+    }
+}
@@ -1,27 +1,38 @@
-use std::sync::Arc;
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use futures::StreamExt;
 use lapin::{
+    BasicProperties,
     Channel,
     Connection,
     ConnectionProperties,
     ExchangeKind,
+    message::Delivery,
     options::{
         BasicAckOptions,
         BasicConsumeOptions,
         BasicNackOptions,
+        BasicPublishOptions,
         BasicQosOptions,
+        ConfirmSelectOptions,
         ExchangeDeclareOptions,
         QueueBindOptions,
         QueueDeclareOptions,
     },
-    types::FieldTable,
+    publisher_confirm::Confirmation,
+    types::{AMQPValue, FieldTable},
 };
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
-    api::state::{AppState, TokenStorePort},
+    api::state::{AppState, ExecutionStorePort, StoreError, StoreResult, TokenStorePort},
     domain::models::{
         CompletionMessage,
         ExecutionToken,
@@ -30,10 +41,57 @@ use crate::{
         NodeStatusMessage,
         WorkerMessage,
     },
+    infra::{consumer_health::ConsumerReporter, prometheus},
 };
 
 const EXCHANGE_NAME: &str = "workflows";
 
+/// Name of the dead-letter queue paired with a consumer queue.
+pub fn dlq_name(queue_name: &str) -> String {
+    format!("{queue_name}.dlq")
+}
+
+/// Queue collecting every payload a consumer failed to parse, tagged with
+/// the error and the queue it came from. No consumer reads it.
+pub const PARSE_ERRORS_QUEUE: &str = "parse-errors";
+
+/// Name of the delay queue holding a consumer queue's messages until their
+/// retry is due; expired messages dead-letter back onto the consumer queue.
+pub fn retry_queue_name(queue_name: &str) -> String {
+    format!("{queue_name}.retry")
+}
+
+/// `schema_version` of the worker messages (execution, status, completion)
+/// this build reads. Messages without one predate versioning and are read
+/// as version 1.
+pub const MESSAGE_SCHEMA_VERSION: u64 = 1;
+
+/// Parse a worker message, first bringing it from its `schema_version` to
+/// the current shape with [`migrate`].
+fn parse_worker_message<T: DeserializeOwned>(data: &[u8]) -> Result<T, String> {
+    let message: Value = serde_json::from_slice(data).map_err(|e| e.to_string())?;
+    let version = match message.get("schema_version") {
+        None | Some(Value::Null) => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("invalid schema_version {version}"))?,
+    };
+    serde_json::from_value(migrate(version, message)?).map_err(|e| e.to_string())
+}
+
+/// Rewrite a worker message of schema `version` into the shape of
+/// [`MESSAGE_SCHEMA_VERSION`]. Each bump adds the step from the previous
+/// version here; a version this build doesn't know is an error, so the
+/// message is set aside during a rolling deploy instead of being misread.
+fn migrate(version: u64, message: Value) -> Result<Value, String> {
+    match version {
+        1 => Ok(message),
+        _ => Err(format!(
+            "unsupported schema_version {version} (this build reads {MESSAGE_SCHEMA_VERSION})"
+        )),
+    }
+}
+
 fn expand_tokens_from_payload(payload_bytes: &[u8]) -> Result<Vec<ExecutionToken>, String> {
     let payload = serde_json::from_slice::<ExecutionTokenPayload>(payload_bytes)
         .map_err(|e| format!("Failed to deserialize token payload: {e}"))?;
@@ -44,6 +102,326 @@ fn declare_options(durable: bool) -> QueueDeclareOptions {
     QueueDeclareOptions { durable, ..QueueDeclareOptions::default() }
 }
 
+/// Header counting how often a message was republished after a processing
+/// failure.
+pub const RETRY_HEADER: &str = "x-rune-retries";
+
+fn retry_count(properties: &BasicProperties) -> u32 {
+    counter_header(properties, RETRY_HEADER)
+}
+
+/// Read a counter header such as [`RETRY_HEADER`]; missing or malformed
+/// values count as 0.
+pub(crate) fn counter_header(properties: &BasicProperties, header: &str) -> u32 {
+    properties
+        .headers()
+        .as_ref()
+        .and_then(|headers| headers.inner().get(header))
+        .and_then(|value| {
+            value
+                .as_long_long_int()
+                .or_else(|| value.as_long_uint().map(i64::from))
+        })
+        .and_then(|count| u32::try_from(count).ok())
+        .unwrap_or(0)
+}
+
+/// Header holding why a message in `parse-errors` could not be parsed.
+pub const PARSE_ERROR_HEADER: &str = "x-rune-parse-error";
+
+/// Header naming the queue an unparsable message was consumed from.
+pub const SOURCE_QUEUE_HEADER: &str = "x-rune-source-queue";
+
+fn with_parse_error(
+    properties: &BasicProperties,
+    queue_name: &str,
+    error: &str,
+) -> BasicProperties {
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    headers.insert(PARSE_ERROR_HEADER.into(), AMQPValue::LongString(error.into()));
+    headers.insert(SOURCE_QUEUE_HEADER.into(), AMQPValue::LongString(queue_name.into()));
+    properties.clone().with_headers(headers)
+}
+
+fn with_retry_count(properties: &BasicProperties, count: u32) -> BasicProperties {
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    headers.insert(RETRY_HEADER.into(), AMQPValue::LongLongInt(i64::from(count)));
+    properties.clone().with_headers(headers)
+}
+
+/// Properties of retry `attempt`, parked on the retry queue for `delay_ms`
+/// times the attempt number before it expires back onto its queue.
+fn retry_properties(properties: &BasicProperties, attempt: u32, delay_ms: u64) -> BasicProperties {
+    let expiration = delay_ms.saturating_mul(u64::from(attempt));
+    with_retry_count(properties, attempt).with_expiration(expiration.to_string().into())
+}
+
+/// Publish `payload` to `queue` through the default exchange and wait for
+/// the broker to confirm it was routed. `channel` must be in confirm mode.
+pub(crate) async fn publish_confirmed(
+    channel: &Channel,
+    queue: &str,
+    payload: &[u8],
+    properties: BasicProperties,
+) -> Result<(), String> {
+    let confirmation = channel
+        .basic_publish(
+            "",
+            queue,
+            BasicPublishOptions { mandatory: true, ..BasicPublishOptions::default() },
+            payload,
+            properties,
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match confirmation {
+        Confirmation::Ack(None) => Ok(()),
+        Confirmation::Ack(Some(_)) => Err(format!("queue '{queue}' is not routable")),
+        Confirmation::Nack(_) => Err(format!("broker rejected publish to '{queue}'")),
+        Confirmation::NotRequested => Err("publisher confirms not enabled".to_string()),
+    }
+}
+
+/// Open a consumer channel in confirm mode, so messages it republishes are
+/// acked off their queue only once the broker has them.
+async fn consumer_channel(conn: &Connection) -> Result<Channel, lapin::Error> {
+    let channel = conn.create_channel().await?;
+    channel
+        .confirm_select(ConfirmSelectOptions::default())
+        .await?;
+    Ok(channel)
+}
+
+/// Ack a delivery, counting it for `/metrics`.
+async fn ack(delivery: &Delivery, queue_name: &str) {
+    prometheus::message_acked(queue_name);
+    let _ = delivery.ack(BasicAckOptions::default()).await;
+}
+
+/// Nack a delivery without requeueing it, so it is dropped or dead-lettered.
+async fn reject(delivery: &Delivery, queue_name: &str) {
+    prometheus::message_nacked(queue_name);
+    let _ = delivery
+        .nack(BasicNackOptions { requeue: false, ..BasicNackOptions::default() })
+        .await;
+}
+
+/// Set aside a payload that failed to parse without losing why: the raw
+/// bytes are published to [`PARSE_ERRORS_QUEUE`] with the error and source
+/// queue as headers, whether or not `RABBITMQ_ENABLE_DLQ` is set, and the
+/// original is acked once the broker confirms that publish; if it fails, the
+/// original is requeued so it isn't lost.
+async fn reject_unparsable(channel: &Channel, delivery: &Delivery, queue_name: &str, error: &str) {
+    warn!(
+        queue = queue_name,
+        error,
+        payload_bytes = delivery.data.len(),
+        "Setting aside unparsable message"
+    );
+    let properties = with_parse_error(&delivery.properties, queue_name, error);
+    match publish_confirmed(channel, PARSE_ERRORS_QUEUE, &delivery.data, properties).await {
+        Ok(()) => {
+            prometheus::message_nacked(queue_name);
+            let _ = delivery.ack(BasicAckOptions::default()).await;
+        },
+        Err(e) => {
+            warn!(queue = queue_name, error = %e, "Failed to set aside unparsable message");
+            requeue(delivery, queue_name).await;
+        },
+    }
+}
+
+/// Nack a delivery back onto its queue for redelivery.
+async fn requeue(delivery: &Delivery, queue_name: &str) {
+    prometheus::message_nacked(queue_name);
+    let _ = delivery
+        .nack(BasicNackOptions { requeue: true, ..BasicNackOptions::default() })
+        .await;
+}
+
+/// Handle a store error while processing a delivery. Below
+/// `RABBITMQ_MAX_RETRIES` the message is published to `<queue>.retry` with
+/// an incremented `x-rune-retries`, expiring back onto `queue_name` after
+/// `RABBITMQ_RETRY_DELAY_MS` times the attempt number, and the original is
+/// acked once the broker confirms that publish (requeued if it fails). The
+/// consumer never waits out the delay. After that, or at once for a
+/// [`StoreError::Fatal`] no retry can clear, it is nacked without requeue so
+/// it dead-letters.
+async fn retry_or_dead_letter(
+    channel: &Channel,
+    queue_name: &str,
+    delivery: &Delivery,
+    error: &StoreError,
+) {
+    if matches!(error, StoreError::Fatal(_)) {
+        warn!(queue = queue_name, "Store error won't clear on retry; dead-lettering message");
+        reject(delivery, queue_name).await;
+        return;
+    }
+    let cfg = crate::config::Config::get();
+    let retries = retry_count(&delivery.properties);
+    if retries >= cfg.rabbitmq_max_retries {
+        warn!(queue = queue_name, retries, "Retries exhausted; dead-lettering message");
+        reject(delivery, queue_name).await;
+        return;
+    }
+
+    let attempt = retries + 1;
+    let properties = retry_properties(&delivery.properties, attempt, cfg.rabbitmq_retry_delay_ms);
+    match publish_confirmed(channel, &retry_queue_name(queue_name), &delivery.data, properties)
+        .await
+    {
+        Ok(()) => {
+            warn!(queue = queue_name, attempt, "Scheduled message for retry");
+            ack(delivery, queue_name).await;
+        },
+        Err(e) => {
+            // Keep the message rather than lose it; the broker redelivers it
+            error!(queue = queue_name, error = %e, "Failed to schedule message for retry");
+            requeue(delivery, queue_name).await;
+        },
+    }
+}
+
+/// Drive a consumer's processing future. Once `cancel_token` fires the
+/// consumer stops taking deliveries, and in-flight ones get up to
+/// `RABBITMQ_DRAIN_TIMEOUT_SECS` to finish and ack; whatever is still unacked
+/// after that is redelivered by the broker when the connection closes.
+async fn drain_on_cancel<F>(processing: F, cancel_token: &CancellationToken, queue_name: &str)
+where
+    F: Future<Output = ()>,
+{
+    tokio::pin!(processing);
+    tokio::select! {
+        () = &mut processing => return,
+        () = cancel_token.cancelled() => {},
+    }
+
+    let timeout = Duration::from_secs(crate::config::Config::get().rabbitmq_drain_timeout_secs);
+    info!(queue = queue_name, "Draining in-flight messages");
+    if tokio::time::timeout(timeout, processing).await.is_err() {
+        warn!(
+            queue = queue_name,
+            "Drain timed out after {:?}; unacked messages will be redelivered", timeout
+        );
+    }
+}
+
+/// Republishes persisted node statuses to `RABBITMQ_PROCESSED_EXCHANGE` so
+/// other services can react to them. One channel is opened per status
+/// consumer connection and shared by all its deliveries.
+struct ProcessedPublisher {
+    channel:  Channel,
+    exchange: String,
+}
+
+impl ProcessedPublisher {
+    async fn new(conn: &Connection, exchange: &str) -> Result<Self, lapin::Error> {
+        let channel = conn.create_channel().await?;
+        channel
+            .exchange_declare(
+                exchange,
+                ExchangeKind::Topic,
+                ExchangeDeclareOptions { durable: true, ..ExchangeDeclareOptions::default() },
+                FieldTable::default(),
+            )
+            .await?;
+        info!("Publishing processed node statuses to exchange '{}'", exchange);
+        Ok(Self { channel, exchange: exchange.to_string() })
+    }
+
+    /// Routing key `node.status.<status>`, so subscribers can bind to one
+    /// status or to `node.status.#`.
+    fn routing_key(msg: &NodeStatusMessage) -> String {
+        format!("node.status.{}", msg.status)
+    }
+
+    /// Publish a persisted status. Failures are logged and never fail the
+    /// delivery, since the status is already stored.
+    async fn publish(&self, msg: &NodeStatusMessage) {
+        let payload = match serde_json::to_vec(msg) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!(error = %e, "Failed to serialize processed node status");
+                return;
+            },
+        };
+        let published = self
+            .channel
+            .basic_publish(
+                &self.exchange,
+                &Self::routing_key(msg),
+                BasicPublishOptions::default(),
+                &payload,
+                BasicProperties::default().with_content_type("application/json".into()),
+            )
+            .await;
+        if let Err(e) = published {
+            warn!(
+                exchange = %self.exchange,
+                execution_id = %msg.execution_id,
+                node_id = %msg.node_id,
+                error = %e,
+                "Failed to publish processed node status"
+            );
+        }
+    }
+}
+
+/// Result of a consumer whose delivery stream ended: fine on shutdown,
+/// otherwise the connection or channel closed and the caller must reconnect.
+fn consumer_stopped(
+    cancel_token: &CancellationToken,
+    queue_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cancel_token.is_cancelled() {
+        Ok(())
+    } else {
+        Err(format!("Consumer on '{queue_name}' stopped: connection or channel closed").into())
+    }
+}
+
+/// Declare a consumer queue and its `<queue>.retry` delay queue, which has
+/// no consumer and dead-letters each message back onto the queue when its
+/// TTL expires, plus the shared durable [`PARSE_ERRORS_QUEUE`]. With
+/// `RABBITMQ_ENABLE_DLQ` set, `<queue>.dlq` is declared as well and messages
+/// nacked without requeue are routed there. RabbitMQ refuses to change
+/// arguments of an existing queue, so turning this on for a deployed queue
+/// means deleting that queue first.
+async fn declare_queue(
+    channel: &Channel,
+    queue_name: &str,
+    durable: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut retry_arguments = FieldTable::default();
+    retry_arguments.insert("x-dead-letter-exchange".into(), AMQPValue::LongString("".into()));
+    retry_arguments
+        .insert("x-dead-letter-routing-key".into(), AMQPValue::LongString(queue_name.into()));
+    channel
+        .queue_declare(&retry_queue_name(queue_name), declare_options(durable), retry_arguments)
+        .await?;
+    channel
+        .queue_declare(PARSE_ERRORS_QUEUE, declare_options(true), FieldTable::default())
+        .await?;
+
+    let mut arguments = FieldTable::default();
+    if crate::config::Config::get().rabbitmq_enable_dlq {
+        let dlq = dlq_name(queue_name);
+        channel
+            .queue_declare(&dlq, declare_options(durable), FieldTable::default())
+            .await?;
+        arguments.insert("x-dead-letter-exchange".into(), AMQPValue::LongString("".into()));
+        arguments.insert("x-dead-letter-routing-key".into(), AMQPValue::LongString(dlq.into()));
+    }
+    channel
+        .queue_declare(queue_name, declare_options(durable), arguments)
+        .await?;
+    Ok(())
+}
+
 /// Declare the workflows exchange (topic) if it doesn't exist.
 /// Note: durable must match the existing exchange created by the worker.
 async fn declare_exchange(channel: &Channel) -> Result<(), Box<dyn std::error::Error>> {
@@ -84,23 +462,24 @@ pub async fn start_token_consumer(
     amqp_addr: &str,
     token_store: Arc<dyn TokenStorePort>,
     cancel_token: CancellationToken,
+    reporter: &ConsumerReporter,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let conn = Connection::connect(amqp_addr, ConnectionProperties::default()).await?;
-    let channel = conn.create_channel().await?;
+    let channel = consumer_channel(&conn).await?;
 
     let cfg = crate::config::Config::get();
     let queue_name = &cfg.rabbitmq_token_queue;
     let consumer_tag = &cfg.rabbitmq_consumer_tag;
-    let prefetch_count = cfg.rabbitmq_prefetch_count;
-    let concurrent_messages = cfg.rabbitmq_concurrent_messages;
+    // Reloaded QoS applies from the next connection
+    let live = crate::config::Config::current();
+    let prefetch_count = live.rabbitmq_prefetch_count;
+    let concurrent_messages = live.rabbitmq_concurrent_messages;
 
     channel
         .basic_qos(prefetch_count, BasicQosOptions::default())
         .await?;
 
-    let _queue = channel
-        .queue_declare(queue_name, declare_options(true), FieldTable::default())
-        .await?;
+    declare_queue(&channel, queue_name, true).await?;
 
     let consumer = channel
         .basic_consume(
@@ -115,28 +494,54 @@ pub async fn start_token_consumer(
         "Started token consumer on queue: {} with prefetch: {} and concurrency: {}",
         queue_name, prefetch_count, concurrent_messages
     );
+    reporter.running();
 
-    consumer
+    let processing = consumer
         .take_until(cancel_token.cancelled())
         .for_each_concurrent(Some(concurrent_messages), |delivery| {
             let token_store = token_store.clone();
+            let channel = &channel;
             async move {
-                if let Ok(delivery) = delivery {
-                    process_token_delivery(delivery, token_store.as_ref()).await;
+                match delivery {
+                    Ok(delivery) => {
+                        process_token_delivery(delivery, token_store.as_ref(), channel, queue_name)
+                            .await;
+                    },
+                    Err(e) => error!(queue = queue_name, error = %e, "Consumer delivery error"),
                 }
             }
-        })
-        .await;
-
-    Ok(())
+        });
+    drain_on_cancel(processing, &cancel_token, queue_name).await;
+    consumer_stopped(&cancel_token, queue_name)
 }
 
 async fn process_token_delivery(
-    delivery: lapin::message::Delivery,
+    delivery: Delivery,
     token_store: &dyn TokenStorePort,
+    channel: &Channel,
+    queue_name: &str,
 ) {
+    prometheus::message_consumed(queue_name);
     match expand_tokens_from_payload(&delivery.data) {
         Ok(tokens) => {
+            let now = chrono::Utc::now().timestamp();
+            let skew = crate::config::Config::get().token_clock_skew_secs;
+            if let Some((token, reason)) = tokens.iter().find_map(|token| {
+                token
+                    .validity_error(now, skew)
+                    .map(|reason| (token, reason))
+            }) {
+                warn!(
+                    user_id = %token.user_id,
+                    workflow_id = %token.workflow_id,
+                    iat = token.iat,
+                    exp = token.exp,
+                    "Rejecting execution token: {}",
+                    reason
+                );
+                reject(&delivery, queue_name).await;
+                return;
+            }
             for token in &tokens {
                 info!(
                     "Received token for user: {} workflow: {} execution: {}",
@@ -146,19 +551,15 @@ async fn process_token_delivery(
                 );
                 if let Err(e) = token_store.add_token(token).await {
                     error!("Failed to store token: {}", e);
-                    let _ = delivery
-                        .nack(BasicNackOptions { requeue: false, ..BasicNackOptions::default() })
-                        .await;
+                    retry_or_dead_letter(channel, queue_name, &delivery, &e).await;
                     return;
                 }
             }
-            let _ = delivery.ack(BasicAckOptions::default()).await;
+            ack(&delivery, queue_name).await;
         },
         Err(e) => {
             error!("{}", e);
-            let _ = delivery
-                .nack(BasicNackOptions { requeue: false, ..BasicNackOptions::default() })
-                .await;
+            reject_unparsable(channel, &delivery, queue_name, &e).await;
         },
     }
 }
@@ -167,23 +568,23 @@ pub async fn start_execution_consumer(
     amqp_addr: &str,
     state: AppState,
     cancel_token: CancellationToken,
+    reporter: &ConsumerReporter,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let conn = Connection::connect(amqp_addr, ConnectionProperties::default()).await?;
-    let channel = conn.create_channel().await?;
+    let channel = consumer_channel(&conn).await?;
 
     let cfg = crate::config::Config::get();
     let queue_name = &cfg.rabbitmq_execution_queue;
+    let tuning = crate::config::Config::current().rabbitmq_execution_tuning;
+
+    channel
+        .basic_qos(tuning.prefetch, BasicQosOptions::default())
+        .await?;
 
     // Declare the workflows exchange
     declare_exchange(&channel).await?;
 
-    let _queue = channel
-        .queue_declare(
-            queue_name,
-            declare_options(cfg.rabbitmq_queue_durable),
-            FieldTable::default(),
-        )
-        .await?;
+    declare_queue(&channel, queue_name, cfg.rabbitmq_queue_durable).await?;
 
     // Bind queue to exchange with the queue name as routing key
     bind_queue(&channel, queue_name, queue_name).await?;
@@ -197,68 +598,90 @@ pub async fn start_execution_consumer(
         )
         .await?;
 
-    info!("Started execution consumer on queue: {}", queue_name);
-
-    let mut stream = Box::pin(consumer.take_until(cancel_token.cancelled()));
-
-    while let Some(delivery) = stream.next().await {
-        if let Ok(delivery) = delivery {
-            match serde_json::from_slice::<NodeExecutionMessage>(&delivery.data) {
-                Ok(msg) => {
-                    if let Err(e) = state
-                        .execution_store
-                        .upsert_execution_definition(&msg)
-                        .await
-                    {
-                        error!("Failed to upsert execution definition: {}", e);
-                        let _ = delivery
-                            .nack(BasicNackOptions {
-                                requeue: false,
-                                ..BasicNackOptions::default()
-                            })
-                            .await;
-                    } else {
-                        let _ = state.tx.send(WorkerMessage::NodeExecution(Box::new(msg)));
-                        let _ = delivery.ack(BasicAckOptions::default()).await;
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to deserialize execution message: {}", e);
-                    let _ = delivery
-                        .nack(BasicNackOptions { requeue: false, ..BasicNackOptions::default() })
-                        .await;
-                },
+    info!(
+        "Started execution consumer on queue: {} with prefetch: {} and concurrency: {}",
+        queue_name, tuning.prefetch, tuning.concurrency
+    );
+    reporter.running();
+
+    let processing = consumer
+        .take_until(cancel_token.cancelled())
+        .for_each_concurrent(Some(tuning.concurrency), |delivery| {
+            let state = state.clone();
+            let channel = &channel;
+            async move {
+                match delivery {
+                    Ok(delivery) => {
+                        process_execution_delivery(delivery, &state, channel, queue_name).await;
+                    },
+                    Err(e) => error!(queue = queue_name, error = %e, "Consumer delivery error"),
+                }
             }
-        }
+        });
+    drain_on_cancel(processing, &cancel_token, queue_name).await;
+    consumer_stopped(&cancel_token, queue_name)
+}
+
+async fn process_execution_delivery(
+    delivery: Delivery,
+    state: &AppState,
+    channel: &Channel,
+    queue_name: &str,
+) {
+    prometheus::message_consumed(queue_name);
+    match parse_worker_message::<NodeExecutionMessage>(&delivery.data) {
+        Ok(msg) => {
+            if let Err(e) = state
+                .execution_store
+                .upsert_execution_definition(&msg)
+                .await
+            {
+                error!("Failed to upsert execution definition: {}", e);
+                retry_or_dead_letter(channel, queue_name, &delivery, &e).await;
+            } else {
+                // The execution exists now; stop answering probes with 404
+                state.negative_cache.forget(&msg.execution_id);
+                let _ = state.tx.send(WorkerMessage::NodeExecution(Box::new(msg)));
+                ack(&delivery, queue_name).await;
+            }
+        },
+        Err(e) => {
+            error!("Failed to deserialize execution message: {}", e);
+            reject_unparsable(channel, &delivery, queue_name, &e).await;
+        },
     }
-    Ok(())
 }
 
 pub async fn start_status_consumer(
     amqp_addr: &str,
     state: AppState,
     cancel_token: CancellationToken,
+    reporter: &ConsumerReporter,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let conn = Connection::connect(amqp_addr, ConnectionProperties::default()).await?;
-    let channel = conn.create_channel().await?;
+    let channel = consumer_channel(&conn).await?;
 
     let cfg = crate::config::Config::get();
     let queue_name = &cfg.rabbitmq_status_queue;
+    let tuning = crate::config::Config::current().rabbitmq_status_tuning;
+
+    channel
+        .basic_qos(tuning.prefetch, BasicQosOptions::default())
+        .await?;
 
     // Declare the workflows exchange
     declare_exchange(&channel).await?;
 
-    let _queue = channel
-        .queue_declare(
-            queue_name,
-            declare_options(cfg.rabbitmq_queue_durable),
-            FieldTable::default(),
-        )
-        .await?;
+    declare_queue(&channel, queue_name, cfg.rabbitmq_queue_durable).await?;
 
     // Bind queue to exchange with the queue name as routing key
     bind_queue(&channel, queue_name, queue_name).await?;
 
+    let publisher = match &cfg.rabbitmq_processed_exchange {
+        Some(exchange) => Some(ProcessedPublisher::new(&conn, exchange).await?),
+        None => None,
+    };
+
     let consumer = channel
         .basic_consume(
             queue_name,
@@ -268,60 +691,220 @@ pub async fn start_status_consumer(
         )
         .await?;
 
-    info!("Started status consumer on queue: {}", queue_name);
-
-    let mut stream = Box::pin(consumer.take_until(cancel_token.cancelled()));
-
-    while let Some(delivery) = stream.next().await {
-        if let Ok(delivery) = delivery {
-            match serde_json::from_slice::<NodeStatusMessage>(&delivery.data) {
-                Ok(msg) => {
-                    if let Err(e) = state.execution_store.update_node_status(&msg).await {
-                        error!("Failed to update node status: {}", e);
-                        let _ = delivery
-                            .nack(BasicNackOptions {
-                                requeue: false,
-                                ..BasicNackOptions::default()
-                            })
+    reporter.running();
+
+    if cfg.rabbitmq_status_batch_size > 1 {
+        info!(
+            "Started status consumer on queue: {} with prefetch: {} and batches of up to {} \
+             messages / {}ms",
+            queue_name,
+            tuning.prefetch,
+            cfg.rabbitmq_status_batch_size,
+            cfg.rabbitmq_status_batch_ms
+        );
+        // Batches are written one at a time so statuses of an execution stay
+        // in delivery order
+        let batches = tokio_stream::StreamExt::chunks_timeout(
+            consumer.take_until(cancel_token.cancelled()),
+            cfg.rabbitmq_status_batch_size,
+            Duration::from_millis(cfg.rabbitmq_status_batch_ms),
+        );
+        let processing = batches.for_each(|deliveries| {
+            process_status_batch(deliveries, &state, &channel, queue_name, publisher.as_ref())
+        });
+        drain_on_cancel(processing, &cancel_token, queue_name).await;
+        return consumer_stopped(&cancel_token, queue_name);
+    }
+
+    info!(
+        "Started status consumer on queue: {} with prefetch: {} and concurrency: {}",
+        queue_name, tuning.prefetch, tuning.concurrency
+    );
+
+    let processing = consumer
+        .take_until(cancel_token.cancelled())
+        .for_each_concurrent(Some(tuning.concurrency), |delivery| {
+            let state = state.clone();
+            let channel = &channel;
+            let publisher = publisher.as_ref();
+            async move {
+                match delivery {
+                    Ok(delivery) => {
+                        process_status_delivery(delivery, &state, channel, queue_name, publisher)
                             .await;
-                    } else {
-                        let _ = state.tx.send(WorkerMessage::NodeStatus(Box::new(msg)));
-                        let _ = delivery.ack(BasicAckOptions::default()).await;
+                    },
+                    Err(e) => error!(queue = queue_name, error = %e, "Consumer delivery error"),
+                }
+            }
+        });
+    drain_on_cancel(processing, &cancel_token, queue_name).await;
+    consumer_stopped(&cancel_token, queue_name)
+}
+
+/// Write a batch of status deliveries with [`store_status_batch`]. Each
+/// delivery is acked only once its status is stored; if the whole batch
+/// fails, every delivery in it is retried, otherwise only the ones whose
+/// write failed are retried or dead-lettered. Only the statuses the store
+/// applied are broadcast and published.
+async fn process_status_batch(
+    deliveries: Vec<Result<Delivery, lapin::Error>>,
+    state: &AppState,
+    channel: &Channel,
+    queue_name: &str,
+    publisher: Option<&ProcessedPublisher>,
+) {
+    let mut parsed = Vec::with_capacity(deliveries.len());
+    for delivery in deliveries {
+        let delivery = match delivery {
+            Ok(delivery) => delivery,
+            Err(e) => {
+                error!(queue = queue_name, error = %e, "Consumer delivery error");
+                continue;
+            },
+        };
+        prometheus::message_consumed(queue_name);
+        match parse_worker_message::<NodeStatusMessage>(&delivery.data) {
+            Ok(msg) => parsed.push((delivery, msg)),
+            Err(e) => {
+                error!("Failed to deserialize status message: {}", e);
+                reject_unparsable(channel, &delivery, queue_name, &e).await;
+            },
+        }
+    }
+    if parsed.is_empty() {
+        return;
+    }
+
+    let (deliveries, msgs): (Vec<_>, Vec<_>) = parsed.into_iter().unzip();
+    let started = Instant::now();
+    let stored = store_status_batch(state.execution_store.as_ref(), &msgs).await;
+    prometheus::status_write(started.elapsed());
+    let results = match stored {
+        Ok(results) => results,
+        Err(e) => {
+            error!(messages = msgs.len(), "Failed to update node status batch: {}", e);
+            futures::future::join_all(
+                deliveries
+                    .iter()
+                    .map(|delivery| retry_or_dead_letter(channel, queue_name, delivery, &e)),
+            )
+            .await;
+            return;
+        },
+    };
+
+    for ((delivery, msg), result) in deliveries.into_iter().zip(msgs).zip(results) {
+        match result {
+            Ok(applied) => {
+                if applied {
+                    broadcast_status(state, publisher, msg).await;
+                }
+                ack(&delivery, queue_name).await;
+            },
+            Err(e) => {
+                error!("Failed to update node status: {}", e);
+                retry_or_dead_letter(channel, queue_name, &delivery, &e).await;
+            },
+        }
+    }
+}
+
+/// Store status messages with one
+/// [`update_node_statuses`](ExecutionStorePort::update_node_statuses) call,
+/// returning for each whether it was applied.
+///
+/// A transient failure fails the whole batch. Any other may come from a
+/// single message, so the messages are then written again one at a time, in
+/// order, and only the ones that still fail carry an error.
+pub async fn store_status_batch(
+    store: &dyn ExecutionStorePort,
+    msgs: &[NodeStatusMessage],
+) -> StoreResult<Vec<StoreResult<bool>>> {
+    match store.update_node_statuses(msgs).await {
+        Ok(applied) => Ok(applied.into_iter().map(Ok).collect()),
+        Err(e) if e.is_transient() || msgs.len() == 1 => Err(e),
+        Err(e) => {
+            warn!(
+                messages = msgs.len(),
+                error = %e,
+                "Failed to update node status batch; writing its messages one at a time"
+            );
+            let mut results = Vec::with_capacity(msgs.len());
+            for msg in msgs {
+                results.push(store.update_node_status(msg).await);
+            }
+            Ok(results)
+        },
+    }
+}
+
+/// Hand an applied status to live clients and the processed exchange.
+async fn broadcast_status(
+    state: &AppState,
+    publisher: Option<&ProcessedPublisher>,
+    msg: NodeStatusMessage,
+) {
+    if let Some(publisher) = publisher {
+        publisher.publish(&msg).await;
+    }
+    let _ = state.tx.send(WorkerMessage::NodeStatus(Box::new(msg)));
+}
+
+async fn process_status_delivery(
+    delivery: Delivery,
+    state: &AppState,
+    channel: &Channel,
+    queue_name: &str,
+    publisher: Option<&ProcessedPublisher>,
+) {
+    prometheus::message_consumed(queue_name);
+    match parse_worker_message::<NodeStatusMessage>(&delivery.data) {
+        Ok(msg) => {
+            let started = Instant::now();
+            let stored = state.execution_store.update_node_status(&msg).await;
+            prometheus::status_write(started.elapsed());
+            match stored {
+                Ok(applied) => {
+                    // A dropped status must not reach clients either
+                    if applied {
+                        broadcast_status(state, publisher, msg).await;
                     }
+                    ack(&delivery, queue_name).await;
                 },
                 Err(e) => {
-                    error!("Failed to deserialize status message: {}", e);
-                    let _ = delivery
-                        .nack(BasicNackOptions { requeue: false, ..BasicNackOptions::default() })
-                        .await;
+                    error!("Failed to update node status: {}", e);
+                    retry_or_dead_letter(channel, queue_name, &delivery, &e).await;
                 },
             }
-        }
+        },
+        Err(e) => {
+            error!("Failed to deserialize status message: {}", e);
+            reject_unparsable(channel, &delivery, queue_name, &e).await;
+        },
     }
-    Ok(())
 }
 
 pub async fn start_completion_consumer(
     amqp_addr: &str,
     state: AppState,
     cancel_token: CancellationToken,
+    reporter: &ConsumerReporter,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let conn = Connection::connect(amqp_addr, ConnectionProperties::default()).await?;
-    let channel = conn.create_channel().await?;
+    let channel = consumer_channel(&conn).await?;
 
     let cfg = crate::config::Config::get();
     let queue_name = &cfg.rabbitmq_completion_queue;
+    let tuning = crate::config::Config::current().rabbitmq_completion_tuning;
+
+    channel
+        .basic_qos(tuning.prefetch, BasicQosOptions::default())
+        .await?;
 
     // Declare the workflows exchange
     declare_exchange(&channel).await?;
 
-    let _queue = channel
-        .queue_declare(
-            queue_name,
-            declare_options(cfg.rabbitmq_queue_durable),
-            FieldTable::default(),
-        )
-        .await?;
+    declare_queue(&channel, queue_name, cfg.rabbitmq_queue_durable).await?;
 
     // Bind queue to exchange with the queue name as routing key
     bind_queue(&channel, queue_name, queue_name).await?;
@@ -335,47 +918,98 @@ pub async fn start_completion_consumer(
         )
         .await?;
 
-    info!("Started completion consumer on queue: {}", queue_name);
-
-    let mut stream = Box::pin(consumer.take_until(cancel_token.cancelled()));
-
-    while let Some(delivery) = stream.next().await {
-        if let Ok(delivery) = delivery {
-            match serde_json::from_slice::<CompletionMessage>(&delivery.data) {
-                Ok(msg) => {
-                    if let Err(e) = state.execution_store.complete_execution(&msg).await {
-                        error!("Failed to complete execution: {}", e);
-                        let _ = delivery
-                            .nack(BasicNackOptions {
-                                requeue: false,
-                                ..BasicNackOptions::default()
-                            })
-                            .await;
-                    } else {
-                        let _ = state
-                            .tx
-                            .send(WorkerMessage::WorkflowCompletion(Box::new(msg)));
-                        let _ = delivery.ack(BasicAckOptions::default()).await;
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to deserialize completion message: {}", e);
-                    let _ = delivery
-                        .nack(BasicNackOptions { requeue: false, ..BasicNackOptions::default() })
-                        .await;
-                },
+    info!(
+        "Started completion consumer on queue: {} with prefetch: {} and concurrency: {}",
+        queue_name, tuning.prefetch, tuning.concurrency
+    );
+    reporter.running();
+
+    let processing = consumer
+        .take_until(cancel_token.cancelled())
+        .for_each_concurrent(Some(tuning.concurrency), |delivery| {
+            let state = state.clone();
+            let channel = &channel;
+            async move {
+                match delivery {
+                    Ok(delivery) => {
+                        process_completion_delivery(delivery, &state, channel, queue_name).await;
+                    },
+                    Err(e) => error!(queue = queue_name, error = %e, "Consumer delivery error"),
+                }
             }
-        }
+        });
+    drain_on_cancel(processing, &cancel_token, queue_name).await;
+    consumer_stopped(&cancel_token, queue_name)
+}
+
+async fn process_completion_delivery(
+    delivery: Delivery,
+    state: &AppState,
+    channel: &Channel,
+    queue_name: &str,
+) {
+    prometheus::message_consumed(queue_name);
+    match parse_worker_message::<CompletionMessage>(&delivery.data) {
+        Ok(msg) => match store_completion(state, msg).await {
+            Ok(_) => ack(&delivery, queue_name).await,
+            Err(e) => {
+                error!("Failed to complete execution: {}", e);
+                retry_or_dead_letter(channel, queue_name, &delivery, &e).await;
+            },
+        },
+        Err(e) => {
+            error!("Failed to deserialize completion message: {}", e);
+            reject_unparsable(channel, &delivery, queue_name, &e).await;
+        },
     }
-    Ok(())
+}
+
+/// Record a completion and hand it to live clients; `false`, and not
+/// broadcast, when the execution had already recorded it, since a
+/// redelivered completion was broadcast the first time.
+pub async fn store_completion(state: &AppState, msg: CompletionMessage) -> StoreResult<bool> {
+    let applied = state.execution_store.complete_execution(&msg).await?;
+    if applied {
+        let _ = state
+            .tx
+            .send(WorkerMessage::WorkflowCompletion(Box::new(msg)));
+    }
+    Ok(applied)
 }
 
 #[cfg(test)]
 #[allow(clippy::expect_used, clippy::indexing_slicing)]
 mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use lapin::{
+        BasicProperties,
+        types::{AMQPValue, FieldTable},
+    };
     use serde_json::json;
+    use tokio_util::sync::CancellationToken;
 
-    use super::expand_tokens_from_payload;
+    use super::{
+        MESSAGE_SCHEMA_VERSION,
+        PARSE_ERROR_HEADER,
+        SOURCE_QUEUE_HEADER,
+        drain_on_cancel,
+        expand_tokens_from_payload,
+        migrate,
+        parse_worker_message,
+        retry_count,
+        retry_properties,
+        retry_queue_name,
+        with_parse_error,
+        with_retry_count,
+    };
+    use crate::domain::models::{CompletionMessage, NodeExecutionMessage, NodeStatusMessage};
 
     #[test]
     fn expands_single_id_payload() {
@@ -406,4 +1040,164 @@ mod tests {
             .expect("multi-id token payload should parse");
         assert_eq!(tokens.len(), 4);
     }
+
+    #[test]
+    fn retry_count_increments_and_keeps_other_headers() {
+        let mut headers = FieldTable::default();
+        headers.insert("traceparent".into(), AMQPValue::LongString("00-abc-def-01".into()));
+        let fresh = BasicProperties::default().with_headers(headers);
+        assert_eq!(retry_count(&fresh), 0);
+        assert_eq!(retry_count(&BasicProperties::default()), 0);
+
+        let retried = with_retry_count(&with_retry_count(&fresh, 1), 2);
+        assert_eq!(retry_count(&retried), 2);
+        assert!(
+            retried
+                .headers()
+                .as_ref()
+                .is_some_and(|h| h.contains_key("traceparent"))
+        );
+    }
+
+    #[test]
+    fn retries_wait_on_the_retry_queue_for_longer_each_attempt() {
+        let first = retry_properties(&BasicProperties::default(), 1, 1000);
+        assert_eq!(retry_count(&first), 1);
+        assert_eq!(
+            first
+                .expiration()
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("1000")
+        );
+
+        let third = retry_properties(&first, 3, 1000);
+        assert_eq!(retry_count(&third), 3);
+        assert_eq!(
+            third
+                .expiration()
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("3000")
+        );
+        assert_eq!(retry_queue_name("workflow.node.status"), "workflow.node.status.retry");
+    }
+
+    #[tokio::test]
+    async fn cancellation_waits_for_in_flight_processing() {
+        let _ = crate::config::Config::init();
+        let cancel_token = CancellationToken::new();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let in_flight = {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                finished.store(true, Ordering::SeqCst);
+            }
+        };
+        cancel_token.cancel();
+        drain_on_cancel(in_flight, &cancel_token, "workflow.node.status").await;
+
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unparsable_messages_carry_the_error_and_source_queue() {
+        let mut headers = FieldTable::default();
+        headers.insert("traceparent".into(), AMQPValue::LongString("00-abc-def-01".into()));
+        let properties = BasicProperties::default().with_headers(headers);
+
+        let error = serde_json::from_slice::<serde_json::Value>(b"{not json")
+            .expect_err("payload should not parse")
+            .to_string();
+        let tagged = with_parse_error(&properties, "workflow.node.status", &error);
+        let headers = tagged.headers().clone().expect("headers");
+        let header = |name: &str| match headers.inner().get(name) {
+            Some(AMQPValue::LongString(value)) => Some(value.to_string()),
+            _ => None,
+        };
+
+        assert_eq!(header(PARSE_ERROR_HEADER), Some(error));
+        assert_eq!(header(SOURCE_QUEUE_HEADER).as_deref(), Some("workflow.node.status"));
+        assert_eq!(header("traceparent").as_deref(), Some("00-abc-def-01"));
+    }
+
+    #[test]
+    fn v1_worker_messages_parse_into_the_current_types() {
+        let status = json!({
+            "workflow_id": "wf-1",
+            "execution_id": "exec-1",
+            "node_id": "node-1",
+            "node_name": "Node",
+            "status": "success",
+            "executed_at": "2026-01-01T00:00:00Z",
+            "duration_ms": 5,
+        });
+        let versioned = |mut message: serde_json::Value, version: serde_json::Value| {
+            message["schema_version"] = version;
+            message.to_string().into_bytes()
+        };
+
+        // Unversioned messages predate versioning and read as v1
+        let unversioned: NodeStatusMessage =
+            parse_worker_message(status.to_string().as_bytes()).expect("unversioned status");
+        let v1: NodeStatusMessage =
+            parse_worker_message(&versioned(status.clone(), json!(1))).expect("v1 status");
+        assert_eq!(unversioned, v1);
+        assert_eq!(v1.seq, None);
+
+        let completion = json!({
+            "schema_version": 1,
+            "workflow_id": "wf-1",
+            "execution_id": "exec-1",
+            "status": "completed",
+            "final_context": {},
+            "completed_at": "2026-01-01T00:00:00Z",
+            "total_duration_ms": 10,
+        });
+        let completion: CompletionMessage =
+            parse_worker_message(completion.to_string().as_bytes()).expect("v1 completion");
+        assert_eq!(completion.failure_reason, None);
+
+        let execution = json!({
+            "schema_version": 1,
+            "workflow_id": "wf-1",
+            "workflow_version": 1,
+            "workflow_version_id": 1,
+            "execution_id": "exec-1",
+            "current_node": "node-1",
+            "workflow_definition": {},
+            "accumulated_context": {},
+        });
+        let _: NodeExecutionMessage =
+            parse_worker_message(execution.to_string().as_bytes()).expect("v1 execution");
+
+        // Other or malformed versions are refused so they are set aside
+        for version in [json!(0), json!(MESSAGE_SCHEMA_VERSION + 1)] {
+            let err =
+                parse_worker_message::<NodeStatusMessage>(&versioned(status.clone(), version))
+                    .expect_err("other schema versions should be refused");
+            assert!(err.contains("unsupported schema_version"), "{err}");
+        }
+        assert!(
+            parse_worker_message::<NodeStatusMessage>(&versioned(status, json!("v1"))).is_err()
+        );
+    }
+
+    #[test]
+    fn v1_messages_migrate_unchanged() {
+        let message = json!({
+            "schema_version": 1,
+            "workflow_id": "wf-1",
+            "execution_id": "exec-1",
+            "node_id": "node-1",
+            "status": "success",
+        });
+
+        assert_eq!(migrate(1, message.clone()), Ok(message.clone()));
+        assert!(migrate(MESSAGE_SCHEMA_VERSION + 1, message).is_err());
+    }
 }
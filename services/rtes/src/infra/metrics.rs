@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use opentelemetry::{
+    global,
+    metrics::{Histogram, Meter},
+};
+use tracing::warn;
+
+/// MongoDB rejects documents above 16 MiB.
+const MONGO_MAX_DOCUMENT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Records the BSON size of execution documents on every Nth write so
+/// operators can alert on document bloat before MongoDB's hard limit is hit.
+#[derive(Debug)]
+pub struct DocumentSizeSampler {
+    every:     u64,
+    writes:    AtomicU64,
+    histogram: Histogram<u64>,
+}
+
+impl DocumentSizeSampler {
+    /// Sample every `every` writes using the global meter; 0 disables
+    /// sampling.
+    pub fn new(every: u64) -> Self {
+        Self::with_meter(every, &global::meter("rtes"))
+    }
+
+    pub fn with_meter(every: u64, meter: &Meter) -> Self {
+        let histogram = meter
+            .u64_histogram("rtes.execution_document.size")
+            .with_unit("By")
+            .with_description("BSON size of execution documents, sampled on writes")
+            .build();
+        Self { every, writes: AtomicU64::new(0), histogram }
+    }
+
+    /// Count a write and report whether its document size should be sampled.
+    pub fn should_sample(&self) -> bool {
+        if self.every == 0 {
+            return false;
+        }
+        let writes = self.writes.fetch_add(1, Ordering::Relaxed) + 1;
+        writes.is_multiple_of(self.every)
+    }
+
+    pub fn record(&self, execution_id: &str, size_bytes: u64) {
+        self.histogram.record(size_bytes, &[]);
+        if size_bytes >= MONGO_MAX_DOCUMENT_BYTES / 4 * 3 {
+            warn!(
+                execution_id = %execution_id,
+                size_bytes,
+                limit_bytes = MONGO_MAX_DOCUMENT_BYTES,
+                "Execution document is approaching the MongoDB document size limit"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{
+        InMemoryMetricExporter,
+        PeriodicReader,
+        SdkMeterProvider,
+        data::{AggregatedMetrics, MetricData, ResourceMetrics, ScopeMetrics},
+    };
+
+    use super::DocumentSizeSampler;
+
+    #[test]
+    fn records_size_only_when_sampling_triggers() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let sampler = DocumentSizeSampler::with_meter(3, &provider.meter("test"));
+
+        let mut recorded = 0;
+        for _ in 0..7 {
+            if sampler.should_sample() {
+                sampler.record("exec-1", 1024);
+                recorded += 1;
+            }
+        }
+        assert_eq!(recorded, 2);
+
+        provider.force_flush().expect("metrics should flush");
+        let metrics = exporter
+            .get_finished_metrics()
+            .expect("exported metrics should be readable");
+        let histogram = metrics
+            .iter()
+            .flat_map(ResourceMetrics::scope_metrics)
+            .flat_map(ScopeMetrics::metrics)
+            .find(|m| m.name() == "rtes.execution_document.size")
+            .expect("size histogram should be exported");
+        let AggregatedMetrics::U64(MetricData::Histogram(data)) = histogram.data() else {
+            unreachable!("size metric should be a u64 histogram");
+        };
+        let point = data
+            .data_points()
+            .next()
+            .expect("histogram should have a data point");
+        assert_eq!(point.count(), 2);
+        assert_eq!(point.sum(), 2048);
+    }
+
+    #[test]
+    fn zero_disables_sampling() {
+        let sampler = DocumentSizeSampler::new(0);
+        assert!((0..10).all(|_| !sampler.should_sample()));
+    }
+}
@@ -1,4 +1,11 @@
+pub mod consumer_health;
+pub mod dlq;
 pub mod execution_store;
+pub mod memory_token_store;
 pub mod messaging;
+pub mod metrics;
+pub mod prometheus;
+pub mod queue_depth;
+pub mod redis_connection;
 pub mod telemetry;
 pub mod token_store;
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, OnceLock, PoisonError},
+    time::Duration,
+};
+
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use tracing::warn;
+
+const MESSAGES_CONSUMED: &str = "rtes_messages_consumed_total";
+const MESSAGES_ACKED: &str = "rtes_messages_acked_total";
+const MESSAGES_NACKED: &str = "rtes_messages_nacked_total";
+const STATUS_WRITE_SECONDS: &str = "rtes_node_status_write_seconds";
+const WEBSOCKET_CONNECTIONS: &str = "rtes_websocket_connections";
+const AUTH_FAILURES: &str = "rtes_auth_failures_total";
+const MESSAGES_UNACKED: &str = "rtes_messages_unacked";
+const QUEUE_MESSAGES: &str = "rtes_queue_messages";
+
+/// Status writes are a single MongoDB round trip (or one `bulk_write` per
+/// batch), so the buckets concentrate below a second.
+const STATUS_WRITE_BUCKETS: [f64; 11] =
+    [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Deliveries taken off each queue and not yet acked or nacked, kept beside
+/// the gauge exporting them so `GET /queues` can read them back.
+static UNACKED: LazyLock<Mutex<HashMap<String, u64>>> = LazyLock::new(Mutex::default);
+
+/// Install the process-wide Prometheus recorder on first use and return the
+/// handle `GET /metrics` renders. Call it at startup: anything recorded
+/// before the recorder exists is dropped.
+pub fn handle() -> &'static PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        // Only fails for an empty bucket list
+        let recorder = PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Full(STATUS_WRITE_SECONDS.to_string()),
+                &STATUS_WRITE_BUCKETS,
+            )
+            .unwrap_or_else(|_| PrometheusBuilder::new())
+            .build_recorder();
+        let handle = recorder.handle();
+        if metrics::set_global_recorder(recorder).is_err() {
+            warn!("A metrics recorder is already installed; /metrics will stay empty");
+        }
+        handle
+    })
+}
+
+/// Prometheus text exposition of everything recorded so far.
+pub fn render() -> String {
+    let handle = handle();
+    handle.run_upkeep();
+    handle.render()
+}
+
+pub fn message_consumed(queue: &str) {
+    counter!(MESSAGES_CONSUMED, "queue" => queue.to_owned()).increment(1);
+    track_unacked(queue, |unacked| unacked.saturating_add(1));
+}
+
+pub fn message_acked(queue: &str) {
+    counter!(MESSAGES_ACKED, "queue" => queue.to_owned()).increment(1);
+    track_unacked(queue, |unacked| unacked.saturating_sub(1));
+}
+
+/// Counts every nack, whether it requeues the message or dead-letters it.
+pub fn message_nacked(queue: &str) {
+    counter!(MESSAGES_NACKED, "queue" => queue.to_owned()).increment(1);
+    track_unacked(queue, |unacked| unacked.saturating_sub(1));
+}
+
+fn track_unacked(queue: &str, update: impl FnOnce(u64) -> u64) {
+    let mut unacked = UNACKED.lock().unwrap_or_else(PoisonError::into_inner);
+    let entry = unacked.entry(queue.to_owned()).or_default();
+    *entry = update(*entry);
+    let count = *entry;
+    drop(unacked);
+    #[allow(clippy::cast_precision_loss)]
+    gauge!(MESSAGES_UNACKED, "queue" => queue.to_owned()).set(count as f64);
+}
+
+/// Deliveries of `queue` this instance holds without having acked or nacked
+/// them yet.
+pub fn unacked(queue: &str) -> u64 {
+    UNACKED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(queue)
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Messages waiting in `queue` as of the last poll; `state` is `ready` for
+/// the queue itself and `dlq` for its dead-letter queue.
+pub fn queue_messages(queue: &str, state: &'static str, count: u32) {
+    gauge!(QUEUE_MESSAGES, "queue" => queue.to_owned(), "state" => state).set(f64::from(count));
+}
+
+pub fn status_write(elapsed: Duration) {
+    histogram!(STATUS_WRITE_SECONDS).record(elapsed);
+}
+
+/// Counts a refused JWT or a caller holding no matching grant.
+pub fn auth_failure(reason: &'static str) {
+    counter!(AUTH_FAILURES, "reason" => reason).increment(1);
+}
+
+/// Counts an open `/rt` connection until dropped.
+#[derive(Debug)]
+pub struct WebSocketConnection(());
+
+impl WebSocketConnection {
+    pub fn open() -> Self {
+        gauge!(WEBSOCKET_CONNECTIONS).increment(1);
+        Self(())
+    }
+}
+
+impl Drop for WebSocketConnection {
+    fn drop(&mut self) {
+        gauge!(WEBSOCKET_CONNECTIONS).decrement(1);
+    }
+}
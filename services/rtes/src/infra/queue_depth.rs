@@ -0,0 +1,123 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use lapin::{Connection, ConnectionProperties, options::QueueDeclareOptions, types::FieldTable};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::infra::{messaging::dlq_name, prometheus};
+
+/// Backlog of one consumed queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueDepth {
+    /// Messages waiting in the queue
+    pub ready:   u32,
+    /// Deliveries this instance holds without having acked or nacked them
+    pub unacked: u64,
+    /// Messages in the queue's `.dlq`; `None` unless `RABBITMQ_ENABLE_DLQ`
+    /// is set
+    pub dlq:     Option<u32>,
+}
+
+/// Depth of every consumed queue as of the last [`poll`], so `GET /queues`
+/// shows whether the consumers keep up.
+#[derive(Clone, Debug, Default)]
+pub struct QueueDepths {
+    depths: Arc<Mutex<BTreeMap<String, QueueDepth>>>,
+}
+
+impl QueueDepths {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, QueueDepth>> {
+        self.depths.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Last polled depth of every queue, ordered by name.
+    pub fn snapshot(&self) -> BTreeMap<String, QueueDepth> {
+        self.lock().clone()
+    }
+
+    /// Record a freshly polled depth and export it to `/metrics`.
+    pub fn record(&self, queue: &str, depth: QueueDepth) {
+        prometheus::queue_messages(queue, "ready", depth.ready);
+        if let Some(dlq) = depth.dlq {
+            prometheus::queue_messages(queue, "dlq", dlq);
+        }
+        self.lock().insert(queue.to_owned(), depth);
+    }
+}
+
+/// Poll the depth of `queues` (and their DLQs when `with_dlq` is set) every
+/// `interval` until `cancel_token` fires.
+///
+/// The connection is opened on the first poll and reopened once it drops; a
+/// failed poll leaves the last depths in place.
+pub async fn poll(
+    amqp_url: String,
+    queues: Vec<String>,
+    with_dlq: bool,
+    interval: Duration,
+    depths: QueueDepths,
+    cancel_token: CancellationToken,
+) {
+    let mut connection: Option<Connection> = None;
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    info!(interval = ?interval, "Polling RabbitMQ queue depths");
+    loop {
+        tokio::select! {
+            () = cancel_token.cancelled() => return,
+            _ = ticks.tick() => {},
+        }
+        if !connection
+            .as_ref()
+            .is_some_and(|conn| conn.status().connected())
+        {
+            match Connection::connect(&amqp_url, ConnectionProperties::default()).await {
+                Ok(conn) => connection = Some(conn),
+                Err(e) => {
+                    warn!(error = %e, "Queue depth poll could not connect to RabbitMQ");
+                    continue;
+                },
+            }
+        }
+        let Some(conn) = &connection else {
+            continue;
+        };
+        for queue in &queues {
+            match depth(conn, queue, with_dlq).await {
+                Ok(depth) => depths.record(queue, depth),
+                Err(e) => warn!(queue = %queue, error = %e, "Queue depth poll failed"),
+            }
+        }
+    }
+}
+
+async fn depth(conn: &Connection, queue: &str, with_dlq: bool) -> Result<QueueDepth, lapin::Error> {
+    let ready = message_count(conn, queue).await?;
+    let dlq = if with_dlq {
+        Some(message_count(conn, &dlq_name(queue)).await?)
+    } else {
+        None
+    };
+    Ok(QueueDepth { ready, unacked: prometheus::unacked(queue), dlq })
+}
+
+/// Messages ready in `queue`, read with a passive declare so a missing queue
+/// is an error instead of being created.
+async fn message_count(conn: &Connection, queue: &str) -> Result<u32, lapin::Error> {
+    // A failed passive declare closes its channel, so each gets its own
+    let channel = conn.create_channel().await?;
+    let declared = channel
+        .queue_declare(
+            queue,
+            QueueDeclareOptions { passive: true, ..QueueDeclareOptions::default() },
+            FieldTable::default(),
+        )
+        .await?;
+    let _ = channel.close(200, "OK").await;
+    Ok(declared.message_count())
+}
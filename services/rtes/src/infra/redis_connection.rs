@@ -0,0 +1,272 @@
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use redis::{
+    AsyncConnectionConfig,
+    Client as RedisClient,
+    Cmd,
+    Pipeline,
+    RedisFuture,
+    RedisResult,
+    Value,
+    aio::{ConnectionLike, MultiplexedConnection},
+};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::util::retry::{RetryPolicy, with_backoff};
+
+/// How the token store opens its Redis connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedisConnectionConfig {
+    /// Longest a single connection attempt may take
+    pub connect_timeout:    Duration,
+    /// Connection attempts before a caller gets the error; at least one is
+    /// always made
+    pub reconnect_attempts: u32,
+    /// Backoff ceiling before the second attempt, doubled for each one
+    /// after; each wait is a random fraction of it
+    pub reconnect_backoff:  Duration,
+}
+
+impl Default for RedisConnectionConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout:    Duration::from_secs(5),
+            reconnect_attempts: 3,
+            reconnect_backoff:  Duration::from_millis(100),
+        }
+    }
+}
+
+/// Longest wait between two connection attempts.
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Open a multiplexed connection, retrying per `config`.
+pub(crate) async fn connect(
+    client: &RedisClient,
+    config: RedisConnectionConfig,
+) -> RedisResult<MultiplexedConnection> {
+    let options = AsyncConnectionConfig::new().set_connection_timeout(Some(config.connect_timeout));
+    let policy = RetryPolicy {
+        base_delay:   config.reconnect_backoff,
+        max_attempts: config.reconnect_attempts,
+        max_backoff:  MAX_RECONNECT_BACKOFF,
+    };
+    let conn = with_backoff(&policy, "redis_connect", || {
+        client.get_multiplexed_async_connection_with_config(&options)
+    })
+    .await?;
+    info!("Opened Redis connection");
+    Ok(conn)
+}
+
+/// One connection shared by every clone of the handle: opened on first use
+/// and kept until [`SharedConnection::discard`] drops it, so the next caller
+/// reconnects.
+#[derive(Debug)]
+pub(crate) struct SharedConnection<C> {
+    /// The open connection and its generation (the `opened` count it got)
+    slot:   Arc<Mutex<Option<(C, u64)>>>,
+    opened: Arc<AtomicU64>,
+}
+
+impl<C> Clone for SharedConnection<C> {
+    fn clone(&self) -> Self {
+        Self { slot: self.slot.clone(), opened: self.opened.clone() }
+    }
+}
+
+impl<C> Default for SharedConnection<C> {
+    fn default() -> Self {
+        Self { slot: Arc::new(Mutex::new(None)), opened: Arc::default() }
+    }
+}
+
+impl<C: Clone> SharedConnection<C> {
+    /// The cached connection and its generation, opened with `connect` when
+    /// there is none. Concurrent callers wait for a single connect instead
+    /// of each opening their own.
+    pub(crate) async fn get<F, Fut, E>(&self, connect: F) -> Result<(C, u64), E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<C, E>>,
+    {
+        let mut slot = self.slot.lock().await;
+        if let Some(cached) = slot.as_ref() {
+            return Ok(cached.clone());
+        }
+        let conn = connect().await?;
+        let generation = self.opened.fetch_add(1, Ordering::Relaxed) + 1;
+        *slot = Some((conn.clone(), generation));
+        drop(slot);
+        Ok((conn, generation))
+    }
+
+    /// Drop the cached connection of `generation` after it broke. A newer
+    /// connection opened meanwhile is kept.
+    pub(crate) async fn discard(&self, generation: u64) {
+        let mut slot = self.slot.lock().await;
+        if slot
+            .as_ref()
+            .is_some_and(|(_, cached)| *cached == generation)
+        {
+            *slot = None;
+        }
+    }
+
+    /// Connections opened so far.
+    pub(crate) fn opened(&self) -> u64 {
+        self.opened.load(Ordering::Relaxed)
+    }
+}
+
+/// The Redis connection of a store, shared by all its clones and reopened
+/// (per [`RedisConnectionConfig`]) once a command finds it broken.
+#[derive(Clone)]
+pub(crate) struct RedisConnections {
+    client: RedisClient,
+    config: RedisConnectionConfig,
+    shared: SharedConnection<MultiplexedConnection>,
+}
+
+impl RedisConnections {
+    pub(crate) fn new(client: RedisClient, config: RedisConnectionConfig) -> Self {
+        Self { client, config, shared: SharedConnection::default() }
+    }
+
+    /// The shared connection, opening it if needed.
+    pub(crate) async fn get(&self) -> RedisResult<PooledConnection> {
+        let (inner, generation) = self
+            .shared
+            .get(|| connect(&self.client, self.config))
+            .await?;
+        Ok(PooledConnection { inner, generation, shared: self.shared.clone() })
+    }
+
+    /// Connections opened so far.
+    pub(crate) fn opened(&self) -> u64 {
+        self.shared.opened()
+    }
+}
+
+/// A handle on the shared connection that drops it from the store when a
+/// command fails in a way only a new connection can fix.
+pub(crate) struct PooledConnection {
+    inner:      MultiplexedConnection,
+    generation: u64,
+    shared:     SharedConnection<MultiplexedConnection>,
+}
+
+impl PooledConnection {
+    async fn check<T>(&self, result: RedisResult<T>) -> RedisResult<T> {
+        if let Err(e) = &result
+            && e.is_unrecoverable_error()
+        {
+            warn!(error = %e, "Redis connection broke; reconnecting on next use");
+            self.shared.discard(self.generation).await;
+        }
+        result
+    }
+}
+
+impl ConnectionLike for PooledConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let result = self.inner.req_packed_command(cmd).await;
+            self.check(result).await
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let result = self
+                .inner
+                .req_packed_commands(pipeline, offset, count)
+                .await;
+            self.check(result).await
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::{RedisConnectionConfig, SharedConnection, connect};
+
+    #[tokio::test]
+    async fn repeated_calls_share_one_connection() {
+        let shared = SharedConnection::<u64>::default();
+        let connects = Arc::new(AtomicU64::new(0));
+
+        let calls = (0..200).map(|_| {
+            let shared = shared.clone();
+            let connects = connects.clone();
+            tokio::spawn(async move {
+                shared
+                    .get(|| async move { Ok::<_, ()>(connects.fetch_add(1, Ordering::SeqCst)) })
+                    .await
+            })
+        });
+        for call in futures::future::join_all(calls).await {
+            assert_eq!(call.expect("task should finish"), Ok((0, 1)));
+        }
+
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+        assert_eq!(shared.opened(), 1);
+    }
+
+    #[tokio::test]
+    async fn discarded_or_failed_connections_are_reopened() {
+        let shared = SharedConnection::<&str>::default();
+
+        assert_eq!(shared.get(|| async { Err("refused") }).await, Err("refused"));
+        assert_eq!(shared.opened(), 0);
+        assert_eq!(shared.get(|| async { Ok::<_, &str>("first") }).await, Ok(("first", 1)));
+
+        shared.discard(1).await;
+        assert_eq!(shared.get(|| async { Ok::<_, &str>("second") }).await, Ok(("second", 2)));
+        // A stale handle can't drop the connection that replaced its own
+        shared.discard(1).await;
+        assert_eq!(shared.get(|| async { Ok::<_, &str>("third") }).await, Ok(("second", 2)));
+        assert_eq!(shared.opened(), 2);
+    }
+
+    #[tokio::test]
+    async fn connect_gives_up_after_the_configured_attempts() {
+        // Nothing listens on port 1
+        let client = redis::Client::open("redis://127.0.0.1:1/").expect("redis URL should parse");
+        let config = RedisConnectionConfig {
+            connect_timeout:    Duration::from_secs(1),
+            reconnect_attempts: 2,
+            reconnect_backoff:  Duration::from_millis(1),
+        };
+
+        let started = Instant::now();
+        assert!(connect(&client, config).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
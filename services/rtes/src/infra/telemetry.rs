@@ -1,3 +1,5 @@
+use std::sync::OnceLock;
+
 use opentelemetry::{KeyValue, global, trace::TracerProvider};
 use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::{
@@ -7,7 +9,16 @@ use opentelemetry_sdk::{
     propagation::TraceContextPropagator,
     trace::{self as sdktrace},
 };
-use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{
+    EnvFilter,
+    Registry,
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+};
+
+/// Swaps the installed subscriber's filter; set by [`init_telemetry`].
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 fn otlp_base_endpoint(endpoint: &str) -> String {
     let mut e = endpoint.trim_end_matches('/').to_string();
@@ -27,36 +38,91 @@ fn otlp_endpoint(endpoint: &str, path: &str) -> String {
     format!("{}/{}", otlp_base_endpoint(endpoint), path.trim_start_matches('/'))
 }
 
+/// Install the global tracing subscriber filtered by `log_filter` (an
+/// `EnvFilter` directive, `info` if invalid), exporting over OTLP when given
+/// an endpoint.
+///
+/// Without one, or when an exporter cannot be built, logs only go to stdout
+/// and the service starts anyway.
 pub fn init_telemetry(
+    service_name: &'static str,
+    endpoint: Option<&str>,
+    log_filter: &str,
+) -> Option<sdktrace::SdkTracerProvider> {
+    let Some(endpoint) = endpoint else {
+        init_fmt(log_filter);
+        return None;
+    };
+    match init_otlp(service_name, endpoint, log_filter) {
+        Ok(tracer_provider) => Some(tracer_provider),
+        Err(e) => {
+            init_fmt(log_filter);
+            tracing::warn!("OpenTelemetry export disabled, exporter setup failed: {}", e);
+            None
+        },
+    }
+}
+
+/// Replace the log filter of the installed subscriber. An invalid directive
+/// leaves the current filter in place.
+pub fn set_log_filter(directive: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(directive).map_err(|e| e.to_string())?;
+    FILTER
+        .get()
+        .ok_or("Telemetry is not initialized")?
+        .reload(filter)
+        .map_err(|e| e.to_string())
+}
+
+/// Reloadable filter layer; the first subscriber built registers its handle.
+fn env_filter(directive: &str) -> reload::Layer<EnvFilter, Registry> {
+    let filter = EnvFilter::try_new(directive).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (layer, handle) = reload::Layer::new(filter);
+    let _ = FILTER.set(handle);
+    layer
+}
+
+fn init_fmt(log_filter: &str) {
+    Registry::default()
+        .with(env_filter(log_filter))
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+}
+
+fn init_otlp(
     service_name: &'static str,
     endpoint: &str,
+    log_filter: &str,
 ) -> Result<sdktrace::SdkTracerProvider, Box<dyn std::error::Error>> {
-    // 1. Set Propagator
-    global::set_text_map_propagator(TraceContextPropagator::new());
-
     let resource = Resource::builder()
         .with_attributes(vec![KeyValue::new("service.name", service_name)])
         .build();
-    // 2. Traces
+    // Build every exporter before touching global state, so a failure leaves
+    // nothing half-installed for the fallback subscriber
     let tracer_provider = init_tracer(endpoint, resource.clone())?;
+    let meter_provider = init_metrics(endpoint, resource.clone())?;
+    let logger_provider = init_logs(endpoint, resource)?;
+
+    // 1. Set Propagator
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    // 2. Traces
+    global::set_tracer_provider(tracer_provider.clone());
     let tracer = tracer_provider.tracer("rtes");
 
     // 3. Metrics
-    let meter_provider = init_metrics(endpoint, resource.clone())?;
     global::set_meter_provider(meter_provider);
 
     // 4. Logs
-    let logger_provider = init_logs(endpoint, resource)?;
     let log_layer =
         opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge::new(&logger_provider);
 
     // 5. Subscriber Registry
     let telemetry_layer = tracing_opentelemetry::layer().with_tracer(tracer);
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
     let fmt_layer = tracing_subscriber::fmt::layer();
 
     Registry::default()
-        .with(env_filter)
+        .with(env_filter(log_filter))
         .with(telemetry_layer)
         .with(log_layer)
         .with(fmt_layer)
@@ -79,7 +145,6 @@ fn init_tracer(
         .with_resource(resource)
         .build();
 
-    global::set_tracer_provider(provider.clone());
     Ok(provider)
 }
 
@@ -1,22 +1,48 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
-use redis::{AsyncCommands, Client as RedisClient, RedisResult};
-use tracing::info;
+use futures::TryStreamExt;
+use redis::{
+    AsyncCommands,
+    Client as RedisClient,
+    ErrorKind,
+    RedisError,
+    RedisResult,
+    RetryMethod,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 
 use crate::{
-    api::state::{StoreResult, TokenStorePort},
-    domain::models::ExecutionToken,
+    api::state::{StoreError, StoreResult, TokenStorePort},
+    domain::models::{ExecutionToken, TokenScope},
+    infra::redis_connection::{PooledConnection, RedisConnectionConfig, RedisConnections},
 };
 
+/// Key patterns of the sorted-set indexes swept by
+/// [`TokenStore::prune_expired`].
+const INDEX_PATTERNS: [&str; 3] = ["user_id_*", "execution_id_*", "workflow_id_*"];
+
 #[derive(Clone)]
 pub struct TokenStore {
-    client: RedisClient,
+    connections: RedisConnections,
 }
 
 impl TokenStore {
-    pub const fn new(client: RedisClient) -> Self {
-        Self { client }
+    pub fn new(client: RedisClient) -> Self {
+        Self::with_connection_config(client, RedisConnectionConfig::default())
+    }
+
+    /// Share one multiplexed connection across every call, opened lazily
+    /// per `config` and reopened after it breaks.
+    pub fn with_connection_config(client: RedisClient, config: RedisConnectionConfig) -> Self {
+        Self { connections: RedisConnections::new(client, config) }
+    }
+
+    /// Redis connections opened so far; stays at one while the connection
+    /// holds up.
+    pub fn connections_opened(&self) -> u64 {
+        self.connections.opened()
     }
 
     fn get_user_key(user_id: &str) -> String {
@@ -31,85 +57,137 @@ impl TokenStore {
         format!("workflow_id_{workflow_id}")
     }
 
+    fn get_jti_key(jti: &str) -> String {
+        format!("token_jti_{jti}")
+    }
+
+    /// Hash of a user's current jti per scope (see [`scope_field`]).
+    fn get_scope_key(user_id: &str) -> String {
+        format!("token_scope_{user_id}")
+    }
+
+    /// Store a grant. Indexes hold the token's `jti` as the sorted-set member
+    /// (scored by `exp`) and the token itself lives under its jti key, so
+    /// adding the same jti again updates the grant instead of duplicating it.
+    /// A new jti for a scope the user already holds replaces the earlier
+    /// grant, so re-issued tokens don't pile up in the indexes.
     pub(crate) async fn add_token(&self, token: &ExecutionToken) -> RedisResult<()> {
-        let mut conn = self.client.get_multiplexed_async_connection().await?;
-        let member = serde_json::to_string(token).map_err(|e| {
-            redis::RedisError::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        let mut conn = self.connections.get().await?;
+        let data = serde_json::to_string(token).map_err(|e| {
+            RedisError::from((ErrorKind::Client, "token serialization failed", e.to_string()))
         })?;
 
+        let jti_key = Self::get_jti_key(&token.jti);
+        let () = conn.set(&jti_key, &data).await?;
+        self.ensure_key_ttl(&mut conn, &jti_key, token.exp).await?;
+
         // Index by user_id
         let user_key = Self::get_user_key(&token.user_id);
-        let _: i64 = conn.zadd(&user_key, &member, token.exp).await?;
+        let _: i64 = conn.zadd(&user_key, &token.jti, token.exp).await?;
         self.ensure_key_ttl(&mut conn, &user_key, token.exp).await?;
 
         // Also index by execution_id if present (for WebSocket auth without JWT)
         if let Some(execution_id) = &token.execution_id {
             let exec_key = Self::get_execution_key(execution_id);
-            let _: i64 = conn.zadd(&exec_key, &member, token.exp).await?;
+            let _: i64 = conn.zadd(&exec_key, &token.jti, token.exp).await?;
             self.ensure_key_ttl(&mut conn, &exec_key, token.exp).await?;
         }
 
         // Also index by workflow_id for wildcard tokens (for HTTP history without JWT)
         if token.execution_id.is_none() {
             let wf_key = Self::get_workflow_key(&token.workflow_id);
-            let _: i64 = conn.zadd(&wf_key, &member, token.exp).await?;
+            let _: i64 = conn.zadd(&wf_key, &token.jti, token.exp).await?;
             self.ensure_key_ttl(&mut conn, &wf_key, token.exp).await?;
         }
 
+        let scope_key = Self::get_scope_key(&token.user_id);
+        let field = scope_field(token);
+        let previous: Option<String> = conn.hget(&scope_key, &field).await?;
+        let _: i64 = conn.hset(&scope_key, &field, &token.jti).await?;
+        self.ensure_key_ttl(&mut conn, &scope_key, token.exp)
+            .await?;
+        if let Some(previous) = previous.filter(|previous| *previous != token.jti) {
+            let _: i64 = conn.zrem(&user_key, &previous).await?;
+            let _: i64 = conn.zrem(index_key(token), &previous).await?;
+            let _: i64 = conn.del(Self::get_jti_key(&previous)).await?;
+            info!("Replaced execution token {} with {}", previous, token.jti);
+        }
+
         Ok(())
     }
 
+    /// Remove the token with this jti from every index it was added to.
+    /// Returns `false` when no such token is stored (unknown or expired).
+    pub(crate) async fn revoke_token(&self, jti: &str) -> RedisResult<bool> {
+        let mut conn = self.connections.get().await?;
+        let jti_key = Self::get_jti_key(jti);
+
+        let data: Option<String> = conn.get(&jti_key).await?;
+        let Some(data) = data else {
+            return Ok(false);
+        };
+
+        if let Ok(token) = serde_json::from_str::<ExecutionToken>(&data) {
+            let _: i64 = conn.zrem(Self::get_user_key(&token.user_id), jti).await?;
+            let _: i64 = conn.zrem(index_key(&token), jti).await?;
+
+            let scope_key = Self::get_scope_key(&token.user_id);
+            let field = scope_field(&token);
+            let current: Option<String> = conn.hget(&scope_key, &field).await?;
+            if current.as_deref() == Some(jti) {
+                let _: i64 = conn.hdel(&scope_key, &field).await?;
+            }
+        }
+
+        let _: i64 = conn.del(&jti_key).await?;
+        info!("Revoked execution token {}", jti);
+        Ok(true)
+    }
+
     pub(crate) async fn validate_access(
         &self,
         user_id: &str,
         target_execution_id: Option<&str>,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> RedisResult<bool> {
-        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let mut conn = self.connections.get().await?;
         let key = Self::get_user_key(user_id);
 
         self.remove_expired_tokens(&mut conn, &key).await?;
 
         let tokens = self.fetch_valid_tokens(&mut conn, &key).await?;
 
-        for token_str in tokens {
-            if let Ok(token) = serde_json::from_str::<ExecutionToken>(&token_str)
-                && self.check_token_permissions(&token, target_execution_id, target_workflow_id)
-            {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
+        Ok(tokens.iter().any(|token| {
+            self.check_token_permissions(
+                token,
+                target_execution_id,
+                target_workflow_id,
+                target_org_id,
+                required_scope,
+            )
+        }))
     }
 
+    /// Reclaim the members of `key` that have expired. Lookups already skip
+    /// them, so this only keeps the sorted sets from growing.
     async fn remove_expired_tokens(
         &self,
-        conn: &mut redis::aio::MultiplexedConnection,
+        conn: &mut PooledConnection,
         key: &str,
     ) -> RedisResult<()> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let now = i64::try_from(now).unwrap_or(i64::MAX);
-        let _: i64 = conn.zrembyscore(key, "-inf", now).await?;
+        let _: i64 = conn.zrembyscore(key, "-inf", now_secs()).await?;
         Ok(())
     }
 
     async fn ensure_key_ttl(
         &self,
-        conn: &mut redis::aio::MultiplexedConnection,
+        conn: &mut PooledConnection,
         key: &str,
         exp_epoch_secs: i64,
     ) -> RedisResult<()> {
-        let now_secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let now_secs = i64::try_from(now_secs).unwrap_or(i64::MAX);
-
-        let expire_in = exp_epoch_secs.saturating_sub(now_secs);
+        let expire_in = exp_epoch_secs.saturating_sub(now_secs());
         if expire_in <= 0 {
             return Ok(());
         }
@@ -121,13 +199,38 @@ impl TokenStore {
         Ok(())
     }
 
-    #[allow(dead_code)]
+    /// Unexpired tokens indexed under `key`, selected by score so members
+    /// the cleanup hasn't reclaimed yet are never read. Members are jtis
+    /// resolved through their jti keys; members written before jtis existed
+    /// are the token JSON itself and are still read as-is. Unreadable members
+    /// are skipped.
     async fn fetch_valid_tokens(
         &self,
-        conn: &mut redis::aio::MultiplexedConnection,
+        conn: &mut PooledConnection,
         key: &str,
-    ) -> RedisResult<Vec<String>> {
-        conn.zrange(key, 0, -1).await
+    ) -> RedisResult<Vec<ExecutionToken>> {
+        let members: Vec<String> = conn
+            .zrangebyscore(key, unexpired_min_score(now_secs()), "+inf")
+            .await?;
+        let (legacy, jtis): (Vec<_>, Vec<_>) = members
+            .into_iter()
+            .partition(|member| member.starts_with('{'));
+
+        let mut tokens: Vec<ExecutionToken> = legacy
+            .iter()
+            .filter_map(|member| serde_json::from_str(member).ok())
+            .collect();
+        if !jtis.is_empty() {
+            let jti_keys: Vec<String> = jtis.iter().map(|jti| Self::get_jti_key(jti)).collect();
+            let data: Vec<Option<String>> =
+                redis::cmd("MGET").arg(&jti_keys).query_async(conn).await?;
+            tokens.extend(
+                data.iter()
+                    .flatten()
+                    .filter_map(|token_str| serde_json::from_str(token_str).ok()),
+            );
+        }
+        Ok(tokens)
     }
 
     #[allow(clippy::unused_self)]
@@ -136,21 +239,16 @@ impl TokenStore {
         token: &ExecutionToken,
         target_execution_id: Option<&str>,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> bool {
-        if token.workflow_id != target_workflow_id {
-            return false;
-        }
-
-        info!(
-            "Token executionId: {}, Target executionId: {}",
-            token.execution_id.as_deref().unwrap_or("None"),
-            target_execution_id.unwrap_or("None")
-        );
-        match (target_execution_id, token.execution_id.as_deref()) {
-            (Some(req_eid), Some(tok_eid)) => *req_eid == *tok_eid,
-            (Some(_) | None, None) => true,
-            (None, Some(_)) => false,
-        }
+        check_token_permissions(
+            token,
+            target_execution_id,
+            target_workflow_id,
+            target_org_id,
+            required_scope,
+        )
     }
 
     /// Validate access for a specific execution (simpler version for WebSocket)
@@ -160,25 +258,27 @@ impl TokenStore {
         &self,
         user_id: &str,
         target_execution_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> RedisResult<bool> {
-        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let mut conn = self.connections.get().await?;
         let key = Self::get_user_key(user_id);
 
         self.remove_expired_tokens(&mut conn, &key).await?;
 
         let tokens = self.fetch_valid_tokens(&mut conn, &key).await?;
 
-        for token_str in tokens {
-            if let Ok(token) = serde_json::from_str::<ExecutionToken>(&token_str) {
-                // Match if: execution matches exactly, OR token has wildcard (None execution)
-                let matches = token
+        for token in tokens {
+            // Match if: execution matches exactly, OR token has wildcard (None execution)
+            let matches = token.scope.allows(required_scope)
+                && token.in_org(target_org_id)
+                && token
                     .execution_id
                     .as_deref()
                     .is_none_or(|tok_eid| tok_eid == target_execution_id);
-                if matches {
-                    info!("Access granted for user {} execution {}", user_id, target_execution_id);
-                    return Ok(true);
-                }
+            if matches {
+                info!("Access granted for user {} execution {}", user_id, target_execution_id);
+                return Ok(true);
             }
         }
 
@@ -195,24 +295,27 @@ impl TokenStore {
         &self,
         target_execution_id: &str,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> RedisResult<bool> {
-        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let mut conn = self.connections.get().await?;
         let key = Self::get_execution_key(target_execution_id);
 
         self.remove_expired_tokens(&mut conn, &key).await?;
 
         let tokens = self.fetch_valid_tokens(&mut conn, &key).await?;
 
-        for token_str in tokens {
-            if let Ok(token) = serde_json::from_str::<ExecutionToken>(&token_str) {
-                // Verify workflow_id matches
-                if token.workflow_id == target_workflow_id {
-                    info!(
-                        "Access granted for execution {} workflow {}",
-                        target_execution_id, target_workflow_id
-                    );
-                    return Ok(true);
-                }
+        for token in tokens {
+            // Verify workflow_id matches
+            if token.workflow_id == target_workflow_id
+                && token.in_org(target_org_id)
+                && token.scope.allows(required_scope)
+            {
+                info!(
+                    "Access granted for execution {} workflow {}",
+                    target_execution_id, target_workflow_id
+                );
+                return Ok(true);
             }
         }
 
@@ -228,19 +331,23 @@ impl TokenStore {
     pub(crate) async fn validate_workflow_access(
         &self,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
     ) -> RedisResult<bool> {
-        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let mut conn = self.connections.get().await?;
         let key = Self::get_workflow_key(target_workflow_id);
 
         self.remove_expired_tokens(&mut conn, &key).await?;
 
         let tokens = self.fetch_valid_tokens(&mut conn, &key).await?;
 
-        if !tokens.is_empty() {
+        let granted = tokens
+            .iter()
+            .filter(|token| token.in_org(target_org_id))
+            .count();
+        if granted > 0 {
             info!(
                 "Access granted for workflow {} - found {} valid token(s)",
-                target_workflow_id,
-                tokens.len()
+                target_workflow_id, granted
             );
             return Ok(true);
         }
@@ -248,14 +355,163 @@ impl TokenStore {
         info!("Access denied for workflow {} - no matching grant found", target_workflow_id);
         Ok(false)
     }
+
+    /// Remove expired members from every token index, walking the keys with
+    /// `SCAN` so Redis isn't blocked. Lookups only prune the key they read,
+    /// so this reclaims indexes nobody validates against any more. The
+    /// patterns aren't namespaced, so keys of another type, say another
+    /// service's in a shared Redis, are skipped rather than failing the
+    /// sweep. Returns the number of members removed.
+    pub async fn prune_expired(&self) -> RedisResult<usize> {
+        let mut conn = self.connections.get().await?;
+        let mut pruned = 0;
+        for pattern in INDEX_PATTERNS {
+            let keys: Vec<String> = conn.scan_match(pattern).await?.try_collect().await?;
+            for key in keys {
+                let key_type: String = redis::cmd("TYPE").arg(&key).query_async(&mut conn).await?;
+                if key_type != "zset" {
+                    debug!(key = %key, key_type = %key_type, "Not pruning a key that isn't a token index");
+                    continue;
+                }
+                let removed: usize = conn.zrembyscore(&key, "-inf", now_secs()).await?;
+                pruned += removed;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Periodically prune expired tokens until `cancel_token` fires.
+    pub fn spawn_pruner(&self, interval: Duration, cancel_token: CancellationToken) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    () = cancel_token.cancelled() => break,
+                    _ = ticker.tick() => match store.prune_expired().await {
+                        Ok(pruned) => info!(pruned, "Pruned expired tokens from Redis"),
+                        Err(e) => warn!(error = %e, "Failed to prune expired tokens"),
+                    },
+                }
+            }
+        });
+    }
+
+    pub(crate) async fn ping(&self) -> RedisResult<()> {
+        let mut conn = self.connections.get().await?;
+        let _: String = redis::cmd("PING").query_async(&mut conn).await?;
+        Ok(())
+    }
+
+    /// Unexpired tokens granted to a user, skipping members that fail to
+    /// deserialize.
+    pub(crate) async fn list_user_tokens(&self, user_id: &str) -> RedisResult<Vec<ExecutionToken>> {
+        let mut conn = self.connections.get().await?;
+        let key = Self::get_user_key(user_id);
+
+        self.remove_expired_tokens(&mut conn, &key).await?;
+
+        self.fetch_valid_tokens(&mut conn, &key).await
+    }
+}
+
+fn now_secs() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    i64::try_from(now).unwrap_or(i64::MAX)
+}
+
+/// Lowest sorted-set score (`exp`) still valid at `now`, exclusive: a token
+/// stops granting access once `exp` is reached.
+fn unexpired_min_score(now: i64) -> String {
+    format!("({now}")
+}
+
+/// Execution index (specific grants) or workflow index (wildcard grants)
+/// holding `token` besides its user's.
+fn index_key(token: &ExecutionToken) -> String {
+    token.execution_id.as_deref().map_or_else(
+        || TokenStore::get_workflow_key(&token.workflow_id),
+        TokenStore::get_execution_key,
+    )
+}
+
+/// Field naming a token's scope (workflow and execution, if any, grant
+/// scope and organization) in its user's scope hash. Tokens differing only
+/// in `iat`, `exp` or `jti` share it.
+fn scope_field(token: &ExecutionToken) -> String {
+    // Owner grants without an organization keep the field of grants stored
+    // before scopes and organizations existed, so their reissues still
+    // replace them
+    if token.scope == TokenScope::Owner && token.org_id.is_none() {
+        return serde_json::json!([token.workflow_id, token.execution_id]).to_string();
+    }
+    serde_json::json!([token.workflow_id, token.execution_id, token.scope, token.org_id])
+        .to_string()
+}
+
+/// Whether two tokens grant the same user the same scope, so the later one
+/// replaces the earlier; an owner and a viewer grant, or grants of two
+/// organizations, are kept side by side. Shared by every `TokenStorePort`
+/// backend.
+pub(crate) fn same_scope(a: &ExecutionToken, b: &ExecutionToken) -> bool {
+    a.user_id == b.user_id
+        && a.workflow_id == b.workflow_id
+        && a.execution_id == b.execution_id
+        && a.scope == b.scope
+        && a.org_id == b.org_id
+}
+
+/// Whether `token` grants at least `required_scope` on the target workflow
+/// and (optionally) execution of `target_org_id`. Shared by every
+/// `TokenStorePort` backend.
+pub(crate) fn check_token_permissions(
+    token: &ExecutionToken,
+    target_execution_id: Option<&str>,
+    target_workflow_id: &str,
+    target_org_id: Option<&str>,
+    required_scope: TokenScope,
+) -> bool {
+    if token.workflow_id != target_workflow_id
+        || !token.in_org(target_org_id)
+        || !token.scope.allows(required_scope)
+    {
+        return false;
+    }
+
+    info!(
+        "Token executionId: {}, Target executionId: {}",
+        token.execution_id.as_deref().unwrap_or("None"),
+        target_execution_id.unwrap_or("None")
+    );
+    match (target_execution_id, token.execution_id.as_deref()) {
+        (Some(req_eid), Some(tok_eid)) => *req_eid == *tok_eid,
+        (Some(_) | None, None) => true,
+        (None, Some(_)) => false,
+    }
+}
+
+/// Connection failures, timeouts and replies asking to wait (`LOADING`,
+/// `BUSY`, `TRYAGAIN`, ...) are transient; anything else is fatal.
+impl From<RedisError> for StoreError {
+    fn from(e: RedisError) -> Self {
+        if e.is_io_error()
+            || e.is_timeout()
+            || matches!(e.retry_method(), RetryMethod::WaitAndRetry)
+        {
+            Self::Transient(Box::new(e))
+        } else {
+            Self::Fatal(Box::new(e))
+        }
+    }
 }
 
 #[async_trait]
 impl TokenStorePort for TokenStore {
     async fn add_token(&self, token: &ExecutionToken) -> StoreResult<()> {
-        Self::add_token(self, token)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+        Self::add_token(self, token).await.map_err(StoreError::from)
     }
 
     async fn validate_access(
@@ -263,44 +519,92 @@ impl TokenStorePort for TokenStore {
         user_id: &str,
         target_execution_id: Option<&str>,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool> {
-        Self::validate_access(self, user_id, target_execution_id, target_workflow_id)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+        Self::validate_access(
+            self,
+            user_id,
+            target_execution_id,
+            target_workflow_id,
+            target_org_id,
+            required_scope,
+        )
+        .await
+        .map_err(StoreError::from)
     }
 
     async fn validate_access_for_execution(
         &self,
         user_id: &str,
         target_execution_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool> {
-        Self::validate_access_for_execution(self, user_id, target_execution_id)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+        Self::validate_access_for_execution(
+            self,
+            user_id,
+            target_execution_id,
+            target_org_id,
+            required_scope,
+        )
+        .await
+        .map_err(StoreError::from)
     }
 
     async fn validate_execution_access(
         &self,
         target_execution_id: &str,
         target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool> {
-        Self::validate_execution_access(self, target_execution_id, target_workflow_id)
+        Self::validate_execution_access(
+            self,
+            target_execution_id,
+            target_workflow_id,
+            target_org_id,
+            required_scope,
+        )
+        .await
+        .map_err(StoreError::from)
+    }
+
+    async fn validate_workflow_access(
+        &self,
+        target_workflow_id: &str,
+        target_org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        Self::validate_workflow_access(self, target_workflow_id, target_org_id)
+            .await
+            .map_err(StoreError::from)
+    }
+
+    async fn list_user_tokens(&self, user_id: &str) -> StoreResult<Vec<ExecutionToken>> {
+        Self::list_user_tokens(self, user_id)
             .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+            .map_err(StoreError::from)
     }
 
-    async fn validate_workflow_access(&self, target_workflow_id: &str) -> StoreResult<bool> {
-        Self::validate_workflow_access(self, target_workflow_id)
+    async fn revoke_token(&self, jti: &str) -> StoreResult<bool> {
+        Self::revoke_token(self, jti)
             .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+            .map_err(StoreError::from)
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        Self::ping(self).await.map_err(StoreError::from)
     }
 }
 
 #[cfg(test)]
 #[allow(clippy::expect_used)]
 mod tests {
-    use super::TokenStore;
-    use crate::domain::models::ExecutionToken;
+    use super::{TokenStore, same_scope, scope_field, unexpired_min_score};
+    use crate::{
+        api::state::StoreError,
+        domain::models::{ExecutionToken, TokenScope},
+    };
 
     fn make_store() -> TokenStore {
         let client =
@@ -315,6 +619,9 @@ mod tests {
             iat:          1,
             exp:          2,
             user_id:      "user-1".to_string(),
+            jti:          "jti-1".to_string(),
+            scope:        TokenScope::Owner,
+            org_id:       None,
         }
     }
 
@@ -322,24 +629,147 @@ mod tests {
     fn specific_execution_token_matches_exact_execution_and_workflow() {
         let store = make_store();
         let granted = token("wf-1", Some("exec-1"));
-        assert!(store.check_token_permissions(&granted, Some("exec-1"), "wf-1"));
-        assert!(!store.check_token_permissions(&granted, Some("exec-2"), "wf-1"));
-        assert!(!store.check_token_permissions(&granted, Some("exec-1"), "wf-2"));
+        assert!(store.check_token_permissions(
+            &granted,
+            Some("exec-1"),
+            "wf-1",
+            None,
+            TokenScope::Owner
+        ));
+        assert!(!store.check_token_permissions(
+            &granted,
+            Some("exec-2"),
+            "wf-1",
+            None,
+            TokenScope::Owner
+        ));
+        assert!(!store.check_token_permissions(
+            &granted,
+            Some("exec-1"),
+            "wf-2",
+            None,
+            TokenScope::Owner
+        ));
     }
 
     #[test]
     fn wildcard_execution_token_matches_any_execution_in_workflow() {
         let store = make_store();
         let granted = token("wf-1", None);
-        assert!(store.check_token_permissions(&granted, Some("exec-99"), "wf-1"));
-        assert!(store.check_token_permissions(&granted, None, "wf-1"));
-        assert!(!store.check_token_permissions(&granted, Some("exec-99"), "wf-2"));
+        assert!(store.check_token_permissions(
+            &granted,
+            Some("exec-99"),
+            "wf-1",
+            None,
+            TokenScope::Owner
+        ));
+        assert!(store.check_token_permissions(&granted, None, "wf-1", None, TokenScope::Owner));
+        assert!(!store.check_token_permissions(
+            &granted,
+            Some("exec-99"),
+            "wf-2",
+            None,
+            TokenScope::Owner
+        ));
     }
 
     #[test]
     fn specific_execution_token_does_not_match_workflow_listing() {
         let store = make_store();
         let granted = token("wf-1", Some("exec-1"));
-        assert!(!store.check_token_permissions(&granted, None, "wf-1"));
+        assert!(!store.check_token_permissions(&granted, None, "wf-1", None, TokenScope::Owner));
+    }
+
+    #[test]
+    fn reissued_tokens_share_a_scope_regardless_of_iat_and_jti() {
+        let first = token("wf-1", Some("exec-1"));
+        let reissued =
+            ExecutionToken { iat: 10, exp: 20, jti: "jti-2".to_string(), ..first.clone() };
+        assert!(same_scope(&first, &reissued));
+        assert_eq!(scope_field(&first), scope_field(&reissued));
+
+        let wildcard = token("wf-1", None);
+        assert!(!same_scope(&first, &wildcard));
+        assert_ne!(scope_field(&first), scope_field(&wildcard));
+        // Ids containing the separator of a naive key can't collide
+        assert_ne!(scope_field(&token("a_b", Some("c"))), scope_field(&token("a", Some("b_c"))));
+        // Owner grants without an organization keep their pre-scope field
+        assert_eq!(scope_field(&first), r#"["wf-1","exec-1"]"#);
+    }
+
+    #[test]
+    fn grants_of_another_scope_or_organization_are_kept_apart() {
+        let owner = token("wf-1", Some("exec-1"));
+        let viewer =
+            ExecutionToken { scope: TokenScope::Viewer, jti: "jti-2".to_string(), ..owner.clone() };
+        let other_org = ExecutionToken { org_id: Some("org-b".to_string()), ..owner.clone() };
+        for other in [&viewer, &other_org] {
+            assert!(!same_scope(&owner, other));
+            assert_ne!(scope_field(&owner), scope_field(other));
+        }
+        assert_ne!(viewer.grant_jti(), owner.grant_jti());
+    }
+
+    #[test]
+    fn lookups_skip_members_expiring_now() {
+        // Matches remove_expired_tokens, which reclaims scores up to and
+        // including now
+        assert_eq!(unexpired_min_score(1_700_000_000), "(1700000000");
+    }
+
+    #[test]
+    fn viewer_tokens_only_satisfy_read_access() {
+        let store = make_store();
+        let viewer = ExecutionToken { scope: TokenScope::Viewer, ..token("wf-1", None) };
+        assert!(store.check_token_permissions(
+            &viewer,
+            Some("exec-1"),
+            "wf-1",
+            None,
+            TokenScope::Viewer
+        ));
+        assert!(!store.check_token_permissions(
+            &viewer,
+            Some("exec-1"),
+            "wf-1",
+            None,
+            TokenScope::Owner
+        ));
+
+        let owner = token("wf-1", None);
+        assert!(store.check_token_permissions(
+            &owner,
+            Some("exec-1"),
+            "wf-1",
+            None,
+            TokenScope::Viewer
+        ));
+        assert!(store.check_token_permissions(
+            &owner,
+            Some("exec-1"),
+            "wf-1",
+            None,
+            TokenScope::Owner
+        ));
+
+        // Grants stored before scopes existed are owners
+        let legacy: ExecutionToken = serde_json::from_str(
+            r#"{"execution_id":null,"workflow_id":"wf-1","iat":1,"exp":2,"user_id":"user-1"}"#,
+        )
+        .expect("legacy token should parse");
+        assert_eq!(legacy.scope, TokenScope::Owner);
+    }
+
+    #[test]
+    fn redis_errors_are_classified_by_kind() {
+        let refused =
+            redis::RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        assert!(matches!(StoreError::from(refused), StoreError::Transient(_)));
+
+        let bad_reply = redis::RedisError::from((
+            redis::ErrorKind::UnexpectedReturnType,
+            "expected a bulk string",
+        ));
+        assert!(matches!(StoreError::from(bad_reply), StoreError::Fatal(_)));
     }
 }
@@ -4,32 +4,95 @@
 //!
 //! This service handles execution tokens and real-time events.
 
-use std::future::Future;
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
 
-use rtes::{api, config, infra};
+use rtes::{
+    api,
+    api::state::TokenStorePort,
+    config,
+    infra::{self, consumer_health::ConsumerReporter},
+    util,
+};
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenvy::dotenv().ok();
+    let environment = load_dotenv();
     config::Config::init()?;
     let cfg = config::Config::get();
 
-    let tracer_provider = infra::telemetry::init_telemetry("rtes", &cfg.otel_endpoint)?;
+    let tracer_provider =
+        infra::telemetry::init_telemetry("rtes", cfg.otel_endpoint.as_deref(), &cfg.log_filter);
 
     info!("Starting RTES service...");
+    info!(config = ?cfg.redacted(), "Loaded configuration");
+    // Install the recorder before consumers start counting
+    infra::prometheus::handle();
 
-    let client = redis::Client::open(cfg.redis_url.as_str())?;
-    let token_store = infra::token_store::TokenStore::new(client);
+    let cancel_token = CancellationToken::new();
+    let cancel_token_clone = cancel_token.clone();
 
-    let execution_store =
-        infra::execution_store::ExecutionStore::new(&cfg.mongodb_url, "rtes_db").await?;
+    let token_store: Arc<dyn TokenStorePort> = match cfg.token_store {
+        config::TokenStoreBackend::Redis => {
+            let client = redis::Client::open(cfg.redis_url.as_str())?;
+            let store = infra::token_store::TokenStore::with_connection_config(
+                client,
+                infra::redis_connection::RedisConnectionConfig {
+                    connect_timeout:    Duration::from_millis(cfg.redis_connect_timeout_ms),
+                    reconnect_attempts: cfg.redis_reconnect_attempts,
+                    reconnect_backoff:  Duration::from_millis(cfg.redis_reconnect_backoff_ms),
+                },
+            );
+            if let Some(secs) = cfg.token_prune_interval_secs {
+                store.spawn_pruner(Duration::from_secs(secs), cancel_token.clone());
+            }
+            Arc::new(store)
+        },
+        config::TokenStoreBackend::Memory => {
+            info!("Using in-memory token store");
+            let store = infra::memory_token_store::MemoryTokenStore::new();
+            store.spawn_sweeper(
+                Duration::from_secs(cfg.token_store_sweep_secs.max(1)),
+                cancel_token.clone(),
+            );
+            Arc::new(store)
+        },
+    };
 
-    let state = api::state::AppState::new(token_store.clone(), execution_store);
+    let execution_ttl = cfg.execution_ttl_days.map(|days| {
+        Duration::from_hours(24).saturating_mul(u32::try_from(days).unwrap_or(u32::MAX))
+    });
+    let execution_store = infra::execution_store::ExecutionStore::new(
+        &cfg.mongodb_url,
+        &cfg.mongodb_db,
+        execution_ttl,
+        cfg.mongo_read_preference,
+        cfg.mongo_write_concern,
+    )
+    .await?
+    .with_document_size_sampling(cfg.execution_doc_size_sample_every)
+    .with_retry_policy(util::retry::RetryPolicy {
+        base_delay:   Duration::from_millis(cfg.retry_base_delay_ms),
+        max_attempts: cfg.retry_max_attempts,
+        max_backoff:  Duration::from_millis(cfg.retry_max_backoff_ms),
+    });
 
-    let cancel_token = CancellationToken::new();
-    let cancel_token_clone = cancel_token.clone();
+    let rate_limiter = api::rate_limit::RateLimiter::new(cfg.rate_limit_rps, cfg.rate_limit_burst)
+        .with_trusted_proxies(&cfg.rate_limit_trusted_proxies);
+    if rate_limiter.is_enabled() {
+        rate_limiter.spawn_sweeper(Duration::from_mins(1), cancel_token.clone());
+    }
+    let state = api::state::AppState::from_shared(
+        token_store,
+        Arc::new(execution_store),
+        cfg.broadcast_capacity,
+    )
+    .with_rate_limiter(rate_limiter)
+    .with_negative_cache(api::negative_cache::NegativeCache::new(Duration::from_millis(
+        cfg.negative_cache_ttl_ms,
+    )));
 
     tokio::spawn(async move {
         if matches!(tokio::signal::ctrl_c().await, Ok(())) {
@@ -37,109 +100,300 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             cancel_token_clone.cancel();
         }
     });
+    #[cfg(unix)]
+    spawn_config_reloader(environment, cancel_token.clone())?;
 
     // Start RabbitMQ consumers (each consumer handles its own exchange/queue setup)
-    spawn_consumers(&cfg.amqp_url, &state, &cancel_token);
+    let consumer_tasks = spawn_consumers(&cfg.amqp_url, &state, &cancel_token);
+    spawn_queue_depth_poller(&state, cancel_token.clone());
 
-    start_server(state, cancel_token).await?;
+    let consumers = state.consumers.clone();
+    let served = start_server(state, cancel_token.clone()).await;
 
-    let _ = tracer_provider.shutdown();
+    // Consumers started draining when the token fired; wait for them so
+    // in-flight messages are acked before the runtime shuts down, but don't
+    // let one stuck in a store call hold the process open
+    cancel_token.cancel();
+    let shutdown_timeout = Duration::from_secs(cfg.shutdown_timeout_secs);
+    for name in util::shutdown::join_or_abort(consumer_tasks, shutdown_timeout).await {
+        warn!(
+            consumer = name,
+            "{} consumer did not stop within {:?}, aborted", name, shutdown_timeout
+        );
+    }
+    served?;
+
+    if let Some(tracer_provider) = tracer_provider {
+        let _ = tracer_provider.shutdown();
+    }
     info!("RTES service stopped");
 
+    let failures = consumers.failures();
+    if cfg.rabbitmq_require_consumers && !failures.is_empty() {
+        let names: Vec<_> = failures.iter().map(|(name, _)| *name).collect();
+        return Err(
+            format!("Required RabbitMQ consumers failed to start: {}", names.join(", ")).into()
+        );
+    }
+
     Ok(())
 }
 
-const RABBITMQ_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+/// Poll the consumed queues' depth into `state.queues` every
+/// `QUEUE_DEPTH_POLL_SECS` until shutdown, unless that is 0.
+fn spawn_queue_depth_poller(state: &api::state::AppState, cancel_token: CancellationToken) {
+    let cfg = config::Config::get();
+    if cfg.queue_depth_poll_secs == 0 {
+        return;
+    }
+    tokio::spawn(infra::queue_depth::poll(
+        cfg.amqp_url.clone(),
+        cfg.consumer_queues().map(ToOwned::to_owned).to_vec(),
+        cfg.rabbitmq_enable_dlq,
+        Duration::from_secs(cfg.queue_depth_poll_secs),
+        state.queues.clone(),
+        cancel_token,
+    ));
+}
 
+/// Load `.env` into the process environment, returning the environment from
+/// before, whose variables keep winning over `.env` on reloads too.
+fn load_dotenv() -> std::collections::HashMap<String, String> {
+    let environment = std::env::vars().collect();
+    dotenvy::dotenv().ok();
+    environment
+}
+
+/// Reload the hot-reloadable settings on every SIGHUP until shutdown.
+/// `environment` is the process environment from before `.env` was loaded.
+#[cfg(unix)]
+fn spawn_config_reloader(
+    environment: std::collections::HashMap<String, String>,
+    cancel_token: CancellationToken,
+) -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                () = cancel_token.cancelled() => break,
+                received = hangups.recv() => match received {
+                    Some(()) => reload_config(&environment),
+                    None => break,
+                },
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Re-read `.env` under the startup `environment`, which wins as it does at
+/// startup, then apply the log filter now and the consumer
+/// prefetch/concurrency on each consumer's next reconnect.
+#[cfg(unix)]
+fn reload_config(environment: &std::collections::HashMap<String, String>) {
+    use std::collections::HashMap;
+
+    info!("SIGHUP received, reloading configuration");
+    let dotenv: HashMap<String, String> = dotenvy::dotenv_iter()
+        .map(|vars| vars.filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    let lookup = |name: &str| config::Config::reload_value(environment, &dotenv, name);
+    match config::Config::reload(lookup) {
+        Ok(cfg) => {
+            if let Err(e) = infra::telemetry::set_log_filter(&cfg.log_filter) {
+                warn!("Keeping the current log filter, RUST_LOG is invalid: {}", e);
+            }
+            info!(
+                log_filter = %cfg.log_filter,
+                prefetch = cfg.rabbitmq_prefetch_count,
+                concurrency = cfg.rabbitmq_concurrent_messages,
+                execution = ?cfg.rabbitmq_execution_tuning,
+                status = ?cfg.rabbitmq_status_tuning,
+                completion = ?cfg.rabbitmq_completion_tuning,
+                "Configuration reloaded; consumer tuning applies on reconnect"
+            );
+        },
+        Err(e) => warn!("Configuration reload rejected, keeping the current one: {}", e),
+    }
+}
+
+/// First reconnect delay; doubled per consecutive failure up to the max.
+const RABBITMQ_RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RABBITMQ_RECONNECT_MAX_DELAY: Duration = Duration::from_mins(1);
+
+/// Run a consumer until shutdown, reconnecting with exponential backoff
+/// whenever its connection, channel or startup fails.
 async fn run_consumer_with_retry<F, Fut>(
-    name: &'static str,
+    reporter: ConsumerReporter,
+    queue_name: &'static str,
     amqp_url: String,
     cancel_token: CancellationToken,
     start: F,
 ) where
-    F: Fn(String, CancellationToken) -> Fut + Send + Sync + 'static,
+    F: Fn(String, CancellationToken, ConsumerReporter) -> Fut + Send + Sync + 'static,
     Fut: Future<Output = Result<(), String>> + Send + 'static,
 {
-    let mut attempt: u64 = 0;
+    let name = reporter.name();
+    let required = config::Config::get().rabbitmq_require_consumers;
+    let mut attempt: u32 = 0;
     loop {
         if cancel_token.is_cancelled() {
             return;
         }
         attempt += 1;
-        info!("Connecting to RabbitMQ for {} at {} (attempt {})", name, amqp_url, attempt);
-        match start(amqp_url.clone(), cancel_token.clone()).await {
+        info!(
+            consumer = name,
+            queue = queue_name,
+            attempt,
+            "Connecting to RabbitMQ for {} consumer",
+            name
+        );
+        reporter.starting();
+        match start(amqp_url.clone(), cancel_token.clone(), reporter.clone()).await {
             Ok(()) => return,
             Err(e) => {
-                tracing::error!("{} error: {} - retrying in {:?}", name, e, RABBITMQ_RETRY_DELAY);
-                if cancel_token.is_cancelled() {
+                let never_started = reporter.is_starting();
+                reporter.failed(&e);
+                if required && never_started {
+                    tracing::error!(
+                        "{} consumer failed to start and RABBITMQ_REQUIRE_CONSUMERS is set - \
+                         shutting down",
+                        name
+                    );
+                    cancel_token.cancel();
                     return;
                 }
-                tokio::time::sleep(RABBITMQ_RETRY_DELAY).await;
+                // A consumer that was running lost its connection: back off from the start
+                if !never_started {
+                    attempt = 1;
+                }
+                let delay = util::retry::exponential_delay(
+                    RABBITMQ_RECONNECT_BASE_DELAY,
+                    attempt,
+                    RABBITMQ_RECONNECT_MAX_DELAY,
+                );
+                tracing::error!(
+                    consumer = name,
+                    queue = queue_name,
+                    attempt,
+                    "{} consumer reconnecting in {:?}",
+                    name,
+                    delay
+                );
+                tokio::select! {
+                    () = cancel_token.cancelled() => return,
+                    () = tokio::time::sleep(delay) => {},
+                }
             },
         }
     }
 }
 
-fn spawn_consumers(amqp_url: &str, state: &api::state::AppState, cancel_token: &CancellationToken) {
+/// Spawn [`run_consumer_with_retry`], returning the task with its consumer
+/// name.
+fn spawn_consumer<F, Fut>(
+    reporter: ConsumerReporter,
+    queue_name: &'static str,
+    amqp_url: String,
+    cancel_token: CancellationToken,
+    start: F,
+) -> (&'static str, JoinHandle<()>)
+where
+    F: Fn(String, CancellationToken, ConsumerReporter) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let name = reporter.name();
+    let task =
+        tokio::spawn(run_consumer_with_retry(reporter, queue_name, amqp_url, cancel_token, start));
+    (name, task)
+}
+
+fn spawn_consumers(
+    amqp_url: &str,
+    state: &api::state::AppState,
+    cancel_token: &CancellationToken,
+) -> Vec<(&'static str, JoinHandle<()>)> {
+    let cfg = config::Config::get();
+    let mut tasks = Vec::new();
     let url = amqp_url.to_string();
     let token_store = state.token_store.clone();
+    let reporter = state.consumers.register("token");
     let ct = cancel_token.clone();
-    tokio::spawn(async move {
-        run_consumer_with_retry("Token Consumer", url, ct, move |amqp_url, ct| {
+    tasks.push(spawn_consumer(
+        reporter,
+        &cfg.rabbitmq_token_queue,
+        url,
+        ct,
+        move |amqp_url, ct, reporter| {
             let token_store = token_store.clone();
             async move {
-                infra::messaging::start_token_consumer(&amqp_url, token_store, ct)
+                infra::messaging::start_token_consumer(&amqp_url, token_store, ct, &reporter)
                     .await
                     .map_err(|e| e.to_string())
             }
-        })
-        .await;
-    });
+        },
+    ));
 
     let url = amqp_url.to_string();
     let s = state.clone();
+    let reporter = state.consumers.register("execution");
     let ct = cancel_token.clone();
-    tokio::spawn(async move {
-        run_consumer_with_retry("Execution Consumer", url, ct, move |amqp_url, ct| {
+    tasks.push(spawn_consumer(
+        reporter,
+        &cfg.rabbitmq_execution_queue,
+        url,
+        ct,
+        move |amqp_url, ct, reporter| {
             let s = s.clone();
             async move {
-                infra::messaging::start_execution_consumer(&amqp_url, s, ct)
+                infra::messaging::start_execution_consumer(&amqp_url, s, ct, &reporter)
                     .await
                     .map_err(|e| e.to_string())
             }
-        })
-        .await;
-    });
+        },
+    ));
 
     let url = amqp_url.to_string();
     let s = state.clone();
+    let reporter = state.consumers.register("status");
     let ct = cancel_token.clone();
-    tokio::spawn(async move {
-        run_consumer_with_retry("Status Consumer", url, ct, move |amqp_url, ct| {
+    tasks.push(spawn_consumer(
+        reporter,
+        &cfg.rabbitmq_status_queue,
+        url,
+        ct,
+        move |amqp_url, ct, reporter| {
             let s = s.clone();
             async move {
-                infra::messaging::start_status_consumer(&amqp_url, s, ct)
+                infra::messaging::start_status_consumer(&amqp_url, s, ct, &reporter)
                     .await
                     .map_err(|e| e.to_string())
             }
-        })
-        .await;
-    });
+        },
+    ));
 
     let url = amqp_url.to_string();
     let s = state.clone();
+    let reporter = state.consumers.register("completion");
     let ct = cancel_token.clone();
-    tokio::spawn(async move {
-        run_consumer_with_retry("Completion Consumer", url, ct, move |amqp_url, ct| {
+    tasks.push(spawn_consumer(
+        reporter,
+        &cfg.rabbitmq_completion_queue,
+        url,
+        ct,
+        move |amqp_url, ct, reporter| {
             let s = s.clone();
             async move {
-                infra::messaging::start_completion_consumer(&amqp_url, s, ct)
+                infra::messaging::start_completion_consumer(&amqp_url, s, ct, &reporter)
                     .await
                     .map_err(|e| e.to_string())
             }
-        })
-        .await;
-    });
+        },
+    ));
+
+    tasks
 }
 
 async fn start_server(
@@ -147,14 +401,33 @@ async fn start_server(
     cancel_token: CancellationToken,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let cfg = config::Config::get();
-    let app = api::routes::app(state);
-    let addr = format!("0.0.0.0:{}", cfg.port);
+    let Some(admin_port) = cfg.admin_port else {
+        serve(cfg.port, api::routes::app(state), "Server", cancel_token).await?;
+        return Ok(());
+    };
+
+    // Split mode: the public port never exposes metrics/admin routes
+    tokio::try_join!(
+        serve(cfg.port, api::routes::public_app(state.clone()), "Server", cancel_token.clone()),
+        serve(admin_port, api::routes::admin_app(state), "Admin server", cancel_token),
+    )?;
+    Ok(())
+}
+
+async fn serve(
+    port: u16,
+    app: axum::Router,
+    name: &'static str,
+    cancel_token: CancellationToken,
+) -> std::io::Result<()> {
+    let addr = format!("0.0.0.0:{port}");
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    info!("Listening on {}", listener.local_addr()?);
-    axum::serve(listener, app)
+    info!("{} listening on {}", name, listener.local_addr()?);
+    // Peer addresses key the rate limiter when no proxy header is sent
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(async move {
             cancel_token.cancelled().await;
-            info!("Server shutting down");
+            info!("{} shutting down", name);
         })
         .await?;
     Ok(())
@@ -0,0 +1,65 @@
+//! Opaque, URL-safe pagination cursors.
+//!
+//! A cursor is a list of string parts (e.g. a sort key plus a tie-breaker id)
+//! joined with a unit separator and hex-encoded, so clients can pass it back
+//! verbatim in a query string.
+
+use std::fmt::Write;
+
+const SEPARATOR: char = '\u{1f}';
+
+/// Encode cursor parts into an opaque token.
+pub fn encode(parts: &[&str]) -> String {
+    let joined = parts.join(&SEPARATOR.to_string());
+    joined
+        .as_bytes()
+        .iter()
+        .fold(String::with_capacity(joined.len() * 2), |mut out, byte| {
+            let _ = write!(out, "{byte:02x}");
+            out
+        })
+}
+
+/// Decode an opaque token into its parts, returning `None` when the token is
+/// malformed or does not contain exactly `expected_parts` parts.
+pub fn decode(cursor: &str, expected_parts: usize) -> Option<Vec<String>> {
+    if cursor.is_empty() || !cursor.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes = cursor
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()?;
+
+    let joined = String::from_utf8(bytes).ok()?;
+    let parts: Vec<String> = joined.split(SEPARATOR).map(String::from).collect();
+    (parts.len() == expected_parts).then_some(parts)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn round_trips_parts() {
+        let cursor = encode(&["2026-01-01T00:00:00+00:00", "abc123"]);
+        assert!(cursor.chars().all(|c| c.is_ascii_hexdigit()));
+        let parts = decode(&cursor, 2).expect("cursor should decode");
+        assert_eq!(parts, vec!["2026-01-01T00:00:00+00:00", "abc123"]);
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        assert!(decode("", 2).is_none());
+        assert!(decode("abc", 2).is_none());
+        assert!(decode("zz", 1).is_none());
+        assert!(decode(&encode(&["only-one"]), 2).is_none());
+    }
+}
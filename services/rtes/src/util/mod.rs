@@ -1 +1,3 @@
+pub mod cursor;
 pub mod retry;
+pub mod shutdown;
@@ -1,23 +1,99 @@
-use std::{future::Future, time::Duration};
+use std::{
+    future::Future,
+    hash::{BuildHasher, RandomState},
+    time::Duration,
+};
 
 use tokio::time::sleep;
 use tracing::warn;
 
-/// Retry an async closure with exponential backoff (250ms base) up to five
-/// attempts.
-pub(crate) async fn with_backoff<F, Fut, T, E>(mut f: F, label: &'static str) -> Result<T, E>
+/// Delay before retry number `attempt` (1-based): `base` doubled per
+/// previous attempt, capped at `max`.
+pub fn exponential_delay(base: Duration, attempt: u32, max: Duration) -> Duration {
+    let doublings = attempt.saturating_sub(1).min(31);
+    base.saturating_mul(1 << doublings).min(max)
+}
+
+/// A uniformly random delay between zero and `max` ("full jitter"), so
+/// callers failing together don't retry together.
+pub fn full_jitter(max: Duration) -> Duration {
+    let max_nanos = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+    if max_nanos == 0 {
+        return Duration::ZERO;
+    }
+    // Every `RandomState` is freshly seeded, which is random enough to
+    // spread retries without pulling in an RNG
+    let random = RandomState::new().hash_one(max_nanos);
+    Duration::from_nanos(random % max_nanos.saturating_add(1))
+}
+
+/// How often and how patiently a failed operation is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Backoff ceiling before the first retry, doubled for each one after
+    pub base_delay:   Duration,
+    /// Attempts in total, the first included; at least one is made
+    pub max_attempts: u32,
+    /// Cap on the backoff ceiling
+    pub max_backoff:  Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay:   Duration::from_millis(250),
+            max_attempts: 5,
+            max_backoff:  Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// This policy starting from `base_delay` instead, for operations that
+    /// wait on something slower than a failed request to recover.
+    #[must_use]
+    pub const fn with_base_delay(self, base_delay: Duration) -> Self {
+        Self { base_delay, ..self }
+    }
+
+    /// Jittered wait after failed attempt number `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        full_jitter(exponential_delay(self.base_delay, attempt, self.max_backoff))
+    }
+}
+
+/// Retry an async closure with jittered exponential backoff per `policy`.
+pub(crate) async fn with_backoff<F, Fut, T, E>(
+    policy: &RetryPolicy,
+    label: &'static str,
+    f: F,
+) -> Result<T, E>
 where
     F: FnMut() -> Fut,
     Fut: Future<Output = Result<T, E>>,
 {
-    let mut backoff = Duration::from_millis(250);
-    let max_attempts = 5;
+    with_backoff_if(policy, label, f, |_| true).await
+}
 
-    for attempt in 1..=max_attempts {
+/// Like [`with_backoff`], but an error `retryable` rejects is returned at
+/// once.
+pub(crate) async fn with_backoff_if<F, Fut, T, E>(
+    policy: &RetryPolicy,
+    label: &'static str,
+    mut f: F,
+    retryable: impl Fn(&E) -> bool,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
         match f().await {
             Ok(value) => return Ok(value),
-            Err(err) if attempt == max_attempts => return Err(err),
+            Err(err) if attempt >= policy.max_attempts || !retryable(&err) => return Err(err),
             Err(_) => {
+                let backoff = policy.backoff(attempt);
                 warn!(
                     label,
                     attempt,
@@ -25,50 +101,64 @@ where
                     "operation failed, retrying with backoff"
                 );
                 sleep(backoff).await;
-                backoff = backoff.saturating_mul(2);
+                attempt += 1;
             },
         }
     }
-    unreachable!()
 }
 
-/// Retry the provided async block with exponential backoff. The macro expands
-/// into a future that resolves to the borrowed block result, so the caller must
-/// `.await` it.
+/// Retry the provided async block with jittered exponential backoff per a
+/// [`RetryPolicy`]. The macro expands into a future that resolves to the
+/// borrowed block result, so the caller must `.await` it.
 ///
 /// Example:
 /// ```ignore
-/// retry_backoff!("status_update", { some_async_operation().await })?.await;
+/// retry_backoff!(&policy, "status_update", { some_async_operation().await }).await?;
 /// ```
 #[macro_export]
 macro_rules! retry_backoff {
-    ($label:expr, $body:block) => {
-        $crate::util::retry::with_backoff(|| async move $body, $label)
+    ($policy:expr, $label:expr, $body:block) => {
+        $crate::util::retry::with_backoff($policy, $label, || async move $body)
     };
 }
 
 #[cfg(test)]
 #[allow(clippy::expect_used)]
 mod tests {
-    use std::sync::{
-        Arc,
-        atomic::{AtomicUsize, Ordering},
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
     };
 
-    use super::with_backoff;
+    use super::{RetryPolicy, exponential_delay, full_jitter, with_backoff, with_backoff_if};
+
+    /// Retries without waiting, so the tests run instantly.
+    const IMMEDIATE: RetryPolicy =
+        RetryPolicy { base_delay: Duration::ZERO, max_attempts: 5, max_backoff: Duration::ZERO };
+
+    #[test]
+    fn exponential_delay_doubles_up_to_the_cap() {
+        let base = Duration::from_secs(1);
+        let max = Duration::from_mins(1);
+        let delays: Vec<_> = (1..=8)
+            .map(|attempt| exponential_delay(base, attempt, max).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(exponential_delay(base, 1000, max), max);
+    }
 
     #[tokio::test]
     async fn retries_until_operation_succeeds() {
         let attempts = Arc::new(AtomicUsize::new(0));
         let attempts_for_closure = attempts.clone();
 
-        let result: Result<u32, &'static str> = with_backoff(
-            move || {
-                let attempt = attempts_for_closure.fetch_add(1, Ordering::SeqCst);
-                async move { if attempt < 2 { Err("transient") } else { Ok(7) } }
-            },
-            "retry_test",
-        )
+        let result: Result<u32, &'static str> = with_backoff(&IMMEDIATE, "retry_test", move || {
+            let attempt = attempts_for_closure.fetch_add(1, Ordering::SeqCst);
+            async move { if attempt < 2 { Err("transient") } else { Ok(7) } }
+        })
         .await;
 
         assert_eq!(result.expect("third attempt should succeed"), 7);
@@ -80,16 +170,61 @@ mod tests {
         let attempts = Arc::new(AtomicUsize::new(0));
         let attempts_for_closure = attempts.clone();
 
-        let result: Result<u32, &'static str> = with_backoff(
+        let result: Result<u32, &'static str> = with_backoff(&IMMEDIATE, "retry_test", move || {
+            attempts_for_closure.fetch_add(1, Ordering::SeqCst);
+            async move { Err("still failing") }
+        })
+        .await;
+
+        assert_eq!(result.expect_err("operation should fail"), "still failing");
+        assert_eq!(attempts.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn errors_that_are_not_retryable_return_at_once() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let attempts_for_closure = attempts.clone();
+
+        let result: Result<u32, &'static str> = with_backoff_if(
+            &IMMEDIATE,
+            "retry_test",
             move || {
                 attempts_for_closure.fetch_add(1, Ordering::SeqCst);
-                async move { Err("still failing") }
+                async move { Err("fatal") }
             },
-            "retry_test",
+            |err| *err != "fatal",
         )
         .await;
 
-        assert_eq!(result.expect_err("operation should fail"), "still failing");
-        assert_eq!(attempts.load(Ordering::SeqCst), 5);
+        assert_eq!(result.expect_err("operation should fail"), "fatal");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn jittered_backoff_stays_within_the_exponential_ceiling() {
+        let policy = RetryPolicy {
+            base_delay:   Duration::from_millis(100),
+            max_attempts: 5,
+            max_backoff:  Duration::from_millis(500),
+        };
+        for attempt in 1..=6 {
+            let ceiling = exponential_delay(policy.base_delay, attempt, policy.max_backoff);
+            for _ in 0..50 {
+                assert!(policy.backoff(attempt) <= ceiling);
+            }
+        }
+        assert_eq!(full_jitter(Duration::ZERO), Duration::ZERO);
+
+        // Concurrent callers shouldn't all pick the same delay
+        let delays: std::collections::HashSet<_> = (0..20).map(|_| policy.backoff(3)).collect();
+        assert!(delays.len() > 1);
+    }
+
+    #[test]
+    fn base_delay_can_be_overridden_per_call_site() {
+        let policy = RetryPolicy::default().with_base_delay(Duration::from_secs(1));
+        assert_eq!(policy.base_delay, Duration::from_secs(1));
+        assert_eq!(policy.max_attempts, RetryPolicy::default().max_attempts);
+        assert_eq!(policy.max_backoff, RetryPolicy::default().max_backoff);
     }
 }
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use tokio::{task::JoinHandle, time::Instant};
+
+/// Wait for named tasks to finish, sharing one `timeout` between them, and
+/// abort whichever are still running once it elapses. Returns the names of
+/// the aborted tasks.
+pub async fn join_or_abort(
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+    timeout: Duration,
+) -> Vec<&'static str> {
+    let deadline = Instant::now() + timeout;
+    let mut aborted = Vec::new();
+    for (name, mut task) in tasks {
+        if tokio::time::timeout_at(deadline, &mut task).await.is_err() {
+            task.abort();
+            aborted.push(name);
+        }
+    }
+    aborted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::join_or_abort;
+
+    #[tokio::test]
+    async fn tasks_still_running_at_the_deadline_are_aborted() {
+        let finished = tokio::spawn(async {});
+        let stuck = tokio::spawn(std::future::pending::<()>());
+        let stuck_abort = stuck.abort_handle();
+
+        let aborted = join_or_abort(
+            vec![("finished", finished), ("stuck", stuck)],
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert_eq!(aborted, vec!["stuck"]);
+        tokio::task::yield_now().await;
+        assert!(stuck_abort.is_finished());
+    }
+}
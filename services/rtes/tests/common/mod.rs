@@ -1,22 +1,37 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
 };
 
 use async_trait::async_trait;
+use jsonwebtoken::{EncodingKey, Header, encode};
 use rtes::{
-    api::state::{AppState, ExecutionStorePort, StoreResult, TokenStorePort},
+    api::state::{AppState, ExecutionStorePort, StoreError, StoreResult, TokenStorePort},
     config::Config,
     domain::models::{
         CompletionMessage,
+        ExecutionAccessScope,
+        ExecutionCursor,
         ExecutionDocument,
+        ExecutionOwner,
+        ExecutionPage,
+        ExecutionSummary,
         ExecutionToken,
         HydratedNode,
         NodeExecutionInstance,
         NodeExecutionMessage,
         NodeStatusMessage,
+        NodeStatusRecord,
+        StatusCursor,
+        TokenScope,
     },
 };
+use serde::Serialize;
+use tokio::sync::Barrier;
 
 #[derive(Default)]
 #[allow(clippy::struct_excessive_bools)]
@@ -25,7 +40,20 @@ pub(crate) struct MockTokenStore {
     pub validate_access_for_execution_result: bool,
     pub validate_execution_access_result: bool,
     pub validate_workflow_access_result: bool,
+    pub ping_fails: bool,
     pub added_tokens: Mutex<Vec<ExecutionToken>>,
+    /// Executions a JWT user is refused even when the result above is `true`
+    pub denied_executions: Vec<String>,
+    /// Grants are viewer-only, so checks requiring an owner fail
+    pub viewer_only: bool,
+    /// Organization every grant is confined to
+    pub org_id: Option<String>,
+}
+
+impl MockTokenStore {
+    fn allows(&self, required_scope: TokenScope) -> bool {
+        !self.viewer_only || required_scope == TokenScope::Viewer
+    }
 }
 
 #[async_trait]
@@ -43,28 +71,76 @@ impl TokenStorePort for MockTokenStore {
         _user_id: &str,
         _target_execution_id: Option<&str>,
         _target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool> {
-        Ok(self.validate_access_result)
+        Ok(self.validate_access_result
+            && self.allows(required_scope)
+            && self.org_id.as_deref() == target_org_id)
     }
 
     async fn validate_access_for_execution(
         &self,
         _user_id: &str,
-        _target_execution_id: &str,
+        target_execution_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool> {
-        Ok(self.validate_access_for_execution_result)
+        Ok(self.validate_access_for_execution_result
+            && self.allows(required_scope)
+            && self.org_id.as_deref() == target_org_id
+            && !self
+                .denied_executions
+                .iter()
+                .any(|id| id == target_execution_id))
     }
 
     async fn validate_execution_access(
         &self,
         _target_execution_id: &str,
         _target_workflow_id: &str,
+        target_org_id: Option<&str>,
+        required_scope: TokenScope,
     ) -> StoreResult<bool> {
-        Ok(self.validate_execution_access_result)
+        Ok(self.validate_execution_access_result
+            && self.allows(required_scope)
+            && self.org_id.as_deref() == target_org_id)
+    }
+
+    async fn validate_workflow_access(
+        &self,
+        _target_workflow_id: &str,
+        target_org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        Ok(self.validate_workflow_access_result && self.org_id.as_deref() == target_org_id)
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        if self.ping_fails {
+            return Err(StoreError::Transient("redis unreachable".into()));
+        }
+        Ok(())
     }
 
-    async fn validate_workflow_access(&self, _target_workflow_id: &str) -> StoreResult<bool> {
-        Ok(self.validate_workflow_access_result)
+    async fn list_user_tokens(&self, user_id: &str) -> StoreResult<Vec<ExecutionToken>> {
+        Ok(self
+            .added_tokens
+            .lock()
+            .expect("mock token store mutex should not be poisoned")
+            .iter()
+            .filter(|token| token.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn revoke_token(&self, jti: &str) -> StoreResult<bool> {
+        let mut tokens = self
+            .added_tokens
+            .lock()
+            .expect("mock token store mutex should not be poisoned");
+        let before = tokens.len();
+        tokens.retain(|token| token.jti != jti);
+        Ok(tokens.len() != before)
     }
 }
 
@@ -72,6 +148,34 @@ impl TokenStorePort for MockTokenStore {
 pub(crate) struct MockExecutionStore {
     pub execution_documents_by_id: Mutex<HashMap<String, ExecutionDocument>>,
     pub executions_by_workflow:    Mutex<HashMap<String, Vec<ExecutionDocument>>>,
+    pub status_history:            Mutex<Vec<NodeStatusRecord>>,
+    pub ping_fails:                bool,
+    /// Error execution document and workflow id lookups fail with, when set
+    pub load_error:                Option<fn() -> StoreError>,
+    /// Calls to `get_execution_document`
+    pub document_loads:            AtomicUsize,
+    /// When set, `get_execution_document` meets the test at this barrier
+    /// once on starting and again before returning, so the test can act
+    /// while a load is in flight
+    pub load_gate:                 Option<Arc<Barrier>>,
+    /// Execution whose document can't be loaded, though its workflow id
+    /// lookup still answers
+    pub unreadable_document:       Option<String>,
+    /// Store round trips made to write node statuses, batched or not
+    pub status_writes:             AtomicUsize,
+    /// Node whose statuses fail to store, failing any batch holding one
+    pub failing_status_node:       Option<String>,
+    /// Completions recorded so far; one recorded again is a duplicate
+    pub completions:               Mutex<Vec<CompletionMessage>>,
+}
+
+impl MockExecutionStore {
+    fn store_status(&self, msg: &NodeStatusMessage) -> StoreResult<bool> {
+        if self.failing_status_node.as_deref() == Some(msg.node_id.as_str()) {
+            return Err(StoreError::Fatal(format!("cannot store {}", msg.node_id).into()));
+        }
+        Ok(true)
+    }
 }
 
 #[async_trait]
@@ -83,38 +187,286 @@ impl ExecutionStorePort for MockExecutionStore {
     async fn get_execution_document(
         &self,
         execution_id: &str,
+        org_id: Option<&str>,
     ) -> StoreResult<Option<ExecutionDocument>> {
+        self.document_loads.fetch_add(1, Ordering::Relaxed);
+        if let Some(gate) = &self.load_gate {
+            gate.wait().await;
+            gate.wait().await;
+        }
+        if let Some(load_error) = self.load_error {
+            return Err(load_error());
+        }
+        if self.unreadable_document.as_deref() == Some(execution_id) {
+            return Err(StoreError::Transient(format!("cannot read {execution_id}").into()));
+        }
         let guard = self
             .execution_documents_by_id
             .lock()
             .expect("mock execution store mutex should not be poisoned");
-        Ok(guard.get(execution_id).cloned())
+        Ok(guard
+            .get(execution_id)
+            .filter(|doc| doc.org_id.as_deref() == org_id)
+            .cloned())
+    }
+
+    async fn get_execution_owner(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<Option<ExecutionOwner>> {
+        if let Some(load_error) = self.load_error {
+            return Err(load_error());
+        }
+        let guard = self
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        Ok(guard
+            .get(execution_id)
+            .filter(|doc| doc.org_id.as_deref() == org_id)
+            .map(|doc| ExecutionOwner {
+                workflow_id: doc.workflow_id.clone(),
+                org_id:      doc.org_id.clone(),
+            }))
     }
 
     async fn get_executions_for_workflow(
         &self,
         workflow_id: &str,
+        org_id: Option<&str>,
     ) -> StoreResult<Vec<ExecutionDocument>> {
         let guard = self
             .executions_by_workflow
             .lock()
             .expect("mock execution store mutex should not be poisoned");
-        Ok(guard.get(workflow_id).cloned().unwrap_or_default())
+        Ok(guard
+            .get(workflow_id)
+            .into_iter()
+            .flatten()
+            .filter(|doc| doc.org_id.as_deref() == org_id)
+            .cloned()
+            .collect())
     }
 
-    async fn update_node_status(&self, _msg: &NodeStatusMessage) -> StoreResult<()> {
-        Ok(())
+    async fn get_executions_for_workflow_paginated(
+        &self,
+        workflow_id: &str,
+        org_id: Option<&str>,
+        after: Option<&ExecutionCursor>,
+        node_type: Option<&str>,
+        limit: i64,
+    ) -> StoreResult<ExecutionPage> {
+        let mut executions: Vec<ExecutionDocument> = self
+            .executions_by_workflow
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .get(workflow_id)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|doc| doc.org_id.as_deref() == org_id)
+            .filter(|doc| {
+                after.is_none_or(|cursor| {
+                    (doc.created_at, &doc.execution_id) < (cursor.created_at, &cursor.execution_id)
+                })
+            })
+            .filter(|doc| node_type.is_none_or(|node_type| doc.ran_node_type(node_type)))
+            .collect();
+        executions
+            .sort_by(|a, b| (b.created_at, &b.execution_id).cmp(&(a.created_at, &a.execution_id)));
+
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        let next_cursor = if executions.len() > limit {
+            executions.truncate(limit);
+            executions
+                .last()
+                .map(|doc| ExecutionCursor::after(doc).encode())
+        } else {
+            None
+        };
+        Ok(ExecutionPage { executions, next_cursor })
+    }
+
+    async fn list_executions_for_user(
+        &self,
+        scope: &ExecutionAccessScope,
+        org_id: Option<&str>,
+        limit: i64,
+        offset: u64,
+    ) -> StoreResult<Vec<ExecutionSummary>> {
+        let mut summaries: Vec<ExecutionSummary> = self
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .values()
+            .filter(|doc| doc.org_id.as_deref() == org_id)
+            .filter(|doc| {
+                scope.workflow_ids.contains(&doc.workflow_id)
+                    || scope.execution_ids.contains(&doc.execution_id)
+            })
+            .map(|doc| ExecutionSummary {
+                execution_id: doc.execution_id.clone(),
+                workflow_id:  doc.workflow_id.clone(),
+                status:       doc.status.clone(),
+                created_at:   doc.created_at,
+                updated_at:   doc.updated_at,
+                progress:     doc.progress,
+            })
+            .collect();
+        summaries
+            .sort_by(|a, b| (b.created_at, &b.execution_id).cmp(&(a.created_at, &a.execution_id)));
+        Ok(summaries
+            .into_iter()
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(usize::try_from(limit).unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn update_node_status(&self, msg: &NodeStatusMessage) -> StoreResult<bool> {
+        self.status_writes.fetch_add(1, Ordering::Relaxed);
+        self.store_status(msg)
+    }
+
+    async fn update_node_statuses(&self, msgs: &[NodeStatusMessage]) -> StoreResult<Vec<bool>> {
+        self.status_writes.fetch_add(1, Ordering::Relaxed);
+        msgs.iter().map(|msg| self.store_status(msg)).collect()
+    }
+
+    async fn get_status_history(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+        after: Option<&StatusCursor>,
+        offset: u64,
+        limit: i64,
+    ) -> StoreResult<Vec<NodeStatusRecord>> {
+        let mut records: Vec<NodeStatusRecord> = self
+            .status_history
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .iter()
+            .filter(|record| record.message.execution_id == execution_id)
+            .filter(|record| record.message.org_id.as_deref() == org_id)
+            .filter(|record| {
+                after.is_none_or(|cursor| {
+                    (record.message.executed_at.as_str(), record.id.as_str())
+                        > (cursor.executed_at.as_str(), cursor.id.as_str())
+                })
+            })
+            .cloned()
+            .collect();
+        records
+            .sort_by(|a, b| (&a.message.executed_at, &a.id).cmp(&(&b.message.executed_at, &b.id)));
+        Ok(records
+            .into_iter()
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(usize::try_from(limit).unwrap_or(usize::MAX))
+            .collect())
     }
 
-    async fn complete_execution(&self, _msg: &CompletionMessage) -> StoreResult<()> {
+    async fn complete_execution(&self, msg: &CompletionMessage) -> StoreResult<bool> {
+        let mut completions = self
+            .completions
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        let duplicate = completions.iter().any(|recorded| {
+            recorded.execution_id == msg.execution_id && recorded.completed_at == msg.completed_at
+        });
+        if !duplicate {
+            completions.push(msg.clone());
+        }
+        drop(completions);
+        Ok(!duplicate)
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        if self.ping_fails {
+            return Err(StoreError::Transient("mongodb unreachable".into()));
+        }
         Ok(())
     }
+
+    async fn repair_execution(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        Ok(self
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .get(execution_id)
+            .is_some_and(|doc| doc.org_id.as_deref() == org_id))
+    }
+
+    async fn delete_execution(
+        &self,
+        execution_id: &str,
+        org_id: Option<&str>,
+    ) -> StoreResult<bool> {
+        let mut docs = self
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        if docs
+            .get(execution_id)
+            .is_none_or(|doc| doc.org_id.as_deref() != org_id)
+        {
+            return Ok(false);
+        }
+        self.status_history
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .retain(|record| record.message.execution_id != execution_id);
+        Ok(docs.remove(execution_id).is_some())
+    }
 }
 
 pub(crate) fn init_test_config() {
     let _ = Config::init();
 }
 
+#[derive(Serialize)]
+struct JwtClaims {
+    sub:    String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    org_id: Option<String>,
+    exp:    usize,
+}
+
+pub(crate) fn jwt_for_user(user_id: &str) -> String {
+    jwt_with_claims(user_id, None)
+}
+
+/// A JWT for `user_id` acting for `org_id`.
+#[allow(dead_code)] // Only the HTTP tests act for an organization
+pub(crate) fn jwt_for_user_in_org(user_id: &str, org_id: &str) -> String {
+    jwt_with_claims(user_id, Some(org_id))
+}
+
+/// A JWT for `user_id` signed with another secret than the service's.
+#[allow(dead_code)] // Only the HTTP tests forge tokens
+pub(crate) fn jwt_signed_with(user_id: &str, secret: &str) -> String {
+    sign_claims(&JwtClaims { sub: user_id.to_string(), org_id: None, exp: usize::MAX / 2 }, secret)
+}
+
+fn jwt_with_claims(user_id: &str, org_id: Option<&str>) -> String {
+    sign_claims(
+        &JwtClaims {
+            sub:    user_id.to_string(),
+            org_id: org_id.map(str::to_owned),
+            exp:    usize::MAX / 2,
+        },
+        &Config::get().jwt_secret,
+    )
+}
+
+fn sign_claims(claims: &JwtClaims, secret: &str) -> String {
+    encode(&Header::default(), claims, &EncodingKey::from_secret(secret.as_bytes()))
+        .expect("jwt should be generated in tests")
+}
+
 pub(crate) fn sample_execution(
     execution_id: &str,
     workflow_id: &str,
@@ -143,11 +495,43 @@ pub(crate) fn sample_execution(
     }
 }
 
+pub(crate) fn sample_status(
+    execution_id: &str,
+    node_id: &str,
+    status: &str,
+    executed_at: &str,
+) -> NodeStatusMessage {
+    NodeStatusMessage {
+        workflow_id:      "wf-1".to_string(),
+        execution_id:     execution_id.to_string(),
+        node_id:          node_id.to_string(),
+        node_name:        node_id.to_string(),
+        status:           status.to_string(),
+        input:            None,
+        parameters:       None,
+        output:           None,
+        error:            None,
+        executed_at:      executed_at.to_string(),
+        duration_ms:      1,
+        branch_id:        None,
+        split_node_id:    None,
+        item_index:       None,
+        total_items:      None,
+        processed_count:  None,
+        aggregator_state: None,
+        lineage_stack:    None,
+        lineage_hash:     None,
+        used_inputs:      None,
+        seq:              None,
+        org_id:           None,
+    }
+}
+
 pub(crate) fn build_state(
     token_store: Arc<MockTokenStore>,
     execution_store: Arc<MockExecutionStore>,
 ) -> AppState {
     let token_store_dyn: Arc<dyn TokenStorePort> = token_store;
     let execution_store_dyn: Arc<dyn ExecutionStorePort> = execution_store;
-    AppState::from_shared(token_store_dyn, execution_store_dyn)
+    AppState::from_shared(token_store_dyn, execution_store_dyn, Config::get().broadcast_capacity)
 }
@@ -2,32 +2,61 @@
 
 mod common;
 
-use std::sync::Arc;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::Duration,
+};
 
 use axum::{
     body::{Body, to_bytes},
+    extract::ConnectInfo,
     http::{Request, StatusCode},
 };
-use common::{MockExecutionStore, MockTokenStore, build_state, init_test_config, sample_execution};
-use jsonwebtoken::{EncodingKey, Header, encode};
-use rtes::{api::routes::app, config::Config, domain::models::ExecutionDocument};
-use serde::Serialize;
+use common::{
+    MockExecutionStore,
+    MockTokenStore,
+    build_state,
+    init_test_config,
+    jwt_for_user,
+    jwt_for_user_in_org,
+    jwt_signed_with,
+    sample_execution,
+    sample_status,
+};
+use mongodb::bson::DateTime;
+use rtes::{
+    api::{
+        negative_cache::NegativeCache,
+        rate_limit::RateLimiter,
+        routes::{admin_app, app, public_app},
+        state::{ExecutionStorePort, StoreError, TokenStorePort},
+    },
+    domain::models::{
+        CompletionMessage,
+        ExecutionDocument,
+        ExecutionGraph,
+        ExecutionPage,
+        ExecutionToken,
+        HydratedNode,
+        NodeEntry,
+        NodeExecutionInstance,
+        NodeStatusMessage,
+        NodeStatusRecord,
+        SplitIterations,
+        TokenScope,
+        WorkerMessage,
+        WorkflowStats,
+    },
+    infra::{
+        messaging::{store_completion, store_status_batch},
+        queue_depth::QueueDepth,
+    },
+};
 use tower::ServiceExt;
 
-#[derive(Serialize)]
-struct JwtClaims {
-    sub: String,
-    exp: usize,
-}
-
-fn jwt_for_user(user_id: &str) -> String {
-    encode(
-        &Header::default(),
-        &JwtClaims { sub: user_id.to_string(), exp: usize::MAX / 2 },
-        &EncodingKey::from_secret(Config::get().jwt_secret.as_bytes()),
-    )
-    .expect("jwt should be generated in tests")
-}
+/// Bound on response bodies read by these tests
+const MAX_BODY: usize = 16 * 1024 * 1024;
 
 #[tokio::test]
 async fn health_endpoint_returns_ok() {
@@ -104,7 +133,7 @@ async fn get_execution_with_valid_jwt_returns_document() {
         .expect("router should respond");
 
     assert_eq!(response.status(), StatusCode::OK);
-    let body = to_bytes(response.into_body(), usize::MAX)
+    let body = to_bytes(response.into_body(), MAX_BODY)
         .await
         .expect("body should be readable");
     let document: ExecutionDocument =
@@ -114,6 +143,324 @@ async fn get_execution_with_valid_jwt_returns_document() {
     assert_eq!(document.workflow_version_id, Some(1));
 }
 
+#[tokio::test]
+async fn get_execution_requires_a_grant_from_the_executions_organization() {
+    init_test_config();
+
+    for (grant_org, expected) in [("org-a", StatusCode::OK), ("org-b", StatusCode::FORBIDDEN)] {
+        let token_store = Arc::new(MockTokenStore {
+            validate_access_for_execution_result: true,
+            org_id: Some(grant_org.to_string()),
+            ..MockTokenStore::default()
+        });
+        let execution_store = Arc::new(MockExecutionStore::default());
+        {
+            let mut docs = execution_store
+                .execution_documents_by_id
+                .lock()
+                .expect("mock execution store mutex should not be poisoned");
+            let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+            doc.org_id = Some("org-a".to_string());
+            docs.insert("exec-1".to_string(), doc);
+        }
+        let router = app(build_state(token_store, execution_store));
+        let jwt = jwt_for_user_in_org("user-1", "org-a");
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/executions/exec-1")
+                    .header("Authorization", format!("Bearer {jwt}"))
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+
+        assert_eq!(response.status(), expected, "grant from {grant_org}");
+    }
+}
+
+#[tokio::test]
+async fn executions_of_another_organization_are_not_found() {
+    init_test_config();
+
+    // The caller holds a grant in its own organization, org-b
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        org_id: Some("org-b".to_string()),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.org_id = Some("org-a".to_string());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store.clone()));
+    let jwt = jwt_for_user_in_org("user-1", "org-b");
+    let send = |method: &str, uri: &str, body: Body| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {jwt}"))
+            .header("Content-Type", "application/json")
+            .header("x-org-id", "org-b")
+            .body(body)
+            .expect("request should build")
+    };
+
+    for uri in ["/executions/exec-1", "/executions/exec-1/nodes/node-1"] {
+        let response = router
+            .clone()
+            .oneshot(send("GET", uri, Body::empty()))
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "GET {uri}");
+    }
+
+    let response = router
+        .clone()
+        .oneshot(send(
+            "POST",
+            "/executions:batch",
+            Body::from(serde_json::json!({ "execution_ids": ["exec-1"] }).to_string()),
+        ))
+        .await
+        .expect("router should respond");
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let entries: serde_json::Value = serde_json::from_slice(&body).expect("batch should be json");
+    assert_eq!(entries, serde_json::json!({ "exec-1": "not_found" }));
+
+    let response = router
+        .oneshot(send("DELETE", "/executions/exec-1", Body::empty()))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(
+        execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .contains_key("exec-1"),
+        "another organization's execution is left alone"
+    );
+}
+
+#[tokio::test]
+async fn an_org_header_contradicting_the_jwt_is_refused_and_not_cached() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        org_id: Some("org-a".to_string()),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.org_id = Some("org-a".to_string());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let get = |jwt: String, org: Option<&str>| {
+        let mut request = Request::builder()
+            .uri("/executions/exec-1")
+            .header("Authorization", format!("Bearer {jwt}"));
+        if let Some(org) = org {
+            request = request.header("x-org-id", org);
+        }
+        router
+            .clone()
+            .oneshot(request.body(Body::empty()).expect("request should build"))
+    };
+
+    // Naming another organization than the token's is refused, not a miss
+    for (jwt, org) in
+        [(jwt_for_user_in_org("user-1", "org-a"), "org-b"), (jwt_for_user("user-1"), "org-a")]
+    {
+        let response = get(jwt, Some(org)).await.expect("router should respond");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{org}");
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        let error: serde_json::Value = serde_json::from_slice(&body).expect("error body is JSON");
+        assert_eq!(error.get("code"), Some(&serde_json::json!("ORG_MISMATCH")));
+    }
+
+    // The token's organization applies with or without the header
+    for org in [None, Some("org-a")] {
+        let response = get(jwt_for_user_in_org("user-1", "org-a"), org)
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::OK, "{org:?}");
+    }
+}
+
+#[tokio::test]
+async fn workflow_listing_and_stats_leave_out_another_organizations_executions() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_result: true,
+        org_id: Some("org-a".to_string()),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let in_org = |execution_id: &str, org_id: &str| ExecutionDocument {
+        org_id: Some(org_id.to_string()),
+        ..sample_execution(execution_id, "wf-1", Some("completed"))
+    };
+    execution_store
+        .executions_by_workflow
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("wf-1".to_string(), vec![in_org("exec-a", "org-a"), in_org("exec-b", "org-b")]);
+    let router = app(build_state(token_store, execution_store));
+    let get = |uri: &str| {
+        router.clone().oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(uri)
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", jwt_for_user_in_org("user-1", "org-a")),
+                )
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+
+    let response = get("/workflows/wf-1/executions")
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let page: ExecutionPage = serde_json::from_slice(&body).expect("response should be a page");
+    let listed: Vec<&str> = page
+        .executions
+        .iter()
+        .map(|doc| doc.execution_id.as_str())
+        .collect();
+    assert_eq!(listed, ["exec-a"]);
+
+    let response = get("/workflows/wf-1/stats")
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let stats: WorkflowStats = serde_json::from_slice(&body).expect("response should be stats");
+    assert_eq!(stats.total, 1);
+    assert_eq!(stats.by_status.get("completed"), Some(&1));
+}
+
+#[tokio::test]
+async fn workflow_routes_without_a_jwt_need_a_grant_of_the_named_organization() {
+    init_test_config();
+
+    // The workflow's only wildcard grant is org-a's
+    let token_store = Arc::new(MockTokenStore {
+        validate_workflow_access_result: true,
+        org_id: Some("org-a".to_string()),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .executions_by_workflow
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert(
+            "wf-1".to_string(),
+            vec![ExecutionDocument {
+                org_id: Some("org-b".to_string()),
+                ..sample_execution("exec-b", "wf-1", Some("completed"))
+            }],
+        );
+    let router = app(build_state(token_store, execution_store));
+    let get = |uri: &str, org_id: &str| {
+        router.clone().oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(uri)
+                .header("X-Org-Id", org_id)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+
+    for uri in ["/workflows/wf-1/executions", "/workflows/wf-1/stats"] {
+        let response = get(uri, "org-b").await.expect("router should respond");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        let response = get(uri, "org-a").await.expect("router should respond");
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn get_execution_is_gzipped_when_the_client_accepts_it() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    if let Some(latest) = doc
+        .nodes
+        .get_mut("node-1")
+        .and_then(|node| node.latest.as_mut())
+    {
+        latest.output = Some(serde_json::json!({ "rows": vec!["row"; 10_000] }));
+    }
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+    let fetch = |accept_encoding: Option<&'static str>| {
+        let mut builder = Request::builder()
+            .uri("/executions/exec-1")
+            .header("Authorization", format!("Bearer {jwt}"));
+        if let Some(value) = accept_encoding {
+            builder = builder.header("Accept-Encoding", value);
+        }
+        router
+            .clone()
+            .oneshot(builder.body(Body::empty()).expect("request should build"))
+    };
+
+    let response = fetch(Some("gzip")).await.expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-encoding")
+            .and_then(|v| v.to_str().ok()),
+        Some("gzip")
+    );
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    assert!(body.starts_with(&[0x1f, 0x8b]), "body should be a gzip stream");
+    assert!(body.len() < 10_000, "repetitive output should compress well");
+
+    let response = fetch(None).await.expect("router should respond");
+    assert!(response.headers().get("content-encoding").is_none());
+}
+
 #[tokio::test]
 async fn get_execution_without_jwt_uses_fallback_token_auth() {
     init_test_config();
@@ -184,11 +531,12 @@ async fn get_workflow_executions_with_valid_jwt_returns_documents() {
         .expect("router should respond");
 
     assert_eq!(response.status(), StatusCode::OK);
-    let body = to_bytes(response.into_body(), usize::MAX)
+    let body = to_bytes(response.into_body(), MAX_BODY)
         .await
         .expect("body should be readable");
-    let documents: Vec<ExecutionDocument> =
-        serde_json::from_slice(&body).expect("response should be a document array");
+    let page: ExecutionPage = serde_json::from_slice(&body).expect("response should be a page");
+    assert_eq!(page.next_cursor, None);
+    let documents = page.executions;
     assert_eq!(documents.len(), 2);
     assert!(documents.iter().all(|doc| doc.workflow_version == Some(1)));
     assert!(
@@ -198,6 +546,55 @@ async fn get_workflow_executions_with_valid_jwt_returns_documents() {
     );
 }
 
+#[tokio::test]
+async fn get_workflow_stats_summarizes_executions() {
+    init_test_config();
+
+    let token_store =
+        Arc::new(MockTokenStore { validate_access_result: true, ..MockTokenStore::default() });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let finished = |execution_id: &str, status: &str, duration_ms: i64| ExecutionDocument {
+        total_duration_ms: Some(duration_ms),
+        ..sample_execution(execution_id, "wf-1", Some(status))
+    };
+    execution_store
+        .executions_by_workflow
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert(
+            "wf-1".to_string(),
+            vec![
+                finished("exec-1", "completed", 100),
+                finished("exec-2", "failed", 300),
+                sample_execution("exec-3", "wf-1", None),
+            ],
+        );
+    let router = app(build_state(token_store, execution_store));
+
+    let response = router
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/workflows/wf-1/stats")
+                .header("Authorization", format!("Bearer {}", jwt_for_user("user-1")))
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let stats: WorkflowStats = serde_json::from_slice(&body).expect("response should be stats");
+    assert_eq!(stats.total, 3);
+    assert_eq!(stats.by_status.get("running"), Some(&1));
+    assert_eq!(stats.by_status.get("failed"), Some(&1));
+    assert_eq!(stats.avg_duration_ms, Some(200));
+    assert_eq!(stats.p95_duration_ms, Some(300));
+}
+
 #[tokio::test]
 async fn get_workflow_executions_fallback_unauthorized_returns_unauthorized() {
     init_test_config();
@@ -221,4 +618,2430 @@ async fn get_workflow_executions_fallback_unauthorized_returns_unauthorized() {
         .expect("router should respond");
 
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let error: serde_json::Value = serde_json::from_slice(&body).expect("error should be json");
+    assert_eq!(error.get("code"), Some(&serde_json::json!("UNAUTHORIZED")));
+}
+
+#[tokio::test]
+async fn valid_jwt_without_a_grant_is_forbidden() {
+    init_test_config();
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+    // Every grant check fails, so only the credentials differ between requests
+    let router = app(build_state(Arc::new(MockTokenStore::default()), execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    for uri in ["/executions/exec-1", "/workflows/wf-1/executions"] {
+        let request = |authorization: Option<String>| {
+            let mut builder = Request::builder().method("GET").uri(uri);
+            if let Some(value) = authorization {
+                builder = builder.header("Authorization", value);
+            }
+            builder.body(Body::empty()).expect("request should build")
+        };
+        let status = |request: Request<Body>| async {
+            router
+                .clone()
+                .oneshot(request)
+                .await
+                .expect("router should respond")
+                .status()
+        };
+
+        assert_eq!(
+            status(request(Some(format!("Bearer {jwt}")))).await,
+            StatusCode::FORBIDDEN,
+            "{uri}"
+        );
+        assert_eq!(
+            status(request(Some("Bearer invalid.token.value".to_string()))).await,
+            StatusCode::UNAUTHORIZED,
+            "{uri}"
+        );
+        assert_eq!(status(request(None)).await, StatusCode::UNAUTHORIZED, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn api_errors_carry_machine_readable_codes() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    for (uri, authorized, status, code) in [
+        ("/executions/missing", true, StatusCode::NOT_FOUND, "EXECUTION_NOT_FOUND"),
+        ("/executions/missing/nodes/node-1", true, StatusCode::NOT_FOUND, "EXECUTION_NOT_FOUND"),
+        ("/executions/exec-1/nodes/missing", true, StatusCode::NOT_FOUND, "NODE_NOT_FOUND"),
+        (
+            "/executions/exec-1/context?path=/missing",
+            true,
+            StatusCode::NOT_FOUND,
+            "CONTEXT_PATH_NOT_FOUND",
+        ),
+        ("/executions", false, StatusCode::UNAUTHORIZED, "UNAUTHORIZED"),
+        ("/workflows/wf-1/executions", true, StatusCode::FORBIDDEN, "FORBIDDEN"),
+        (
+            "/workflows/wf-1/executions?after=garbage",
+            true,
+            StatusCode::BAD_REQUEST,
+            "INVALID_CURSOR",
+        ),
+        (
+            "/executions/exec-1/statuses?after=garbage",
+            true,
+            StatusCode::BAD_REQUEST,
+            "INVALID_CURSOR",
+        ),
+    ] {
+        let mut request = Request::builder().method("GET").uri(uri);
+        if authorized {
+            request = request.header("Authorization", format!("Bearer {jwt}"));
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).expect("request should build"))
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), status, "{uri}");
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        let error: serde_json::Value = serde_json::from_slice(&body).expect("error should be json");
+        assert_eq!(error.get("code"), Some(&serde_json::json!(code)), "{uri}");
+        assert!(
+            error
+                .get("message")
+                .is_some_and(serde_json::Value::is_string),
+            "{uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn unreachable_stores_answer_503_and_broken_ones_500() {
+    init_test_config();
+    let load = |load_error: fn() -> StoreError| async move {
+        let execution_store =
+            MockExecutionStore { load_error: Some(load_error), ..MockExecutionStore::default() };
+        let response =
+            app(build_state(Arc::new(MockTokenStore::default()), Arc::new(execution_store)))
+                .oneshot(
+                    Request::builder()
+                        .uri("/executions/exec-1")
+                        .header("Authorization", format!("Bearer {}", jwt_for_user("user-1")))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+        let status = response.status();
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        let error: serde_json::Value = serde_json::from_slice(&body).expect("error should be json");
+        (status, error.get("code").cloned())
+    };
+
+    assert_eq!(
+        load(|| StoreError::Transient("mongodb unreachable".into())).await,
+        (StatusCode::SERVICE_UNAVAILABLE, Some(serde_json::json!("SERVICE_UNAVAILABLE")))
+    );
+    assert_eq!(
+        load(|| StoreError::Fatal("corrupt document".into())).await,
+        (StatusCode::INTERNAL_SERVER_ERROR, Some(serde_json::json!("DB_ERROR")))
+    );
+}
+
+fn status_record(id: &str, node_id: &str, executed_at: &str) -> NodeStatusRecord {
+    NodeStatusRecord {
+        id:      id.to_string(),
+        message: sample_status("exec-1", node_id, "success", executed_at),
+    }
+}
+
+#[tokio::test]
+async fn get_execution_statuses_paginates_stably_while_statuses_arrive() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    execution_store
+        .status_history
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .extend([
+            status_record("a", "node-1", "2026-01-01T00:00:01Z"),
+            status_record("b", "node-2", "2026-01-01T00:00:02Z"),
+            status_record("c", "node-3", "2026-01-01T00:00:02Z"),
+        ]);
+    let router = app(build_state(token_store, execution_store.clone()));
+
+    let first = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/executions/exec-1/statuses?limit=2")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(first.status(), StatusCode::OK);
+    let cursor = first
+        .headers()
+        .get("x-next-cursor")
+        .expect("first page should carry a next cursor")
+        .to_str()
+        .expect("cursor should be ASCII")
+        .to_string();
+    let body = to_bytes(first.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let page: Vec<NodeStatusRecord> = serde_json::from_slice(&body).expect("status page");
+    assert_eq!(page.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+
+    // A late status that sorts before the cursor and a new one after it
+    execution_store
+        .status_history
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .extend([
+            status_record("z", "node-0", "2026-01-01T00:00:00Z"),
+            status_record("d", "node-4", "2026-01-01T00:00:03Z"),
+        ]);
+
+    let second = router
+        .oneshot(
+            Request::builder()
+                .uri(format!("/executions/exec-1/statuses?limit=2&after={cursor}"))
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(second.status(), StatusCode::OK);
+    assert!(second.headers().get("x-next-cursor").is_none());
+    let body = to_bytes(second.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let page: Vec<NodeStatusRecord> = serde_json::from_slice(&body).expect("status page");
+    assert_eq!(page.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["c", "d"]);
+    // Paging the log never reads the execution document itself
+    assert_eq!(execution_store.document_loads.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn status_history_pages_by_offset() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    execution_store
+        .status_history
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .extend([
+            status_record("a", "node-1", "2026-01-01T00:00:01Z"),
+            status_record("b", "node-2", "2026-01-01T00:00:02Z"),
+            status_record("c", "node-3", "2026-01-01T00:00:03Z"),
+        ]);
+
+    let response = app(build_state(token_store, execution_store))
+        .oneshot(
+            Request::builder()
+                .uri("/executions/exec-1/status-history?limit=1&offset=1")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("x-next-cursor").is_some());
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let page: Vec<NodeStatusRecord> = serde_json::from_slice(&body).expect("status page");
+    assert_eq!(page.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["b"]);
+}
+
+#[tokio::test]
+async fn status_history_leaves_out_another_organizations_entries() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        org_id: Some("org-a".to_string()),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.org_id = Some("org-a".to_string());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let in_org = |id: &str, org_id: &str| {
+        let mut record = status_record(id, "node-1", "2026-01-01T00:00:01Z");
+        record.message.org_id = Some(org_id.to_string());
+        record
+    };
+    execution_store
+        .status_history
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .extend([in_org("a", "org-a"), in_org("b", "org-b")]);
+
+    let response = app(build_state(token_store, execution_store))
+        .oneshot(
+            Request::builder()
+                .uri("/executions/exec-1/statuses")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", jwt_for_user_in_org("user-1", "org-a")),
+                )
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let page: Vec<NodeStatusRecord> = serde_json::from_slice(&body).expect("status page");
+    assert_eq!(page.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["a"]);
+}
+
+#[tokio::test]
+async fn get_execution_statuses_rejects_invalid_cursor() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+
+    let response = app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/executions/exec-1/statuses?after=not-a-cursor")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cors_preflight_allows_the_org_header() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+
+    let response = app(state)
+        .oneshot(
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/executions/exec-1")
+                .header("Origin", rtes::config::Config::get().cors_origin.as_str())
+                .header("Access-Control-Request-Method", "GET")
+                .header("Access-Control-Request-Headers", "authorization,x-org-id")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let allowed = response
+        .headers()
+        .get("access-control-allow-headers")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    assert!(allowed.split(',').any(|name| name.trim() == "x-org-id"), "{allowed}");
+}
+
+#[tokio::test]
+async fn event_stream_rejects_unparsable_query_params_with_a_code() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+
+    for uri in [
+        "/executions/exec-1/events?since=yesterday",
+        "/executions/exec-1/events?include=everything",
+    ] {
+        let response = app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        let error: serde_json::Value = serde_json::from_slice(&body).expect("error body is JSON");
+        assert_eq!(error.get("code"), Some(&serde_json::json!("INVALID_QUERY")), "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn split_routers_keep_internal_routes_off_the_public_port() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+
+    let get = |uri: &str| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .body(Body::empty())
+            .expect("request should build")
+    };
+
+    let public = public_app(state.clone())
+        .oneshot(get("/metrics"))
+        .await
+        .expect("public router should respond");
+    assert_eq!(public.status(), StatusCode::NOT_FOUND);
+
+    let admin = admin_app(state.clone())
+        .oneshot(get("/health"))
+        .await
+        .expect("admin router should respond");
+    assert_eq!(admin.status(), StatusCode::OK);
+
+    let admin_api = admin_app(state.clone())
+        .oneshot(get("/executions/exec-1"))
+        .await
+        .expect("admin router should respond");
+    assert_eq!(admin_api.status(), StatusCode::NOT_FOUND);
+
+    let public_dlq = public_app(state.clone())
+        .oneshot(get("/dlq/workflow.node.status.dlq"))
+        .await
+        .expect("public router should respond");
+    assert_eq!(public_dlq.status(), StatusCode::NOT_FOUND);
+
+    let repair = |uri: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(Body::empty())
+            .expect("request should build")
+    };
+    let public_repair = public_app(state.clone())
+        .oneshot(repair("/executions/exec-1/repair"))
+        .await
+        .expect("public router should respond");
+    assert_eq!(public_repair.status(), StatusCode::NOT_FOUND);
+    // RTES_ADMIN_TOKEN is unset in tests, so the admin route refuses
+    let admin_repair = admin_app(state)
+        .oneshot(repair("/executions/exec-1/repair"))
+        .await
+        .expect("admin router should respond");
+    assert_eq!(admin_repair.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn batched_status_writes_take_one_store_round_trip_per_batch() {
+    let statuses: Vec<NodeStatusMessage> = (0..100)
+        .map(|i| {
+            sample_status(
+                &format!("exec-{}", i % 4),
+                &format!("node-{i}"),
+                "success",
+                "2026-01-01T00:00:00Z",
+            )
+        })
+        .collect();
+
+    // What the consumer does with RABBITMQ_STATUS_BATCH_SIZE=1
+    let unbatched = MockExecutionStore::default();
+    for msg in &statuses {
+        assert!(
+            unbatched
+                .update_node_status(msg)
+                .await
+                .expect("status stored")
+        );
+    }
+
+    let batched = MockExecutionStore::default();
+    let results = store_status_batch(&batched, &statuses)
+        .await
+        .expect("batch stored");
+    assert!(results.iter().all(|applied| matches!(applied, Ok(true))));
+
+    let round_trips = |store: &MockExecutionStore| store.status_writes.load(Ordering::Relaxed);
+    assert_eq!((round_trips(&unbatched), round_trips(&batched)), (100, 1));
+}
+
+#[tokio::test]
+async fn a_status_batch_failing_on_one_message_still_stores_the_others() {
+    let statuses: Vec<NodeStatusMessage> = ["node-1", "node-2", "node-3"]
+        .into_iter()
+        .map(|node_id| sample_status("exec-1", node_id, "success", "2026-01-01T00:00:00Z"))
+        .collect();
+    let store = MockExecutionStore {
+        failing_status_node: Some("node-2".to_string()),
+        ..MockExecutionStore::default()
+    };
+
+    let results = store_status_batch(&store, &statuses)
+        .await
+        .expect("only a transient failure fails the batch");
+    assert!(matches!(results.as_slice(), [Ok(true), Err(StoreError::Fatal(_)), Ok(true)]));
+    // The failed batch, then each message on its own
+    assert_eq!(store.status_writes.load(Ordering::Relaxed), 4);
+}
+
+#[tokio::test]
+async fn a_redelivered_completion_is_not_recorded_or_broadcast_again() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    let mut rx = state.tx.subscribe();
+    let completion = CompletionMessage {
+        workflow_id:       "wf-1".to_string(),
+        execution_id:      "exec-1".to_string(),
+        status:            "completed".to_string(),
+        final_context:     serde_json::json!({}),
+        completed_at:      "2026-01-01T00:00:00Z".to_string(),
+        total_duration_ms: 10,
+        failure_reason:    None,
+        org_id:            None,
+    };
+
+    let first = store_completion(&state, completion.clone()).await;
+    let redelivered = store_completion(&state, completion).await;
+    assert!(matches!((first, redelivered), (Ok(true), Ok(false))));
+
+    assert!(matches!(rx.try_recv(), Ok(WorkerMessage::WorkflowCompletion(_))));
+    assert!(rx.try_recv().is_err(), "the redelivery is not broadcast");
+}
+
+#[tokio::test]
+async fn metrics_route_renders_prometheus_counters() {
+    init_test_config();
+    rtes::infra::prometheus::handle();
+    rtes::infra::prometheus::message_consumed("metrics.test");
+    rtes::infra::prometheus::message_acked("metrics.test");
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+
+    let response = admin_app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let text = String::from_utf8(body.to_vec()).expect("metrics should be utf-8");
+    assert!(text.contains("rtes_messages_consumed_total{queue=\"metrics.test\"} 1"), "{text}");
+    assert!(text.contains("rtes_messages_acked_total{queue=\"metrics.test\"} 1"), "{text}");
+}
+
+#[tokio::test]
+async fn refused_callers_are_counted_by_reason() {
+    init_test_config();
+    rtes::infra::prometheus::handle();
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+    let state = build_state(Arc::new(MockTokenStore::default()), execution_store);
+    let request = |token: &str| {
+        Request::builder()
+            .uri("/executions/exec-1")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .expect("request should build")
+    };
+
+    let no_grant = app(state.clone())
+        .oneshot(request(&jwt_for_user("user-1")))
+        .await
+        .expect("router should respond");
+    assert_eq!(no_grant.status(), StatusCode::FORBIDDEN);
+    let invalid = app(state.clone())
+        .oneshot(request("not-a-jwt"))
+        .await
+        .expect("router should respond");
+    assert_eq!(invalid.status(), StatusCode::UNAUTHORIZED);
+
+    let response = admin_app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let text = String::from_utf8(body.to_vec()).expect("metrics should be utf-8");
+    assert!(text.contains("rtes_auth_failures_total{reason=\"no-grant\"}"), "{text}");
+    assert!(text.contains("rtes_auth_failures_total{reason=\"invalid\"}"), "{text}");
+}
+
+#[tokio::test]
+async fn a_refused_jwt_is_counted_once_per_request() {
+    init_test_config();
+    rtes::infra::prometheus::handle();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    let forged = jwt_signed_with("user-1", "not-the-service-secret");
+
+    for uri in ["/executions/exec-1", "/executions", "/workflows/wf-1/executions"] {
+        let response = app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("Authorization", format!("Bearer {forged}"))
+                    .header("X-Org-Id", "org-1")
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+    }
+
+    let response = admin_app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let text = String::from_utf8(body.to_vec()).expect("metrics should be utf-8");
+    assert!(text.contains("rtes_auth_failures_total{reason=\"invalid-signature\"} 3"), "{text}");
+}
+
+#[tokio::test]
+async fn connections_route_reports_open_websockets() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    state.connections.fetch_add(2, Ordering::Relaxed);
+
+    let response = admin_app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/connections")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let json: serde_json::Value = serde_json::from_slice(&body).expect("body should be json");
+    assert_eq!(json, serde_json::json!({ "active": 2 }));
+}
+
+#[tokio::test]
+async fn queues_route_reports_polled_depths_and_unacked_deliveries() {
+    init_test_config();
+    rtes::infra::prometheus::handle();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+
+    // Two deliveries taken, one settled
+    for _ in 0..2 {
+        rtes::infra::prometheus::message_consumed("queues.test");
+    }
+    rtes::infra::prometheus::message_acked("queues.test");
+    state.queues.record(
+        "queues.test",
+        QueueDepth {
+            ready:   7,
+            unacked: rtes::infra::prometheus::unacked("queues.test"),
+            dlq:     Some(2),
+        },
+    );
+
+    let get = |uri: &'static str| {
+        let state = state.clone();
+        async move {
+            let response = admin_app(state)
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            assert_eq!(response.status(), StatusCode::OK);
+            to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable")
+        }
+    };
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&get("/queues").await).expect("body should be json");
+    assert_eq!(json, serde_json::json!({ "queues.test": { "ready": 7, "unacked": 1, "dlq": 2 } }));
+    let metrics =
+        String::from_utf8(get("/metrics").await.to_vec()).expect("metrics should be utf-8");
+    assert!(metrics.contains("rtes_messages_unacked{queue=\"queues.test\"} 1"), "{metrics}");
+    assert!(
+        metrics.contains("rtes_queue_messages{queue=\"queues.test\",state=\"ready\"} 7"),
+        "{metrics}"
+    );
+}
+
+#[tokio::test]
+async fn dlq_routes_require_the_admin_secret() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    let queue = format!("{}.dlq", rtes::config::Config::get().rabbitmq_status_queue);
+
+    // Without ADMIN_PORT the admin routes are served by the public router too,
+    // so both must refuse before any broker connection is attempted.
+    // RTES_ADMIN_TOKEN is unset in tests, so no secret is accepted
+    for router in [app(state.clone()), admin_app(state.clone())] {
+        for (method, uri) in
+            [("GET", format!("/dlq/{queue}")), ("POST", format!("/dlq/{queue}/replay"))]
+        {
+            for token in [None, Some("guess")] {
+                let mut request = Request::builder().method(method).uri(&uri);
+                if let Some(token) = token {
+                    request = request.header("x-admin-token", token);
+                }
+                let response = router
+                    .clone()
+                    .oneshot(request.body(Body::empty()).expect("request should build"))
+                    .await
+                    .expect("router should respond");
+                assert_eq!(response.status(), StatusCode::FORBIDDEN, "{method} {uri} {token:?}");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn monitoring_routes_on_the_public_port_require_the_admin_secret() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    let get = |router: axum::Router, uri: &'static str| {
+        router.oneshot(
+            Request::builder()
+                .uri(uri)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+
+    // RTES_ADMIN_TOKEN is unset in tests, so the shared port refuses them
+    // while the firewalled admin port serves them as is
+    for uri in ["/metrics", "/connections", "/queues"] {
+        let shared = get(app(state.clone()), uri)
+            .await
+            .expect("router should respond");
+        assert_eq!(shared.status(), StatusCode::FORBIDDEN, "{uri}");
+        let admin = get(admin_app(state.clone()), uri)
+            .await
+            .expect("router should respond");
+        assert_eq!(admin.status(), StatusCode::OK, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn rate_limit_throttles_each_client_but_not_probes() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    // Refilling takes a second, far longer than the requests below
+    let state = build_state(token_store, execution_store).with_rate_limiter(RateLimiter::new(1, 2));
+    let router = app(state);
+    let send = |uri: &str, header: (&'static str, String)| {
+        router.clone().oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(header.0, header.1)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+    let user = |id: &str| ("Authorization", format!("Bearer {}", jwt_for_user(id)));
+
+    for _ in 0..2 {
+        let response = send("/executions/exec-1", user("user-1"))
+            .await
+            .expect("respond");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let throttled = send("/executions/exec-1", user("user-1"))
+        .await
+        .expect("respond");
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = throttled
+        .headers()
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .expect("429 should carry a numeric Retry-After");
+    assert!(retry_after >= 1);
+    let body = to_bytes(throttled.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let error: serde_json::Value = serde_json::from_slice(&body).expect("error body is JSON");
+    assert_eq!(error.get("code"), Some(&serde_json::json!("RATE_LIMITED")));
+
+    // Another user, and anonymous clients keyed by address, have their own
+    // buckets
+    let other = send("/executions/exec-1", user("user-2"))
+        .await
+        .expect("respond");
+    assert_eq!(other.status(), StatusCode::OK);
+    let forwarded = ("x-forwarded-for", "203.0.113.7, 10.0.0.1".to_string());
+    for expected in
+        [StatusCode::UNAUTHORIZED, StatusCode::UNAUTHORIZED, StatusCode::TOO_MANY_REQUESTS]
+    {
+        let response = send("/workflows/wf-1/executions", forwarded.clone())
+            .await
+            .expect("respond");
+        assert_eq!(response.status(), expected);
+    }
+
+    for _ in 0..5 {
+        let response = send("/health", user("user-1")).await.expect("respond");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn rate_limit_believes_forwarded_headers_only_from_trusted_proxies() {
+    init_test_config();
+    let proxy: IpAddr = "10.0.0.1".parse().expect("proxy IP");
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()))
+            .with_rate_limiter(RateLimiter::new(1, 1).with_trusted_proxies(&[proxy]));
+    let router = app(state);
+    let send = |peer: &str, forwarded_for: &str| {
+        let peer: SocketAddr = format!("{peer}:40000").parse().expect("peer address");
+        router.clone().oneshot(
+            Request::builder()
+                .uri("/workflows/wf-1/executions")
+                .header("x-forwarded-for", forwarded_for)
+                .extension(ConnectInfo(peer))
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+
+    // A direct client can't get a fresh bucket by spoofing the header
+    let first = send("198.51.100.9", "203.0.113.7").await.expect("respond");
+    assert_eq!(first.status(), StatusCode::UNAUTHORIZED);
+    let spoofed = send("198.51.100.9", "203.0.113.8").await.expect("respond");
+    assert_eq!(spoofed.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Behind the trusted proxy each forwarded client has its own bucket
+    for client in ["203.0.113.7", "203.0.113.8"] {
+        let response = send("10.0.0.1", client).await.expect("respond");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{client}");
+    }
+    // A hop the client prepended itself doesn't count
+    let prepended = send("10.0.0.1", "192.0.2.1, 203.0.113.7")
+        .await
+        .expect("respond");
+    assert_eq!(prepended.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn oversized_request_bodies_are_rejected_with_413() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    let oversized = rtes::config::Config::get().max_body_bytes + 1;
+    let request = |method: &str, uri: &str| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-length", oversized)
+            .body(Body::from(vec![0_u8; oversized]))
+            .expect("request should build")
+    };
+
+    let response = public_app(state.clone())
+        .oneshot(request("GET", "/executions/exec-1"))
+        .await
+        .expect("public router should respond");
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let response = admin_app(state)
+        .oneshot(request("POST", "/tokens/some-jti/revoke"))
+        .await
+        .expect("admin router should respond");
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn token_revocation_is_disabled_without_admin_secret() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore::default());
+    let state = build_state(token_store.clone(), Arc::new(MockExecutionStore::default()));
+    let grant = ExecutionToken {
+        execution_id: Some("exec-1".to_string()),
+        workflow_id:  "wf-1".to_string(),
+        iat:          1,
+        exp:          i64::MAX,
+        user_id:      "user-1".to_string(),
+        jti:          ExecutionToken::new_jti(),
+        scope:        TokenScope::Owner,
+        org_id:       None,
+    };
+    token_store.add_token(&grant).await.expect("add token");
+
+    let response = admin_app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/tokens/{}/revoke", grant.jti))
+                .header("x-admin-token", "guess")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("admin router should respond");
+
+    // RTES_ADMIN_TOKEN is unset in tests, so no secret is accepted
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        token_store
+            .list_user_tokens("user-1")
+            .await
+            .expect("list tokens"),
+        vec![grant]
+    );
+}
+
+#[tokio::test]
+async fn readyz_reports_consumer_that_failed_to_start() {
+    init_test_config();
+    let state =
+        build_state(Arc::new(MockTokenStore::default()), Arc::new(MockExecutionStore::default()));
+    state.consumers.register("token").running();
+    state
+        .consumers
+        .register("status")
+        .failed("PRECONDITION_FAILED - inequivalent arg 'x-dead-letter-exchange'");
+
+    let response = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/readyz")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let json: serde_json::Value = serde_json::from_slice(&body).expect("body should be json");
+    assert_eq!(json.pointer("/ready"), Some(&serde_json::json!(false)));
+    assert_eq!(json.pointer("/consumers/token/state"), Some(&serde_json::json!("running")));
+    assert_eq!(json.pointer("/consumers/status/state"), Some(&serde_json::json!("failed")));
+    assert!(
+        json.pointer("/consumers/status/error")
+            .and_then(serde_json::Value::as_str)
+            .is_some_and(|e| e.contains("PRECONDITION_FAILED"))
+    );
+}
+
+#[tokio::test]
+async fn list_user_executions_returns_granted_summaries_newest_first() {
+    init_test_config();
+    let grant = |user_id: &str, workflow_id: &str, execution_id: Option<&str>| ExecutionToken {
+        execution_id: execution_id.map(ToOwned::to_owned),
+        workflow_id:  workflow_id.to_string(),
+        iat:          0,
+        exp:          i64::MAX,
+        user_id:      user_id.to_string(),
+        jti:          ExecutionToken::new_jti(),
+        scope:        TokenScope::Owner,
+        org_id:       None,
+    };
+    let token_store = Arc::new(MockTokenStore {
+        added_tokens: Mutex::new(vec![
+            grant("user-1", "wf-1", None),
+            grant("user-1", "wf-2", Some("exec-2")),
+            grant("user-2", "wf-3", None),
+        ]),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    {
+        let mut docs = execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        for (millis, (execution_id, workflow_id)) in
+            [("exec-1", "wf-1"), ("exec-2", "wf-2"), ("exec-3", "wf-3"), ("exec-4", "wf-2")]
+                .into_iter()
+                .enumerate()
+        {
+            let mut doc = sample_execution(execution_id, workflow_id, Some("completed"));
+            doc.created_at = Some(DateTime::from_millis(i64::try_from(millis).unwrap_or(0)));
+            docs.insert(execution_id.to_string(), doc);
+        }
+    }
+    let router = app(build_state(token_store, execution_store));
+
+    let response = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/executions?limit=10")
+                .header("Authorization", format!("Bearer {}", jwt_for_user("user-1")))
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let summaries: Vec<serde_json::Value> =
+        serde_json::from_slice(&body).expect("response should be a summary array");
+    let ids: Vec<_> = summaries
+        .iter()
+        .filter_map(|summary| {
+            summary
+                .get("execution_id")
+                .and_then(serde_json::Value::as_str)
+        })
+        .collect();
+    assert_eq!(ids, vec!["exec-2", "exec-1"]);
+    assert!(
+        summaries
+            .iter()
+            .all(|summary| summary.get("nodes").is_none())
+    );
+
+    let unauthenticated = router
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/executions")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn list_user_executions_only_uses_grants_of_the_callers_organization() {
+    init_test_config();
+    let grant = |org_id: &str| ExecutionToken {
+        execution_id: None,
+        workflow_id:  "wf-1".to_string(),
+        iat:          0,
+        exp:          i64::MAX,
+        user_id:      "user-1".to_string(),
+        jti:          ExecutionToken::new_jti(),
+        scope:        TokenScope::Owner,
+        org_id:       Some(org_id.to_string()),
+    };
+    let token_store = Arc::new(MockTokenStore {
+        added_tokens: Mutex::new(vec![grant("org-b")]),
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert(
+            "exec-a".to_string(),
+            ExecutionDocument {
+                org_id: Some("org-a".to_string()),
+                ..sample_execution("exec-a", "wf-1", Some("completed"))
+            },
+        );
+    let router = app(build_state(token_store.clone(), execution_store));
+    let list = || async {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/executions")
+                    .header(
+                        "Authorization",
+                        format!("Bearer {}", jwt_for_user_in_org("user-1", "org-a")),
+                    )
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        serde_json::from_slice::<Vec<serde_json::Value>>(&body)
+            .expect("response should be a summary array")
+    };
+
+    // org-b's grant for wf-1 doesn't reach org-a's run of it
+    assert!(list().await.is_empty());
+    token_store
+        .added_tokens
+        .lock()
+        .expect("mock token store mutex should not be poisoned")
+        .push(grant("org-a"));
+    assert_eq!(list().await.len(), 1);
+}
+
+#[tokio::test]
+async fn get_workflow_executions_pages_newest_first_by_cursor() {
+    init_test_config();
+    let token_store =
+        Arc::new(MockTokenStore { validate_access_result: true, ..MockTokenStore::default() });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    {
+        let mut docs = execution_store
+            .executions_by_workflow
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        let executions = ["exec-a", "exec-b", "exec-c"]
+            .into_iter()
+            .map(|execution_id| {
+                let mut doc = sample_execution(execution_id, "wf-1", Some("completed"));
+                // exec-b and exec-c share a timestamp; execution_id breaks the tie
+                doc.created_at =
+                    Some(DateTime::from_millis(if execution_id == "exec-a" { 1 } else { 2 }));
+                doc
+            })
+            .collect();
+        docs.insert("wf-1".to_string(), executions);
+    }
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let mut seen = Vec::new();
+    let mut uri = "/workflows/wf-1/executions?limit=2".to_string();
+    loop {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(&uri)
+                    .header("Authorization", format!("Bearer {jwt}"))
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        let page: ExecutionPage = serde_json::from_slice(&body).expect("response should be a page");
+        seen.extend(page.executions.into_iter().map(|doc| doc.execution_id));
+        match page.next_cursor {
+            Some(cursor) => uri = format!("/workflows/wf-1/executions?limit=2&after={cursor}"),
+            None => break,
+        }
+    }
+    assert_eq!(seen, vec!["exec-c", "exec-b", "exec-a"]);
+
+    let invalid = router
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/workflows/wf-1/executions?after=not-a-cursor")
+                .header("Authorization", format!("Bearer {jwt}"))
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_workflow_executions_filters_by_node_type_across_pages() {
+    init_test_config();
+    let token_store =
+        Arc::new(MockTokenStore { validate_access_result: true, ..MockTokenStore::default() });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let executions =
+        [("exec-a", "http"), ("exec-b", "code"), ("exec-c", "http"), ("exec-d", "http")]
+            .into_iter()
+            .zip(1..)
+            .map(|((execution_id, node_type), created_ms)| {
+                let mut doc = sample_execution(execution_id, "wf-1", Some("completed"));
+                doc.created_at = Some(DateTime::from_millis(created_ms));
+                if let Some(latest) = doc
+                    .nodes
+                    .get_mut("node-1")
+                    .and_then(|node| node.latest.as_mut())
+                {
+                    latest.node_type = Some(node_type.to_string());
+                }
+                doc
+            })
+            .collect();
+    execution_store
+        .executions_by_workflow
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("wf-1".to_string(), executions);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: String| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            serde_json::from_slice::<ExecutionPage>(&body).expect("response should be a page")
+        }
+    };
+
+    let first = fetch("/workflows/wf-1/executions?limit=2&node_type=http".to_string()).await;
+    let cursor = first.next_cursor.expect("a second page of http executions");
+    let second =
+        fetch(format!("/workflows/wf-1/executions?limit=2&node_type=http&after={cursor}")).await;
+    let seen: Vec<_> = first
+        .executions
+        .into_iter()
+        .chain(second.executions)
+        .map(|doc| doc.execution_id)
+        .collect();
+    assert_eq!(seen, vec!["exec-d", "exec-c", "exec-a"]);
+    assert_eq!(second.next_cursor, None);
+
+    let none = fetch("/workflows/wf-1/executions?node_type=email".to_string()).await;
+    assert!(none.executions.is_empty());
+    assert_eq!(none.next_cursor, None);
+}
+
+#[tokio::test]
+async fn delete_execution_requires_access_and_removes_document() {
+    init_test_config();
+    let seeded_store = || {
+        let execution_store = Arc::new(MockExecutionStore::default());
+        execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+        execution_store
+            .status_history
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .push(NodeStatusRecord {
+                id:      "s-1".to_string(),
+                message: sample_status("exec-1", "node-1", "success", "2026-01-01T00:00:00Z"),
+            });
+        execution_store
+    };
+    let delete = |jwt: &str| {
+        Request::builder()
+            .method("DELETE")
+            .uri("/executions/exec-1")
+            .header("Authorization", format!("Bearer {jwt}"))
+            .body(Body::empty())
+            .expect("request should build")
+    };
+    let jwt = jwt_for_user("user-1");
+
+    let denied_store = seeded_store();
+    let denied = app(build_state(Arc::new(MockTokenStore::default()), denied_store.clone()))
+        .oneshot(delete(&jwt))
+        .await
+        .expect("router should respond");
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+    assert!(
+        denied_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .contains_key("exec-1")
+    );
+
+    let execution_store = seeded_store();
+    let router = app(build_state(
+        Arc::new(MockTokenStore {
+            validate_access_for_execution_result: true,
+            ..MockTokenStore::default()
+        }),
+        execution_store.clone(),
+    ));
+    let deleted = router
+        .clone()
+        .oneshot(delete(&jwt))
+        .await
+        .expect("router should respond");
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    // Ownership is checked from the owner projection, not the document
+    assert_eq!(execution_store.document_loads.load(Ordering::Relaxed), 0);
+    assert!(
+        execution_store
+            .status_history
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .is_empty()
+    );
+
+    let missing = router
+        .oneshot(delete(&jwt))
+        .await
+        .expect("router should respond");
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn viewer_grants_can_read_but_not_delete_an_execution() {
+    init_test_config();
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+    let router = app(build_state(
+        Arc::new(MockTokenStore {
+            validate_access_for_execution_result: true,
+            viewer_only: true,
+            ..MockTokenStore::default()
+        }),
+        execution_store.clone(),
+    ));
+    let request = |method: &str| {
+        Request::builder()
+            .method(method)
+            .uri("/executions/exec-1")
+            .header("Authorization", format!("Bearer {}", jwt_for_user("user-1")))
+            .body(Body::empty())
+            .expect("request should build")
+    };
+
+    let read = router
+        .clone()
+        .oneshot(request("GET"))
+        .await
+        .expect("router should respond");
+    assert_eq!(read.status(), StatusCode::OK);
+
+    let delete = router
+        .clone()
+        .oneshot(request("DELETE"))
+        .await
+        .expect("router should respond");
+    assert_eq!(delete.status(), StatusCode::FORBIDDEN);
+    assert!(
+        execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .contains_key("exec-1")
+    );
+
+    // The refused delete isn't remembered as a read denial
+    let reread = router
+        .oneshot(request("GET"))
+        .await
+        .expect("router should respond");
+    assert_eq!(reread.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn get_execution_filters_nodes_by_latest_status() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    {
+        let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+        for (node_id, status) in [("node-2", "failed"), ("node-3", "Running")] {
+            doc.nodes.insert(
+                node_id.to_string(),
+                HydratedNode {
+                    latest: Some(NodeExecutionInstance {
+                        status: Some(status.to_string()),
+                        ..NodeExecutionInstance::default()
+                    }),
+                    ..HydratedNode::default()
+                },
+            );
+        }
+        execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .insert("exec-1".to_string(), doc);
+    }
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: &'static str| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            let doc: ExecutionDocument =
+                serde_json::from_slice(&body).expect("response should be a document");
+            let mut node_ids: Vec<String> = doc.nodes.into_keys().collect();
+            node_ids.sort();
+            node_ids
+        }
+    };
+
+    assert_eq!(fetch("/executions/exec-1").await, vec!["node-1", "node-2", "node-3"]);
+    assert_eq!(fetch("/executions/exec-1?status=FAILED,running").await, vec!["node-2", "node-3"]);
+    assert!(fetch("/executions/exec-1?status=skipped").await.is_empty());
+}
+
+#[tokio::test]
+async fn get_execution_compact_view_omits_node_payloads() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    if let Some(latest) = doc
+        .nodes
+        .get_mut("node-1")
+        .and_then(|node| node.latest.as_mut())
+    {
+        latest.input = Some(serde_json::json!({ "payload": "large" }));
+        latest.output = Some(serde_json::json!({ "result": "large" }));
+    }
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: &'static str| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            (status, serde_json::from_slice::<serde_json::Value>(&body).ok())
+        }
+    };
+
+    let (status, full) = fetch("/executions/exec-1?view=full").await;
+    assert_eq!(status, StatusCode::OK);
+    let full = full.expect("body should be json");
+    assert_eq!(
+        full.pointer("/nodes/node-1/latest/input/payload"),
+        Some(&serde_json::json!("large"))
+    );
+    // Stored without counters, so they are derived from the nodes
+    assert_eq!(
+        full.get("progress"),
+        Some(&serde_json::json!({ "total_nodes": 1, "succeeded": 1, "failed": 0, "running": 0 }))
+    );
+
+    let (status, compact) = fetch("/executions/exec-1?view=compact").await;
+    assert_eq!(status, StatusCode::OK);
+    let compact = compact.expect("body should be json");
+    let latest = compact
+        .pointer("/nodes/node-1/latest")
+        .expect("compact view keeps the latest run");
+    assert_eq!(latest.get("status"), Some(&serde_json::json!("success")));
+    assert_eq!(latest.get("input"), Some(&serde_json::Value::Null));
+    assert_eq!(latest.get("output"), Some(&serde_json::Value::Null));
+
+    let (status, _) = fetch("/executions/exec-1?view=tiny").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_execution_context_selects_a_path() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.accumulated_context = serde_json::json!({
+        "$trigger": { "user": { "email": "ada@example.com" } },
+        "items": [{ "id": 7 }],
+    });
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: &'static str| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            (status, serde_json::from_slice::<serde_json::Value>(&body).ok())
+        }
+    };
+
+    let (status, whole) = fetch("/executions/exec-1/context").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(whole.and_then(|v| v.get("items").cloned()), Some(serde_json::json!([{ "id": 7 }])));
+
+    let (status, email) = fetch("/executions/exec-1/context?path=$trigger.user.email").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(email, Some(serde_json::json!("ada@example.com")));
+
+    let (status, id) = fetch("/executions/exec-1/context?path=/items/0/id").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(id, Some(serde_json::json!(7)));
+
+    let (status, _) = fetch("/executions/exec-1/context?path=$trigger.missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = fetch("/executions/exec-2/context").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn execution_events_replay_history_then_stream_live_updates() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    let state = build_state(token_store, execution_store);
+    let tx = state.tx.clone();
+
+    let response = app(state)
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/executions/exec-1/events")
+                .header("Authorization", format!("Bearer {}", jwt_for_user("user-1")))
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok()),
+        Some("text/event-stream")
+    );
+
+    // The stream subscribed before responding; other executions are filtered out
+    for (execution_id, node_id) in [("exec-2", "node-9"), ("exec-1", "node-2")] {
+        tx.send(WorkerMessage::NodeStatus(Box::new(sample_status(
+            execution_id,
+            node_id,
+            "running",
+            "2026-01-01T00:00:00Z",
+        ))))
+        .expect("the SSE stream should be subscribed");
+    }
+    // Dropping the last sender ends the stream so the body can be collected
+    drop(tx);
+
+    let body =
+        tokio::time::timeout(Duration::from_secs(5), to_bytes(response.into_body(), MAX_BODY))
+            .await
+            .expect("stream should end once the channel closes")
+            .expect("body should be readable");
+    let frames: Vec<serde_json::Value> = String::from_utf8_lossy(&body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).expect("frame should be json"))
+        .collect();
+    // The replay ends with the marker, before any live frame
+    assert_eq!(frames.get(2), Some(&serde_json::json!({ "type": "snapshot_complete" })));
+    let frames: Vec<_> = frames
+        .iter()
+        .map(|frame| (frame.get("node_id").cloned(), frame.get("status").cloned()))
+        .collect();
+    assert_eq!(
+        frames,
+        vec![
+            (Some(serde_json::json!("node-1")), Some(serde_json::json!("success"))),
+            (Some(serde_json::Value::Null), Some(serde_json::json!("running"))),
+            (None, None),
+            (Some(serde_json::json!("node-2")), Some(serde_json::json!("running"))),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_execution_node_returns_one_node_with_its_lineages() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.nodes.insert(
+        "loop-body".to_string(),
+        HydratedNode {
+            latest: Some(NodeExecutionInstance {
+                status: Some("running".to_string()),
+                ..NodeExecutionInstance::default()
+            }),
+            lineages: [("lineage-a", "success"), ("lineage-b", "running")]
+                .into_iter()
+                .map(|(hash, status)| {
+                    (
+                        hash.to_string(),
+                        NodeExecutionInstance {
+                            status: Some(status.to_string()),
+                            ..NodeExecutionInstance::default()
+                        },
+                    )
+                })
+                .collect(),
+            lineage_count: 2,
+            ..HydratedNode::default()
+        },
+    );
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: &'static str| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            (status, serde_json::from_slice::<HydratedNode>(&body).ok())
+        }
+    };
+
+    let (status, node) = fetch("/executions/exec-1/nodes/loop-body").await;
+    assert_eq!(status, StatusCode::OK);
+    let node = node.expect("body should be a node");
+    assert_eq!(node.lineage_count, 2);
+    assert_eq!(
+        node.lineages
+            .get("lineage-a")
+            .and_then(|run| run.status.as_deref()),
+        Some("success")
+    );
+
+    let (status, _) = fetch("/executions/exec-1/nodes/missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = fetch("/executions/exec-2/nodes/loop-body").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn stream_execution_nodes_writes_one_node_per_line() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.nodes
+        .insert("node-2".to_string(), HydratedNode { lineage_count: 3, ..HydratedNode::default() });
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let request = |uri: &str| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {jwt}"))
+            .body(Body::empty())
+            .expect("request should build")
+    };
+    let response = router
+        .clone()
+        .oneshot(request("/executions/exec-1/nodes:stream"))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok()),
+        Some("application/x-ndjson")
+    );
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let body = String::from_utf8(body.to_vec()).expect("body should be UTF-8");
+    assert!(body.ends_with('\n'));
+    let mut nodes: Vec<(String, Option<String>, u64)> = body
+        .lines()
+        .map(|line| {
+            let entry: NodeEntry =
+                serde_json::from_str(line).expect("each line should be a node entry");
+            let status = entry.node.latest.and_then(|run| run.status);
+            (entry.node_id, status, entry.node.lineage_count)
+        })
+        .collect();
+    nodes.sort();
+    assert_eq!(
+        nodes,
+        vec![
+            ("node-1".to_string(), Some("success".to_string()), 0),
+            ("node-2".to_string(), None, 3)
+        ]
+    );
+
+    let response = router
+        .oneshot(request("/executions/exec-2/nodes:stream"))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn get_execution_split_lists_branch_runs_by_item_index() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    let run = |item_index: i32, status: &str| NodeExecutionInstance {
+        status: Some(status.to_string()),
+        output: Some(serde_json::json!({ "large": "payload" })),
+        split_node_id: Some("split-1".to_string()),
+        item_index: Some(item_index),
+        total_items: Some(3),
+        processed_count: Some(2),
+        ..NodeExecutionInstance::default()
+    };
+    doc.nodes.insert(
+        "loop-body".to_string(),
+        HydratedNode {
+            latest: Some(run(2, "running")),
+            lineages: [("lineage-c", run(2, "running")), ("lineage-a", run(0, "success"))]
+                .into_iter()
+                .map(|(hash, run)| (hash.to_string(), run))
+                .collect(),
+            ..HydratedNode::default()
+        },
+    );
+    doc.nodes.insert(
+        "aggregate".to_string(),
+        HydratedNode { latest: Some(run(1, "success")), ..HydratedNode::default() },
+    );
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: &'static str| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            (status, serde_json::from_slice::<SplitIterations>(&body).ok())
+        }
+    };
+
+    let (status, split) = fetch("/executions/exec-1/splits/split-1").await;
+    assert_eq!(status, StatusCode::OK);
+    let split = split.expect("body should list the split's iterations");
+    let order: Vec<_> = split
+        .iterations
+        .iter()
+        .map(|iteration| (iteration.node_id.as_str(), iteration.run.item_index))
+        .collect();
+    assert_eq!(order, [("loop-body", Some(0)), ("aggregate", Some(1)), ("loop-body", Some(2))]);
+    assert!(
+        split
+            .iterations
+            .iter()
+            .all(|iteration| iteration.run.output.is_none())
+    );
+
+    let (status, split) = fetch("/executions/exec-1/splits/other").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(split.expect("empty split").iterations.is_empty());
+
+    let (status, _) = fetch("/executions/exec-2/splits/split-1").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn get_execution_graph_returns_topology_without_run_data() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let definition = [
+        serde_json::json!({ "id": "start", "name": "Start", "type": "trigger", "trigger": true }),
+        serde_json::json!({ "id": "send", "name": "Send", "type": "http" }),
+    ];
+    let edges = vec![serde_json::json!({ "id": "e1", "src": "start", "dst": "send" })];
+    let mut stored = sample_execution("exec-1", "wf-1", Some("running"));
+    stored.graph = Some(ExecutionGraph::from_definition(&definition, edges.clone()));
+    stored.nodes.insert(
+        "send".to_string(),
+        HydratedNode {
+            latest: Some(NodeExecutionInstance {
+                output: Some(serde_json::json!({ "large": "payload" })),
+                ..NodeExecutionInstance::default()
+            }),
+            ..HydratedNode::default()
+        },
+    );
+    // Stored before graphs were: only the definitions kept on the nodes
+    let mut legacy = sample_execution("exec-2", "wf-1", Some("completed"));
+    legacy.edges.clone_from(&edges);
+    legacy.nodes.clear();
+    for node in &definition {
+        let mut extra: std::collections::HashMap<String, serde_json::Value> =
+            serde_json::from_value(node.clone()).expect("definition is an object");
+        let id = extra
+            .remove("id")
+            .and_then(|id| id.as_str().map(str::to_owned))
+            .expect("definition has an id");
+        legacy
+            .nodes
+            .insert(id, HydratedNode { extra, ..HydratedNode::default() });
+    }
+    {
+        let mut docs = execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        docs.insert("exec-1".to_string(), stored);
+        docs.insert("exec-2".to_string(), legacy);
+    }
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |uri: &'static str| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let response = router
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header("Authorization", format!("Bearer {jwt}"))
+                        .body(Body::empty())
+                        .expect("request should build"),
+                )
+                .await
+                .expect("router should respond");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            (status, body)
+        }
+    };
+
+    let (status, body) = fetch("/executions/exec-1/graph").await;
+    assert_eq!(status, StatusCode::OK);
+    let graph: ExecutionGraph = serde_json::from_slice(&body).expect("body should be a graph");
+    let ids: Vec<_> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(ids, ["start", "send"]);
+    assert_eq!(graph.edges, edges);
+    assert!(!String::from_utf8_lossy(&body).contains("payload"));
+
+    let (status, body) = fetch("/executions/exec-2/graph").await;
+    assert_eq!(status, StatusCode::OK);
+    let rebuilt: ExecutionGraph = serde_json::from_slice(&body).expect("body should be a graph");
+    assert_eq!(rebuilt.nodes.len(), 2);
+    assert!(
+        rebuilt
+            .nodes
+            .iter()
+            .any(|node| node.trigger && node.id == "start")
+    );
+    assert_eq!(rebuilt.edges, edges);
+
+    let (status, _) = fetch("/executions/exec-3/graph").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn graph_and_context_reads_authorize_before_reading_the_document() {
+    init_test_config();
+
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", None));
+    let router = app(build_state(Arc::new(MockTokenStore::default()), execution_store.clone()));
+
+    for uri in ["/executions/exec-1/graph", "/executions/exec-1/context"] {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("Authorization", format!("Bearer {}", jwt_for_user("user-1")))
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+    }
+    assert_eq!(execution_store.document_loads.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn get_execution_returns_not_modified_for_matching_etag() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+    doc.updated_at = Some(DateTime::from_millis(1_700_000_000_000));
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc.clone());
+    let router = app(build_state(token_store, execution_store.clone()));
+    let jwt = jwt_for_user("user-1");
+
+    let fetch = |if_none_match: Option<String>| {
+        let router = router.clone();
+        let jwt = jwt.clone();
+        async move {
+            let mut request = Request::builder()
+                .method("GET")
+                .uri("/executions/exec-1")
+                .header("Authorization", format!("Bearer {jwt}"));
+            if let Some(etag) = if_none_match {
+                request = request.header("If-None-Match", etag);
+            }
+            let response = router
+                .oneshot(request.body(Body::empty()).expect("request should build"))
+                .await
+                .expect("router should respond");
+            let status = response.status();
+            let etag = response
+                .headers()
+                .get("etag")
+                .map(|value| value.to_str().expect("etag is ascii").to_string());
+            let body = to_bytes(response.into_body(), MAX_BODY)
+                .await
+                .expect("body should be readable");
+            (status, etag, body.len())
+        }
+    };
+
+    let (status, etag, _) = fetch(None).await;
+    assert_eq!(status, StatusCode::OK);
+    let etag = etag.expect("execution with updated_at has an etag");
+    assert!(etag.starts_with("W/"));
+
+    let (status, revalidated, body_len) = fetch(Some(etag.clone())).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(revalidated.as_ref(), Some(&etag));
+    assert_eq!(body_len, 0);
+
+    // A later write changes the tag, so the full document is sent again
+    doc.updated_at = Some(DateTime::from_millis(1_700_000_001_000));
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), doc);
+    let (status, changed, _) = fetch(Some(etag.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(changed, Some(etag));
+}
+
+#[tokio::test]
+async fn readyz_names_unreachable_dependency() {
+    init_test_config();
+    let readyz = |state| async move {
+        let response = app(state)
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("router should respond");
+        let status = response.status();
+        let body = to_bytes(response.into_body(), MAX_BODY)
+            .await
+            .expect("body should be readable");
+        let json: serde_json::Value = serde_json::from_slice(&body).expect("body should be json");
+        (status, json)
+    };
+
+    let (status, json) = readyz(build_state(
+        Arc::new(MockTokenStore::default()),
+        Arc::new(MockExecutionStore::default()),
+    ))
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json.pointer("/failed"), Some(&serde_json::json!([])));
+
+    let (status, json) = readyz(build_state(
+        Arc::new(MockTokenStore::default()),
+        Arc::new(MockExecutionStore { ping_fails: true, ..MockExecutionStore::default() }),
+    ))
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json.pointer("/failed"), Some(&serde_json::json!(["mongodb"])));
+    assert_eq!(json.pointer("/dependencies/redis/ok"), Some(&serde_json::json!(true)));
+    assert_eq!(
+        json.pointer("/dependencies/mongodb/error"),
+        Some(&serde_json::json!("mongodb unreachable"))
+    );
+}
+
+#[tokio::test]
+async fn batch_get_executions_reports_inaccessible_ids_like_missing_ones() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        denied_executions: vec!["exec-2".to_string()],
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    {
+        let mut docs = execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        for id in ["exec-1", "exec-2"] {
+            docs.insert(id.to_string(), sample_execution(id, "wf-1", Some("completed")));
+        }
+    }
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+    let batch = |ids: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/executions:batch")
+            .header("Authorization", format!("Bearer {jwt}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::json!({ "execution_ids": ids }).to_string()))
+            .expect("request should build")
+    };
+
+    let response = router
+        .clone()
+        .oneshot(batch(serde_json::json!(["exec-1", "exec-2", "missing", "exec-1"])))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let entries: serde_json::Value = serde_json::from_slice(&body).expect("batch should be json");
+    let entries = entries.as_object().expect("batch should be an object");
+    assert_eq!(entries.len(), 3, "one entry per distinct id: {entries:?}");
+    assert_eq!(
+        entries
+            .get("exec-1")
+            .and_then(|doc| doc.get("execution_id")),
+        Some(&serde_json::json!("exec-1"))
+    );
+    assert_eq!(entries.get("exec-2"), Some(&serde_json::json!("not_found")));
+    assert_eq!(entries.get("missing"), Some(&serde_json::json!("not_found")));
+
+    let too_many: Vec<String> = (0..=rtes::config::Config::get().executions_batch_max)
+        .map(|i| format!("exec-{i}"))
+        .collect();
+    let response = router
+        .oneshot(batch(serde_json::json!(too_many)))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_execution_returns_only_requested_fields() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let mut execution = sample_execution("exec-1", "wf-1", Some("completed"));
+    execution.nodes.insert(
+        "node-1".to_string(),
+        HydratedNode {
+            latest: Some(NodeExecutionInstance {
+                status: Some("success".to_string()),
+                output: Some(serde_json::json!({ "rows": [1, 2, 3] })),
+                ..NodeExecutionInstance::default()
+            }),
+            ..HydratedNode::default()
+        },
+    );
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), execution);
+    let router = app(build_state(token_store, execution_store));
+    let jwt = jwt_for_user("user-1");
+    let get = |uri: &str| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {jwt}"))
+            .body(Body::empty())
+            .expect("request should build")
+    };
+
+    let response = router
+        .clone()
+        .oneshot(get("/executions/exec-1?fields=status,nodes.latest.status"))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let execution: serde_json::Value = serde_json::from_slice(&body).expect("body should be json");
+    assert_eq!(
+        execution,
+        serde_json::json!({
+            "status": "completed",
+            "nodes": { "node-1": { "latest": { "status": "success" } } },
+        })
+    );
+
+    let response = router
+        .oneshot(get("/executions/exec-1?fields=status,nodes.latest.$where"))
+        .await
+        .expect("router should respond");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = to_bytes(response.into_body(), MAX_BODY)
+        .await
+        .expect("body should be readable");
+    let error: serde_json::Value = serde_json::from_slice(&body).expect("error should be json");
+    assert_eq!(error.get("code"), Some(&serde_json::json!("INVALID_FIELD")));
+}
+
+#[tokio::test]
+async fn repeated_probes_are_answered_from_the_negative_cache() {
+    init_test_config();
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    let negative_cache = NegativeCache::new(Duration::from_mins(1));
+    let router = app(build_state(token_store, execution_store.clone())
+        .with_negative_cache(negative_cache.clone()));
+    let status = |uri: &'static str, authorization: Option<&'static str>| {
+        let router = router.clone();
+        async move {
+            let mut request = Request::builder().method("GET").uri(uri);
+            if let Some(value) = authorization {
+                request = request.header("Authorization", value);
+            }
+            router
+                .oneshot(request.body(Body::empty()).expect("request should build"))
+                .await
+                .expect("router should respond")
+                .status()
+        }
+    };
+
+    assert_eq!(status("/executions/exec-1", None).await, StatusCode::NOT_FOUND);
+    // Stored behind the cache's back: the remembered 404 still answers
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+    assert_eq!(status("/executions/exec-1", None).await, StatusCode::NOT_FOUND);
+    // An invalid JWT bypasses the cache and is refused as such
+    assert_eq!(
+        status("/executions/exec-1", Some("Bearer invalid.token.value")).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Consuming the execution message forgets it, and grants are never cached
+    negative_cache.forget("exec-1");
+    for _ in 0..2 {
+        assert_eq!(status("/executions/exec-1", None).await, StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn node_and_split_reads_remember_missing_executions() {
+    init_test_config();
+
+    for uri in ["/executions/exec-1/nodes/node-1", "/executions/exec-1/splits/split-1"] {
+        let token_store = Arc::new(MockTokenStore {
+            validate_execution_access_result: true,
+            ..MockTokenStore::default()
+        });
+        let execution_store = Arc::new(MockExecutionStore::default());
+        let router = app(build_state(token_store, execution_store.clone())
+            .with_negative_cache(NegativeCache::new(Duration::from_mins(1))));
+        let status = || {
+            let router = router.clone();
+            async move {
+                router
+                    .oneshot(
+                        Request::builder()
+                            .uri(uri)
+                            .body(Body::empty())
+                            .expect("request should build"),
+                    )
+                    .await
+                    .expect("router should respond")
+                    .status()
+            }
+        };
+
+        assert_eq!(status().await, StatusCode::NOT_FOUND, "{uri}");
+        // Stored behind the cache's back: the remembered 404 still answers
+        execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+        assert_eq!(status().await, StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn unauthorized_callers_never_load_the_execution_document() {
+    init_test_config();
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("completed")));
+    let get = |uri: &str, token_store: MockTokenStore| {
+        app(build_state(Arc::new(token_store), execution_store.clone())).oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(uri)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+
+    // The SSE stream authorizes the same way as the document read
+    for uri in ["/executions/exec-1", "/executions/exec-1/events"] {
+        let denied = get(uri, MockTokenStore::default())
+            .await
+            .expect("router should respond");
+        assert_eq!(denied.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        assert_eq!(execution_store.document_loads.load(Ordering::Relaxed), 0, "{uri}");
+    }
+
+    for (loads, uri) in [(1, "/executions/exec-1"), (2, "/executions/exec-1/events")] {
+        let granted = get(
+            uri,
+            MockTokenStore { validate_execution_access_result: true, ..MockTokenStore::default() },
+        )
+        .await
+        .expect("router should respond");
+        assert_eq!(granted.status(), StatusCode::OK, "{uri}");
+        assert_eq!(execution_store.document_loads.load(Ordering::Relaxed), loads, "{uri}");
+    }
 }
@@ -2,14 +2,28 @@
 
 mod common;
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
 
-use common::{MockExecutionStore, MockTokenStore, build_state, init_test_config, sample_execution};
-use futures::StreamExt;
-use rtes::domain::models::{NodeStatusMessage, WorkerMessage};
-use serde_json::Value;
-use tokio::net::TcpListener;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use common::{
+    MockExecutionStore,
+    MockTokenStore,
+    build_state,
+    init_test_config,
+    jwt_for_user,
+    sample_execution,
+    sample_status,
+};
+use futures::{SinkExt, StreamExt};
+use rtes::domain::models::{HydratedNode, NodeExecutionInstance, NodeStatusMessage, WorkerMessage};
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, sync::Barrier};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{Error as WsError, Message, client::IntoClientRequest},
+};
 
 #[tokio::test]
 async fn websocket_streams_history_then_live_updates() {
@@ -25,7 +39,15 @@ async fn websocket_streams_history_then_live_updates() {
             .execution_documents_by_id
             .lock()
             .expect("mock execution store mutex should not be poisoned");
-        docs.insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+        let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+        if let Some(latest) = doc
+            .nodes
+            .get_mut("node-1")
+            .and_then(|node| node.latest.as_mut())
+        {
+            latest.output = Some(json!({ "rows": [1, 2] }));
+        }
+        docs.insert("exec-1".to_string(), doc);
     }
 
     let state = build_state(token_store, execution_store);
@@ -56,31 +78,14 @@ async fn websocket_streams_history_then_live_updates() {
         other => panic!("expected text frame, got {other:?}"),
     };
     assert_eq!(history_json["node_id"], "node-1");
+    // Output is sent as JSON, not as a string the client has to re-parse
+    assert_eq!(history_json["output"], json!({ "rows": [1, 2] }));
 
-    let _ = state
-        .tx
-        .send(WorkerMessage::NodeStatus(Box::new(NodeStatusMessage {
-            workflow_id:      "wf-1".to_string(),
-            execution_id:     "exec-1".to_string(),
-            node_id:          "node-live".to_string(),
-            node_name:        "Node Live".to_string(),
-            status:           "running".to_string(),
-            input:            None,
-            parameters:       None,
-            output:           None,
-            error:            None,
-            executed_at:      "2026-01-01T00:00:00Z".to_string(),
-            duration_ms:      1,
-            branch_id:        None,
-            split_node_id:    None,
-            item_index:       None,
-            total_items:      None,
-            processed_count:  None,
-            aggregator_state: None,
-            lineage_stack:    None,
-            lineage_hash:     None,
-            used_inputs:      None,
-        })));
+    let live = NodeStatusMessage {
+        output: Some(json!({ "partial": true })),
+        ..sample_status("exec-1", "node-live", "running", "2026-01-01T00:00:00Z")
+    };
+    let _ = state.tx.send(WorkerMessage::NodeStatus(Box::new(live)));
 
     let mut found_live_update = false;
     for _ in 0..5 {
@@ -97,11 +102,758 @@ async fn websocket_streams_history_then_live_updates() {
         };
         if json["node_id"] == "node-live" {
             assert_eq!(json["status"], "running");
+            assert_eq!(json["output"], json!({ "partial": true }));
             found_live_update = true;
             break;
         }
     }
     assert!(found_live_update, "expected websocket to emit the live node update");
+    assert_eq!(state.connections.load(Ordering::Relaxed), 1);
+
+    ws_stream.close(None).await.expect("close should send");
+    tokio::time::timeout(Duration::from_secs(3), async {
+        while state.connections.load(Ordering::Relaxed) != 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("closed socket should stop counting as a connection");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_branch_filter_applies_to_history_and_live_updates() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    {
+        let mut doc = sample_execution("exec-1", "wf-1", Some("running"));
+        doc.nodes.clear();
+        let lineage = |branch_id: &str| NodeExecutionInstance {
+            status: Some("success".to_string()),
+            branch_id: Some(branch_id.to_string()),
+            lineage_hash: Some(format!("lineage-{branch_id}")),
+            ..NodeExecutionInstance::default()
+        };
+        let mut node = HydratedNode::default();
+        node.lineages
+            .insert("lineage-b1".to_string(), lineage("b1"));
+        node.lineages
+            .insert("lineage-b2".to_string(), lineage("b2"));
+        doc.nodes.insert("node-loop".to_string(), node);
+        execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned")
+            .insert("exec-1".to_string(), doc);
+    }
+
+    let state = build_state(token_store, execution_store);
+    let app = rtes::api::routes::app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let ws_url = format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1&branch_id=b1");
+    let (mut ws_stream, _) = connect_async(ws_url)
+        .await
+        .expect("websocket connection should succeed");
+
+    let mut next_json = async || {
+        let message = tokio::time::timeout(Duration::from_secs(3), ws_stream.next())
+            .await
+            .expect("message timeout")
+            .expect("message should exist")
+            .expect("frame should be valid");
+        match message {
+            Message::Text(text) => {
+                serde_json::from_str::<Value>(&text).expect("frame must be JSON")
+            },
+            other => panic!("expected text frame, got {other:?}"),
+        }
+    };
+
+    // History: only the b1 lineage, then the execution status
+    let history = next_json().await;
+    assert_eq!(history["node_id"], "node-loop");
+    assert_eq!(history["status"], "success");
+    let status = next_json().await;
+    assert!(status["node_id"].is_null());
+    assert_eq!(status["status"], "running");
+    assert_eq!(next_json().await, json!({ "type": "snapshot_complete" }));
+
+    for (node_id, branch_id) in [("node-b2", "b2"), ("node-b1", "b1")] {
+        let mut msg = sample_status("exec-1", node_id, "running", "2026-01-01T00:00:00Z");
+        msg.branch_id = Some(branch_id.to_string());
+        let _ = state.tx.send(WorkerMessage::NodeStatus(Box::new(msg)));
+    }
+
+    // The b2 update is filtered out, so the next frame is the b1 update
+    let live = next_json().await;
+    assert_eq!(live["node_id"], "node-b1");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_accepts_jwt_from_token_query_param() {
+    init_test_config();
+
+    // Only the JWT path grants access, so a successful upgrade proves the
+    // query token was used
+    let token_store = Arc::new(MockTokenStore {
+        validate_access_for_execution_result: true,
+        ..MockTokenStore::default()
+    });
+    let state = build_state(token_store, Arc::new(MockExecutionStore::default()));
+    let app = rtes::api::routes::app(state);
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let base = format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1");
+    let token = jwt_for_user("user-1");
+    connect_async(format!("{base}&token={token}"))
+        .await
+        .expect("websocket connection with query token should succeed");
+
+    match connect_async(format!("{base}&token=invalid.token.value")).await {
+        Err(WsError::Http(response)) => assert_eq!(response.status(), 401),
+        other => panic!("expected 401 for an invalid query token, got {other:?}"),
+    }
+
+    // No JWT and no execution token grant: missing credentials, not a
+    // permission failure
+    match connect_async(base.as_str()).await {
+        Err(WsError::Http(response)) => assert_eq!(response.status(), 401),
+        other => panic!("expected 401 without credentials, got {other:?}"),
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_rejects_workflow_id_the_execution_does_not_belong_to() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    let app = rtes::api::routes::app(build_state(token_store, execution_store));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    match connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-2")).await {
+        Err(WsError::Http(response)) => assert_eq!(response.status(), 403),
+        other => panic!("expected 403 for a mismatched workflow, got {other:?}"),
+    }
+
+    // Not stored yet: nothing to contradict the claimed workflow
+    connect_async(format!("ws://{addr}/rt?execution_id=exec-new&workflow_id=wf-2"))
+        .await
+        .expect("websocket for an unstored execution should connect");
+    connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+        .await
+        .expect("websocket with the owning workflow should connect");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_without_a_grant_does_not_learn_whether_the_execution_exists() {
+    init_test_config();
+
+    let execution_store = Arc::new(MockExecutionStore::default());
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", Some("running")));
+    let app =
+        rtes::api::routes::app(build_state(Arc::new(MockTokenStore::default()), execution_store));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    // Stored under another workflow or not stored at all: both are a 401
+    for execution_id in ["exec-1", "exec-missing"] {
+        let url = format!("ws://{addr}/rt?execution_id={execution_id}&workflow_id=wf-2");
+        match connect_async(url).await {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), 401),
+            other => panic!("expected 401 for {execution_id} without a grant, got {other:?}"),
+        }
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_rejects_unparsable_query_params_with_a_code() {
+    init_test_config();
+
+    let app = rtes::api::routes::app(build_state(
+        Arc::new(MockTokenStore::default()),
+        Arc::new(MockExecutionStore::default()),
+    ));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let url = format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1&since=yesterday");
+    match connect_async(url).await {
+        Err(WsError::Http(response)) => {
+            assert_eq!(response.status(), 400);
+            let error: Value =
+                serde_json::from_slice(response.body().as_deref().unwrap_or_default())
+                    .expect("error body is JSON");
+            assert_eq!(error["code"], "INVALID_QUERY");
+        },
+        other => panic!("expected 400 for an unparsable since, got {other:?}"),
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_upgrade_survives_response_compression() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let app =
+        rtes::api::routes::app(build_state(token_store, Arc::new(MockExecutionStore::default())));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    // Browsers advertise compression on the upgrade request too
+    let mut request = format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1")
+        .into_client_request()
+        .expect("request should build");
+    request
+        .headers_mut()
+        .insert("Accept-Encoding", "gzip, br".parse().expect("header value"));
+    let (_, response) = connect_async(request)
+        .await
+        .expect("websocket upgrade should succeed");
+    assert_eq!(response.status(), 101);
+    assert!(response.headers().get("content-encoding").is_none());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_follows_executions_added_with_subscribe() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore::default());
+    {
+        let mut docs = execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        docs.insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", None));
+        docs.insert("exec-2".to_string(), sample_execution("exec-2", "wf-1", None));
+        docs.insert("exec-3".to_string(), sample_execution("exec-3", "wf-2", None));
+    }
+    let state = build_state(token_store, execution_store);
+    let app = rtes::api::routes::app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let (ws_stream, _) =
+        connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+            .await
+            .expect("websocket connection should succeed");
+    let (mut sink, mut frames) = ws_stream.split();
+    let mut next_json = async || loop {
+        let message = tokio::time::timeout(Duration::from_secs(3), frames.next())
+            .await
+            .expect("frame timeout")
+            .expect("frame should exist")
+            .expect("frame should be valid");
+        if let Message::Text(text) = message {
+            break serde_json::from_str::<Value>(&text).expect("frame must be JSON");
+        }
+    };
+    let history = next_json().await;
+    assert_eq!(history["node_id"], "node-1");
+    assert!(history.get("execution_id").is_none());
+    assert_eq!(next_json().await, json!({ "type": "snapshot_complete" }));
+
+    sink.send(Message::text(r#"{"action": "subscribe", "execution_id": "exec-2"}"#))
+        .await
+        .expect("subscribe should send");
+    assert_eq!(next_json().await, json!({"type": "subscribed", "execution_id": "exec-2"}));
+    let history = next_json().await;
+    assert_eq!(history["execution_id"], "exec-2");
+    assert_eq!(history["node_id"], "node-1");
+    assert_eq!(next_json().await, json!({ "type": "snapshot_complete", "execution_id": "exec-2" }));
+    assert_eq!(state.execution_connections.open_for("exec-2"), 1);
+
+    let _ = state
+        .tx
+        .send(WorkerMessage::NodeStatus(Box::new(sample_status(
+            "exec-2",
+            "node-live",
+            "running",
+            "2026-01-01T00:00:00Z",
+        ))));
+    let live = next_json().await;
+    assert_eq!(live["execution_id"], "exec-2");
+    assert_eq!(live["node_id"], "node-live");
+
+    // exec-3 belongs to another workflow than the one the socket names
+    sink.send(Message::text(r#"{"action": "subscribe", "execution_id": "exec-3"}"#))
+        .await
+        .expect("subscribe should send");
+    assert_eq!(
+        next_json().await,
+        json!({"type": "subscribe_failed", "execution_id": "exec-3", "code": "FORBIDDEN"})
+    );
+
+    sink.close().await.expect("close should send");
+    tokio::time::timeout(Duration::from_secs(3), async {
+        while state.execution_connections.open_for("exec-2") != 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("closed socket should release its subscriptions");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_closes_with_an_error_when_history_cannot_be_read() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore {
+        unreadable_document: Some("exec-1".to_string()),
+        ..MockExecutionStore::default()
+    });
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", None));
+    let state = build_state(token_store, execution_store);
+    let app = rtes::api::routes::app(state);
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let (mut ws_stream, _) =
+        connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+            .await
+            .expect("websocket connection should succeed");
+    let mut next_message = async || {
+        tokio::time::timeout(Duration::from_secs(3), ws_stream.next())
+            .await
+            .expect("frame timeout")
+            .expect("frame should exist")
+            .expect("frame should be valid")
+    };
+    let Message::Text(text) = next_message().await else {
+        panic!("expected a replay_failed frame");
+    };
+    assert_eq!(
+        serde_json::from_str::<Value>(&text).expect("frame must be JSON"),
+        json!({"type": "replay_failed", "code": "SERVICE_UNAVAILABLE"})
+    );
+    let Message::Close(Some(close)) = next_message().await else {
+        panic!("expected the socket to close instead of completing the snapshot");
+    };
+    assert_eq!(u16::from(close.code), 1011);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_subscribe_fails_when_history_cannot_be_read() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let execution_store = Arc::new(MockExecutionStore {
+        unreadable_document: Some("exec-2".to_string()),
+        ..MockExecutionStore::default()
+    });
+    {
+        let mut docs = execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        docs.insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", None));
+        docs.insert("exec-2".to_string(), sample_execution("exec-2", "wf-1", None));
+    }
+    let state = build_state(token_store, execution_store);
+    let app = rtes::api::routes::app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let (ws_stream, _) =
+        connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+            .await
+            .expect("websocket connection should succeed");
+    let (mut sink, mut frames) = ws_stream.split();
+    let mut next_json = async || loop {
+        let message = tokio::time::timeout(Duration::from_secs(3), frames.next())
+            .await
+            .expect("frame timeout")
+            .expect("frame should exist")
+            .expect("frame should be valid");
+        if let Message::Text(text) = message {
+            break serde_json::from_str::<Value>(&text).expect("frame must be JSON");
+        }
+    };
+    assert_eq!(next_json().await["node_id"], "node-1");
+    assert_eq!(next_json().await, json!({ "type": "snapshot_complete" }));
+
+    sink.send(Message::text(r#"{"action": "subscribe", "execution_id": "exec-2"}"#))
+        .await
+        .expect("subscribe should send");
+    assert_eq!(
+        next_json().await,
+        json!({"type": "subscribe_failed", "execution_id": "exec-2", "code": "SERVICE_UNAVAILABLE"})
+    );
+    assert_eq!(state.execution_connections.open_for("exec-2"), 0);
+
+    sink.send(Message::text(r#"{"action": "list"}"#))
+        .await
+        .expect("list should send");
+    assert_eq!(next_json().await, json!({"type": "subscriptions", "execution_ids": ["exec-1"]}));
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_lists_and_unsubscribes_followed_executions() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let state = build_state(token_store, Arc::new(MockExecutionStore::default()));
+    let app = rtes::api::routes::app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let (ws_stream, _) =
+        connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+            .await
+            .expect("websocket connection should succeed");
+    let (mut sink, mut frames) = ws_stream.split();
+    // The answer to `command`; replay markers are skipped
+    let mut reply = async |command: &str| {
+        sink.send(Message::text(command))
+            .await
+            .expect("command should send");
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(3), frames.next())
+                .await
+                .expect("frame timeout")
+                .expect("frame should exist")
+                .expect("frame should be valid");
+            if let Message::Text(text) = message {
+                let json = serde_json::from_str::<Value>(&text).expect("frame must be JSON");
+                if json["type"] != "snapshot_complete" {
+                    break json;
+                }
+            }
+        }
+    };
+
+    assert_eq!(
+        reply(r#"{"action": "list"}"#).await,
+        json!({"type": "subscriptions", "execution_ids": ["exec-1"]})
+    );
+    let subscribe = r#"{"action": "subscribe", "execution_id": "exec-2"}"#;
+    assert_eq!(reply(subscribe).await["type"], "subscribed");
+    assert_eq!(
+        reply(r#"{"action": "list"}"#).await,
+        json!({"type": "subscriptions", "execution_ids": ["exec-1", "exec-2"]})
+    );
+    assert_eq!(state.execution_connections.open_for("exec-2"), 1);
+
+    for execution_id in ["exec-2", "exec-1"] {
+        assert_eq!(
+            reply(&format!(r#"{{"action": "unsubscribe", "execution_id": "{execution_id}"}}"#))
+                .await,
+            json!({"type": "unsubscribed", "execution_id": execution_id})
+        );
+        assert_eq!(state.execution_connections.open_for(execution_id), 0);
+    }
+    // Nothing followed any more, so this live update is dropped
+    let _ = state
+        .tx
+        .send(WorkerMessage::NodeStatus(Box::new(sample_status(
+            "exec-1",
+            "node-live",
+            "running",
+            "2026-01-01T00:00:00Z",
+        ))));
+    assert_eq!(
+        reply(r#"{"action": "list"}"#).await,
+        json!({"type": "subscriptions", "execution_ids": []})
+    );
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_live_updates_during_replay_follow_it_in_order() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let gate = Arc::new(Barrier::new(2));
+    let execution_store = Arc::new(MockExecutionStore {
+        load_gate: Some(gate.clone()),
+        ..MockExecutionStore::default()
+    });
+    execution_store
+        .execution_documents_by_id
+        .lock()
+        .expect("mock execution store mutex should not be poisoned")
+        .insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", None));
+    let state = build_state(token_store, execution_store);
+    let app = rtes::api::routes::app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let (mut ws_stream, _) =
+        connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+            .await
+            .expect("websocket connection should succeed");
+
+    // While the history is being read, more updates arrive than the
+    // broadcast channel holds
+    gate.wait().await;
+    let updates = rtes::config::Config::get().broadcast_capacity * 3 / 2;
+    for index in 0..updates {
+        let _ = state
+            .tx
+            .send(WorkerMessage::NodeStatus(Box::new(sample_status(
+                "exec-1",
+                &format!("node-live-{index}"),
+                "running",
+                "2026-01-01T00:00:00Z",
+            ))));
+        if index % 10 == 9 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+    gate.wait().await;
+
+    let mut frames = Vec::new();
+    while frames.len() < updates + 2 {
+        let message = tokio::time::timeout(Duration::from_secs(3), ws_stream.next())
+            .await
+            .expect("frame timeout")
+            .expect("frame should exist")
+            .expect("frame should be valid");
+        if let Message::Text(text) = message {
+            frames.push(serde_json::from_str::<Value>(&text).expect("frame must be JSON"));
+        }
+    }
+    assert_eq!(frames[0]["node_id"], "node-1");
+    assert_eq!(frames[1], json!({ "type": "snapshot_complete" }));
+    let live: Vec<_> = frames[2..]
+        .iter()
+        .map(|frame| {
+            frame["node_id"]
+                .as_str()
+                .expect("a live node frame")
+                .to_owned()
+        })
+        .collect();
+    let expected: Vec<_> = (0..updates)
+        .map(|index| format!("node-live-{index}"))
+        .collect();
+    assert_eq!(live, expected, "no update lost or reordered, and no resync needed");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn websocket_live_updates_during_subscribe_replay_follow_it_in_order() {
+    init_test_config();
+
+    let token_store = Arc::new(MockTokenStore {
+        validate_execution_access_result: true,
+        ..MockTokenStore::default()
+    });
+    let gate = Arc::new(Barrier::new(2));
+    let execution_store = Arc::new(MockExecutionStore {
+        load_gate: Some(gate.clone()),
+        ..MockExecutionStore::default()
+    });
+    {
+        let mut docs = execution_store
+            .execution_documents_by_id
+            .lock()
+            .expect("mock execution store mutex should not be poisoned");
+        docs.insert("exec-1".to_string(), sample_execution("exec-1", "wf-1", None));
+        docs.insert("exec-2".to_string(), sample_execution("exec-2", "wf-1", None));
+    }
+    let state = build_state(token_store, execution_store);
+    let app = rtes::api::routes::app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("address should be available");
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("server should run for websocket test");
+    });
+
+    let (ws_stream, _) =
+        connect_async(format!("ws://{addr}/rt?execution_id=exec-1&workflow_id=wf-1"))
+            .await
+            .expect("websocket connection should succeed");
+    let (mut sink, mut frames) = ws_stream.split();
+    // Let the initial replay through
+    gate.wait().await;
+    gate.wait().await;
+
+    sink.send(Message::text(r#"{"action": "subscribe", "execution_id": "exec-2"}"#))
+        .await
+        .expect("subscribe should send");
+    // While exec-2's history is being read, it gets a live update
+    gate.wait().await;
+    let _ = state
+        .tx
+        .send(WorkerMessage::NodeStatus(Box::new(sample_status(
+            "exec-2",
+            "node-live",
+            "running",
+            "2026-01-01T00:00:00Z",
+        ))));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    gate.wait().await;
+
+    let mut received = Vec::new();
+    while received.len() < 6 {
+        let message = tokio::time::timeout(Duration::from_secs(3), frames.next())
+            .await
+            .expect("frame timeout")
+            .expect("frame should exist")
+            .expect("frame should be valid");
+        if let Message::Text(text) = message {
+            received.push(serde_json::from_str::<Value>(&text).expect("frame must be JSON"));
+        }
+    }
+    assert_eq!(received[1], json!({ "type": "snapshot_complete" }));
+    assert_eq!(received[2], json!({ "type": "subscribed", "execution_id": "exec-2" }));
+    assert_eq!(
+        (&received[3]["execution_id"], &received[3]["node_id"]),
+        (&json!("exec-2"), &json!("node-1"))
+    );
+    assert_eq!(received[4], json!({ "type": "snapshot_complete", "execution_id": "exec-2" }));
+    assert_eq!(
+        (&received[5]["execution_id"], &received[5]["node_id"]),
+        (&json!("exec-2"), &json!("node-live"))
+    );
 
     server.abort();
 }